@@ -0,0 +1,41 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// Keep in sync with SMALL_PERIODS_BOUND in src/pisano.rs.
+const SMALL_PERIODS_BOUND: u32 = 4_096;
+
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // Node resolves the N-API symbols when it loads the addon.
+    #[cfg(feature = "node")]
+    napi_build::setup();
+
+    // π(m) for every small modulo, by walking through the period until (0, 1) comes back. The
+    // period is at most 6m, so this is a few tens of millions of additions.
+    let periods: Vec<String> = (0..=SMALL_PERIODS_BOUND).map(|m| walk_period(m).to_string()).collect();
+    let table = format!("[{}]\n", periods.join(", "));
+    let out_dir = env::var("OUT_DIR").expect("cargo sets OUT_DIR");
+    fs::write(Path::new(&out_dir).join("small_pisano_periods.rs"), table).expect("can write to OUT_DIR");
+}
+
+
+fn walk_period(modulo: u32) -> u32 {
+    if modulo == 0 {
+        return 0;
+    }
+    let start = (0, 1 % modulo);
+    let (mut a, mut b) = start;
+    let mut period = 0;
+    loop {
+        let next = (a + b) % modulo;
+        a = b;
+        b = next;
+        period += 1;
+        if (a, b) == start {
+            return period;
+        }
+    }
+}
@@ -0,0 +1,34 @@
+/* C bindings for fast-fibonacci, built with the `ffi` feature. */
+
+#ifndef FAST_FIBONACCI_H
+#define FAST_FIBONACCI_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define FAST_FIBONACCI_OK 0
+#define FAST_FIBONACCI_ZERO_MODULUS 1
+#define FAST_FIBONACCI_OVERFLOW 2
+#define FAST_FIBONACCI_INVALID_ARGUMENT 3
+#define FAST_FIBONACCI_PANIC 4
+#define FAST_FIBONACCI_CANCELLED 5
+#define FAST_FIBONACCI_TIMEOUT 6
+
+/* fib(n) mod modulo. Returns 0 and sets *error on failure. error may be NULL. */
+uint64_t fast_fibonacci_fib_with_mod(uint64_t n, uint64_t modulo, int *error);
+
+/* fib(n) mod modulo for decimal strings n and modulo, as a decimal string. Returns NULL and sets
+ * *error on failure. The result must be freed with fast_fibonacci_string_free. */
+char *fast_fibonacci_bigfib_with_mod(const char *n, const char *modulo, int *error);
+
+/* Frees a string returned by fast_fibonacci_bigfib_with_mod. Does nothing for NULL. */
+void fast_fibonacci_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
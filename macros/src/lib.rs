@@ -0,0 +1,109 @@
+//! # fast-fibonacci-macros
+//!
+//! `fib!(n, modulo)` expands to the u64 literal fib(n) mod modulo, computed while compiling.
+//! Usually used through the `macros` feature of `fast-fibonacci`.
+
+extern crate proc_macro;
+
+use proc_macro::{Literal, TokenStream, TokenTree};
+
+
+/// Expands to fib(n) mod modulo as a u64 literal. Runtime O(log(n)), at compile time.
+///
+/// n can be any integer literal up to u128::MAX, and modulo any positive u64 literal.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci_macros::fib;
+///
+/// const X: u64 = fib!(1_000_000_000, 1_000_000_007);
+/// assert_eq!(21, X);
+/// assert_eq!(55, fib!(10, 100));
+/// ```
+#[proc_macro]
+pub fn fib(input: TokenStream) -> TokenStream {
+    match parse_args(input) {
+        Ok((n, modulo)) => TokenTree::Literal(Literal::u64_suffixed(fib_with_mod(n, modulo))).into(),
+        Err(message) => format!("compile_error!({:?})", message).parse().unwrap(),
+    }
+}
+
+
+// (n, modulo) from `n, modulo`.
+fn parse_args(input: TokenStream) -> Result<(u128, u64), String> {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let (n, modulo) = match tokens.as_slice() {
+        [n, TokenTree::Punct(comma), modulo] if comma.as_char() == ',' => (n, modulo),
+        _ => return Err("expected fib!(n, modulo)".to_string()),
+    };
+    let n = parse_int(n)?;
+    let modulo = parse_int(modulo)?;
+    if modulo == 0 {
+        return Err("modulo must be positive".to_string());
+    }
+    if modulo > u64::MAX as u128 {
+        return Err("modulo must fit in a u64".to_string());
+    }
+    Ok((n, modulo as u64))
+}
+
+
+// A decimal integer literal, with underscores and an optional integer suffix.
+fn parse_int(token: &TokenTree) -> Result<u128, String> {
+    let text = match token {
+        TokenTree::Literal(literal) => literal.to_string(),
+        // Macro arguments from other macros arrive wrapped in an invisible group.
+        TokenTree::Group(group) => match group.stream().into_iter().collect::<Vec<_>>().as_slice() {
+            [inner] => return parse_int(inner),
+            _ => return Err(format!("expected an integer literal, found {}", group)),
+        },
+        other => return Err(format!("expected an integer literal, found {}", other)),
+    };
+    let number = match text.find(['u', 'i']) {
+        Some(suffix) => &text[..suffix],
+        None => &text[..],
+    };
+    number
+        .chars()
+        .filter(|&c| c != '_')
+        .collect::<String>()
+        .parse()
+        .map_err(|_| format!("expected an integer literal, found {}", text))
+}
+
+
+// Fast doubling, fib(2k) = fib(k) * (2 * fib(k + 1) - fib(k)) and fib(2k + 1) = fib(k)^2 + fib(k + 1)^2.
+fn fib_with_mod(n: u128, modulo: u64) -> u64 {
+    let m = modulo as u128;
+    let (mut a, mut b) = (0, 1 % m);
+    for bit in (0..128).rev() {
+        let c = a * ((2 * b + m - a) % m) % m;
+        let d = (a * a % m + b * b % m) % m;
+        if (n >> bit) & 1 == 1 {
+            a = d;
+            b = (c + d) % m;
+        } else {
+            a = c;
+            b = d;
+        }
+    }
+    a as u64
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_fib_with_mod() {
+        let mut pair = (0u128, 1u128);
+        for n in 0..140 {
+            for &modulo in &[1, 2, 10, 1_000_000_007, u64::MAX] {
+                assert_eq!(fib_with_mod(n, modulo) as u128, pair.0 % modulo as u128, "n = {}", n);
+            }
+            pair = (pair.1, pair.0 + pair.1);
+        }
+        assert_eq!(fib_with_mod(1_000_000_000_000_000, 1_000_000), 546_875);
+    }
+}
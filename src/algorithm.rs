@@ -0,0 +1,690 @@
+//! Explicit choice between the ways of computing fib(n) with modulo, and a `Dispatcher` that
+//! makes the choice from the inputs.
+
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use num_bigint::BigUint;
+
+use crate::computation::BigFibComputation;
+use crate::nt::is_prime;
+use crate::period_cache;
+use crate::pisano::{prime_period_multiple, try_pisano_period, PisanoTable};
+use crate::{
+    add_mod, fib_by_iteration, fib_pair_u128, mul_mod, small_fib_table, try_bigfib_with_mod, try_fib_with_mod,
+    FibError, GoldenInt, ITERATION_THRESHOLD,
+};
+
+
+/// An algorithm for fib(n) with modulo, see `fib_with_mod_using`.
+///
+/// All of them give the same results; pinning one is for reproducibility and benchmarking.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Algorithm {
+    /// Exponentiation by squaring of the matrix [[0, 1], [1, 1]], as `fib_with_mod` does, which
+    /// adds up the sequence instead for n below 128, and doubles without divisions for Mersenne
+    /// moduli. Runtime O(log(n))
+    MatrixPower,
+    /// fib(2k) = fib(k) * (2 * fib(k + 1) - fib(k)) and fib(2k + 1) = fib(k)^2 + fib(k + 1)^2
+    /// over the bits of n. Runtime O(log(n)), with fewer multiplications than `MatrixPower`.
+    FastDoubling,
+    /// Reduces n modulo the pisano period first. Runtime O(sqrt(modulo)), dominated by factoring,
+    /// which only pays off when the period is reused.
+    PisanoReduce,
+    /// Binet's formula fib(n) = (φ^n - ψ^n) / √5 in the ring Z/modulo[√5], which is the finite
+    /// field of modulo^2 elements when modulo is a prime that 5 isn't a square of. Needs an odd
+    /// modulo, since φ = (1 + √5) / 2. Runtime O(log(n))
+    FiniteFieldBinet,
+    /// fib(n) as the φ coefficient of φ^n in Z[φ], see `GoldenInt`. Runtime O(log(n)), with half
+    /// the multiplications of `MatrixPower`.
+    GoldenPower,
+}
+
+impl Algorithm {
+    /// Every algorithm, in declaration order.
+    pub const ALL: [Algorithm; 5] = [
+        Algorithm::MatrixPower,
+        Algorithm::FastDoubling,
+        Algorithm::PisanoReduce,
+        Algorithm::FiniteFieldBinet,
+        Algorithm::GoldenPower,
+    ];
+}
+
+
+/// Finds the nth fibonacci number with modulo, using the given algorithm.
+///
+/// Returns `FibError::ZeroModulus` for a 0 modulo, and `FibError::InvalidArgument` for an even
+/// modulo with `Algorithm::FiniteFieldBinet`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::{fib_with_mod_using, Algorithm};
+///
+/// for &algorithm in Algorithm::ALL.iter() {
+///     assert_eq!(Ok(55), fib_with_mod_using(algorithm, 10, 1_000_001));
+/// }
+/// assert!(fib_with_mod_using(Algorithm::FiniteFieldBinet, 10, 100).is_err());
+/// ```
+pub fn fib_with_mod_using(algorithm: Algorithm, n: u64, modulo: u64) -> Result<u64, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    match algorithm {
+        Algorithm::MatrixPower => try_fib_with_mod(n, modulo),
+        Algorithm::FastDoubling => Ok(fib_pair_u128(n as u128, modulo).0),
+        Algorithm::PisanoReduce => match period_cache::pisano_period(modulo) {
+            Ok(period) => try_fib_with_mod(n % period, modulo),
+            // The period is past u64::MAX, so n is already below it.
+            Err(FibError::OverflowRisk) => try_fib_with_mod(n, modulo),
+            Err(err) => Err(err),
+        },
+        Algorithm::FiniteFieldBinet => {
+            if modulo.is_multiple_of(2) {
+                return Err(FibError::InvalidArgument("FiniteFieldBinet needs an odd modulo"));
+            }
+            Ok(binet(n, modulo))
+        }
+        Algorithm::GoldenPower => Ok(GoldenInt::phi(modulo).pow(n).b()),
+    }
+}
+
+
+/// Checks that two independent algorithms, matrix power and fast doubling, agree on fib(n) with
+/// modulo. False for a 0 modulo, which has no result to agree on. Runtime O(log(n)), about twice
+/// that of `fib_with_mod`
+///
+/// For pipelines where a silently wrong answer costs more than the second computation.
+///
+/// # Examples
+/// ```
+/// assert!(fast_fibonacci::verify(1_000_000_000_000_000, 1_000_000));
+/// assert!(!fast_fibonacci::verify(10, 0));
+/// ```
+pub fn verify(n: u64, modulo: u64) -> bool {
+    match fib_with_mod_using(Algorithm::MatrixPower, n, modulo) {
+        Ok(fib) => fib_with_mod_using(Algorithm::FastDoubling, n, modulo) == Ok(fib),
+        Err(_) => false,
+    }
+}
+
+
+/// BigUint version of verify. Checks that the matrix power of `bigfib_with_mod`, or GMP with the
+/// `rug` feature, and the fast doubling of `BigFibComputation` agree on fib(n) with modulo.
+/// Runtime O(log(n))
+///
+/// # Examples
+/// ```
+/// use num_bigint::BigUint;
+///
+/// let n = num::pow(BigUint::from(10u32), 100);
+/// assert!(fast_fibonacci::bigverify(&n, &BigUint::from(1_000_000_007u32)));
+/// assert!(!fast_fibonacci::bigverify(&n, &BigUint::from(0u32)));
+/// ```
+pub fn bigverify(n: &BigUint, modulo: &BigUint) -> bool {
+    match (try_bigfib_with_mod(n, modulo), BigFibComputation::new(n, modulo)) {
+        (Ok(fib), Ok(mut computation)) => computation.run() == fib,
+        _ => false,
+    }
+}
+
+
+/// A rule of `Dispatcher`: use `algorithm` for queries that meet every condition.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Crossover {
+    /// The algorithm for the queries that meet the conditions below.
+    pub algorithm: Algorithm,
+    /// n has at least this many bits.
+    pub min_index_bits: u32,
+    /// modulo has at most this many bits.
+    pub max_modulo_bits: u32,
+    /// The dispatcher has seen the modulo at least this many times, counting the current query.
+    pub min_modulo_uses: u64,
+    /// The modulo is hinted to be prime, see `Hints`.
+    pub prime_modulo: bool,
+}
+
+/// The crossovers of `Dispatcher::new`.
+///
+/// For a prime modulo p, the pisano period divides p - 1 or 2(p + 1), so n can be reduced without
+/// factoring. Otherwise the period costs O(sqrt(modulo)) to find, which is worth it for small
+/// moduli that come back. Everything else uses fast doubling.
+pub const DEFAULT_CROSSOVERS: [Crossover; 2] = [
+    Crossover {
+        algorithm: Algorithm::PisanoReduce,
+        min_index_bits: 40,
+        max_modulo_bits: 32,
+        min_modulo_uses: 1,
+        prime_modulo: true,
+    },
+    Crossover {
+        algorithm: Algorithm::PisanoReduce,
+        min_index_bits: 40,
+        max_modulo_bits: 24,
+        min_modulo_uses: 8,
+        prime_modulo: false,
+    },
+];
+
+/// How many moduli a `Dispatcher` keeps context for, by default.
+pub const MAX_TRACKED_MODULI: usize = 10_000;
+
+// Config::tuned and Dispatcher::with_config keep tables of at most 2^12 fibonacci numbers per
+// modulo.
+const MAX_ITERATION_THRESHOLD_BITS: u32 = 12;
+
+
+/// The thresholds of a `Dispatcher`. The defaults are `DEFAULT_CROSSOVERS`,
+/// `MAX_TRACKED_MODULI` and `ITERATION_THRESHOLD`, and `tuned` measures them on the current
+/// machine instead.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::{Config, Dispatcher};
+///
+/// let config = Config { max_tracked_moduli: 100, iteration_threshold: 16, ..Config::default() };
+/// let mut dispatcher = Dispatcher::with_config(config);
+/// assert_eq!(Ok(546_875), dispatcher.fib_with_mod(1_000_000_000_000_000, 1_000_000));
+/// assert_eq!(Ok(610), dispatcher.fib_with_mod(15, 1_000_000));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+    /// Tried in order, see `Dispatcher::with_crossovers`.
+    pub crossovers: Vec<Crossover>,
+    /// Past this many moduli, the dispatcher forgets their uses and periods.
+    pub max_tracked_moduli: usize,
+    /// Queries with n below this are answered from a table of that many fibonacci numbers per
+    /// modulo, filled by additions. 0 turns the tables off, and larger values trade memory for
+    /// speed, up to 4096: `Dispatcher::with_config` lowers larger values to that, since every
+    /// tracked modulo may get a table.
+    pub iteration_threshold: u64,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            crossovers: DEFAULT_CROSSOVERS.to_vec(),
+            max_tracked_moduli: MAX_TRACKED_MODULI,
+            iteration_threshold: ITERATION_THRESHOLD,
+        }
+    }
+}
+
+impl Config {
+    /// Times fast doubling against finding pisano periods on this machine, and sets the
+    /// crossover to `Algorithm::PisanoReduce` for moduli that aren't hinted prime from that:
+    /// the largest modulo size whose period pays for itself within 64 queries, after as many
+    /// queries as it takes. The iteration threshold is the first power of two n at which adding
+    /// up loses to fast doubling. Takes tens of milliseconds.
+    ///
+    /// The crossover for prime moduli is kept, since reducing by a multiple of their period
+    /// costs nothing to find.
+    pub fn tuned() -> Config {
+        // The time a full 64 bit fast doubling takes, less one over the bits left after reducing.
+        let doubling = |n: u64, modulo: u64| {
+            fastest(|| {
+                for i in 0..256 {
+                    black_box(fib_pair_u128(black_box(n - i) as u128, modulo));
+                }
+            }) / 256
+        };
+        let period = |modulo: u64| {
+            fastest(|| {
+                black_box(try_pisano_period(black_box(modulo)).ok());
+            })
+        };
+        let iteration_loses = |n: u64| {
+            let iteration = fastest(|| {
+                for i in 0..16 {
+                    black_box(fib_by_iteration(black_box(n - i), 1_000_000_007));
+                }
+            });
+            let doubling = fastest(|| {
+                for i in 0..16 {
+                    black_box(fib_pair_u128(black_box(n - i) as u128, 1_000_000_007));
+                }
+            });
+            iteration > doubling
+        };
+        Config::tuned_from(period, doubling, iteration_loses)
+    }
+
+    // `tuned` from the time it takes to find the pisano period of a modulo, the time fast
+    // doubling takes for n and modulo, and whether adding up to n loses to fast doubling.
+    fn tuned_from<P, D, I>(mut period: P, mut doubling: D, mut iteration_loses: I) -> Config
+    where
+        P: FnMut(u64) -> Duration,
+        D: FnMut(u64, u64) -> Duration,
+        I: FnMut(u64) -> bool,
+    {
+        let mut composite = None;
+        for modulo_bits in (8..=32).step_by(4) {
+            let modulo = (1..).map(|i| (1u64 << modulo_bits) - i).find(|&m| !is_prime(m)).unwrap();
+            let period = period(modulo);
+            // Periods are at most 6 * modulo, under 3 more bits.
+            let saved = doubling(u64::MAX, modulo).saturating_sub(doubling((1 << (modulo_bits + 3)) - 1, modulo));
+            let uses = period.as_nanos().checked_div(saved.as_nanos()).map(|uses| uses as u64 + 1);
+            match uses {
+                Some(uses) if uses <= 64 => composite = Some((modulo_bits, uses)),
+                _ => break,
+            }
+        }
+
+        let iteration_threshold = (4..MAX_ITERATION_THRESHOLD_BITS)
+            .map(|bits| 1u64 << bits)
+            .find(|&n| iteration_loses(n))
+            .unwrap_or(1 << MAX_ITERATION_THRESHOLD_BITS);
+
+        let mut config = Config { iteration_threshold, ..Config::default() };
+        config.crossovers.retain(|crossover| crossover.prime_modulo);
+        if let Some((max_modulo_bits, min_modulo_uses)) = composite {
+            config.crossovers.push(Crossover {
+                algorithm: Algorithm::PisanoReduce,
+                min_index_bits: 40,
+                max_modulo_bits,
+                min_modulo_uses,
+                prime_modulo: false,
+            });
+        }
+        config
+    }
+}
+
+
+/// What the caller knows about a query besides n and modulo.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Hints {
+    /// The modulo is prime. A wrong hint gives wrong results.
+    pub prime_modulo: bool,
+}
+
+
+/// Picks an algorithm per query from a table of crossovers, and keeps per modulo context: how
+/// often each modulo was used, the pisano periods it found, and a table of the fibonacci
+/// numbers below `Config::iteration_threshold`, which answers queries with small n without any
+/// arithmetic. Past `MAX_TRACKED_MODULI` moduli, the context starts over rather than growing
+/// without bound.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::{Algorithm, Dispatcher, Hints};
+///
+/// let mut dispatcher = Dispatcher::new();
+/// let prime = Hints { prime_modulo: true };
+/// assert_eq!(Algorithm::FastDoubling, dispatcher.choose(10, 1_000_000_007, prime));
+/// assert_eq!(Algorithm::PisanoReduce, dispatcher.choose(1 << 50, 1_000_000_007, prime));
+/// assert_eq!(Ok(546_875), dispatcher.fib_with_mod(1_000_000_000_000_000, 1_000_000));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Dispatcher {
+    crossovers: Vec<Crossover>,
+    max_tracked_moduli: usize,
+    iteration_threshold: u64,
+    uses: HashMap<u64, u64>,
+    periods: HashMap<u64, u64>,
+    small_fibs: HashMap<u64, Vec<u64>>,
+    table: Option<PisanoTable>,
+}
+
+impl Default for Dispatcher {
+    fn default() -> Self {
+        Dispatcher::new()
+    }
+}
+
+impl Dispatcher {
+    /// A dispatcher with `DEFAULT_CROSSOVERS`.
+    pub fn new() -> Dispatcher {
+        Dispatcher::with_crossovers(DEFAULT_CROSSOVERS.to_vec())
+    }
+
+    /// A dispatcher with the given crossovers, tried in order. Crossovers to
+    /// `Algorithm::FiniteFieldBinet` never match an even modulo. Queries that match none use
+    /// `Algorithm::FastDoubling`.
+    pub fn with_crossovers(crossovers: Vec<Crossover>) -> Dispatcher {
+        Dispatcher::with_config(Config { crossovers, ..Config::default() })
+    }
+
+    /// A dispatcher with the given thresholds, e.g. from `Config::tuned`, and an iteration
+    /// threshold of at most 4096.
+    pub fn with_config(config: Config) -> Dispatcher {
+        Dispatcher {
+            crossovers: config.crossovers,
+            max_tracked_moduli: config.max_tracked_moduli,
+            iteration_threshold: config.iteration_threshold.min(1 << MAX_ITERATION_THRESHOLD_BITS),
+            uses: HashMap::new(),
+            periods: HashMap::new(),
+            small_fibs: HashMap::new(),
+            table: None,
+        }
+    }
+
+    /// The crossovers, tried in order.
+    pub fn crossovers(&self) -> &[Crossover] {
+        &self.crossovers
+    }
+
+    /// Past this many moduli, the dispatcher forgets their uses and periods.
+    pub fn max_tracked_moduli(&self) -> usize {
+        self.max_tracked_moduli
+    }
+
+    /// The n below which queries are answered from a table per modulo, at most 4096, see
+    /// `Config::iteration_threshold`.
+    pub fn iteration_threshold(&self) -> u64 {
+        self.iteration_threshold
+    }
+
+    /// Takes the pisano periods of the moduli in the table instead of finding them. Those moduli
+    /// count as used often, so crossovers with `min_modulo_uses` apply from their first query.
+    pub fn set_pisano_table(&mut self, table: PisanoTable) {
+        self.table = Some(table);
+    }
+
+    /// The table set by `set_pisano_table`, if any.
+    pub fn pisano_table(&self) -> Option<&PisanoTable> {
+        self.table.as_ref()
+    }
+
+    /// The algorithm the next query with n and modulo would use.
+    pub fn choose(&self, n: u64, modulo: u64, hints: Hints) -> Algorithm {
+        let uses = match self.table.as_ref().and_then(|table| table.period(modulo)) {
+            Some(_) => u64::MAX,
+            None => self.uses.get(&modulo).copied().unwrap_or(0) + 1,
+        };
+        let index_bits = 64 - n.leading_zeros();
+        let modulo_bits = 64 - modulo.leading_zeros();
+        self.crossovers
+            .iter()
+            .find(|crossover| {
+                index_bits >= crossover.min_index_bits
+                    && modulo_bits <= crossover.max_modulo_bits
+                    && uses >= crossover.min_modulo_uses
+                    && (hints.prime_modulo || !crossover.prime_modulo)
+                    && (crossover.algorithm != Algorithm::FiniteFieldBinet || !modulo.is_multiple_of(2))
+            })
+            .map_or(Algorithm::FastDoubling, |crossover| crossover.algorithm)
+    }
+
+    /// Finds the nth fibonacci number with modulo, with the algorithm `choose` picks.
+    pub fn fib_with_mod(&mut self, n: u64, modulo: u64) -> Result<u64, FibError> {
+        self.fib_with_mod_hinted(n, modulo, Hints::default())
+    }
+
+    /// Like `fib_with_mod`, with hints about the modulo.
+    pub fn fib_with_mod_hinted(&mut self, n: u64, modulo: u64, hints: Hints) -> Result<u64, FibError> {
+        if modulo == 0 {
+            return Err(FibError::ZeroModulus);
+        }
+        if n < self.iteration_threshold {
+            self.track(modulo);
+            let len = self.iteration_threshold as usize;
+            let small_fibs = self.small_fibs.entry(modulo).or_insert_with(|| small_fib_table(modulo, len));
+            return Ok(small_fibs[n as usize]);
+        }
+        let algorithm = self.choose(n, modulo, hints);
+        self.track(modulo);
+        if algorithm != Algorithm::PisanoReduce {
+            return fib_with_mod_using(algorithm, n, modulo);
+        }
+
+        let known_period = self.table.as_ref().and_then(|table| table.period(modulo));
+        let period_multiple = match known_period.or_else(|| self.periods.get(&modulo).copied()) {
+            Some(period) => period as u128,
+            None if hints.prime_modulo => prime_period_multiple(modulo),
+            None => match period_cache::pisano_period(modulo) {
+                Ok(period) => *self.periods.entry(modulo).or_insert(period) as u128,
+                Err(FibError::OverflowRisk) => u64::MAX as u128 + 1,
+                Err(err) => return Err(err),
+            },
+        };
+        Ok(fib_pair_u128(n as u128 % period_multiple, modulo).0)
+    }
+
+    // Counts a use of the modulo, forgetting every modulo first when there are too many.
+    fn track(&mut self, modulo: u64) {
+        if self.uses.len() >= self.max_tracked_moduli && !self.uses.contains_key(&modulo) {
+            self.uses.clear();
+            self.periods.clear();
+            self.small_fibs.clear();
+        }
+        *self.uses.entry(modulo).or_insert(0) += 1;
+    }
+}
+
+
+// The shortest of a few runs of f.
+fn fastest<F: FnMut()>(mut f: F) -> Duration {
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+// a + b√5, with a and b reduced modulo an odd modulo.
+type Sqrt5 = (u64, u64);
+
+fn binet(n: u64, modulo: u64) -> u64 {
+    let half = (modulo / 2 + 1) % modulo;
+    // φ^n = a + b√5 and ψ^n = a - b√5, so (φ^n - ψ^n) / √5 = 2b.
+    let (_, b) = sqrt5_pow((half, half), n, modulo);
+    add_mod(b, b, modulo)
+}
+
+
+fn sqrt5_pow(base: Sqrt5, mut exponent: u64, modulo: u64) -> Sqrt5 {
+    let mut result = (1 % modulo, 0);
+    let mut base = base;
+    while exponent > 0 {
+        if exponent % 2 == 1 {
+            result = sqrt5_mul(result, base, modulo);
+        }
+        base = sqrt5_mul(base, base, modulo);
+        exponent /= 2;
+    }
+    result
+}
+
+
+fn sqrt5_mul((a, b): Sqrt5, (c, d): Sqrt5, modulo: u64) -> Sqrt5 {
+    let five_bd = mul_mod(5 % modulo, mul_mod(b, d, modulo), modulo);
+    (
+        add_mod(mul_mod(a, c, modulo), five_bd, modulo),
+        add_mod(mul_mod(a, d, modulo), mul_mod(b, c, modulo), modulo),
+    )
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::algorithm::*;
+    use crate::fib_with_mod;
+
+    #[test]
+    fn test_algorithms_agree() {
+        for &modulo in &[1, 2, 3, 5, 10, 1_000, 1_000_000_007, 998_244_353 * 3, u64::MAX] {
+            for &n in &[0, 1, 2, 3, 10, 99, 1_000_000_000_000_000, u64::MAX - 1, u64::MAX] {
+                for &algorithm in Algorithm::ALL.iter() {
+                    if algorithm == Algorithm::FiniteFieldBinet && modulo.is_multiple_of(2) {
+                        continue;
+                    }
+                    assert_eq!(
+                        fib_with_mod_using(algorithm, n, modulo),
+                        Ok(fib_with_mod(n, modulo)),
+                        "{:?}, n = {}, modulo = {}", algorithm, n, modulo
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        for &algorithm in Algorithm::ALL.iter() {
+            assert_eq!(fib_with_mod_using(algorithm, 10, 0), Err(FibError::ZeroModulus));
+        }
+        assert!(matches!(
+            fib_with_mod_using(Algorithm::FiniteFieldBinet, 10, 10),
+            Err(FibError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn test_verify() {
+        for &modulo in &[1, 2, 10, 1_000_000_007, u64::MAX] {
+            for &n in &[0, 1, 2, 99, 1_000_000_000_000_000, u64::MAX] {
+                assert!(verify(n, modulo), "n = {}, modulo = {}", n, modulo);
+                assert!(bigverify(&BigUint::from(n), &BigUint::from(modulo)), "n = {}, modulo = {}", n, modulo);
+            }
+        }
+        let huge = num::pow(BigUint::from(3u32), 1_000);
+        assert!(bigverify(&huge, &(&huge - 1u32)));
+        assert!(!verify(5, 0));
+        assert!(!bigverify(&BigUint::from(5u32), &BigUint::from(0u32)));
+    }
+
+    #[test]
+    fn test_dispatcher() {
+        let mut dispatcher = Dispatcher::new();
+        let prime = Hints { prime_modulo: true };
+        for &modulo in &[2, 3, 5, 7, 11, 1_000_000_007, 18_446_744_073_709_551_557] {
+            for &n in &[0, 1, 10, 1 << 45, 1_000_000_000_000_000, u64::MAX] {
+                assert_eq!(dispatcher.fib_with_mod_hinted(n, modulo, prime), Ok(fib_with_mod(n, modulo)));
+            }
+        }
+
+        assert_eq!(dispatcher.choose(1 << 45, 1_000, Hints::default()), Algorithm::FastDoubling);
+        for _ in 0..10 {
+            for &n in &[0, 1 << 45, u64::MAX] {
+                assert_eq!(dispatcher.fib_with_mod(n, 1_000), Ok(fib_with_mod(n, 1_000)));
+            }
+        }
+        assert_eq!(dispatcher.choose(1 << 45, 1_000, Hints::default()), Algorithm::PisanoReduce);
+        assert_eq!(dispatcher.periods.get(&1_000), Some(&1_500));
+        assert_eq!(dispatcher.fib_with_mod(10, 0), Err(FibError::ZeroModulus));
+        assert_eq!(dispatcher.small_fibs.get(&1_000).map(Vec::len), Some(ITERATION_THRESHOLD as usize));
+        for n in 0..ITERATION_THRESHOLD {
+            assert_eq!(dispatcher.fib_with_mod(n, 1), Ok(0));
+            assert_eq!(dispatcher.fib_with_mod(n, u64::MAX), Ok(fib_with_mod(n, u64::MAX)));
+        }
+
+        let mut binet = Dispatcher::with_crossovers(vec![Crossover {
+            algorithm: Algorithm::FiniteFieldBinet,
+            min_index_bits: 0,
+            max_modulo_bits: 64,
+            min_modulo_uses: 0,
+            prime_modulo: false,
+        }]);
+        assert_eq!(binet.choose(10, 99, Hints::default()), Algorithm::FiniteFieldBinet);
+        assert_eq!(binet.fib_with_mod(10, 99), Ok(55));
+        assert_eq!(binet.choose(10, 100, Hints::default()), Algorithm::FastDoubling);
+        assert_eq!(binet.fib_with_mod(10, 100), Ok(55));
+    }
+
+    #[test]
+    fn test_dispatcher_with_pisano_table() {
+        let mut dispatcher = Dispatcher::new();
+        dispatcher.set_pisano_table(PisanoTable::new(1_000));
+        assert_eq!(dispatcher.choose(1 << 45, 1_000, Hints::default()), Algorithm::PisanoReduce);
+        assert_eq!(dispatcher.choose(1 << 45, 1_001, Hints::default()), Algorithm::FastDoubling);
+        for &modulo in &[1, 10, 999, 1_000, 1_001] {
+            for &n in &[0, 1 << 45, u64::MAX] {
+                assert_eq!(dispatcher.fib_with_mod(n, modulo), Ok(fib_with_mod(n, modulo)));
+            }
+        }
+        // The table's periods aren't copied into the cache.
+        assert!(dispatcher.periods.is_empty());
+        assert_eq!(dispatcher.pisano_table().map(PisanoTable::bound), Some(1_000));
+    }
+
+    #[test]
+    fn test_config() {
+        let mut small = Dispatcher::with_config(Config { max_tracked_moduli: 3, ..Config::default() });
+        assert_eq!(small.max_tracked_moduli(), 3);
+        for modulo in 1..=4 {
+            small.fib_with_mod(1 << 45, modulo).unwrap();
+        }
+        assert_eq!(small.uses.len(), 1);
+
+        for &iteration_threshold in &[0, 1, 2, 300] {
+            let mut dispatcher = Dispatcher::with_config(Config { iteration_threshold, ..Config::default() });
+            assert_eq!(dispatcher.iteration_threshold(), iteration_threshold);
+            for &modulo in &[1, 1_000, u64::MAX] {
+                for n in 0..400 {
+                    assert_eq!(dispatcher.fib_with_mod(n, modulo), Ok(fib_with_mod(n, modulo)));
+                }
+            }
+            let table_len = dispatcher.small_fibs.get(&1_000).map_or(0, Vec::len);
+            assert_eq!(table_len as u64, iteration_threshold);
+        }
+        // Lowered to 2^12 entries per modulo, rather than allocating 2^40.
+        let mut huge = Dispatcher::with_config(Config { iteration_threshold: 1 << 40, ..Config::default() });
+        assert_eq!(huge.iteration_threshold(), 1 << MAX_ITERATION_THRESHOLD_BITS);
+        assert_eq!(huge.fib_with_mod(10, 1_000), Ok(55));
+        assert_eq!(huge.fib_with_mod(1 << 20, 1_000), Ok(fib_with_mod(1 << 20, 1_000)));
+    }
+
+    #[test]
+    fn test_tuned_from() {
+        // Periods take 1µs per modulo bit, and doubling saves 1µs per bit of n left.
+        let period = |modulo: u64| Duration::from_micros(64 - modulo.leading_zeros() as u64);
+        let doubling = |n: u64, _| Duration::from_micros(64 - n.leading_zeros() as u64);
+        let config = Config::tuned_from(period, doubling, |n| n >= 256);
+        assert_eq!(config.iteration_threshold, 256);
+        assert_eq!(config.max_tracked_moduli, MAX_TRACKED_MODULI);
+        assert!(config.crossovers.contains(&DEFAULT_CROSSOVERS[0]));
+        // 32 bit moduli, whose periods cost 32µs and save 64 - 35 = 29µs per query, take 2 uses.
+        let crossover = Crossover {
+            algorithm: Algorithm::PisanoReduce,
+            min_index_bits: 40,
+            max_modulo_bits: 32,
+            min_modulo_uses: 2,
+            prime_modulo: false,
+        };
+        assert_eq!(config.crossovers.last(), Some(&crossover));
+        assert!(config.crossovers[..config.crossovers.len() - 1].iter().all(|crossover| crossover.prime_modulo));
+
+        // Periods that never pay for themselves, and additions that always win.
+        let config = Config::tuned_from(|_| Duration::from_secs(1), doubling, |_| false);
+        assert_eq!(config.iteration_threshold, 1 << MAX_ITERATION_THRESHOLD_BITS);
+        assert!(config.crossovers.iter().all(|crossover| crossover.prime_modulo));
+    }
+
+    // Benchmarks this machine, so it's run on demand: cargo test -- --ignored
+    #[test]
+    #[ignore]
+    fn test_tuned() {
+        let tuned = Config::tuned();
+        assert!(tuned.iteration_threshold.is_power_of_two());
+        assert!(tuned.crossovers.contains(&DEFAULT_CROSSOVERS[0]));
+        let mut dispatcher = Dispatcher::with_config(tuned);
+        for &modulo in &[1, 10, 1_000, 1 << 20, 1_000_000_007] {
+            for &n in &[0, 1 << 45, u64::MAX] {
+                for _ in 0..3 {
+                    assert_eq!(dispatcher.fib_with_mod(n, modulo), Ok(fib_with_mod(n, modulo)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_dispatcher_forgets_moduli() {
+        let mut dispatcher = Dispatcher::new();
+        for modulo in 1..=MAX_TRACKED_MODULI as u64 {
+            dispatcher.fib_with_mod(1 << 45, modulo).unwrap();
+        }
+        assert_eq!(dispatcher.uses.len(), MAX_TRACKED_MODULI);
+        dispatcher.fib_with_mod(1 << 45, 1).unwrap();
+        assert_eq!(dispatcher.uses.len(), MAX_TRACKED_MODULI);
+        dispatcher.fib_with_mod(1 << 45, MAX_TRACKED_MODULI as u64 + 1).unwrap();
+        assert_eq!(dispatcher.uses.len(), 1);
+    }
+}
@@ -0,0 +1,263 @@
+//! Floating point approximations of fibonacci numbers, based on Binet's formula
+//! fib(n) = (φ^n - ψ^n) / √5.
+
+#[cfg(feature = "bigfloat")]
+use astro_float::{BigFloat, Consts, Radix, RoundingMode, Word};
+#[cfg(feature = "bigfloat")]
+use num_bigint::BigUint;
+#[cfg(feature = "bigfloat")]
+use num::{FromPrimitive, ToPrimitive};
+
+// frac(log10(φ)) as a 0.128 fixed point number. log10(φ) < 1, so this is all of it.
+const LOG10_PHI_FIXED: u128 = 0x358036c82451b7f365d3db23845599f5;
+const LOG10_SQRT_5: f64 = 0.349_485_002_168_009_4;
+
+// fib(78) is the largest fibonacci number below 2^53, so everything up to it is exact in f64.
+const EXACT_F64_LIMIT: u64 = 78;
+// fib(1477) overflows f64::MAX.
+const F64_LIMIT: u64 = 1476;
+
+
+/// Approximates the nth fibonacci number as an f64. Runtime O(1)
+///
+/// Exact for n <= 78. Above that, the relative error is below 1e-14.
+/// Returns `f64::INFINITY` for n > 1476, since fib(n) no longer fits in an f64;
+/// use `fib_approx_sci` to get the magnitude of those.
+///
+/// # Examples
+/// ```
+/// assert_eq!(55.0, fast_fibonacci::fib_approx(10));
+/// assert!((fast_fibonacci::fib_approx(100) / 3.542248481792619e20 - 1.0).abs() < 1e-14);
+/// assert_eq!(f64::INFINITY, fast_fibonacci::fib_approx(1477));
+/// ```
+pub fn fib_approx(n: u64) -> f64 {
+    if n <= EXACT_F64_LIMIT {
+        return exact_f64(n);
+    }
+    if n > F64_LIMIT {
+        return f64::INFINITY;
+    }
+    let (mantissa, exponent) = fib_approx_sci(n);
+    mantissa * 10f64.powi(exponent as i32)
+}
+
+
+/// Approximates the nth fibonacci number in scientific notation. Runtime O(1)
+///
+/// Returns (mantissa, exponent) such that fib(n) ≈ mantissa * 10^exponent, with
+/// 1 <= mantissa < 10 (or (0.0, 0) for n = 0). Works for every u64 n, far beyond the
+/// range of f64. The relative error of the mantissa is below 1e-14.
+///
+/// # Examples
+/// ```
+/// let (mantissa, exponent) = fast_fibonacci::fib_approx_sci(1_000);
+/// assert_eq!(208, exponent);
+/// assert!((mantissa - 4.3466557686937456).abs() < 1e-13);
+///
+/// let (mantissa, exponent) = fast_fibonacci::fib_approx_sci(1_000_000_000_000_000_000);
+/// assert_eq!(208_987_640_249_978_733, exponent);
+/// assert!((mantissa - 2.6289788186792204).abs() < 1e-13);
+/// ```
+pub fn fib_approx_sci(n: u64) -> (f64, u64) {
+    if n == 0 {
+        return (0.0, 0);
+    }
+    if n <= EXACT_F64_LIMIT {
+        let mut mantissa = exact_f64(n);
+        let mut exponent = 0;
+        while mantissa >= 10.0 {
+            mantissa /= 10.0;
+            exponent += 1;
+        }
+        return (mantissa, exponent);
+    }
+
+    // log10(fib(n)) = n * log10(φ) - log10(√5), since ψ^n is negligible here.
+    let (mut exponent, log_frac) = mul_log10_phi(n);
+    let mut log_frac = log_frac - LOG10_SQRT_5;
+    if log_frac < 0.0 {
+        log_frac += 1.0;
+        exponent -= 1;
+    }
+    let mut mantissa = 10f64.powf(log_frac);
+    if mantissa >= 10.0 {
+        mantissa /= 10.0;
+        exponent += 1;
+    }
+    (mantissa, exponent)
+}
+
+
+/// Finds the leading decimal digits of the nth fibonacci number, for arbitrarily large n.
+/// Runtime O(M(log(n) + digits)), with M the big float multiplication cost.
+///
+/// Evaluates Binet's formula with arbitrary precision floats, so the result doesn't depend on
+/// the matrix code at all. Returns (leading digits, exponent) such that
+/// fib(n) = d.ddd... * 10^exponent, where `digits` is the number of significant digits
+/// returned (fewer when fib(n) is shorter than that). The digits are truncated, not rounded.
+///
+/// Requires the `bigfloat` feature.
+///
+/// # Examples
+/// ```
+/// use num::FromPrimitive;
+///
+/// let (digits, exponent) = fast_fibonacci::bigfib_leading_digits(
+///     &FromPrimitive::from_u64(1_000_000_000_000_000_000).unwrap(),
+///     20
+/// );
+/// assert_eq!("26289788186792204674", digits);
+/// assert_eq!(FromPrimitive::from_u64(208_987_640_249_978_733), Some(exponent));
+/// ```
+#[cfg(feature = "bigfloat")]
+pub fn bigfib_leading_digits(n: &BigUint, digits: usize) -> (String, BigUint) {
+    if digits == 0 {
+        return (String::new(), bigfib_leading_digits(n, 1).1);
+    }
+    // Below this, ψ^n still matters and fib(n) is cheap to find exactly.
+    let exact_limit = BigUint::from(digits) * 5u32 + 200u32;
+    if n < &exact_limit {
+        let exact = crate::exact_bigfib_pair(n.to_u64().unwrap()).0.to_str_radix(10);
+        let exponent = FromPrimitive::from_usize(exact.len() - 1).unwrap();
+        return (exact.chars().take(digits).collect(), exponent);
+    }
+
+    let p = digits
+        .checked_mul(4)
+        .and_then(|bits| bits.checked_add(n.bits() + 128))
+        .expect("too many digits for the float precision");
+    let rm = RoundingMode::ToEven;
+    let mut cc = Consts::new().expect("failed to allocate big float constants");
+
+    let big_n = BigFloat::parse(&n.to_str_radix(16), Radix::Hex, p, rm, &mut cc);
+    let sqrt_5 = BigFloat::from_u64(5, p).sqrt(p, rm);
+    let phi = sqrt_5.add(&BigFloat::from_u64(1, p), p, rm).div(&BigFloat::from_u64(2, p), p, rm);
+
+    // log10(fib(n)) = n * log10(φ) - log10(√5), since ψ^n is negligible here.
+    let log_fib = big_n
+        .mul(&phi.log10(p, rm, &mut cc), p, rm)
+        .sub(&sqrt_5.log10(p, rm, &mut cc), p, rm);
+    let mut exponent = bigfloat_floor(&log_fib.int());
+
+    let ten = BigFloat::from_u64(10, p);
+    let mantissa = ten.pow(&log_fib.fract(), p, rm, &mut cc);
+    let scaled = mantissa.mul(&ten.powi(digits - 1, p, rm), p, rm);
+    let mut leading = bigfloat_floor(&scaled).to_str_radix(10);
+    if leading.len() > digits {
+        leading.truncate(digits);
+        exponent += 1u32;
+    }
+    (leading, exponent)
+}
+
+
+// Converts a non-negative big float to a BigUint, discarding the fractional part.
+#[cfg(feature = "bigfloat")]
+fn bigfloat_floor(x: &BigFloat) -> BigUint {
+    let (words, _, _, exponent, _) = match x.as_raw_parts() {
+        Some(parts) => parts,
+        None => return FromPrimitive::from_u64(0).unwrap(),
+    };
+    let mut mantissa: BigUint = FromPrimitive::from_u64(0).unwrap();
+    for word in words.iter().rev() {
+        mantissa = (mantissa << Word::BITS as usize) + BigUint::from(*word);
+    }
+    // x = 0.mantissa * 2^exponent
+    let mantissa_bits = (words.len() * Word::BITS as usize) as i64;
+    let shift = exponent as i64 - mantissa_bits;
+    if shift >= 0 {
+        mantissa << shift as usize
+    } else {
+        mantissa >> (-shift) as usize
+    }
+}
+
+
+// Splits n * log10(φ) into its integer and fractional parts. Done in fixed point so that
+// the fraction keeps full precision even when n * log10(φ) is far beyond 2^53.
+fn mul_log10_phi(n: u64) -> (u64, f64) {
+    let n = n as u128;
+    let lo = n * (LOG10_PHI_FIXED as u64 as u128);
+    let hi = n * (LOG10_PHI_FIXED >> 64);
+    let top = hi + (lo >> 64);
+    let int_part = (top >> 64) as u64;
+    let frac = (top as u64) as f64 / 18_446_744_073_709_551_616.0;
+    (int_part, frac)
+}
+
+
+fn exact_f64(n: u64) -> f64 {
+    let (mut a, mut b) = (0u64, 1u64);
+    for _ in 0..n {
+        let next = a + b;
+        a = b;
+        b = next;
+    }
+    a as f64
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use num_bigint::BigUint;
+    use num::FromPrimitive;
+
+    fn exact(n: u64) -> BigUint {
+        let mut a: BigUint = FromPrimitive::from_u64(0).unwrap();
+        let mut b: BigUint = FromPrimitive::from_u64(1).unwrap();
+        for _ in 0..n {
+            let next = &a + &b;
+            a = b;
+            b = next;
+        }
+        a
+    }
+
+    #[test]
+    fn test_fib_approx_matches_exact() {
+        for n in 0..=1476 {
+            let expected: f64 = exact(n).to_str_radix(10).parse().unwrap();
+            let approx = fib_approx(n);
+            if n <= 78 {
+                assert_eq!(approx, expected);
+            } else {
+                assert!((approx / expected - 1.0).abs() < 1e-14, "n = {}", n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fib_approx_sci() {
+        assert_eq!(fib_approx_sci(0), (0.0, 0));
+        assert_eq!(fib_approx_sci(1), (1.0, 0));
+        assert_eq!(fib_approx_sci(7).1, 1);
+
+        for &n in &[79, 100, 1000, 1476, 5000] {
+            let digits = exact(n).to_str_radix(10);
+            let expected: f64 = format!("{}.{}", &digits[..1], &digits[1..17]).parse().unwrap();
+            let (mantissa, exponent) = fib_approx_sci(n);
+            assert_eq!(exponent as usize, digits.len() - 1);
+            assert!((mantissa / expected - 1.0).abs() < 1e-14, "n = {}", n);
+        }
+    }
+
+    #[cfg(feature = "bigfloat")]
+    #[test]
+    fn test_bigfib_leading_digits() {
+        let (digits, exponent) = bigfib_leading_digits(&FromPrimitive::from_u64(10).unwrap(), 5);
+        assert_eq!((digits.as_str(), exponent), ("55", FromPrimitive::from_u64(1).unwrap()));
+
+        for &n in &[79u64, 1000, 5000, 12345] {
+            let expected = exact(n).to_str_radix(10);
+            let (digits, exponent) = bigfib_leading_digits(&FromPrimitive::from_u64(n).unwrap(), 50);
+            assert_eq!(digits, &expected[..expected.len().min(50)], "n = {}", n);
+            assert_eq!(exponent, FromPrimitive::from_usize(expected.len() - 1).unwrap());
+        }
+
+        for &n in &[0u64, 10, 1_000_000] {
+            let (digits, exponent) = bigfib_leading_digits(&FromPrimitive::from_u64(n).unwrap(), 0);
+            assert_eq!((digits.as_str(), exponent), ("", bigfib_leading_digits(&FromPrimitive::from_u64(n).unwrap(), 1).1));
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! fib(n) with modulo for many (n, modulo) queries at once.
+//!
+//! With the `simd` feature, queries with moduli below 2^26 run in groups of `LANES`, doing the
+//! doubling steps of the whole group in lockstep on f64 lanes: products of such residues are
+//! below 2^52, so they are exact in an f64, and reduce with a multiplication by 1 / modulo instead
+//! of a division. The compiler vectorizes the lanes, and on x86_64 CPUs with AVX2 and FMA, picked
+//! at runtime, 4 of them fit in a register. Other queries use `fib_with_mod` one by one.
+//!
+//! Queries for one modulo with sorted indices can instead walk through the sequence, see
+//! `fib_many_sorted_with_mod`.
+
+use crate::{FibError, FibState};
+
+/// How many queries the `simd` feature computes in lockstep.
+pub const LANES: usize = 8;
+
+/// The moduli the `simd` feature handles in lanes are below this.
+pub const SIMD_MODULO_LIMIT: u64 = 1 << 26;
+
+
+/// Finds fib(n) with modulo for every (n, modulo) query. Runtime O(log(n)) per query.
+///
+/// # Examples
+/// ```
+/// let queries = [(10, 100), (1_000_000_000_000_000, 1_000_000), (90, u64::MAX)];
+/// assert_eq!(
+///     vec![55, 546_875, 2_880_067_194_370_816_120],
+///     fast_fibonacci::batch::fib_with_mod_batch(&queries)
+/// );
+/// ```
+pub fn fib_with_mod_batch(queries: &[(u64, u64)]) -> Vec<u64> {
+    try_fib_with_mod_batch(queries).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_with_mod_batch`, but returns the first error instead of panicking.
+pub fn try_fib_with_mod_batch(queries: &[(u64, u64)]) -> Result<Vec<u64>, FibError> {
+    if queries.iter().any(|&(_, modulo)| modulo == 0) {
+        return Err(FibError::ZeroModulus);
+    }
+    #[cfg(feature = "simd")]
+    return Ok(lanes::fib_with_mod_batch(queries));
+    #[cfg(not(feature = "simd"))]
+    queries.iter().map(|&(n, modulo)| crate::try_fib_with_mod(n, modulo)).collect()
+}
+
+
+/// Finds fib(n) with modulo for every n of a sorted slice, by advancing a `FibState` through
+/// the gaps between them. Runtime O(log(n1) + log(n2 - n1) + ... + log(nk - nk-1))
+///
+/// For dense indices, that's far less than O(log(n)) each from scratch, and a run of equal gaps
+/// costs O(1) per index after the first.
+///
+/// Panics if modulo is 0 or the indices aren't sorted, see `try_fib_many_sorted_with_mod`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::batch::fib_many_sorted_with_mod;
+///
+/// let ns = [10, 11, 1_000_000_000_000_000, 1_000_000_000_000_010];
+/// assert_eq!(vec![55, 89, 546_875, 156_305], fib_many_sorted_with_mod(&ns, 1_000_000));
+/// ```
+pub fn fib_many_sorted_with_mod(sorted_ns: &[u64], modulo: u64) -> Vec<u64> {
+    try_fib_many_sorted_with_mod(sorted_ns, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_many_sorted_with_mod`, but returns an error instead of panicking.
+pub fn try_fib_many_sorted_with_mod(sorted_ns: &[u64], modulo: u64) -> Result<Vec<u64>, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    if sorted_ns.windows(2).any(|pair| pair[0] > pair[1]) {
+        return Err(FibError::InvalidArgument("indices must be sorted"));
+    }
+    let first = match sorted_ns.first() {
+        Some(&first) => first,
+        None => return Ok(Vec::new()),
+    };
+    let mut state = FibState::try_new(first, modulo)?;
+    let mut fibs = Vec::with_capacity(sorted_ns.len());
+    fibs.push(state.fib());
+    for pair in sorted_ns.windows(2) {
+        state.advance(pair[1] - pair[0]);
+        fibs.push(state.fib());
+    }
+    Ok(fibs)
+}
+
+
+#[cfg(feature = "simd")]
+mod lanes {
+    use crate::batch::{LANES, SIMD_MODULO_LIMIT};
+
+    // Positive moduli only.
+    pub(crate) fn fib_with_mod_batch(queries: &[(u64, u64)]) -> Vec<u64> {
+        let mut results = vec![0; queries.len()];
+        let small: Vec<usize> = (0..queries.len()).filter(|&i| queries[i].1 < SIMD_MODULO_LIMIT).collect();
+        for group in small.chunks(LANES) {
+            // Unused lanes compute fib(0) modulo 1.
+            let (mut ns, mut moduli) = ([0; LANES], [1; LANES]);
+            for (lane, &i) in group.iter().enumerate() {
+                (ns[lane], moduli[lane]) = queries[i];
+            }
+            let fibs = fib_lanes(&ns, &moduli);
+            for (lane, &i) in group.iter().enumerate() {
+                results[i] = fibs[lane];
+            }
+        }
+        for (i, &(n, modulo)) in queries.iter().enumerate() {
+            if modulo >= SIMD_MODULO_LIMIT {
+                results[i] = crate::fib_with_mod(n, modulo);
+            }
+        }
+        results
+    }
+
+
+    fn fib_lanes(ns: &[u64; LANES], moduli: &[u64; LANES]) -> [u64; LANES] {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+                // Safe, since the CPU has the features.
+                return unsafe { fib_lanes_avx2(ns, moduli) };
+            }
+        }
+        fib_lanes_portable(ns, moduli)
+    }
+
+
+    // The same code, compiled for AVX2 and FMA.
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn fib_lanes_avx2(ns: &[u64; LANES], moduli: &[u64; LANES]) -> [u64; LANES] {
+        fib_lanes_portable(ns, moduli)
+    }
+
+
+    // fib(n) for each lane's n and modulo below SIMD_MODULO_LIMIT, by fast doubling.
+    #[inline(always)]
+    pub(crate) fn fib_lanes_portable(ns: &[u64; LANES], moduli: &[u64; LANES]) -> [u64; LANES] {
+        let m = moduli.map(|modulo| modulo as f64);
+        let inverse = m.map(|m| 1.0 / m);
+        let (mut a, mut b) = ([0.0; LANES], moduli.map(|modulo| (1 % modulo) as f64));
+        let bits = 64 - ns.iter().fold(0, |all, n| all | n).leading_zeros();
+        for bit in (0..bits).rev() {
+            for lane in 0..LANES {
+                let (m, inverse) = (m[lane], inverse[lane]);
+                // fib(2k) = fib(k) * (2 * fib(k + 1) - fib(k)), fib(2k + 1) = fib(k)^2 + fib(k + 1)^2
+                let even = reduce(a[lane] * reduce(2.0 * b[lane] - a[lane], m, inverse), m, inverse);
+                let odd = reduce(a[lane] * a[lane] + b[lane] * b[lane], m, inverse);
+                let set = (ns[lane] >> bit) & 1 == 1;
+                a[lane] = if set { odd } else { even };
+                b[lane] = if set { reduce(even + odd, m, inverse) } else { odd };
+            }
+        }
+        a.map(|a| a as u64)
+    }
+
+
+    // x modulo m, for an integer x in (-m, 2^53) and m below 2^26. The quotient from the inverse
+    // is off by at most one, which the corrections fix.
+    #[inline(always)]
+    fn reduce(x: f64, m: f64, inverse: f64) -> f64 {
+        let r = x - (x * inverse).floor() * m;
+        let r = if r < 0.0 { r + m } else { r };
+        if r >= m { r - m } else { r }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::batch::*;
+
+    #[test]
+    fn test_matches_fib_with_mod() {
+        let moduli = [1, 2, 10, 1_000, SIMD_MODULO_LIMIT - 1, SIMD_MODULO_LIMIT, 1_000_000_007, u64::MAX];
+        let ns = [0, 1, 2, 3, 10, 93, 1 << 40, 1_000_000_000_000_000, u64::MAX];
+        let queries: Vec<(u64, u64)> = moduli.iter().flat_map(|&m| ns.iter().map(move |&n| (n, m))).collect();
+        // Batch sizes that leave some lanes unused.
+        for length in [0, 1, LANES - 1, LANES + 3, queries.len()].iter().copied() {
+            let expected: Vec<u64> = queries[..length].iter().map(|&(n, m)| crate::fib_with_mod(n, m)).collect();
+            assert_eq!(fib_with_mod_batch(&queries[..length]), expected);
+        }
+        assert_eq!(try_fib_with_mod_batch(&[(10, 100), (10, 0)]), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_fib_many_sorted_with_mod() {
+        let ns = [0, 0, 1, 2, 3, 10, 93, 94, 95, 96, 1 << 40, 1_000_000_000_000_000, u64::MAX - 1, u64::MAX];
+        for &modulo in &[1, 2, 1_000, 1_000_000_007, u64::MAX] {
+            let expected: Vec<u64> = ns.iter().map(|&n| crate::fib_with_mod(n, modulo)).collect();
+            assert_eq!(fib_many_sorted_with_mod(&ns, modulo), expected, "modulo = {}", modulo);
+        }
+        assert_eq!(fib_many_sorted_with_mod(&[], 10), Vec::<u64>::new());
+        assert_eq!(try_fib_many_sorted_with_mod(&[1], 0), Err(FibError::ZeroModulus));
+        let unsorted = try_fib_many_sorted_with_mod(&[2, 1], 10);
+        assert_eq!(unsorted, Err(FibError::InvalidArgument("indices must be sorted")));
+    }
+
+    #[cfg(feature = "simd")]
+    #[test]
+    fn test_lanes_near_the_limit() {
+        // The largest residues, where the products come closest to 2^53.
+        let moduli = [SIMD_MODULO_LIMIT - 1, SIMD_MODULO_LIMIT - 3, (1 << 25) + 1, 3, 5, 7, 1 << 20, 999_983];
+        for n in (0..200).chain((0..200).map(|i| u64::MAX - i * 1_000_003)) {
+            let fibs = lanes::fib_lanes_portable(&[n; LANES], &moduli);
+            for lane in 0..LANES {
+                assert_eq!(fibs[lane], crate::fib_with_mod(n, moduli[lane]), "n = {}, modulo = {}", n, moduli[lane]);
+            }
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! fib(n) with modulo over any big integer type implementing `BigNum`.
+//!
+//! `BigNum` is implemented for num-bigint's `BigUint`, and with the `rug` feature for GMP's
+//! `rug::Integer`. Other backends, such as ibig or malachite, plug in through a newtype that
+//! implements it.
+
+use num_bigint::BigUint;
+use num::{FromPrimitive, Zero};
+
+use crate::FibError;
+
+
+/// The arithmetic `fib_with_mod` needs from a big integer type. Values are never negative.
+///
+/// `square` and `mul_rem` have default implementations in terms of the other methods, and exist
+/// for backends with faster or allocation free versions of them.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::bignum::{self, BigNum};
+///
+/// // A toy backend on u128, for moduli below 2^63, where products like a * (2b + m - a) of
+/// // values below the modulo stay below 3 * 2^126.
+/// #[derive(Clone)]
+/// struct Small(u128);
+///
+/// impl BigNum for Small {
+///     fn from_u64(n: u64) -> Self { Small(n as u128) }
+///     fn is_zero(&self) -> bool { self.0 == 0 }
+///     fn add(&self, other: &Self) -> Self { Small(self.0 + other.0) }
+///     fn sub(&self, other: &Self) -> Self { Small(self.0 - other.0) }
+///     fn mul(&self, other: &Self) -> Self { Small(self.0 * other.0) }
+///     fn rem(&self, modulo: &Self) -> Self { Small(self.0 % modulo.0) }
+///     fn to_bytes_le(&self) -> Vec<u8> { self.0.to_le_bytes().to_vec() }
+/// }
+///
+/// assert_eq!(546_875, bignum::fib_with_mod(&Small(1_000_000_000_000_000), &Small(1_000_000)).0);
+/// ```
+pub trait BigNum: Clone {
+    fn from_u64(n: u64) -> Self;
+
+    fn is_zero(&self) -> bool;
+
+    fn add(&self, other: &Self) -> Self;
+
+    /// self - other, where other <= self.
+    fn sub(&self, other: &Self) -> Self;
+
+    fn mul(&self, other: &Self) -> Self;
+
+    fn rem(&self, modulo: &Self) -> Self;
+
+    /// The little endian bytes of the value, with or without trailing zeros.
+    fn to_bytes_le(&self) -> Vec<u8>;
+
+    fn square(&self) -> Self {
+        self.mul(self)
+    }
+
+    /// self * other % modulo.
+    fn mul_rem(&self, other: &Self, modulo: &Self) -> Self {
+        self.mul(other).rem(modulo)
+    }
+}
+
+impl BigNum for BigUint {
+    fn from_u64(n: u64) -> Self {
+        FromPrimitive::from_u64(n).unwrap()
+    }
+
+    fn is_zero(&self) -> bool {
+        Zero::is_zero(self)
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        self + other
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        self - other
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        self * other
+    }
+
+    fn rem(&self, modulo: &Self) -> Self {
+        self % modulo
+    }
+
+    fn to_bytes_le(&self) -> Vec<u8> {
+        BigUint::to_bytes_le(self)
+    }
+}
+
+
+/// Finds the nth fibonacci number with modulo, on any `BigNum`. Runtime O(log(n))
+///
+/// # Examples
+/// ```
+/// use num::FromPrimitive;
+/// use num_bigint::BigUint;
+///
+/// let n: BigUint = FromPrimitive::from_u64(1_000_000_000_000_000).unwrap();
+/// let modulo: BigUint = FromPrimitive::from_u64(1_000_000).unwrap();
+/// assert_eq!(BigUint::from_u64(546_875).unwrap(), fast_fibonacci::bignum::fib_with_mod(&n, &modulo));
+/// ```
+pub fn fib_with_mod<T: BigNum>(n: &T, modulo: &T) -> T {
+    try_fib_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_with_mod`, but returns an error instead of panicking.
+pub fn try_fib_with_mod<T: BigNum>(n: &T, modulo: &T) -> Result<T, FibError> {
+    if modulo.is_zero() {
+        return Err(FibError::ZeroModulus);
+    }
+    let n_bytes = n.to_bytes_le();
+    let bits = (0..8 * n_bytes.len()).rev().map(|bit| n_bytes[bit / 8] >> (bit % 8) & 1 == 1);
+    Ok(fib_over_bits(bits, modulo))
+}
+
+
+/// Finds the nth fibonacci number exactly, on any `BigNum`. Runtime O(M(n)) for M(n) the time
+/// to multiply two n bit numbers
+///
+/// Big multiplications are all of the cost, so it keeps (fib(k - 1), fib(k)) and doubles k with
+/// two squarings, fib(2k - 1) = fib(k)^2 + fib(k - 1)^2 and
+/// fib(2k + 1) = 4fib(k)^2 - fib(k - 1)^2 + 2(-1)^k, where fast doubling takes three products.
+/// The last step only needs fib(n), which is a single product. With a backend that multiplies by
+/// FFT, such as `rug::Integer` with the `rug` feature, that makes fib(10^8) a matter of seconds.
+///
+/// # Examples
+/// ```
+/// use num_bigint::BigUint;
+///
+/// let fib: BigUint = fast_fibonacci::bignum::fib(100);
+/// assert_eq!("354224848179261915075", fib.to_string());
+/// ```
+pub fn fib<T: BigNum>(n: u64) -> T {
+    fib_by_squares(n, |a: &T, b: &T| (a.square(), b.square()))
+}
+
+
+// `fib` with the two squarings of every doubling step done by `squares`, such as on two threads.
+pub(crate) fn fib_by_squares<T: BigNum, F: Fn(&T, &T) -> (T, T)>(n: u64, squares: F) -> T {
+    if n == 0 {
+        return T::from_u64(0);
+    }
+    let two = T::from_u64(2);
+    // (fib(k - 1), fib(k)) for k the bits of n down to the current one.
+    let (mut previous, mut current) = (T::from_u64(0), T::from_u64(1));
+    let bits = 64 - n.leading_zeros();
+    for bit in (1..bits - 1).rev() {
+        let k_is_odd = n >> (bit + 1) & 1 == 1;
+        let (square, previous_square) = squares(&current, &previous);
+        let twice = square.add(&square);
+        let odd = twice.add(&twice).sub(&previous_square);
+        // fib(2k + 1), where 4fib(k)^2 - fib(k - 1)^2 >= 3 for k >= 1.
+        let odd = if k_is_odd { odd.sub(&two) } else { odd.add(&two) };
+        // fib(2k - 1)
+        let below = square.add(&previous_square);
+        let even = odd.sub(&below);
+        if n >> bit & 1 == 1 {
+            previous = even;
+            current = odd;
+        } else {
+            previous = below;
+            current = even;
+        }
+    }
+    if bits == 1 {
+        return current;
+    }
+    let k_is_odd = n >> 1 & 1 == 1;
+    if n & 1 == 0 {
+        // fib(2k) = fib(k)(fib(k) + 2fib(k - 1))
+        return current.mul(&current.add(&previous).add(&previous));
+    }
+    // fib(2k + 1) = (2fib(k) + fib(k - 1))(2fib(k) - fib(k - 1)) + 2(-1)^k
+    let twice = current.add(&current);
+    let product = twice.add(&previous).mul(&twice.sub(&previous));
+    if k_is_odd { product.sub(&two) } else { product.add(&two) }
+}
+
+
+// fib(n) with a positive modulo, for the bits of n from the most significant.
+pub(crate) fn fib_over_bits<T: BigNum, I: Iterator<Item = bool>>(bits: I, modulo: &T) -> T {
+    let (mut a, mut b) = (T::from_u64(0), T::from_u64(1).rem(modulo));
+    for bit in bits {
+        // The doubling formulas of `fib_pair_doubling`.
+        let even = a.mul_rem(&b.add(&b).add(modulo).sub(&a), modulo);
+        let odd = a.square().add(&b.square()).rem(modulo);
+        if bit {
+            b = even.add(&odd).rem(modulo);
+            a = odd;
+        } else {
+            a = even;
+            b = odd;
+        }
+    }
+    a
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::bignum::*;
+
+    #[test]
+    fn test_matches_bigfib_with_mod() {
+        let big_modulo = (BigUint::from(1u32) << 300) - 1u32;
+        for n in &[BigUint::from(0u32), BigUint::from(1u32), BigUint::from(10u32), &big_modulo * 12_345u32] {
+            for modulo in &[BigUint::from(1u32), BigUint::from(1_000_000_007u32), big_modulo.clone()] {
+                assert_eq!(fib_with_mod(n, modulo), crate::bigfib_with_mod(n, modulo));
+            }
+        }
+        assert_eq!(try_fib_with_mod(&BigUint::from(10u32), &BigUint::from(0u32)), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_exact_fib() {
+        for n in (0..300).chain(vec![1_000, 4_095, 4_096, 4_097, 65_537]) {
+            assert_eq!(fib::<BigUint>(n), crate::exact_bigfib_pair(n).0, "n = {}", n);
+        }
+    }
+}
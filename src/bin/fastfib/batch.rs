@@ -0,0 +1,226 @@
+//! `fastfib batch`: one query `n modulo` per line of stdin, separated by whitespace or a comma,
+//! and one result per line of stdout.
+//!
+//! Failed queries don't stop the batch. With `--format lines` their errors go to stderr, and with
+//! json and csv they're part of the output. Either way the exit status is 1 if any query failed.
+//!
+//! Results are written as the queries come in, so the batch works in the middle of a pipeline:
+//! one by one, or with `--parallel` for chunks of up to `CHUNK_LINES` queries at a time.
+
+use num_bigint::BigUint;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::thread;
+
+// How many queries `--parallel` reads before evaluating them and writing their results.
+const CHUNK_LINES: usize = 4_096;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    // Just the result.
+    Lines,
+    // One JSON object per line, with decimal strings for the numbers.
+    Json,
+    // n,modulo,fib,error with a header row.
+    Csv,
+}
+
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut format = Format::Lines;
+    let mut parallel = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--parallel" => parallel = true,
+            "--format" => {
+                format = match args.next().map(String::as_str) {
+                    Some("lines") => Format::Lines,
+                    Some("json") => Format::Json,
+                    Some("csv") => Format::Csv,
+                    _ => return Err("--format must be lines, json or csv".to_string()),
+                }
+            }
+            _ => return Err(format!("unexpected argument {:?}", arg)),
+        }
+    }
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let failed = process(stdin.lock(), BufWriter::new(stdout.lock()), format, parallel).map_err(|err| err.to_string())?;
+    if failed {
+        return Err("some queries failed".to_string());
+    }
+    Ok(())
+}
+
+
+// Evaluates the queries of input into out, flushing after every query, or every chunk with
+// parallel. True if any query failed.
+fn process<R: BufRead, W: Write>(input: R, mut out: W, format: Format, parallel: bool) -> io::Result<bool> {
+    let mut failed = false;
+    if format == Format::Csv {
+        writeln!(out, "n,modulo,fib,error")?;
+        out.flush()?;
+    }
+    let chunk_lines = if parallel { CHUNK_LINES } else { 1 };
+    let mut lines = input.lines();
+    loop {
+        let mut queries = Vec::with_capacity(chunk_lines);
+        for line in lines.by_ref() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                queries.push(line);
+                if queries.len() == chunk_lines {
+                    break;
+                }
+            }
+        }
+        if queries.is_empty() {
+            return Ok(failed);
+        }
+        let results = if parallel { evaluate_parallel(&queries) } else { evaluate(&queries) };
+        for (query, result) in queries.iter().zip(&results) {
+            failed |= result.is_err();
+            if format == Format::Lines {
+                if let Err(message) = result {
+                    eprintln!("{}: {}", query.trim(), message);
+                    continue;
+                }
+            }
+            writeln!(out, "{}", format_result(format, query, result))?;
+        }
+        out.flush()?;
+    }
+}
+
+
+fn evaluate(queries: &[String]) -> Vec<Result<BigUint, String>> {
+    queries.iter().map(|query| evaluate_query(query)).collect()
+}
+
+
+// Splits the queries into one contiguous chunk per thread, which keeps the output in order.
+fn evaluate_parallel(queries: &[String]) -> Vec<Result<BigUint, String>> {
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let chunk_size = queries.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = queries
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || evaluate(chunk)))
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    })
+}
+
+
+fn evaluate_query(query: &str) -> Result<BigUint, String> {
+    match split_query(query).as_slice() {
+        [n, modulo] => crate::fib_with_mod(n, modulo),
+        _ => Err("expected a line with n and modulo".to_string()),
+    }
+}
+
+
+fn split_query(query: &str) -> Vec<&str> {
+    query
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|field| !field.is_empty())
+        .collect()
+}
+
+
+fn format_result(format: Format, query: &str, result: &Result<BigUint, String>) -> String {
+    let (n, modulo) = match split_query(query).as_slice() {
+        [n, modulo] => (*n, *modulo),
+        _ => (query.trim(), ""),
+    };
+    match (format, result) {
+        (Format::Lines, Ok(fib)) => fib.to_string(),
+        (Format::Lines, Err(message)) => message.clone(),
+        (Format::Json, Ok(fib)) => format!(
+            "{{\"n\":{},\"modulo\":{},\"fib\":\"{}\"}}",
+            json_string(n), json_string(modulo), fib
+        ),
+        (Format::Json, Err(message)) => format!(
+            "{{\"n\":{},\"modulo\":{},\"error\":{}}}",
+            json_string(n), json_string(modulo), json_string(message)
+        ),
+        (Format::Csv, Ok(fib)) => format!("{},{},{},", csv_field(n), csv_field(modulo), fib),
+        (Format::Csv, Err(message)) => {
+            format!("{},{},,{}", csv_field(n), csv_field(modulo), csv_field(message))
+        }
+    }
+}
+
+
+fn json_string(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::batch::*;
+
+    #[test]
+    fn test_evaluate() {
+        let queries: Vec<String> = (0..100u64).map(|n| format!("{}, {}", n * 1_000_000_007, 1_000 + n)).collect();
+        let expected: Vec<_> = (0..100u64)
+            .map(|n| Ok(BigUint::from(fast_fibonacci::fib_with_mod(n * 1_000_000_007, 1_000 + n))))
+            .collect();
+        assert_eq!(evaluate(&queries), expected);
+        assert_eq!(evaluate_parallel(&queries), expected);
+        assert_eq!(evaluate_parallel(&[]), vec![]);
+        assert!(evaluate_query("10").is_err());
+        assert!(evaluate_query("10 20 30").is_err());
+    }
+
+    #[test]
+    fn test_process() {
+        let input: String = (0..2 * CHUNK_LINES as u64 + 3).map(|n| format!("{} 1000\n\n", n)).collect();
+        let expected: String = (0..2 * CHUNK_LINES as u64 + 3).map(|n| format!("{}\n", fast_fibonacci::fib_with_mod(n, 1_000))).collect();
+        for &parallel in &[false, true] {
+            let mut out = Vec::new();
+            assert!(!process(input.as_bytes(), &mut out, Format::Lines, parallel).unwrap());
+            assert_eq!(String::from_utf8(out).unwrap(), expected);
+        }
+        let mut out = Vec::new();
+        assert!(process("10 100\n10 0\n".as_bytes(), &mut out, Format::Csv, false).unwrap());
+        assert_eq!(String::from_utf8(out).unwrap(), "n,modulo,fib,error\n10,100,55,\n10,0,,modulo must be positive\n");
+    }
+
+    #[test]
+    fn test_format_result() {
+        let ok = Ok(BigUint::from(55u32));
+        let err = Err("modulo must be positive".to_string());
+        assert_eq!(format_result(Format::Lines, "10 100", &ok), "55");
+        assert_eq!(format_result(Format::Json, "10 100", &ok), r#"{"n":"10","modulo":"100","fib":"55"}"#);
+        assert_eq!(
+            format_result(Format::Json, "10,0", &err),
+            r#"{"n":"10","modulo":"0","error":"modulo must be positive"}"#
+        );
+        assert_eq!(format_result(Format::Csv, "10\t100", &ok), "10,100,55,");
+        assert_eq!(format_result(Format::Csv, "10 0", &err), "10,0,,modulo must be positive");
+        assert_eq!(format_result(Format::Json, "a\"b", &Err("x".to_string())), r#"{"n":"a\"b","modulo":"","error":"x"}"#);
+    }
+}
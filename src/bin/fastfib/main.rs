@@ -0,0 +1,117 @@
+//! `fastfib`, a command line front end for fast-fibonacci.
+//!
+//! ```text
+//! fastfib <n> <modulo>
+//! fastfib sequence <name> <n> <modulo>
+//! fastfib batch [--format lines|json|csv] [--parallel]
+//! fastfib repl
+//! fastfib serve [--addr host:port]      (with the serve feature)
+//! ```
+
+mod batch;
+mod repl;
+#[cfg(feature = "serve")]
+mod serve;
+
+use fast_fibonacci::sequence::{Sequence, BUILTIN_NAMES};
+use fast_fibonacci::{try_bigfib_with_mod, try_fib_with_mod};
+use num_bigint::BigUint;
+use num::ToPrimitive;
+use std::env;
+use std::process;
+
+const USAGE: &str = "usage:
+    fastfib <n> <modulo>
+    fastfib sequence <name> <n> <modulo>
+    fastfib batch [--format lines|json|csv] [--parallel]
+    fastfib repl
+    fastfib serve [--addr host:port]";
+
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("batch") => batch::run(&args[1..]),
+        Some("repl") => repl::run(&args[1..]),
+        Some("sequence") if args.len() == 4 => {
+            sequence_with_mod(&args[1], &args[2], &args[3]).map(|term| println!("{}", term))
+        }
+        #[cfg(feature = "serve")]
+        Some("serve") => serve::run(&args[1..]),
+        Some("-h") | Some("--help") => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        Some(_) if args.len() == 2 => {
+            fib_with_mod(&args[0], &args[1]).map(|fib| println!("{}", fib))
+        }
+        _ => Err(USAGE.to_string()),
+    };
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        process::exit(1);
+    }
+}
+
+
+// fib(n) mod modulo for decimal strings, through u64 when both fit and BigUint otherwise.
+fn fib_with_mod(n: &str, modulo: &str) -> Result<BigUint, String> {
+    let n = parse_decimal(n)?;
+    let modulo = parse_decimal(modulo)?;
+    if let (Some(small_n), Some(small_modulo)) = (n.to_u64(), modulo.to_u64()) {
+        return try_fib_with_mod(small_n, small_modulo)
+            .map(BigUint::from)
+            .map_err(|err| err.to_string());
+    }
+    try_bigfib_with_mod(&n, &modulo).map_err(|err| err.to_string())
+}
+
+
+// The nth term of a built-in sequence with modulo, for u64 n and modulo.
+fn sequence_with_mod(name: &str, n: &str, modulo: &str) -> Result<u64, String> {
+    let sequence = Sequence::by_name(name)
+        .ok_or_else(|| format!("unknown sequence {:?}, expected one of {}", name, BUILTIN_NAMES.join(", ")))?;
+    let n = parse_decimal(n)?.to_u64().ok_or("n must fit in a u64")?;
+    let modulo = parse_decimal(modulo)?.to_u64().ok_or("modulo must fit in a u64")?;
+    sequence.try_nth_mod(n, modulo).map_err(|err| err.to_string())
+}
+
+
+fn parse_decimal(digits: &str) -> Result<BigUint, String> {
+    let digits = digits.trim().replace('_', "");
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("expected a non-negative integer, found {:?}", digits));
+    }
+    BigUint::parse_bytes(digits.as_bytes(), 10)
+        .ok_or_else(|| format!("expected a non-negative integer, found {:?}", digits))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn test_fib_with_mod() {
+        assert_eq!(fib_with_mod("10", "100"), Ok(BigUint::from(55u32)));
+        assert_eq!(fib_with_mod("1_000_000_000_000_000", "1000000"), Ok(BigUint::from(546_875u32)));
+        let big = BigUint::parse_bytes(b"100000000000000000000", 10).unwrap();
+        assert_eq!(
+            fib_with_mod("100000000000000000000", "100000000000000000000"),
+            Ok(fast_fibonacci::bigfib_with_mod(&big, &big))
+        );
+        assert!(fib_with_mod("10", "0").is_err());
+        assert!(fib_with_mod("100000000000000000000", "0").is_err());
+        assert!(fib_with_mod("-1", "10").is_err());
+        assert!(fib_with_mod("x", "10").is_err());
+    }
+
+    #[test]
+    fn test_sequence_with_mod() {
+        assert_eq!(sequence_with_mod("pell", "10", "10000"), Ok(2_378));
+        assert_eq!(sequence_with_mod("tribonacci", "10", "1000"), Ok(81));
+        assert!(sequence_with_mod("primes", "10", "1000").is_err());
+        assert!(sequence_with_mod("pell", "100000000000000000000", "1000").is_err());
+        assert!(sequence_with_mod("pell", "10", "0").is_err());
+    }
+}
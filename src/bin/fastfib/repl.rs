@@ -0,0 +1,376 @@
+//! `fastfib repl`: evaluates one expression per line, keeping per modulo state between queries.
+//!
+//! ```text
+//! > fib(10^18+7) % 1e9+7
+//! > fib(10, 100)
+//! > pisano(1000)
+//! > rank(1000)
+//! > 2^64 - 1
+//! ```
+//!
+//! Numbers are non-negative integers, with `_` separators and `1e9` style exponents. `+`, `-`, `*`,
+//! `/`, `^` and parentheses work as usual. In `fib(n) % m`, everything after the `%` is the modulo.
+//!
+//! Per modulo state is kept in a `SharedFibCache` between queries.
+
+use fast_fibonacci::{pisano, FibError, SharedFibCache};
+use num_bigint::BigUint;
+use num::{One, ToPrimitive, Zero};
+use std::io::{self, BufRead, Write};
+
+// Keeps `^` from allocating without bound.
+const MAX_RESULT_BITS: u64 = 1 << 20;
+
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    if let Some(arg) = args.first() {
+        return Err(format!("unexpected argument {:?}", arg));
+    }
+    let mut repl = Repl::default();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        io::stdout().flush().map_err(|err| err.to_string())?;
+        let line = match lines.next() {
+            Some(line) => line.map_err(|err| err.to_string())?,
+            None => return Ok(()),
+        };
+        match line.trim() {
+            "" => continue,
+            "quit" | "exit" => return Ok(()),
+            "help" => println!("fib(n) % m, fib(n, m), pisano(m), rank(m), or arithmetic with + - * / ^"),
+            line => match repl.evaluate(line) {
+                Ok(value) => println!("{}", value),
+                Err(message) => println!("error: {}", message),
+            },
+        }
+    }
+}
+
+
+#[derive(Default)]
+struct Repl {
+    cache: SharedFibCache,
+}
+
+impl Repl {
+    fn evaluate(&mut self, line: &str) -> Result<BigUint, String> {
+        let tokens = tokenize(line)?;
+        let mut parser = Parser { tokens: &tokens, position: 0 };
+        let statement = parser.statement()?;
+        if parser.position < tokens.len() {
+            return Err(format!("unexpected {}", tokens[parser.position]));
+        }
+        self.evaluate_expr(&statement)
+    }
+
+    fn evaluate_expr(&mut self, expr: &Expr) -> Result<BigUint, String> {
+        match expr {
+            Expr::Number(n) => Ok(n.clone()),
+            Expr::Binary('%', lhs, rhs) => {
+                let modulo = self.evaluate_expr(rhs)?;
+                match &**lhs {
+                    Expr::Call(name, args) if name == "fib" && args.len() == 1 => {
+                        let n = self.evaluate_expr(&args[0])?;
+                        self.fib(&n, &modulo)
+                    }
+                    lhs => {
+                        let lhs = self.evaluate_expr(lhs)?;
+                        if modulo.is_zero() {
+                            return Err("division by zero".to_string());
+                        }
+                        Ok(lhs % modulo)
+                    }
+                }
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (self.evaluate_expr(lhs)?, self.evaluate_expr(rhs)?);
+                arithmetic(*op, lhs, rhs)
+            }
+            Expr::Call(name, args) => {
+                let args = args.iter().map(|arg| self.evaluate_expr(arg)).collect::<Result<Vec<_>, _>>()?;
+                match (name.as_str(), args.as_slice()) {
+                    ("fib", [n, modulo]) => self.fib(n, modulo),
+                    ("fib", [_]) => Err("fib needs a modulo, as fib(n) % m or fib(n, m)".to_string()),
+                    ("pisano", [modulo]) => self.pisano(modulo).map(BigUint::from),
+                    ("rank", [modulo]) => {
+                        let modulo = small_modulo(modulo)?;
+                        pisano::try_rank_of_apparition(modulo)
+                            .map(BigUint::from)
+                            .map_err(|err| err.to_string())
+                    }
+                    _ => Err(format!("unknown function {}/{}", name, args.len())),
+                }
+            }
+        }
+    }
+
+    fn fib(&mut self, n: &BigUint, modulo: &BigUint) -> Result<BigUint, String> {
+        match modulo.to_u64() {
+            Some(0) => Err(FibError::ZeroModulus.to_string()),
+            Some(modulo) => Ok(BigUint::from(self.cache.bigfib_with_mod(n, modulo))),
+            None => fast_fibonacci::try_bigfib_with_mod(n, modulo).map_err(|err| err.to_string()),
+        }
+    }
+
+    fn pisano(&mut self, modulo: &BigUint) -> Result<u64, String> {
+        let modulo = small_modulo(modulo)?;
+        self.cache.try_pisano_info(modulo).map(|info| info.period).map_err(|err| err.to_string())
+    }
+}
+
+
+fn small_modulo(modulo: &BigUint) -> Result<u64, String> {
+    match modulo.to_u64() {
+        Some(0) => Err("modulo must be positive".to_string()),
+        Some(modulo) => Ok(modulo),
+        None => Err("modulo must fit in a u64".to_string()),
+    }
+}
+
+
+fn arithmetic(op: char, lhs: BigUint, rhs: BigUint) -> Result<BigUint, String> {
+    match op {
+        '+' => Ok(lhs + rhs),
+        '-' if lhs < rhs => Err("negative result".to_string()),
+        '-' => Ok(lhs - rhs),
+        '*' => Ok(lhs * rhs),
+        '/' if rhs.is_zero() => Err("division by zero".to_string()),
+        '/' => Ok(lhs / rhs),
+        '^' => {
+            let exponent = rhs
+                .to_u64()
+                .filter(|&exponent| exponent.saturating_mul(lhs.bits() as u64) <= MAX_RESULT_BITS);
+            match exponent {
+                Some(exponent) => Ok(num::pow(lhs, exponent as usize)),
+                None if lhs <= BigUint::one() => Ok(lhs),
+                None => Err("result too large".to_string()),
+            }
+        }
+        _ => unreachable!("unknown operator {}", op),
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(BigUint),
+    Name(String),
+    Symbol(char),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Name(name) => write!(f, "{}", name),
+            Token::Symbol(c) => write!(f, "{}", c),
+        }
+    }
+}
+
+
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '_') {
+                i += 1;
+            }
+            let mantissa: String = chars[start..i].iter().filter(|&&c| c != '_').collect();
+            let mut n = BigUint::parse_bytes(mantissa.as_bytes(), 10).unwrap();
+            if i + 1 < chars.len() && (chars[i] == 'e' || chars[i] == 'E') && chars[i + 1].is_ascii_digit() {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let exponent: String = chars[start..i].iter().collect();
+                n = arithmetic('*', n, arithmetic('^', BigUint::from(10u32), exponent.parse::<BigUint>().unwrap())?)?;
+            }
+            tokens.push(Token::Number(n));
+        } else if c.is_ascii_alphabetic() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            tokens.push(Token::Name(chars[start..i].iter().collect()));
+        } else if "+-*/^%(),".contains(c) {
+            tokens.push(Token::Symbol(c));
+            i += 1;
+        } else {
+            return Err(format!("unexpected {}", c));
+        }
+    }
+    Ok(tokens)
+}
+
+
+#[derive(Debug, PartialEq)]
+enum Expr {
+    Number(BigUint),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+
+// statement := sum ('%' sum)?
+// sum       := product (('+' | '-') product)*
+// product   := power (('*' | '/') power)*
+// power     := atom ('^' power)?
+// atom      := number | name '(' sum (',' sum)* ')' | '(' sum ')'
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn statement(&mut self) -> Result<Expr, String> {
+        let lhs = self.sum()?;
+        if self.eat('%') {
+            let rhs = self.sum()?;
+            return Ok(Expr::Binary('%', Box::new(lhs), Box::new(rhs)));
+        }
+        Ok(lhs)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.product()?;
+        while let Some(op) = self.eat_any("+-") {
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.product()?));
+        }
+        Ok(lhs)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.power()?;
+        while let Some(op) = self.eat_any("*/") {
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.power()?));
+        }
+        Ok(lhs)
+    }
+
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.atom()?;
+        if self.eat('^') {
+            return Ok(Expr::Binary('^', Box::new(base), Box::new(self.power()?)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let token = self.tokens.get(self.position).cloned().ok_or("unexpected end of line")?;
+        self.position += 1;
+        match token {
+            Token::Number(n) => Ok(Expr::Number(n)),
+            Token::Name(name) => {
+                self.expect('(')?;
+                let mut args = vec![self.sum()?];
+                while self.eat(',') {
+                    args.push(self.sum()?);
+                }
+                self.expect(')')?;
+                Ok(Expr::Call(name, args))
+            }
+            Token::Symbol('(') => {
+                let inner = self.sum()?;
+                self.expect(')')?;
+                Ok(inner)
+            }
+            token => Err(format!("unexpected {}", token)),
+        }
+    }
+
+    fn eat(&mut self, symbol: char) -> bool {
+        self.eat_any(&symbol.to_string()).is_some()
+    }
+
+    fn eat_any(&mut self, symbols: &str) -> Option<char> {
+        match self.tokens.get(self.position) {
+            Some(&Token::Symbol(c)) if symbols.contains(c) => {
+                self.position += 1;
+                Some(c)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), String> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(format!("expected {}", symbol))
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::repl::*;
+
+    fn evaluate(repl: &mut Repl, line: &str) -> Result<String, String> {
+        repl.evaluate(line).map(|value| value.to_string())
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let mut repl = Repl::default();
+        assert_eq!(evaluate(&mut repl, "1 + 2 * 3"), Ok("7".to_string()));
+        assert_eq!(evaluate(&mut repl, "(1 + 2) * 3"), Ok("9".to_string()));
+        assert_eq!(evaluate(&mut repl, "2^3^2"), Ok("512".to_string()));
+        assert_eq!(evaluate(&mut repl, "2^64 - 1"), Ok(u64::MAX.to_string()));
+        assert_eq!(evaluate(&mut repl, "1e9+7"), Ok("1000000007".to_string()));
+        assert_eq!(evaluate(&mut repl, "1_000 / 7 % 10"), Ok("2".to_string()));
+        assert!(evaluate(&mut repl, "1 - 2").is_err());
+        assert!(evaluate(&mut repl, "1 / 0").is_err());
+        assert!(evaluate(&mut repl, "2^(10^9)").is_err());
+        assert_eq!(evaluate(&mut repl, "1^(10^30)"), Ok("1".to_string()));
+        assert!(evaluate(&mut repl, "(1 + 2").is_err());
+        assert!(evaluate(&mut repl, "1 2").is_err());
+        assert!(evaluate(&mut repl, "foo(1)").is_err());
+    }
+
+    #[test]
+    fn test_fib() {
+        let mut repl = Repl::default();
+        assert_eq!(evaluate(&mut repl, "fib(10) % 100"), Ok("55".to_string()));
+        assert_eq!(evaluate(&mut repl, "fib(10, 100)"), Ok("55".to_string()));
+        let expected = fast_fibonacci::fib_with_mod(1_000_000_000_000_000_007, 1_000_000_007);
+        assert_eq!(evaluate(&mut repl, "fib(10^18+7) % 1e9+7"), Ok(expected.to_string()));
+        for n in 0..200u64 {
+            let expected = fast_fibonacci::fib_with_mod(n * 1_000_003, 1_000);
+            assert_eq!(evaluate(&mut repl, &format!("fib({} * 1000003, 1000)", n)), Ok(expected.to_string()));
+        }
+
+        // Past u64, checked against the index reduced by the pisano period.
+        let period = pisano::pisano_period(1_000_000_007) as u128;
+        let reduced = (10u128.pow(30) % period) as u64;
+        let expected = fast_fibonacci::fib_with_mod(reduced, 1_000_000_007);
+        assert_eq!(evaluate(&mut repl, "fib(10^30) % 1e9+7"), Ok(expected.to_string()));
+
+        let big: BigUint = num::pow(BigUint::from(10u32), 30);
+        let expected = fast_fibonacci::bigfib_with_mod(&BigUint::from(12345u32), &big);
+        assert_eq!(evaluate(&mut repl, "fib(12345) % 10^30"), Ok(expected.to_string()));
+        assert!(evaluate(&mut repl, "fib(10) % 0").is_err());
+        assert!(evaluate(&mut repl, "fib(10)").is_err());
+    }
+
+    #[test]
+    fn test_pisano() {
+        let mut repl = Repl::default();
+        assert_eq!(evaluate(&mut repl, "pisano(10)"), Ok("60".to_string()));
+        assert_eq!(evaluate(&mut repl, "pisano(10)"), Ok("60".to_string()));
+        assert_eq!(repl.cache.len(), 1);
+        assert_eq!(evaluate(&mut repl, "rank(1000)"), Ok("750".to_string()));
+        assert!(evaluate(&mut repl, "pisano(0)").is_err());
+        assert!(evaluate(&mut repl, "pisano(2^64)").is_err());
+    }
+}
@@ -0,0 +1,238 @@
+//! `fastfib serve [--addr host:port]`, with the `serve` feature: a tiny HTTP server with JSON
+//! responses.
+//!
+//! ```text
+//! GET /fib?n=<n>&mod=<modulo>   {"n":"10","modulo":"100","fib":"55"}
+//! GET /pisano?mod=<modulo>      {"modulo":"10","period":"60"}
+//! ```
+//!
+//! Numbers are decimal strings. Errors come back as `{"error":"..."}` with a 4xx or 5xx status.
+//! Requests, numbers and concurrent connections are limited, moduli that don't fit in a u64 get a
+//! time budget, and moduli that do share a `SharedFibCache`. `/pisano` factors the modulo by trial
+//! division, so it takes moduli up to 10^12 only.
+
+use fast_fibonacci::{computation, SharedFibCache};
+use num_bigint::BigUint;
+use num::{ToPrimitive, Zero};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const MAX_REQUEST_LINE_BYTES: u64 = 8 * 1024;
+const MAX_DIGITS: usize = 1_000;
+// Trial division up to the square root takes about a millisecond at this size.
+const MAX_PISANO_MODULO: u64 = 1_000_000_000_000;
+const MAX_CONNECTIONS: usize = 64;
+const TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_DRAIN_BYTES: u64 = 64 * 1024;
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    let addr = match args {
+        [] => "127.0.0.1:8080",
+        [flag, addr] if flag == "--addr" => addr.as_str(),
+        _ => return Err("usage: fastfib serve [--addr host:port]".to_string()),
+    };
+    let listener = TcpListener::bind(addr).map_err(|err| format!("{}: {}", addr, err))?;
+    eprintln!("listening on http://{}", listener.local_addr().map_err(|err| err.to_string())?);
+    serve(listener, Arc::new(SharedFibCache::new()));
+    Ok(())
+}
+
+
+fn serve(listener: TcpListener, cache: Arc<SharedFibCache>) {
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let mut stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        if connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            connections.fetch_sub(1, Ordering::SeqCst);
+            let _ = write_response(&mut stream, 503, &error_json("too many connections"));
+            continue;
+        }
+        let (cache, connections) = (Arc::clone(&cache), Arc::clone(&connections));
+        thread::spawn(move || {
+            let _ = handle_connection(&mut stream, &cache);
+            connections.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+
+fn handle_connection(stream: &mut TcpStream, cache: &SharedFibCache) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(Read::by_ref(stream).take(MAX_REQUEST_LINE_BYTES));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let (status, body) = if request_line.ends_with('\n') {
+        handle_request(request_line.trim_end(), cache)
+    } else {
+        (414, error_json("request line too long"))
+    };
+    write_response(stream, status, &body)?;
+
+    // Closing with unread headers left would reset the connection, and could lose the response.
+    stream.shutdown(Shutdown::Write)?;
+    stream.set_read_timeout(Some(DRAIN_TIMEOUT))?;
+    io::copy(&mut Read::by_ref(stream).take(MAX_DRAIN_BYTES), &mut io::sink())?;
+    Ok(())
+}
+
+
+// (status, JSON body) for a request line like `GET /fib?n=10&mod=100 HTTP/1.1`.
+fn handle_request(request_line: &str, cache: &SharedFibCache) -> (u16, String) {
+    let mut parts = request_line.split(' ');
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method, target),
+        _ => return (400, error_json("malformed request")),
+    };
+    if method != "GET" {
+        return (405, error_json("only GET is supported"));
+    }
+    let (path, query) = match target.find('?') {
+        Some(i) => (&target[..i], &target[i + 1..]),
+        None => (target, ""),
+    };
+    let result = match path {
+        "/fib" => fib(query, cache),
+        "/pisano" => period(query, cache),
+        _ => return (404, error_json("not found")),
+    };
+    match result {
+        Ok(body) => (200, body),
+        Err(message) => (400, error_json(&message)),
+    }
+}
+
+
+fn fib(query: &str, cache: &SharedFibCache) -> Result<String, String> {
+    let n = parameter(query, "n")?;
+    let modulo = parameter(query, "mod")?;
+    if modulo.is_zero() {
+        return Err("modulo must be positive".to_string());
+    }
+    let fib = match modulo.to_u64() {
+        // At most MAX_DIGITS digits of n, so a few thousand additions of pairs.
+        Some(small_modulo) => BigUint::from(cache.bigfib_with_mod(&n, small_modulo)),
+        None => computation::bigfib_with_mod_timeout(&n, &modulo, TIMEOUT).map_err(|err| err.to_string())?,
+    };
+    Ok(format!("{{\"n\":\"{}\",\"modulo\":\"{}\",\"fib\":\"{}\"}}", n, modulo, fib))
+}
+
+
+fn period(query: &str, cache: &SharedFibCache) -> Result<String, String> {
+    let modulo = parameter(query, "mod")?
+        .to_u64()
+        .filter(|&modulo| modulo <= MAX_PISANO_MODULO)
+        .ok_or_else(|| format!("modulo must be at most {}", MAX_PISANO_MODULO))?;
+    // Factoring can be slow, and the cache does it without holding a lock.
+    let period = cache.try_pisano_info(modulo).map_err(|err| err.to_string())?.period;
+    Ok(format!("{{\"modulo\":\"{}\",\"period\":\"{}\"}}", modulo, period))
+}
+
+
+fn parameter(query: &str, name: &str) -> Result<BigUint, String> {
+    let value = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|&(key, _)| key == name)
+        .map(|(_, value)| value)
+        .ok_or_else(|| format!("missing parameter {}", name))?;
+    let digits = value.replace('_', "");
+    if digits.len() > MAX_DIGITS {
+        return Err(format!("{} has more than {} digits", name, MAX_DIGITS));
+    }
+    crate::parse_decimal(&digits).map_err(|message| format!("{}: {}", name, message))
+}
+
+
+fn error_json(message: &str) -> String {
+    format!("{{\"error\":\"{}\"}}", message.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+
+fn write_response(stream: &mut TcpStream, status: u16, body: &str) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        414 => "URI Too Long",
+        503 => "Service Unavailable",
+        _ => "",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, reason, body.len(), body
+    )?;
+    stream.flush()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::serve::*;
+
+    #[test]
+    fn test_handle_request() {
+        let cache = SharedFibCache::new();
+        let request = |line: &str| handle_request(line, &cache);
+        assert_eq!(
+            request("GET /fib?n=10&mod=100 HTTP/1.1"),
+            (200, r#"{"n":"10","modulo":"100","fib":"55"}"#.to_string())
+        );
+        assert_eq!(
+            request("GET /fib?mod=1000000&n=1_000_000_000_000_000 HTTP/1.1").1,
+            r#"{"n":"1000000000000000","modulo":"1000000","fib":"546875"}"#
+        );
+        assert_eq!(request("GET /pisano?mod=10 HTTP/1.1"), (200, r#"{"modulo":"10","period":"60"}"#.to_string()));
+        assert_eq!(request(&format!("GET /pisano?mod={} HTTP/1.1", MAX_PISANO_MODULO)).0, 200);
+        assert_eq!(
+            request(&format!("GET /pisano?mod={} HTTP/1.1", MAX_PISANO_MODULO + 1)),
+            (400, r#"{"error":"modulo must be at most 1000000000000"}"#.to_string())
+        );
+        assert_eq!(
+            request(&format!("GET /fib?n={}&mod={} HTTP/1.1", "9".repeat(MAX_DIGITS), "7".repeat(MAX_DIGITS))).0,
+            200
+        );
+
+        assert_eq!(
+            request("GET /fib?n=10&mod=0 HTTP/1.1"),
+            (400, r#"{"error":"modulo must be positive"}"#.to_string())
+        );
+        assert_eq!(request("GET /fib?n=10 HTTP/1.1").0, 400);
+        assert_eq!(request("GET /fib?n=-1&mod=10 HTTP/1.1").0, 400);
+        assert_eq!(request(&format!("GET /fib?n={}&mod=10 HTTP/1.1", "9".repeat(MAX_DIGITS + 1))).0, 400);
+        assert_eq!(request("GET /nope HTTP/1.1").0, 404);
+        assert_eq!(request("POST /fib?n=10&mod=100 HTTP/1.1").0, 405);
+        assert_eq!(request("").0, 400);
+    }
+
+    #[test]
+    fn test_serve() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener, Arc::new(SharedFibCache::new())));
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET /fib?n=10&mod=100 HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+        assert!(response.ends_with("\r\n\r\n{\"n\":\"10\",\"modulo\":\"100\",\"fib\":\"55\"}"), "{}", response);
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET /{} HTTP/1.1\r\n\r\n", "x".repeat(MAX_REQUEST_LINE_BYTES as usize)).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 414 URI Too Long\r\n"), "{}", response);
+    }
+}
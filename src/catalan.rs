@@ -0,0 +1,64 @@
+use crate::combinatorics::{binomial_mod_prime, check_prime};
+use crate::{sub_mod, FibError};
+
+
+/// Finds the nth Catalan number (2n choose n) / (n + 1) with a prime modulo.
+/// Runtime O(min(n, prime) * log_prime(n))
+///
+/// Uses Catalan(n) = (2n choose n) - (2n choose n + 1), which needs no division by n + 1, with
+/// Lucas' theorem splitting the binomial coefficients into base prime digits once 2n reaches the
+/// prime.
+///
+/// modulo must be prime.
+///
+/// # Examples
+/// ```
+/// // 1, 1, 2, 5, 14, 42, 132, ...
+/// assert_eq!(42, fast_fibonacci::catalan_with_mod(5, 1_000_000_007));
+/// assert_eq!(132 % 7, fast_fibonacci::catalan_with_mod(6, 7));
+/// ```
+pub fn catalan_with_mod(n: u64, prime: u64) -> u64 {
+    try_catalan_with_mod(n, prime).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `catalan_with_mod`, but returns an error instead of panicking.
+pub fn try_catalan_with_mod(n: u64, prime: u64) -> Result<u64, FibError> {
+    check_prime(prime)?;
+    let (n, twice_n) = (n as u128, 2 * n as u128);
+    Ok(sub_mod(binomial_mod_prime(twice_n, n, prime), binomial_mod_prime(twice_n, n + 1, prime), prime))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_matches_exact() {
+        // Catalan(n + 1) = Catalan(n) * 2(2n + 1) / (n + 2)
+        let mut catalan = BigUint::from(1u32);
+        for n in 0..200u64 {
+            for &prime in &[2u64, 3, 5, 7, 11, 13, 101, 1_000_003, 1_000_000_007] {
+                assert_eq!(
+                    BigUint::from(catalan_with_mod(n, prime)),
+                    &catalan % BigUint::from(prime),
+                    "n = {}, prime = {}", n, prime
+                );
+            }
+            catalan = catalan * BigUint::from(2 * (2 * n + 1)) / BigUint::from(n + 2);
+        }
+    }
+
+    #[test]
+    fn test_large_n() {
+        // Catalan(n) is odd exactly when n + 1 is a power of two.
+        assert_eq!(catalan_with_mod(u64::MAX, 2), 1);
+        assert_eq!(catalan_with_mod(u64::MAX - 1, 2), 0);
+        assert_eq!(catalan_with_mod((1 << 40) - 1, 2), 1);
+        assert_eq!(catalan_with_mod(1 << 40, 2), 0);
+        assert_eq!(try_catalan_with_mod(5, 0), Err(FibError::ZeroModulus));
+        assert_eq!(try_catalan_with_mod(5, 10), Err(FibError::InvalidArgument("modulo must be prime")));
+    }
+}
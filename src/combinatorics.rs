@@ -0,0 +1,385 @@
+//! Factorials, modular inverses and binomial coefficients with modulo, the counting behind
+//! `catalan_with_mod` and `fibonomial_with_mod`.
+//!
+//! For many queries with the same prime, `FactorialTable` answers each binomial coefficient in
+//! O(1) from precomputed factorials and inverse factorials. The free functions need no setup.
+//!
+//! `hosoya_rows_with_mod` generates the Hosoya triangle, the fibonacci analogue of Pascal's.
+
+use crate::nt::is_prime;
+use crate::{add_mod, fib_pair_with_mod, mul_mod, pow_mod, FibError};
+
+pub use crate::nt::{mod_inverse, try_mod_inverse};
+
+
+/// Finds n! with modulo, which is 0 once n reaches the modulo. Runtime O(min(n, modulo))
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::combinatorics::factorial_with_mod;
+///
+/// assert_eq!(3_628_800 % 1_000, factorial_with_mod(10, 1_000));
+/// assert_eq!(0, factorial_with_mod(u64::MAX, 1_000_000_007));
+/// ```
+pub fn factorial_with_mod(n: u64, modulo: u64) -> u64 {
+    try_factorial_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `factorial_with_mod`, but returns an error instead of panicking.
+pub fn try_factorial_with_mod(n: u64, modulo: u64) -> Result<u64, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    if n >= modulo {
+        return Ok(0);
+    }
+    Ok((2..=n).fold(1 % modulo, |product, i| mul_mod(product, i, modulo)))
+}
+
+
+/// Finds n choose k with a prime modulo, by Lucas' theorem over the base prime digits of n and
+/// k. Runtime O(min(k, prime) * log_prime(n))
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::combinatorics::binomial_with_mod;
+///
+/// assert_eq!(252, binomial_with_mod(10, 5, 1_000_000_007));
+/// // 10 = 13 and 5 = 5 in base 7, and 5 > 3 in the last digit.
+/// assert_eq!(0, binomial_with_mod(10, 5, 7));
+/// ```
+pub fn binomial_with_mod(n: u64, k: u64, prime: u64) -> u64 {
+    try_binomial_with_mod(n, k, prime).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `binomial_with_mod`, but checks that the modulo is prime, and returns an error instead of
+/// panicking.
+pub fn try_binomial_with_mod(n: u64, k: u64, prime: u64) -> Result<u64, FibError> {
+    check_prime(prime)?;
+    Ok(binomial_mod_prime(n as u128, k as u128, prime))
+}
+
+
+/// Factorials and inverse factorials modulo a prime, up to a limit, for O(1) binomial
+/// coefficients. The tables hold min(limit, prime - 1) + 1 entries each.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::combinatorics::FactorialTable;
+///
+/// let table = FactorialTable::new(1_000_000, 1_000_000_007);
+/// assert_eq!(252, table.binomial(10, 5));
+/// assert_eq!(3_628_800, table.factorial(10));
+/// // Past the table, Lucas' theorem splits n and k into base prime digits.
+/// assert_eq!(
+///     fast_fibonacci::combinatorics::binomial_with_mod(1 << 40, 1 << 20, 1_000_000_007),
+///     table.binomial(1 << 40, 1 << 20)
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FactorialTable {
+    factorials: Vec<u64>,
+    inverse_factorials: Vec<u64>,
+    prime: u64,
+}
+
+impl FactorialTable {
+    /// Tables up to limit! with a prime modulo. Runtime O(min(limit, prime))
+    pub fn new(limit: u64, prime: u64) -> FactorialTable {
+        FactorialTable::try_new(limit, prime).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `new`, but returns an error instead of panicking when the modulo isn't prime.
+    pub fn try_new(limit: u64, prime: u64) -> Result<FactorialTable, FibError> {
+        check_prime(prime)?;
+        let size = limit.min(prime - 1) as usize + 1;
+        let mut factorials = Vec::with_capacity(size);
+        factorials.push(1 % prime);
+        for i in 1..size {
+            factorials.push(mul_mod(factorials[i - 1], i as u64, prime));
+        }
+        // One inversion, then (i - 1)! = i! / i going down.
+        let mut inverse_factorials = vec![0; size];
+        inverse_factorials[size - 1] = pow_mod(factorials[size - 1], prime - 2, prime);
+        for i in (1..size).rev() {
+            inverse_factorials[i - 1] = mul_mod(inverse_factorials[i], i as u64, prime);
+        }
+        Ok(FactorialTable { factorials, inverse_factorials, prime })
+    }
+
+    pub fn prime(&self) -> u64 {
+        self.prime
+    }
+
+    /// The largest n with n! in the table.
+    pub fn limit(&self) -> u64 {
+        self.factorials.len() as u64 - 1
+    }
+
+    /// n! with modulo, for n up to `limit`, or any n at or past the prime, where it's 0.
+    /// Runtime O(1)
+    pub fn factorial(&self, n: u64) -> u64 {
+        if n >= self.prime {
+            return 0;
+        }
+        self.factorials[self.index(n)]
+    }
+
+    /// 1 / n! with modulo, for n up to `limit`. Runtime O(1)
+    pub fn inverse_factorial(&self, n: u64) -> u64 {
+        self.inverse_factorials[self.index(n)]
+    }
+
+    /// n choose k with modulo, for any n and k. Runtime O(1) for n up to `limit`, and
+    /// O(log_prime(n)) past it when the table covers every digit below the prime.
+    pub fn binomial(&self, mut n: u64, mut k: u64) -> u64 {
+        let mut result = 1 % self.prime;
+        while k > 0 && result != 0 {
+            let (n_digit, k_digit) = (n % self.prime, k % self.prime);
+            let digit_binomial = if k_digit > n_digit {
+                0
+            } else if n_digit <= self.limit() {
+                let denominator = mul_mod(
+                    self.inverse_factorials[k_digit as usize],
+                    self.inverse_factorials[(n_digit - k_digit) as usize],
+                    self.prime,
+                );
+                mul_mod(self.factorials[n_digit as usize], denominator, self.prime)
+            } else {
+                binomial_mod_prime(n_digit as u128, k_digit as u128, self.prime)
+            };
+            result = mul_mod(result, digit_binomial, self.prime);
+            n /= self.prime;
+            k /= self.prime;
+        }
+        result
+    }
+
+    fn index(&self, n: u64) -> usize {
+        assert!(n <= self.limit(), "n must be at most the table's limit");
+        n as usize
+    }
+}
+
+
+/// Finds the entry k of row n of the Hosoya triangle, fib(k + 1) * fib(n - k + 1), with modulo.
+/// Runtime O(log(n))
+///
+/// Panics if k > n, see `try_hosoya_with_mod`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::combinatorics::hosoya_with_mod;
+///
+/// // fib(3) * fib(4)
+/// assert_eq!(6, hosoya_with_mod(5, 2, 1_000));
+/// ```
+pub fn hosoya_with_mod(n: u64, k: u64, modulo: u64) -> u64 {
+    try_hosoya_with_mod(n, k, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `hosoya_with_mod`, but returns an error instead of panicking.
+pub fn try_hosoya_with_mod(n: u64, k: u64, modulo: u64) -> Result<u64, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    if k > n {
+        return Err(FibError::InvalidArgument("k must be at most n"));
+    }
+    // The second of each pair, for fib(k + 1) and fib(n - k + 1) up to fib(2^64).
+    let (left, right) = (fib_pair_with_mod(k, modulo).1, fib_pair_with_mod(n - k, modulo).1);
+    Ok(mul_mod(left, right, modulo))
+}
+
+
+/// The rows of the Hosoya triangle with modulo, from row 0: 1; 1, 1; 2, 1, 2; 3, 2, 2, 3; ...
+///
+/// Entry k of row n is fib(k + 1) * fib(n - k + 1), the fibonacci analogue of n choose k. Past
+/// the first two rows, every entry is a single addition, as H(n, k) = H(n - 1, k) + H(n - 2, k)
+/// and the last two are fib(n) and fib(n + 1) = H(n - 1, n - 1) + H(n - 2, n - 2).
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::combinatorics::hosoya_rows_with_mod;
+///
+/// let rows: Vec<Vec<u64>> = hosoya_rows_with_mod(5).take(5).collect();
+/// assert_eq!(vec![vec![1], vec![1, 1], vec![2, 1, 2], vec![3, 2, 2, 3], vec![0, 3, 4, 3, 0]], rows);
+/// ```
+pub fn hosoya_rows_with_mod(modulo: u64) -> HosoyaRows {
+    try_hosoya_rows_with_mod(modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `hosoya_rows_with_mod`, but returns an error instead of panicking.
+pub fn try_hosoya_rows_with_mod(modulo: u64) -> Result<HosoyaRows, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    Ok(HosoyaRows { modulo, previous: Vec::new(), current: Vec::new() })
+}
+
+
+/// The rows of the Hosoya triangle with modulo, from `hosoya_rows_with_mod`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct HosoyaRows {
+    modulo: u64,
+    // The last two rows returned, empty before them.
+    previous: Vec<u64>,
+    current: Vec<u64>,
+}
+
+impl Iterator for HosoyaRows {
+    type Item = Vec<u64>;
+
+    fn next(&mut self) -> Option<Vec<u64>> {
+        let modulo = self.modulo;
+        let n = self.current.len();
+        let row = match n {
+            0 => vec![1 % modulo],
+            1 => vec![1 % modulo; 2],
+            _ => {
+                let (previous, current) = (&self.previous, &self.current);
+                let mut row: Vec<u64> = (0..n - 1).map(|k| add_mod(current[k], previous[k], modulo)).collect();
+                row.push(current[n - 1]);
+                row.push(add_mod(current[n - 1], previous[n - 2], modulo));
+                row
+            }
+        };
+        self.previous = core::mem::replace(&mut self.current, row.clone());
+        Some(row)
+    }
+}
+
+
+// ZeroModulus for 0, and InvalidArgument when the modulo isn't prime. Runtime O(log(prime))
+pub(crate) fn check_prime(prime: u64) -> Result<(), FibError> {
+    if prime == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    if !is_prime(prime) {
+        return Err(FibError::InvalidArgument("modulo must be prime"));
+    }
+    Ok(())
+}
+
+
+// n choose k modulo a prime, by Lucas' theorem over the base prime digits of n and k.
+pub(crate) fn binomial_mod_prime(mut n: u128, mut k: u128, prime: u64) -> u64 {
+    let mut result = 1 % prime;
+    while k > 0 {
+        let (n_digit, k_digit) = ((n % prime as u128) as u64, (k % prime as u128) as u64);
+        if k_digit > n_digit {
+            return 0;
+        }
+        // The shorter of the two equivalent products.
+        let k_digit = k_digit.min(n_digit - k_digit);
+        let (mut numerator, mut denominator) = (1 % prime, 1 % prime);
+        for i in 0..k_digit {
+            numerator = mul_mod(numerator, n_digit - i, prime);
+            denominator = mul_mod(denominator, i + 1, prime);
+        }
+        result = mul_mod(result, mul_mod(numerator, pow_mod(denominator, prime - 2, prime), prime), prime);
+        n /= prime as u128;
+        k /= prime as u128;
+    }
+    result
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::combinatorics::*;
+    use crate::fib_with_mod;
+
+    // Pascal's triangle modulo the prime.
+    fn pascal(rows: usize, prime: u64) -> Vec<Vec<u64>> {
+        let mut triangle: Vec<Vec<u64>> = vec![vec![1 % prime]];
+        for n in 1..rows {
+            let row = (0..=n)
+                .map(|k| {
+                    let left = if k > 0 { triangle[n - 1][k - 1] } else { 0 };
+                    let right = if k < n { triangle[n - 1][k] } else { 0 };
+                    (left + right) % prime
+                })
+                .collect();
+            triangle.push(row);
+        }
+        triangle
+    }
+
+    #[test]
+    fn test_binomial_matches_pascal() {
+        for &prime in &[2u64, 3, 5, 7, 13, 1_000_000_007] {
+            let triangle = pascal(100, prime);
+            let full = if prime < 1_000 { Some(FactorialTable::new(u64::MAX, prime)) } else { None };
+            let partial = FactorialTable::new(3, prime);
+            for n in 0..100u64 {
+                for k in 0..=n + 1 {
+                    let expected = triangle[n as usize].get(k as usize).copied().unwrap_or(0);
+                    assert_eq!(binomial_with_mod(n, k, prime), expected, "n = {}, k = {}, prime = {}", n, k, prime);
+                    assert_eq!(partial.binomial(n, k), expected, "n = {}, k = {}, prime = {}", n, k, prime);
+                    if let Some(full) = &full {
+                        assert_eq!(full.binomial(n, k), expected, "n = {}, k = {}, prime = {}", n, k, prime);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_factorials() {
+        let table = FactorialTable::new(1_000, 1_000_003);
+        assert_eq!(table.limit(), 1_000);
+        assert_eq!(FactorialTable::new(u64::MAX, 7).limit(), 6);
+        let mut factorial = 1;
+        for n in 0..=1_000 {
+            if n > 0 {
+                factorial = factorial * n % 1_000_003;
+            }
+            assert_eq!(table.factorial(n), factorial);
+            assert_eq!(factorial_with_mod(n, 1_000_003), factorial);
+            assert_eq!(mul_mod(table.inverse_factorial(n), factorial, 1_000_003), 1);
+        }
+        assert_eq!(table.factorial(1_000_003), 0);
+        assert_eq!(factorial_with_mod(5, 1), 0);
+    }
+
+    #[test]
+    fn test_hosoya() {
+        for &modulo in &[1, 2, 10, 1_000_000_007, u64::MAX] {
+            for (n, row) in hosoya_rows_with_mod(modulo).take(100).enumerate() {
+                let n = n as u64;
+                assert_eq!(row.len() as u64, n + 1);
+                for (k, &entry) in row.iter().enumerate() {
+                    let expected = mul_mod(fib_with_mod(k as u64 + 1, modulo), fib_with_mod(n - k as u64 + 1, modulo), modulo);
+                    assert_eq!(entry, expected, "n = {}, k = {}, modulo = {}", n, k, modulo);
+                    assert_eq!(hosoya_with_mod(n, k as u64, modulo), expected, "n = {}, k = {}, modulo = {}", n, k, modulo);
+                }
+            }
+        }
+        let big = add_mod(fib_with_mod(u64::MAX, 1_000), fib_with_mod(u64::MAX - 1, 1_000), 1_000);
+        assert_eq!(hosoya_with_mod(u64::MAX, 0, 1_000), big);
+        assert_eq!(hosoya_with_mod(u64::MAX, u64::MAX, 1_000), big);
+        assert_eq!(try_hosoya_with_mod(1, 2, 10), Err(FibError::InvalidArgument("k must be at most n")));
+        assert_eq!(try_hosoya_rows_with_mod(0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(FactorialTable::try_new(10, 0), Err(FibError::ZeroModulus));
+        assert_eq!(FactorialTable::try_new(10, 12), Err(FibError::InvalidArgument("modulo must be prime")));
+        assert_eq!(try_binomial_with_mod(5, 2, 1), Err(FibError::InvalidArgument("modulo must be prime")));
+        assert_eq!(try_factorial_with_mod(5, 0), Err(FibError::ZeroModulus));
+        assert_eq!(try_mod_inverse(5, 0), Err(FibError::ZeroModulus));
+        assert_eq!(mod_inverse(5, 1), Some(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "n must be at most the table's limit")]
+    fn test_past_limit() {
+        FactorialTable::new(10, 1_000_000_007).inverse_factorial(11);
+    }
+}
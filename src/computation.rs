@@ -0,0 +1,451 @@
+//! Long running BigUint computations, one step at a time.
+//!
+//! `BigFibComputation` runs fast doubling over the bits of n from the top, so its whole state is
+//! the pair (fib(k), fib(k + 1)) for the prefix k of n processed so far, the same information as
+//! the matrix [[fib(k - 1), fib(k)], [fib(k), fib(k + 1)]]. That state can be saved with
+//! `to_bytes` and resumed with `from_bytes`, for example by batch jobs over indices with millions
+//! of bits.
+
+use num_bigint::BigUint;
+use num::Zero;
+use std::convert::{TryFrom, TryInto};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::wipe::{wipe, wipe_bytes};
+use crate::{fib_pair_doubling, FibError};
+
+const MAGIC: &[u8; 4] = b"FFCK";
+const VERSION: u8 = 1;
+// Doubling steps between two checks for cancellation, so the checks cost little next to the
+// arithmetic.
+pub(crate) const CHECK_INTERVAL: u64 = 16;
+
+
+/// A computation of fib(n) with modulo, see the module documentation.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::computation::BigFibComputation;
+/// use num_bigint::BigUint;
+///
+/// let n = BigUint::parse_bytes(b"1000000000000000", 10).unwrap();
+/// let modulo = BigUint::parse_bytes(b"1000000", 10).unwrap();
+/// let mut computation = BigFibComputation::new(&n, &modulo).unwrap();
+/// computation.step(20);
+///
+/// // Later, or somewhere else.
+/// let bytes = computation.to_bytes();
+/// let mut resumed = BigFibComputation::from_bytes(&bytes).unwrap();
+/// assert_eq!(BigUint::parse_bytes(b"546875", 10).unwrap(), resumed.run());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BigFibComputation {
+    n: BigUint,
+    // n in little endian bytes, so `step` can test bits without encoding n again.
+    n_bytes: Vec<u8>,
+    modulo: BigUint,
+    // How many of n's bits, from the most significant, are processed.
+    bits_done: u64,
+    f_k: BigUint,
+    f_k1: BigUint,
+}
+
+impl BigFibComputation {
+    /// Starts computing fib(n) with modulo. Nothing is computed until `step` or `run`.
+    pub fn new(n: &BigUint, modulo: &BigUint) -> Result<BigFibComputation, FibError> {
+        if modulo.is_zero() {
+            return Err(FibError::ZeroModulus);
+        }
+        Ok(BigFibComputation {
+            n: n.clone(),
+            n_bytes: n.to_bytes_le(),
+            modulo: modulo.clone(),
+            bits_done: 0,
+            f_k: BigUint::zero(),
+            f_k1: BigUint::from(1u32) % modulo,
+        })
+    }
+
+    /// Processes up to `max_bits` more bits of n, one doubling each. Returns whether the
+    /// computation is done.
+    pub fn step(&mut self, max_bits: u64) -> bool {
+        for _ in 0..max_bits {
+            if self.is_done() {
+                break;
+            }
+            let bit = self.total_bits() - 1 - self.bits_done;
+            let m = &self.modulo;
+            // The doubling formulas of `fib_pair_doubling`, on BigUints.
+            let mut f_2k = &self.f_k * ((&self.f_k1 * 2u32 + m - &self.f_k) % m) % m;
+            let f_2k1 = (&self.f_k * &self.f_k + &self.f_k1 * &self.f_k1) % m;
+            wipe(&mut self.f_k);
+            wipe(&mut self.f_k1);
+            if self.n_bytes[(bit / 8) as usize] >> (bit % 8) & 1 == 1 {
+                self.f_k1 = (&f_2k + &f_2k1) % m;
+                self.f_k = f_2k1;
+                wipe(&mut f_2k);
+            } else {
+                self.f_k = f_2k;
+                self.f_k1 = f_2k1;
+            }
+            self.bits_done += 1;
+        }
+        self.is_done()
+    }
+
+    /// Runs the computation to the end, and returns fib(n) with modulo.
+    pub fn run(&mut self) -> BigUint {
+        self.step(u64::MAX);
+        self.f_k.clone()
+    }
+
+    /// Whether every bit of n has been processed.
+    pub fn is_done(&self) -> bool {
+        self.bits_done == self.total_bits()
+    }
+
+    /// fib(n) with modulo, once done.
+    pub fn result(&self) -> Option<&BigUint> {
+        if self.is_done() {
+            Some(&self.f_k)
+        } else {
+            None
+        }
+    }
+
+    /// The fraction of n's bits processed so far, from 0 to 1.
+    pub fn progress(&self) -> f64 {
+        if self.total_bits() == 0 {
+            return 1.0;
+        }
+        self.bits_done as f64 / self.total_bits() as f64
+    }
+
+    /// Number of bits of n, which is the number of steps the whole computation takes.
+    pub fn total_bits(&self) -> u64 {
+        self.n.bits() as u64
+    }
+
+    // With the `zeroize` feature, overwrites the numbers, which all derive from n and modulo.
+    fn wipe(&mut self) {
+        for number in &mut [&mut self.n, &mut self.modulo, &mut self.f_k, &mut self.f_k1] {
+            wipe(number);
+        }
+        wipe_bytes(&mut self.n_bytes);
+    }
+
+    /// Saves the state, to be resumed with `from_bytes`.
+    ///
+    /// The format is "FFCK", a version byte, bits_done as a little endian u64, and then n, modulo,
+    /// fib(k) and fib(k + 1), each as a little endian u64 length followed by that many little
+    /// endian bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend_from_slice(&self.bits_done.to_le_bytes());
+        for number in &[&self.n, &self.modulo, &self.f_k, &self.f_k1] {
+            let number_bytes = number.to_bytes_le();
+            bytes.extend_from_slice(&(number_bytes.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&number_bytes);
+        }
+        bytes
+    }
+
+    /// Resumes a computation saved with `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<BigFibComputation, FibError> {
+        let malformed = FibError::InvalidArgument("malformed checkpoint");
+        let mut reader = Reader { bytes };
+        if reader.take(4) != Some(&MAGIC[..]) || reader.take(1) != Some(&[VERSION][..]) {
+            return Err(malformed);
+        }
+        let bits_done = reader.u64().ok_or(malformed)?;
+        let mut numbers = Vec::with_capacity(4);
+        for _ in 0..4 {
+            let len = reader.u64().ok_or(malformed)?;
+            let len = usize::try_from(len).map_err(|_| malformed)?;
+            numbers.push(BigUint::from_bytes_le(reader.take(len).ok_or(malformed)?));
+        }
+        if !reader.bytes.is_empty() {
+            return Err(malformed);
+        }
+        let f_k1 = numbers.pop().unwrap();
+        let f_k = numbers.pop().unwrap();
+        let modulo = numbers.pop().unwrap();
+        let n = numbers.pop().unwrap();
+        let n_bytes = n.to_bytes_le();
+        let computation = BigFibComputation { n, n_bytes, modulo, bits_done, f_k, f_k1 };
+        if computation.modulo.is_zero()
+            || computation.bits_done > computation.total_bits()
+            || computation.f_k >= computation.modulo
+            || computation.f_k1 >= computation.modulo
+        {
+            return Err(malformed);
+        }
+        Ok(computation)
+    }
+}
+
+
+/// Like `bigfib_with_mod`, but reports progress and can be cancelled. Runtime O(log(n))
+///
+/// After every 16 doubling steps, calls `progress` with the fraction of n's bits processed so
+/// far, and then returns `FibError::Cancelled` if `cancel` is set, e.g. from another thread.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::computation::bigfib_with_mod_cancellable;
+/// use num_bigint::BigUint;
+/// use std::sync::atomic::AtomicBool;
+///
+/// let n = BigUint::parse_bytes(b"1000000000000000", 10).unwrap();
+/// let modulo = BigUint::parse_bytes(b"1000000", 10).unwrap();
+/// let mut last_progress = 0.0;
+/// let result = bigfib_with_mod_cancellable(&n, &modulo, |p| last_progress = p, &AtomicBool::new(false));
+/// assert_eq!(Ok(BigUint::parse_bytes(b"546875", 10).unwrap()), result);
+/// assert_eq!(1.0, last_progress);
+/// ```
+pub fn bigfib_with_mod_cancellable<F: FnMut(f64)>(
+    n: &BigUint,
+    modulo: &BigUint,
+    progress: F,
+    cancel: &AtomicBool,
+) -> Result<BigUint, FibError> {
+    finish(BigFibComputation::new(n, modulo)?, progress, Some(cancel), None)
+}
+
+
+/// Like `fib_with_mod`, but gives up with `FibError::Timeout` once `budget` has passed.
+/// Runtime O(log(n))
+///
+/// The clock is checked before every doubling step, so a zero budget times out for any n > 0.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::computation::fib_with_mod_timeout;
+/// use std::time::Duration;
+///
+/// assert_eq!(Ok(55), fib_with_mod_timeout(10, 1000, Duration::from_secs(1)));
+/// ```
+pub fn fib_with_mod_timeout(n: u64, modulo: u64, budget: Duration) -> Result<u64, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    let deadline = Instant::now().checked_add(budget);
+    let (f_n, _) = fib_pair_doubling(n as u128, modulo, || match deadline {
+        Some(deadline) if Instant::now() >= deadline => Err(FibError::Timeout),
+        _ => Ok(()),
+    })?;
+    Ok(f_n)
+}
+
+
+/// BigUint version of `fib_with_mod_timeout`. Runtime O(log(n))
+///
+/// The clock is checked before every 16 doubling steps.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::computation::bigfib_with_mod_timeout;
+/// use fast_fibonacci::FibError;
+/// use num_bigint::BigUint;
+/// use std::time::Duration;
+///
+/// let n = BigUint::parse_bytes(b"1000000000000000", 10).unwrap();
+/// let modulo = BigUint::parse_bytes(b"1000000", 10).unwrap();
+/// assert_eq!(Err(FibError::Timeout), bigfib_with_mod_timeout(&n, &modulo, Duration::from_secs(0)));
+/// ```
+pub fn bigfib_with_mod_timeout(n: &BigUint, modulo: &BigUint, budget: Duration) -> Result<BigUint, FibError> {
+    let deadline = Instant::now().checked_add(budget);
+    finish(BigFibComputation::new(n, modulo)?, |_| {}, None, deadline)
+}
+
+
+// Runs a computation to the end. Every CHECK_INTERVAL doubling steps, returns an error if
+// `cancel` is set or `deadline` has passed, and otherwise calls `progress` after the steps.
+pub(crate) fn finish<F: FnMut(f64)>(
+    mut computation: BigFibComputation,
+    mut progress: F,
+    cancel: Option<&AtomicBool>,
+    deadline: Option<Instant>,
+) -> Result<BigUint, FibError> {
+    while !computation.is_done() {
+        if cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+            return Err(FibError::Cancelled);
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(FibError::Timeout);
+        }
+        computation.step(CHECK_INTERVAL);
+        progress(computation.progress());
+    }
+    Ok(std::mem::take(&mut computation.f_k))
+}
+
+
+impl Drop for BigFibComputation {
+    fn drop(&mut self) {
+        self.wipe();
+    }
+}
+
+/// Wipes n, modulo and the state, leaving a finished computation of fib(0) modulo 0 that must
+/// not be stepped again. Dropping a computation wipes it anyway.
+#[cfg(feature = "zeroize")]
+impl zeroize::Zeroize for BigFibComputation {
+    fn zeroize(&mut self) {
+        self.wipe();
+        self.bits_done.zeroize();
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl zeroize::ZeroizeOnDrop for BigFibComputation {}
+
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < len {
+            return None;
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(taken)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::computation::*;
+
+    fn big(digits: &str) -> BigUint {
+        BigUint::parse_bytes(digits.as_bytes(), 10).unwrap()
+    }
+
+    #[test]
+    fn test_run() {
+        for &(n, modulo) in &[("0", "10"), ("1", "1"), ("10", "100"), ("1000000000000000", "1000000")] {
+            let (n, modulo) = (big(n), big(modulo));
+            let mut computation = BigFibComputation::new(&n, &modulo).unwrap();
+            assert_eq!(computation.run(), crate::bigfib_with_mod(&n, &modulo));
+            assert!(computation.is_done());
+            assert_eq!(computation.progress(), 1.0);
+        }
+        assert_eq!(BigFibComputation::new(&big("10"), &big("0")), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let n = big("123456789012345678901234567890123456789");
+        let modulo = big("98765432109876543210987654321");
+        let expected = crate::bigfib_with_mod(&n, &modulo);
+
+        let mut computation = BigFibComputation::new(&n, &modulo).unwrap();
+        let mut checkpoints = 0;
+        while !computation.step(7) {
+            assert_eq!(computation.result(), None);
+            let bytes = computation.to_bytes();
+            computation = BigFibComputation::from_bytes(&bytes).unwrap();
+            checkpoints += 1;
+        }
+        assert_eq!(checkpoints, computation.total_bits() / 7);
+        assert_eq!(computation.result(), Some(&expected));
+    }
+
+    #[test]
+    fn test_step_one_bit_at_a_time() {
+        // Stepping used to encode n again on every call, which made this quadratic.
+        let n = (BigUint::from(1u32) << 200_000) - 1u32;
+        let modulo = big("1000000007");
+        let mut computation = BigFibComputation::new(&n, &modulo).unwrap();
+        while !computation.step(1) {}
+        assert_eq!(computation.result(), Some(&BigFibComputation::new(&n, &modulo).unwrap().run()));
+    }
+
+    #[test]
+    fn test_cancellable() {
+        let n = big("123456789012345678901234567890");
+        let modulo = big("1000000007");
+        let cancel = AtomicBool::new(false);
+        let mut reports = Vec::new();
+        let result = bigfib_with_mod_cancellable(&n, &modulo, |p| reports.push(p), &cancel);
+        assert_eq!(result, Ok(crate::bigfib_with_mod(&n, &modulo)));
+        assert_eq!(reports.len() as u64, (n.bits() as u64).div_ceil(CHECK_INTERVAL));
+        assert!(reports.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(reports.last(), Some(&1.0));
+
+        let mut steps = 0;
+        let result = bigfib_with_mod_cancellable(
+            &n,
+            &modulo,
+            |_| {
+                steps += 1;
+                if steps == 3 {
+                    cancel.store(true, Ordering::Relaxed);
+                }
+            },
+            &cancel,
+        );
+        assert_eq!(result, Err(FibError::Cancelled));
+        assert_eq!(steps, 3);
+    }
+
+    #[test]
+    fn test_timeout() {
+        let long = Duration::from_secs(3600);
+        for n in (0..200).chain(u64::MAX - 10..=u64::MAX) {
+            for &modulo in &[1, 2, 1000, 1_000_000_007, u64::MAX] {
+                assert_eq!(fib_with_mod_timeout(n, modulo, long), Ok(crate::fib_with_mod(n, modulo)));
+            }
+        }
+        assert_eq!(fib_with_mod_timeout(10, 0, long), Err(FibError::ZeroModulus));
+        assert_eq!(fib_with_mod_timeout(0, 10, Duration::from_secs(0)), Ok(0));
+        assert_eq!(fib_with_mod_timeout(10, 10, Duration::from_secs(0)), Err(FibError::Timeout));
+        assert_eq!(fib_with_mod_timeout(10, 10, Duration::MAX), Ok(5));
+
+        let n = big("123456789012345678901234567890");
+        let modulo = big("1000000007");
+        assert_eq!(bigfib_with_mod_timeout(&n, &modulo, long), Ok(crate::bigfib_with_mod(&n, &modulo)));
+        assert_eq!(bigfib_with_mod_timeout(&n, &modulo, Duration::from_secs(0)), Err(FibError::Timeout));
+        assert_eq!(bigfib_with_mod_timeout(&n, &big("0"), long), Err(FibError::ZeroModulus));
+    }
+
+    #[cfg(feature = "zeroize")]
+    #[test]
+    fn test_zeroize() {
+        use zeroize::Zeroize;
+
+        let mut computation = BigFibComputation::new(&big("1000"), &big("97")).unwrap();
+        computation.step(3);
+        computation.zeroize();
+        assert!(computation.is_done());
+        assert_eq!(computation.result(), Some(&big("0")));
+    }
+
+    #[test]
+    fn test_malformed_checkpoint() {
+        let computation = BigFibComputation::new(&big("1000"), &big("97")).unwrap();
+        let bytes = computation.to_bytes();
+        assert!(BigFibComputation::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(BigFibComputation::from_bytes(&[bytes.clone(), vec![0]].concat()).is_err());
+        assert!(BigFibComputation::from_bytes(b"nope").is_err());
+        assert!(BigFibComputation::from_bytes(&[]).is_err());
+
+        let mut huge_length = bytes.clone();
+        huge_length[13..21].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(BigFibComputation::from_bytes(&huge_length).is_err());
+
+        let mut too_far = bytes;
+        too_far[5..13].copy_from_slice(&100u64.to_le_bytes());
+        assert!(BigFibComputation::from_bytes(&too_far).is_err());
+    }
+}
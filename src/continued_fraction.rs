@@ -0,0 +1,128 @@
+//! Continued fraction convergents, for φ = [1; 1, 1, 1, ...] and √5 = [2; 4, 4, 4, ...] in particular.
+//!
+//! Both are tied to fibonacci and lucas numbers: the convergents of φ are fib(n + 1) / fib(n), and
+//! the convergents of √5 are (lucas(3k) / 2) / (fib(3k) / 2), which solve the Pell equations
+//! p^2 - 5q^2 = ±1.
+
+use num_bigint::BigUint;
+use num::FromPrimitive;
+use std::iter;
+
+/// The convergents of φ are fib(n + 1) / fib(n), which `golden::convergents` already iterates over.
+pub use crate::golden::convergents as phi_convergents;
+
+
+/// Iterates over the convergents p / q of the continued fraction with the given partial quotients
+/// [a0; a1, a2, ...], as exact (p, q) pairs.
+///
+/// # Examples
+/// ```
+/// use num::ToPrimitive;
+///
+/// // 355 / 113 is the famous convergent of π = [3; 7, 15, 1, 292, ...]
+/// let (p, q) = fast_fibonacci::continued_fraction::convergents(vec![3, 7, 15, 1])
+///     .last()
+///     .unwrap();
+/// assert_eq!((355, 113), (p.to_u64().unwrap(), q.to_u64().unwrap()));
+/// ```
+pub fn convergents<I: IntoIterator<Item = u64>>(quotients: I) -> Convergents<I::IntoIter> {
+    Convergents {
+        quotients: quotients.into_iter(),
+        p: FromPrimitive::from_u64(1).unwrap(),
+        p_prev: FromPrimitive::from_u64(0).unwrap(),
+        q: FromPrimitive::from_u64(0).unwrap(),
+        q_prev: FromPrimitive::from_u64(1).unwrap(),
+    }
+}
+
+
+/// Partial quotients of φ, [1; 1, 1, 1, ...].
+pub fn phi_quotients() -> impl Iterator<Item = u64> {
+    iter::repeat(1)
+}
+
+
+/// Partial quotients of √5, [2; 4, 4, 4, ...].
+pub fn sqrt_5_quotients() -> impl Iterator<Item = u64> {
+    iter::once(2).chain(iter::repeat(4))
+}
+
+
+/// Iterates over the convergents of √5: 2/1, 9/4, 38/17, 161/72, ...
+///
+/// The kth convergent (starting from k = 1) is (lucas(3k) / 2) / (fib(3k) / 2), and satisfies
+/// p^2 - 5q^2 = (-1)^k.
+///
+/// # Examples
+/// ```
+/// use num::ToPrimitive;
+///
+/// let (p, q) = fast_fibonacci::continued_fraction::sqrt_5_convergents().nth(3).unwrap();
+/// assert_eq!((161, 72), (p.to_u64().unwrap(), q.to_u64().unwrap()));
+/// assert_eq!(161 * 161 - 5 * 72 * 72, 1);
+/// ```
+pub fn sqrt_5_convergents() -> Convergents<impl Iterator<Item = u64>> {
+    convergents(sqrt_5_quotients())
+}
+
+
+/// Iterator over continued fraction convergents, created by `convergents`.
+#[derive(Clone, Debug)]
+pub struct Convergents<I> {
+    quotients: I,
+    p: BigUint,
+    p_prev: BigUint,
+    q: BigUint,
+    q_prev: BigUint,
+}
+
+impl<I: Iterator<Item = u64>> Iterator for Convergents<I> {
+    type Item = (BigUint, BigUint);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let a: BigUint = FromPrimitive::from_u64(self.quotients.next()?).unwrap();
+        let p_next = &a * &self.p + &self.p_prev;
+        let q_next = &a * &self.q + &self.q_prev;
+        self.p_prev = std::mem::replace(&mut self.p, p_next);
+        self.q_prev = std::mem::replace(&mut self.q, q_next);
+        Some((self.p.clone(), self.q.clone()))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::continued_fraction::*;
+
+    #[test]
+    fn test_phi_quotients_match_golden() {
+        for (ours, golden) in convergents(phi_quotients()).zip(phi_convergents()).take(100) {
+            assert_eq!(ours, golden);
+        }
+    }
+
+    #[test]
+    fn test_sqrt_5_convergents_are_lucas_over_fib() {
+        let two: BigUint = FromPrimitive::from_u64(2).unwrap();
+        let five: BigUint = FromPrimitive::from_u64(5).unwrap();
+        for (k, (p, q)) in (1..40u64).zip(sqrt_5_convergents()) {
+            let (f_n, f_n1) = crate::exact_bigfib_pair(3 * k);
+            let lucas = &two * &f_n1 - &f_n;
+            assert_eq!(&p * &two, lucas);
+            assert_eq!(&q * &two, f_n);
+
+            let (p2, q2) = (&p * &p, &five * &q * &q);
+            if k % 2 == 0 {
+                assert_eq!(p2, q2 + 1u32);
+            } else {
+                assert_eq!(p2 + 1u32, q2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_finite_quotients() {
+        assert_eq!(convergents(vec![]).next(), None);
+        assert_eq!(convergents(vec![3, 7, 15, 1]).count(), 4);
+    }
+}
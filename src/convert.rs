@@ -0,0 +1,267 @@
+use num_bigint::BigUint;
+use num::{pow, Zero};
+
+use crate::{bignum, FibError};
+
+/// The largest power of ten `parse_biguint` expands, a number of about 415 KB.
+pub const MAX_DECIMAL_EXPONENT: usize = 1_000_000;
+
+
+/// Anything `bigfib_with_mod_of` takes as n or modulo: unsigned integers, BigUints and strings
+/// that `parse_biguint` accepts.
+pub trait IntoBigUint {
+    fn into_biguint(self) -> Result<BigUint, FibError>;
+}
+
+macro_rules! impl_into_biguint {
+    ($($t:ty),*) => {
+        $(
+            impl IntoBigUint for $t {
+                fn into_biguint(self) -> Result<BigUint, FibError> {
+                    Ok(BigUint::from(self))
+                }
+            }
+        )*
+    };
+}
+
+impl_into_biguint!(u8, u16, u32, u64, u128, usize, BigUint);
+
+impl IntoBigUint for &BigUint {
+    fn into_biguint(self) -> Result<BigUint, FibError> {
+        Ok(self.clone())
+    }
+}
+
+impl IntoBigUint for &str {
+    fn into_biguint(self) -> Result<BigUint, FibError> {
+        parse_biguint(self)
+    }
+}
+
+impl IntoBigUint for &String {
+    fn into_biguint(self) -> Result<BigUint, FibError> {
+        self.as_str().into_biguint()
+    }
+}
+
+
+/// Parses a non-negative integer written in decimal, like `123_456`, or in scientific notation,
+/// like `1e100` or `2.5E3`, as long as it's an integer. `_` separators are ignored.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::{parse_biguint, FibError};
+/// use num_bigint::BigUint;
+///
+/// assert_eq!(Ok(BigUint::from(1_000_000_007u32)), parse_biguint("1_000_000_007"));
+/// assert_eq!(Ok(BigUint::from(2_500u32)), parse_biguint("2.5e3"));
+/// assert_eq!(Err(FibError::InvalidArgument("not an integer")), parse_biguint("2.5"));
+/// ```
+pub fn parse_biguint(s: &str) -> Result<BigUint, FibError> {
+    let s = s.replace('_', "");
+    let (mantissa, exponent) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s.as_str(), None),
+    };
+    let (int_digits, frac_digits) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+    let exponent = match exponent {
+        Some(exponent) => decimal_digits(exponent.strip_prefix('+').unwrap_or(exponent))?
+            .parse::<usize>()
+            .map_err(|_| FibError::InvalidArgument("exponent is too large"))?,
+        None => 0,
+    };
+    if int_digits.is_empty() && frac_digits.is_empty() {
+        return Err(MALFORMED);
+    }
+    let digits = format!("{}{}", decimal_digits_or_empty(int_digits)?, decimal_digits_or_empty(frac_digits)?);
+    let mut n = BigUint::parse_bytes(digits.as_bytes(), 10).unwrap();
+    if exponent >= frac_digits.len() {
+        if exponent - frac_digits.len() > MAX_DECIMAL_EXPONENT {
+            return Err(FibError::InvalidArgument("exponent is too large"));
+        }
+        n *= pow(BigUint::from(10u32), exponent - frac_digits.len());
+    } else {
+        let divisor = pow(BigUint::from(10u32), frac_digits.len() - exponent);
+        if !(&n % &divisor).is_zero() {
+            return Err(FibError::InvalidArgument("not an integer"));
+        }
+        n /= divisor;
+    }
+    Ok(n)
+}
+
+
+const MALFORMED: FibError = FibError::InvalidArgument("expected a non-negative integer like 123 or 1e100");
+
+fn decimal_digits(s: &str) -> Result<&str, FibError> {
+    if s.is_empty() {
+        return Err(MALFORMED);
+    }
+    decimal_digits_or_empty(s)
+}
+
+fn decimal_digits_or_empty(s: &str) -> Result<&str, FibError> {
+    if s.bytes().all(|b| b.is_ascii_digit()) {
+        Ok(s)
+    } else {
+        Err(MALFORMED)
+    }
+}
+
+
+/// bigfib_with_mod for any mix of integer types and decimal strings. Runtime O(log(n))
+///
+/// # Examples
+/// ```
+/// use num_bigint::BigUint;
+///
+/// assert_eq!(
+///     BigUint::from(209_783_453u32),
+///     fast_fibonacci::bigfib_with_mod_of(10u64.pow(18), "1000000007")
+/// );
+/// assert_eq!(BigUint::from(55u32), fast_fibonacci::bigfib_with_mod_of("10", 100u8));
+/// ```
+pub fn bigfib_with_mod_of<N: IntoBigUint, M: IntoBigUint>(n: N, modulo: M) -> BigUint {
+    try_bigfib_with_mod_of(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `bigfib_with_mod_of`, but returns an error instead of panicking, including for strings
+/// that aren't integers.
+pub fn try_bigfib_with_mod_of<N: IntoBigUint, M: IntoBigUint>(n: N, modulo: M) -> Result<BigUint, FibError> {
+    crate::try_bigfib_with_mod(&n.into_biguint()?, &modulo.into_biguint()?)
+}
+
+
+/// Finds the nth fibonacci number with modulo, for n and modulo written as `parse_biguint`
+/// accepts. Runtime O(log(n))
+///
+/// # Examples
+/// ```
+/// use num_bigint::BigUint;
+///
+/// assert_eq!(BigUint::from(546_875u32), fast_fibonacci::fib_with_mod_str("1e15", "1_000_000"));
+/// ```
+pub fn fib_with_mod_str(n: &str, modulo: &str) -> BigUint {
+    try_fib_with_mod_str(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_with_mod_str`, but returns an error instead of panicking.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::FibError;
+///
+/// assert_eq!(Err(FibError::ZeroModulus), fast_fibonacci::try_fib_with_mod_str("1e100", "0"));
+/// assert!(fast_fibonacci::try_fib_with_mod_str("ten", "100").is_err());
+/// ```
+pub fn try_fib_with_mod_str(n: &str, modulo: &str) -> Result<BigUint, FibError> {
+    try_bigfib_with_mod_of(n, modulo)
+}
+
+
+/// Finds the nth fibonacci number with modulo, for n given by its big endian bytes, as from a
+/// hash or a network protocol, without converting it to a BigUint. Runtime O(log(n))
+///
+/// # Examples
+/// ```
+/// use num_bigint::BigUint;
+///
+/// let n = 1_000_000_000_000_000u64.to_be_bytes();
+/// let modulo = BigUint::from(1_000_000u32);
+/// assert_eq!(BigUint::from(546_875u32), fast_fibonacci::bigfib_with_mod_bytes(&n, &modulo));
+/// ```
+pub fn bigfib_with_mod_bytes(n_be: &[u8], modulo: &BigUint) -> BigUint {
+    try_bigfib_with_mod_bytes(n_be, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `bigfib_with_mod_bytes`, but returns an error instead of panicking.
+pub fn try_bigfib_with_mod_bytes(n_be: &[u8], modulo: &BigUint) -> Result<BigUint, FibError> {
+    if modulo.is_zero() {
+        return Err(FibError::ZeroModulus);
+    }
+    let bits = n_be.iter().flat_map(|byte| (0..8).rev().map(move |bit| byte >> bit & 1 == 1));
+    Ok(bignum::fib_over_bits(bits, modulo))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::convert::*;
+
+    #[test]
+    fn test_into_biguint() {
+        let expected = BigUint::from(1_000_000u32);
+        assert_eq!(1_000_000u32.into_biguint(), Ok(expected.clone()));
+        assert_eq!(1_000_000u128.into_biguint(), Ok(expected.clone()));
+        assert_eq!((&expected).into_biguint(), Ok(expected.clone()));
+        assert_eq!("1_000_000".into_biguint(), Ok(expected.clone()));
+        assert_eq!((&"1000000".to_string()).into_biguint(), Ok(expected));
+        for &bad in &["", "_", "-1", " 1", "0x10"] {
+            assert!(bad.into_biguint().is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_parse_biguint() {
+        let parse = |s: &str| parse_biguint(s).map(|n| n.to_string());
+        for &(s, expected) in &[
+            ("0", "0"),
+            ("007", "7"),
+            ("1e6", "1000000"),
+            ("1E+6", "1000000"),
+            ("2.5e3", "2500"),
+            (".5e1", "5"),
+            ("5.e0", "5"),
+            ("12.000", "12"),
+            ("1_000e3", "1000000"),
+        ] {
+            assert_eq!(parse(s), Ok(expected.to_string()), "{:?}", s);
+        }
+        assert_eq!(parse("1e100"), Ok(format!("1{}", "0".repeat(100))));
+        assert_eq!(parse_biguint("1e1000"), Ok(pow(BigUint::from(10u32), 1_000)));
+        assert_eq!(parse("1e1000001"), Err(FibError::InvalidArgument("exponent is too large")));
+        assert_eq!(parse("1e100000000000000000000"), Err(FibError::InvalidArgument("exponent is too large")));
+        assert_eq!(parse("1.5"), Err(FibError::InvalidArgument("not an integer")));
+        assert_eq!(parse("15e-1"), Err(MALFORMED));
+        for &bad in &["", ".", "e5", "1e", "1e+", "1.2.3", "1e5e5", "-1e5", "1 e5", "0x10", "1e5.0"] {
+            assert_eq!(parse(bad), Err(MALFORMED), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn test_bigfib_with_mod_of() {
+        let n = BigUint::from(1_000_000_000_000_000u64);
+        let modulo = BigUint::from(1_000_000u32);
+        assert_eq!(bigfib_with_mod_of(&n, &modulo), crate::bigfib_with_mod(&n, &modulo));
+        assert_eq!(bigfib_with_mod_of(1_000_000_000_000_000u64, "1000000"), BigUint::from(546_875u32));
+        assert_eq!(try_bigfib_with_mod_of(10u32, 0u32), Err(FibError::ZeroModulus));
+        assert!(try_bigfib_with_mod_of("ten", 100u32).is_err());
+
+        assert_eq!(fib_with_mod_str("1e15", "1e6"), BigUint::from(546_875u32));
+        assert_eq!(fib_with_mod_str("123456789012345678901234567890", "1000000007"), crate::bigfib_with_mod(
+            &BigUint::parse_bytes(b"123456789012345678901234567890", 10).unwrap(),
+            &BigUint::from(1_000_000_007u32)
+        ));
+        assert_eq!(try_fib_with_mod_str("10", "1.5"), Err(FibError::InvalidArgument("not an integer")));
+    }
+
+    #[test]
+    fn test_bigfib_with_mod_bytes() {
+        let modulo = (BigUint::from(1u32) << 127) - 1u32;
+        for n in &[BigUint::from(0u32), BigUint::from(1u32), BigUint::from(10u32), &modulo * 12_345u32] {
+            let n_be = n.to_bytes_be();
+            assert_eq!(bigfib_with_mod_bytes(&n_be, &modulo), crate::bigfib_with_mod(n, &modulo));
+            // Leading zero bytes don't change n.
+            assert_eq!(bigfib_with_mod_bytes(&[&[0, 0][..], &n_be].concat(), &modulo), crate::bigfib_with_mod(n, &modulo));
+        }
+        assert_eq!(bigfib_with_mod_bytes(&[], &modulo), BigUint::from(0u32));
+        assert_eq!(try_bigfib_with_mod_bytes(&[10], &BigUint::from(0u32)), Err(FibError::ZeroModulus));
+    }
+}
@@ -0,0 +1,142 @@
+//! Constant time fibonacci and lucas numbers with modulo, with the `constant-time` feature.
+//!
+//! The index is treated as secret: every call does the same 64 doubling steps whatever n is,
+//! picks between results with masks instead of branches, and multiplies by shift and add instead
+//! of u128 division, whose timing depends on its operands on many CPUs. The modulo is treated as
+//! public. This is about 100 times slower than `fib_with_mod`, and like any constant time Rust
+//! code, it relies on the compiler not turning the masks back into branches.
+
+use core::hint::black_box;
+
+use crate::FibError;
+
+
+/// Finds the nth fibonacci number with modulo, in time independent of n. Runtime O(1), with
+/// 64 doubling steps of 64 iterations each.
+///
+/// # Examples
+/// ```
+/// assert_eq!(55, fast_fibonacci::ct::fib_with_mod(10, 100));
+/// assert_eq!(546_875, fast_fibonacci::ct::fib_with_mod(1_000_000_000_000_000, 1_000_000));
+/// ```
+pub fn fib_with_mod(n: u64, modulo: u64) -> u64 {
+    try_fib_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_with_mod`, but returns an error instead of panicking.
+pub fn try_fib_with_mod(n: u64, modulo: u64) -> Result<u64, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    Ok(fib_pair(n, modulo).0)
+}
+
+
+/// Finds the nth lucas number with modulo, in time independent of n. Runtime O(1), as
+/// `fib_with_mod`.
+///
+/// Uses lucas(n) = 2 * fib(n + 1) - fib(n).
+///
+/// # Examples
+/// ```
+/// assert_eq!(2, fast_fibonacci::ct::lucas_with_mod(0, 100));
+/// assert_eq!(23, fast_fibonacci::ct::lucas_with_mod(10, 100));
+/// ```
+pub fn lucas_with_mod(n: u64, modulo: u64) -> u64 {
+    try_lucas_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `lucas_with_mod`, but returns an error instead of panicking.
+pub fn try_lucas_with_mod(n: u64, modulo: u64) -> Result<u64, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    let (f_n, f_n1) = fib_pair(n, modulo);
+    Ok(sub_mod(add_mod(f_n1, f_n1, modulo), f_n, modulo))
+}
+
+
+// (fib(n), fib(n + 1)) by fast doubling over all 64 bits of n.
+fn fib_pair(n: u64, modulo: u64) -> (u64, u64) {
+    let (mut a, mut b) = (0, 1 % modulo);
+    for bit in (0..64).rev() {
+        // fib(2k) = fib(k) * (2 * fib(k + 1) - fib(k)), fib(2k + 1) = fib(k)^2 + fib(k + 1)^2
+        let even = mul_mod(a, sub_mod(add_mod(b, b, modulo), a, modulo), modulo);
+        let odd = add_mod(mul_mod(a, a, modulo), mul_mod(b, b, modulo), modulo);
+        let mask = ((n >> bit) & 1).wrapping_neg();
+        a = select(mask, odd, even);
+        b = select(mask, add_mod(even, odd, modulo), odd);
+    }
+    (a, b)
+}
+
+
+// x where mask is all ones, y where it's all zeros.
+fn select(mask: u64, x: u64, y: u64) -> u64 {
+    let mask = black_box(mask);
+    (x & mask) | (y & !mask)
+}
+
+
+// a and b must already be reduced.
+fn add_mod(a: u64, b: u64, modulo: u64) -> u64 {
+    let sum = a as u128 + b as u128;
+    let diff = sum.wrapping_sub(modulo as u128);
+    // All ones when sum < modulo, so the subtraction borrowed.
+    let borrow = ((diff >> 127) as u64).wrapping_neg();
+    select(borrow, sum as u64, diff as u64)
+}
+
+
+// a and b must already be reduced.
+fn sub_mod(a: u64, b: u64, modulo: u64) -> u64 {
+    let (diff, borrowed) = a.overflowing_sub(b);
+    select((borrowed as u64).wrapping_neg(), diff.wrapping_add(modulo), diff)
+}
+
+
+// a and b must already be reduced. Doubles and adds over all 64 bits of b.
+fn mul_mod(a: u64, b: u64, modulo: u64) -> u64 {
+    let mut product = 0;
+    for bit in (0..64).rev() {
+        product = add_mod(product, product, modulo);
+        let mask = ((b >> bit) & 1).wrapping_neg();
+        product = add_mod(product, a & mask, modulo);
+    }
+    product
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::ct::*;
+
+    #[test]
+    fn test_agrees_with_fib_with_mod() {
+        for &modulo in &[1, 2, 3, 10, 1_000_000_007, 1 << 63, u64::MAX - 1, u64::MAX] {
+            for &n in &[0, 1, 2, 3, 10, 1_000_000_000_000_000, u64::MAX] {
+                let (f_n, f_n1) = crate::fib_pair_with_mod(n, modulo);
+                assert_eq!(fib_with_mod(n, modulo), f_n, "n = {}, modulo = {}", n, modulo);
+                assert_eq!(
+                    lucas_with_mod(n, modulo),
+                    crate::sub_mod(crate::add_mod(f_n1, f_n1, modulo), f_n, modulo)
+                );
+            }
+        }
+        assert_eq!(lucas_with_mod(10, 1_000), 123);
+        assert_eq!(try_fib_with_mod(10, 0), Err(FibError::ZeroModulus));
+        assert_eq!(try_lucas_with_mod(10, 0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_mod_arithmetic() {
+        let modulo = u64::MAX - 58;
+        for &(a, b) in &[(0, 0), (1, modulo - 1), (modulo - 1, modulo - 1), (12_345, 67_890)] {
+            assert_eq!(add_mod(a, b, modulo), crate::add_mod(a, b, modulo));
+            assert_eq!(sub_mod(a, b, modulo), crate::sub_mod(a, b, modulo));
+            assert_eq!(mul_mod(a, b, modulo), crate::mul_mod(a, b, modulo));
+        }
+    }
+}
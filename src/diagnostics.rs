@@ -0,0 +1,166 @@
+//! Runs every algorithm on the same input, to time them against each other and cross-check their
+//! results, e.g. before pinning one with `fib_with_mod_using` or `Dispatcher::with_crossovers`,
+//! and reports how a single query was computed, for logs.
+
+use std::time::{Duration, Instant};
+
+use crate::{fib_with_mod_inner, fib_with_mod_using, Algorithm, FibError};
+
+
+/// fib(n) with modulo and how `fib_with_mod_report` computed it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FibReport {
+    /// fib(n) with modulo.
+    pub value: u64,
+    /// `FastDoubling` for Mersenne moduli past the additions, and `MatrixPower` otherwise, which
+    /// adds up small n.
+    pub algorithm: Algorithm,
+    /// Whether n was reduced modulo the pisano period first.
+    pub pisano_reduced: bool,
+    /// The 2x2 matrix multiplications, 0 for algorithms without matrices.
+    pub matrix_multiplications: u64,
+    /// The time the computation took.
+    pub elapsed: Duration,
+}
+
+
+/// Times every algorithm of `Algorithm::ALL` that supports the modulo on fib(n) with modulo, and
+/// returns each with its running time and result, in declaration order.
+///
+/// # Panics
+/// If the modulo is 0, or if two algorithms disagree, which would be a bug in this crate.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::diagnostics::compare_algorithms;
+/// use fast_fibonacci::Algorithm;
+///
+/// let timings = compare_algorithms(1_000_000_000_000_000, 1_000_000_007);
+/// assert_eq!(Algorithm::ALL.len(), timings.len());
+/// let (fastest, _, fib) = *timings.iter().min_by_key(|(_, time, _)| *time).unwrap();
+/// assert_eq!(fast_fibonacci::fib_with_mod(1_000_000_000_000_000, 1_000_000_007), fib);
+///
+/// // FiniteFieldBinet needs an odd modulo.
+/// assert!(compare_algorithms(10, 100).iter().all(|&(algorithm, _, _)| algorithm != Algorithm::FiniteFieldBinet));
+/// ```
+pub fn compare_algorithms(n: u64, modulo: u64) -> Vec<(Algorithm, Duration, u64)> {
+    try_compare_algorithms(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `compare_algorithms`, but returns an error instead of panicking when the modulo is 0.
+/// Still panics if two algorithms disagree.
+pub fn try_compare_algorithms(n: u64, modulo: u64) -> Result<Vec<(Algorithm, Duration, u64)>, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    let mut timings: Vec<(Algorithm, Duration, u64)> = Vec::new();
+    for &algorithm in Algorithm::ALL.iter() {
+        let start = Instant::now();
+        let fib = match fib_with_mod_using(algorithm, n, modulo) {
+            Ok(fib) => fib,
+            // The algorithm doesn't support the modulo.
+            Err(FibError::InvalidArgument(_)) => continue,
+            Err(err) => return Err(err),
+        };
+        let time = start.elapsed();
+        if let Some(&(first, _, expected)) = timings.first() {
+            assert_eq!(
+                fib, expected,
+                "{:?} and {:?} disagree on fib({}) mod {}", first, algorithm, n, modulo
+            );
+        }
+        timings.push((algorithm, time, fib));
+    }
+    Ok(timings)
+}
+
+
+/// Finds the nth fibonacci number with modulo as `fib_with_mod` does, and reports the algorithm,
+/// whether n was reduced by the pisano period, the matrix multiplications and the time it took.
+/// Runtime O(log(n))
+///
+/// Panics if modulo is zero, see `try_fib_with_mod_report`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::diagnostics::fib_with_mod_report;
+/// use fast_fibonacci::Algorithm;
+///
+/// let report = fib_with_mod_report(1_000, 100);
+/// assert_eq!(75, report.value);
+/// assert_eq!(Algorithm::MatrixPower, report.algorithm);
+/// assert!(!report.pisano_reduced);
+/// // A squaring for each of the 9 bits after the first, and a multiplication for each other 1 bit.
+/// assert_eq!(14, report.matrix_multiplications);
+/// // Small n are added up without matrices.
+/// assert_eq!(0, fib_with_mod_report(10, 100).matrix_multiplications);
+/// ```
+pub fn fib_with_mod_report(n: u64, modulo: u64) -> FibReport {
+    try_fib_with_mod_report(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_with_mod_report`, but returns an error instead of panicking.
+pub fn try_fib_with_mod_report(n: u64, modulo: u64) -> Result<FibReport, FibError> {
+    let start = Instant::now();
+    let (value, trace) = fib_with_mod_inner(n, modulo)?;
+    let elapsed = start.elapsed();
+    Ok(FibReport {
+        value,
+        algorithm: if trace.doubling { Algorithm::FastDoubling } else { Algorithm::MatrixPower },
+        pisano_reduced: trace.pisano_reduced,
+        matrix_multiplications: trace.matrix_multiplications,
+        elapsed,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::diagnostics::*;
+
+    #[test]
+    fn test_compare_algorithms() {
+        for &(n, modulo) in &[(0, 1), (10, 100), (1_000_000_000_000_000, 1_000_000_007), (u64::MAX, u64::MAX)] {
+            let timings = compare_algorithms(n, modulo);
+            let algorithms: Vec<Algorithm> = timings.iter().map(|&(algorithm, _, _)| algorithm).collect();
+            let expected: Vec<Algorithm> = Algorithm::ALL
+                .iter()
+                .copied()
+                .filter(|&algorithm| algorithm != Algorithm::FiniteFieldBinet || modulo % 2 == 1)
+                .collect();
+            assert_eq!(algorithms, expected);
+            assert!(timings.iter().all(|&(_, _, fib)| fib == crate::fib_with_mod(n, modulo)));
+        }
+        assert_eq!(try_compare_algorithms(10, 0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_fib_with_mod_report() {
+        let cases = [
+            (0, 10, 0), (10, 100, 0), (127, 100, 0), (128, 100, 7), (129, 10, 8), (1_000, 100, 14),
+            (u64::MAX, u64::MAX - 1, 126),
+        ];
+        for &(n, modulo, multiplications) in &cases {
+            let report = fib_with_mod_report(n, modulo);
+            assert_eq!(report.value, crate::fib_with_mod(n, modulo), "n = {}, modulo = {}", n, modulo);
+            assert_eq!(report.algorithm, Algorithm::MatrixPower);
+            assert!(!report.pisano_reduced);
+            assert_eq!(report.matrix_multiplications, multiplications, "n = {}", n);
+        }
+        let mersenne = [
+            (127, 127, Algorithm::MatrixPower),
+            (128, 127, Algorithm::FastDoubling),
+            (u64::MAX, u64::MAX, Algorithm::FastDoubling),
+        ];
+        for &(n, modulo, algorithm) in &mersenne {
+            let report = fib_with_mod_report(n, modulo);
+            assert_eq!(report.value, crate::fib_with_mod(n, modulo), "n = {}, modulo = {}", n, modulo);
+            assert_eq!(report.algorithm, algorithm, "n = {}, modulo = {}", n, modulo);
+            assert_eq!(report.matrix_multiplications, 0);
+        }
+        assert_eq!(try_fib_with_mod_report(10, 0), Err(FibError::ZeroModulus));
+    }
+}
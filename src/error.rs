@@ -0,0 +1,36 @@
+use core::fmt;
+
+
+/// Why a `try_` function couldn't compute its result.
+///
+/// Only Serialize with the `serde` feature, since the reason of `InvalidArgument` is a static str.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum FibError {
+    /// The modulo was 0, and nothing can be reduced modulo 0.
+    ZeroModulus,
+    /// The result would overflow its integer type.
+    OverflowRisk,
+    /// Some other argument is out of range, e.g. an index past its bound or a modulo that must
+    /// be prime but isn't.
+    InvalidArgument(&'static str),
+    /// The computation was cancelled before it finished.
+    Cancelled,
+    /// The computation ran out of its time budget before it finished.
+    Timeout,
+}
+
+impl fmt::Display for FibError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FibError::ZeroModulus => write!(f, "modulo must be positive"),
+            FibError::OverflowRisk => write!(f, "result would overflow"),
+            FibError::InvalidArgument(reason) => write!(f, "{}", reason),
+            FibError::Cancelled => write!(f, "computation was cancelled"),
+            FibError::Timeout => write!(f, "computation ran out of time"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FibError {}
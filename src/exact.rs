@@ -0,0 +1,403 @@
+//! Exact expansions of fib(n) in decimal and other radices.
+//!
+//! For decimal, the fast doubling runs on base 10^9 limbs with Karatsuba multiplication, so the
+//! digits come out directly, without the quadratic conversion that `BigUint::to_string` would do
+//! on a result of millions of digits. Radices that are powers of two are read straight off the
+//! bits.
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use num_bigint::BigUint;
+
+#[cfg(not(feature = "rug"))]
+use crate::bignum;
+#[cfg(feature = "rug")]
+use crate::gmp;
+use crate::{exact_bigfib_pair, FibError};
+
+const BASE: u64 = 1_000_000_000;
+
+// Below this many limbs in the shorter operand, schoolbook multiplication is faster.
+const KARATSUBA_THRESHOLD: usize = 32;
+
+
+/// Finds the nth fibonacci number exactly, with two squarings per bit of n, see `bignum::fib`.
+/// Runtime O(n^1.59), and much less with the `rug` feature, where GMP's FFT multiplication makes
+/// fib(10^8) a matter of seconds.
+///
+/// # Examples
+/// ```
+/// use num_bigint::BigUint;
+///
+/// assert_eq!(BigUint::from(55u32), fast_fibonacci::bigfib(10));
+/// assert_eq!(69_424, fast_fibonacci::bigfib(100_000).bits());
+/// ```
+pub fn bigfib(n: u64) -> BigUint {
+    #[cfg(feature = "rug")]
+    {
+        gmp::fib(n)
+    }
+    #[cfg(not(feature = "rug"))]
+    {
+        bignum::fib(n)
+    }
+}
+
+
+/// Writes out fib(n) exactly in decimal. Runtime O(n^1.59), which is seconds for n = 10^7 and
+/// minutes for n = 10^8, a number of 20 million digits. With the `rug` feature, GMP computes and
+/// converts it much faster.
+///
+/// # Examples
+/// ```
+/// assert_eq!("55", fast_fibonacci::fib_to_string(10));
+/// assert_eq!("354224848179261915075", fast_fibonacci::fib_to_string(100));
+/// assert_eq!(20_899, fast_fibonacci::fib_to_string(100_000).len());
+/// ```
+pub fn fib_to_string(n: u64) -> String {
+    #[cfg(feature = "rug")]
+    {
+        if n <= u64::from(u32::MAX) {
+            return gmp::fib_to_string_radix(n as u32, 10);
+        }
+    }
+    to_string(&decimal_limbs(n))
+}
+
+
+/// Writes out fib(n) exactly in decimal to a writer, like `fib_to_string`, but in chunks of 64 KiB
+/// straight from the base 10^9 limbs, so that the digits never exist as one string. Runtime as for
+/// `fib_to_string`
+///
+/// With the `rug` feature, GMP's conversion to a string is faster than the limbs for n up to
+/// u32::MAX, so it writes that string instead.
+///
+/// # Examples
+/// ```
+/// let mut digits = Vec::new();
+/// fast_fibonacci::write_fib_decimal(100, &mut digits).unwrap();
+/// assert_eq!(b"354224848179261915075", &digits[..]);
+/// ```
+pub fn write_fib_decimal<W: Write>(n: u64, mut writer: W) -> io::Result<()> {
+    #[cfg(feature = "rug")]
+    {
+        if n <= u64::from(u32::MAX) {
+            return writer.write_all(gmp::fib_to_string_radix(n as u32, 10).as_bytes());
+        }
+    }
+    let limbs = decimal_limbs(n);
+    let mut rest = limbs.iter().rev();
+    let top = rest.next().copied().unwrap_or(0);
+    let mut chunk = Vec::with_capacity(CHUNK_SIZE + 9);
+    write!(chunk, "{}", top)?;
+    for limb in rest {
+        write!(chunk, "{:09}", limb)?;
+        if chunk.len() >= CHUNK_SIZE {
+            writer.write_all(&chunk)?;
+            chunk.clear();
+        }
+    }
+    writer.write_all(&chunk)
+}
+
+
+/// Writes out fib(n) exactly in a radix from 2 to 36, with lowercase letters for digits past 9.
+/// Runtime O(n) for radices that are powers of two, O(n^1.59) for 10 and O(n^2) for the rest.
+///
+/// # Examples
+/// ```
+/// assert_eq!("37", fast_fibonacci::fib_to_string_radix(10, 16));
+/// assert_eq!("110111", fast_fibonacci::fib_to_string_radix(10, 2));
+/// assert_eq!("1j", fast_fibonacci::fib_to_string_radix(10, 36));
+/// ```
+pub fn fib_to_string_radix(n: u64, radix: u32) -> String {
+    try_fib_to_string_radix(n, radix).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_to_string_radix`, but returns an error instead of panicking.
+pub fn try_fib_to_string_radix(n: u64, radix: u32) -> Result<String, FibError> {
+    check_radix(radix)?;
+    if radix == 10 {
+        return Ok(fib_to_string(n));
+    }
+    #[cfg(feature = "rug")]
+    {
+        if n <= u64::from(u32::MAX) {
+            return Ok(gmp::fib_to_string_radix(n as u32, radix));
+        }
+    }
+    if radix.is_power_of_two() {
+        let mut digits = Vec::new();
+        write_power_of_two_radix(n, radix, &mut digits).unwrap();
+        return Ok(String::from_utf8(digits).unwrap());
+    }
+    Ok(exact_bigfib_pair(n).0.to_str_radix(radix))
+}
+
+
+/// Writes fib(n) in a radix from 2 to 36 to a writer, like `fib_to_string_radix`. For decimal and
+/// radices that are powers of two, the digits are streamed out in small chunks instead of being
+/// collected into a string first. Runtime as for `fib_to_string_radix`
+///
+/// An invalid radix is an `InvalidInput` error wrapping the `FibError`.
+///
+/// # Examples
+/// ```
+/// let mut hex = Vec::new();
+/// fast_fibonacci::write_fib_radix(100, 16, &mut hex).unwrap();
+/// assert_eq!(b"1333db76a7c594bfc3", &hex[..]);
+/// ```
+pub fn write_fib_radix<W: Write>(n: u64, radix: u32, mut writer: W) -> io::Result<()> {
+    check_radix(radix).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    if radix.is_power_of_two() {
+        return write_power_of_two_radix(n, radix, writer);
+    }
+    if radix == 10 {
+        return write_fib_decimal(n, writer);
+    }
+    writer.write_all(try_fib_to_string_radix(n, radix).unwrap().as_bytes())
+}
+
+
+// fib(n) in base 10^9 limbs from the least significant, by fast doubling.
+fn decimal_limbs(n: u64) -> Vec<u32> {
+    let (mut a, mut b) = (Vec::new(), vec![1]);
+    let bits = 64 - n.leading_zeros();
+    for bit in (1..bits).rev() {
+        // The doubling formulas of `fib_pair_doubling`.
+        let even = mul(&a, &sub(&add(&b, &b), &a));
+        let odd = add(&mul(&a, &a), &mul(&b, &b));
+        if n >> bit & 1 == 1 {
+            b = add(&even, &odd);
+            a = odd;
+        } else {
+            a = even;
+            b = odd;
+        }
+    }
+    // The last step only needs one of the two, and is about half of the work.
+    if bits > 0 {
+        a = if n & 1 == 1 { add(&mul(&a, &a), &mul(&b, &b)) } else { mul(&a, &sub(&add(&b, &b), &a)) };
+    }
+    a
+}
+
+
+fn check_radix(radix: u32) -> Result<(), FibError> {
+    if (2..=36).contains(&radix) {
+        Ok(())
+    } else {
+        Err(FibError::InvalidArgument("radix must be between 2 and 36"))
+    }
+}
+
+
+// The digits of fib(n) in a radix that is a power of two, from the most significant, each taken
+// from its bits.
+fn write_power_of_two_radix<W: Write>(n: u64, radix: u32, mut writer: W) -> io::Result<()> {
+    let fib = exact_bigfib_pair(n).0;
+    let bytes = fib.to_bytes_le();
+    let bits_per_digit = radix.trailing_zeros() as usize;
+    let digits = fib.bits().div_ceil(bits_per_digit).max(1);
+    let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+    for digit in (0..digits).rev() {
+        let start = digit * bits_per_digit;
+        let window = u16::from(bytes[start / 8]) | u16::from(*bytes.get(start / 8 + 1).unwrap_or(&0)) << 8;
+        let value = u32::from(window >> (start % 8)) & (radix - 1);
+        chunk.push(std::char::from_digit(value, radix).unwrap() as u8);
+        if chunk.len() == CHUNK_SIZE {
+            writer.write_all(&chunk)?;
+            chunk.clear();
+        }
+    }
+    writer.write_all(&chunk)
+}
+
+
+const CHUNK_SIZE: usize = 1 << 16;
+
+
+// Little endian base 10^9 limbs without leading zero limbs, 0 being empty.
+fn trimmed(mut a: &[u32]) -> &[u32] {
+    while let Some((&0, rest)) = a.split_last() {
+        a = rest;
+    }
+    a
+}
+
+
+fn trim(mut a: Vec<u32>) -> Vec<u32> {
+    a.truncate(trimmed(&a).len());
+    a
+}
+
+
+fn add(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (a, b) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut sum = a.to_vec();
+    add_shifted(&mut sum, b, 0);
+    sum
+}
+
+
+// a - b, where a >= b.
+fn sub(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let b = trimmed(b);
+    let mut difference = a.to_vec();
+    let mut borrow = 0;
+    for (i, limb) in difference.iter_mut().enumerate() {
+        if i >= b.len() && borrow == 0 {
+            break;
+        }
+        let subtrahend = u64::from(*b.get(i).unwrap_or(&0)) + borrow;
+        let value = u64::from(*limb);
+        borrow = (value < subtrahend) as u64;
+        *limb = (value + borrow * BASE - subtrahend) as u32;
+    }
+    debug_assert_eq!(borrow, 0, "a < b");
+    trim(difference)
+}
+
+
+// acc += b * BASE^shift
+fn add_shifted(acc: &mut Vec<u32>, b: &[u32], shift: usize) {
+    let b = trimmed(b);
+    // Room for the carry out of the top limb.
+    let len = acc.len().max(shift + b.len()) + 1;
+    acc.resize(len, 0);
+    let mut carry = 0;
+    for (i, limb) in acc[shift..].iter_mut().enumerate() {
+        if i >= b.len() && carry == 0 {
+            break;
+        }
+        let sum = u64::from(*limb) + u64::from(*b.get(i).unwrap_or(&0)) + carry;
+        carry = sum / BASE;
+        *limb = (sum % BASE) as u32;
+    }
+    acc.truncate(trimmed(acc).len());
+}
+
+
+fn mul(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let (a, b) = (trimmed(a), trimmed(b));
+    let (a, b) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    if b.len() < KARATSUBA_THRESHOLD {
+        return schoolbook_mul(a, b);
+    }
+    let half = a.len() / 2;
+    if b.len() <= half {
+        // Too unbalanced to split both, so only a is split.
+        let mut product = mul(&a[..half], b);
+        add_shifted(&mut product, &mul(&a[half..], b), half);
+        return product;
+    }
+    // a * b = z2 * BASE^(2 * half) + z1 * BASE^half + z0, with one multiplication less than the
+    // four of the halves.
+    let (a0, a1) = (&a[..half], &a[half..]);
+    let (b0, b1) = (&b[..half], &b[half..]);
+    let z0 = mul(a0, b0);
+    let z2 = mul(a1, b1);
+    let z1 = sub(&sub(&mul(&add(a0, a1), &add(b0, b1)), &z0), &z2);
+    let mut product = z0;
+    add_shifted(&mut product, &z1, half);
+    add_shifted(&mut product, &z2, 2 * half);
+    product
+}
+
+
+fn schoolbook_mul(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if b.is_empty() {
+        return Vec::new();
+    }
+    let mut product = vec![0; a.len() + b.len()];
+    for (i, &b_limb) in b.iter().enumerate() {
+        let mut carry = 0;
+        for (j, &a_limb) in a.iter().enumerate() {
+            // At most (BASE - 1) + (BASE - 1)^2 + BASE, well within a u64.
+            let limb = u64::from(product[i + j]) + u64::from(a_limb) * u64::from(b_limb) + carry;
+            carry = limb / BASE;
+            product[i + j] = (limb % BASE) as u32;
+        }
+        product[i + a.len()] = carry as u32;
+    }
+    trim(product)
+}
+
+
+fn to_string(a: &[u32]) -> String {
+    let mut limbs = a.iter().rev();
+    let mut digits = match limbs.next() {
+        Some(top) => top.to_string(),
+        None => return "0".to_string(),
+    };
+    digits.reserve(9 * a.len());
+    for limb in limbs {
+        write!(digits, "{:09}", limb).unwrap();
+    }
+    digits
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::exact::*;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_matches_bigfib() {
+        for n in (0..300).chain(vec![1_000, 12_345, 54_321]) {
+            // fib(n) < 2^n, so nothing is reduced.
+            let fib = crate::bigfib_with_mod(&BigUint::from(n), &(BigUint::from(1u32) << n as usize));
+            assert_eq!(fib_to_string(n), fib.to_string(), "n = {}", n);
+        }
+    }
+
+    #[test]
+    fn test_write_decimal() {
+        for n in (0..100).chain(vec![1_000, 400_000]) {
+            let mut written = Vec::new();
+            write_fib_decimal(n, &mut written).unwrap();
+            assert_eq!(String::from_utf8(written).unwrap(), fib_to_string(n), "n = {}", n);
+        }
+    }
+
+    #[test]
+    fn test_radix() {
+        for n in (0..100).chain(vec![1_000, 12_345]) {
+            let fib = exact_bigfib_pair(n).0;
+            for radix in 2..=36 {
+                let expected = fib.to_str_radix(radix);
+                assert_eq!(fib_to_string_radix(n, radix), expected, "n = {}, radix = {}", n, radix);
+                let mut written = Vec::new();
+                write_fib_radix(n, radix, &mut written).unwrap();
+                assert_eq!(String::from_utf8(written).unwrap(), expected, "n = {}, radix = {}", n, radix);
+            }
+        }
+        // More than one chunk.
+        let mut binary = Vec::new();
+        write_fib_radix(100_000, 2, &mut binary).unwrap();
+        assert_eq!(binary.len(), exact_bigfib_pair(100_000).0.bits());
+        assert_eq!(try_fib_to_string_radix(10, 37), Err(FibError::InvalidArgument("radix must be between 2 and 36")));
+        assert_eq!(write_fib_radix(10, 1, Vec::new()).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_limb_arithmetic() {
+        let to_limbs = |s: &str| -> Vec<u32> {
+            let digits: Vec<u8> = s.bytes().map(|b| b - b'0').collect();
+            digits.rchunks(9).map(|chunk| chunk.iter().fold(0, |limb, &d| 10 * limb + u32::from(d))).collect()
+        };
+        // Enough limbs for several levels of Karatsuba, and carries through long runs of 9s.
+        let a = format!("{}{}", "9".repeat(1_000), "123456789".repeat(50));
+        let b = format!("{}{}", "7".repeat(700), "9".repeat(333));
+        let (big_a, big_b) = (a.parse::<BigUint>().unwrap(), b.parse::<BigUint>().unwrap());
+        assert_eq!(to_string(&mul(&to_limbs(&a), &to_limbs(&b))), (&big_a * &big_b).to_string());
+        assert_eq!(to_string(&mul(&to_limbs(&a), &to_limbs("12"))), (&big_a * 12u32).to_string());
+        assert_eq!(to_string(&add(&to_limbs(&a), &to_limbs(&b))), (&big_a + &big_b).to_string());
+        assert_eq!(to_string(&sub(&to_limbs(&a), &to_limbs(&b))), (&big_a - &big_b).to_string());
+        assert_eq!(to_string(&sub(&to_limbs(&a), &to_limbs(&a))), "0");
+        assert_eq!(to_string(&mul(&to_limbs(&a), &[])), "0");
+    }
+}
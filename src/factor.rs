@@ -0,0 +1,218 @@
+//! Factoring moduli, for the pisano and chinese remainder paths when no factorization is at hand.
+//!
+//! Without this feature, u64 moduli are factored by trial division, which takes up to 2^32 steps
+//! for a product of two large primes. With it, every pisano period and `Dispatcher` search
+//! factors by trial division up to 1000 and then Pollard's rho, in O(modulo^(1/4)), and the
+//! factorizations are public here for `pisano::fib_with_mod_factored` and
+//! `pisano::pisano_period_of_product`.
+//!
+//! # Examples
+//! ```
+//! use fast_fibonacci::factor::factorize;
+//! use fast_fibonacci::pisano::pisano_period_of_product;
+//!
+//! let modulo = 4_294_967_291 * 4_294_967_279;
+//! assert_eq!(vec![(4_294_967_279, 1), (4_294_967_291, 1)], factorize(modulo));
+//! assert_eq!(9_223_371_985_315_168_310, pisano_period_of_product(&factorize(modulo)));
+//! ```
+
+use num_bigint::BigUint;
+
+use crate::nt::{self, gcd, is_prime};
+use crate::pisano::try_fib_with_mod_factored;
+use crate::FibError;
+
+
+/// The prime factorization of n, as (prime, exponent) pairs in increasing order, empty for 0 and
+/// 1. Runtime O(n^(1/4))
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::factor::factorize;
+///
+/// assert_eq!(vec![(2, 3), (3, 2), (5, 1)], factorize(360));
+/// assert_eq!(vec![(1_000_000_007, 2)], factorize(1_000_000_007 * 1_000_000_007));
+/// ```
+pub fn factorize(mut n: u64) -> Vec<(u64, u32)> {
+    if n == 0 {
+        return Vec::new();
+    }
+    let mut primes = Vec::new();
+    for prime in 2..1_000 {
+        while n.is_multiple_of(prime) {
+            n /= prime;
+            primes.push(prime);
+        }
+    }
+    let mut stack = vec![n];
+    while let Some(n) = stack.pop() {
+        if n == 1 {
+            continue;
+        }
+        if is_prime(n) {
+            primes.push(n);
+            continue;
+        }
+        let factor = pollard_rho(n);
+        stack.push(n / factor);
+        stack.push(factor);
+    }
+    primes.sort_unstable();
+    let mut factors: Vec<(u64, u32)> = Vec::new();
+    for prime in primes {
+        match factors.last_mut() {
+            Some((last, exponent)) if *last == prime => *exponent += 1,
+            _ => factors.push((prime, 1)),
+        }
+    }
+    factors
+}
+
+
+/// `factorize` for BigUint, empty for 0 and 1. Runtime O(q^(1/2)) multiplications for q the
+/// second largest prime factor
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::factor::big_factorize;
+/// use num_bigint::BigUint;
+///
+/// let mersenne = (BigUint::from(1u32) << 67) - 1u32;
+/// let factors = vec![(BigUint::from(193_707_721u32), 1), (BigUint::from(761_838_257_287u64), 1)];
+/// assert_eq!(factors, big_factorize(&mersenne));
+/// ```
+pub fn big_factorize(n: &BigUint) -> Vec<(BigUint, u32)> {
+    if *n == BigUint::from(0u32) {
+        return Vec::new();
+    }
+    nt::big_factorize(n)
+}
+
+
+/// Finds fib(n) with modulo by factoring the modulo, then reducing n by a multiple of each prime
+/// power's pisano period and combining them by the chinese remainder theorem, as in
+/// `pisano::fib_with_mod_factored`. Runtime O(modulo^(1/4) + k * log(modulo)) for k prime powers
+///
+/// Panics if modulo is zero, see `try_fib_with_mod_crt`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::factor::fib_with_mod_crt;
+///
+/// assert_eq!(fast_fibonacci::fib_with_mod(u64::MAX, 1_000_000), fib_with_mod_crt(u64::MAX, 1_000_000));
+/// ```
+pub fn fib_with_mod_crt(n: u64, modulo: u64) -> u64 {
+    try_fib_with_mod_crt(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_with_mod_crt`, but returns an error instead of panicking.
+pub fn try_fib_with_mod_crt(n: u64, modulo: u64) -> Result<u64, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    try_fib_with_mod_factored(n, &factorize(modulo))
+}
+
+
+// A nontrivial factor of a composite n without factors below 1000, by Brent's variant of
+// Pollard's rho, trying x^2 + c for c = 1, 2, ... until one splits n.
+fn pollard_rho(n: u64) -> u64 {
+    const BATCH: u64 = 128;
+    let multiply = |a: u64, b: u64| (a as u128 * b as u128 % n as u128) as u64;
+    for c in 1..n {
+        let step = |x: u64| ((x as u128 * x as u128 + c as u128) % n as u128) as u64;
+        let (mut x, mut y, mut batch_start) = (2, 2, 2);
+        let (mut product, mut factor) = (1, 1);
+        let mut length = 1;
+        while factor == 1 {
+            x = y;
+            for _ in 0..length {
+                y = step(y);
+            }
+            let mut k = 0;
+            while k < length && factor == 1 {
+                // One gcd per batch of differences multiplied together.
+                batch_start = y;
+                for _ in 0..BATCH.min(length - k) {
+                    y = step(y);
+                    product = multiply(product, x.abs_diff(y));
+                }
+                factor = gcd(product as u128, n as u128) as u64;
+                k += BATCH;
+            }
+            length *= 2;
+        }
+        if factor == n {
+            // The batch multiplied in every factor at once, so retrace it one step at a time.
+            loop {
+                batch_start = step(batch_start);
+                factor = gcd(x.abs_diff(batch_start) as u128, n as u128) as u64;
+                if factor != 1 {
+                    break;
+                }
+            }
+        }
+        if factor != n {
+            return factor;
+        }
+    }
+    unreachable!()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::factor::*;
+    use crate::pisano::{pisano_period, pisano_period_of_product};
+
+    fn check(n: u64) {
+        let factors = factorize(n);
+        assert!(factors.windows(2).all(|pair| pair[0].0 < pair[1].0), "n = {}", n);
+        assert!(factors.iter().all(|&(prime, _)| is_prime(prime)), "n = {}", n);
+        let product = factors.iter().fold(1u128, |product, &(prime, exponent)| product * (prime as u128).pow(exponent));
+        assert_eq!(product, n as u128, "n = {}", n);
+    }
+
+    #[test]
+    fn test_factorize() {
+        assert_eq!(factorize(0), vec![]);
+        assert_eq!(factorize(1), vec![]);
+        for n in (2..20_000).chain(u64::MAX - 2_000..=u64::MAX) {
+            check(n);
+        }
+        // Products of primes past the trial division, squares and cubes among them.
+        for &n in &[
+            1_009 * 1_013,
+            1_000_003 * 1_000_003 * 1_000_003,
+            4_294_967_291 * 4_294_967_291,
+            4_294_967_291 * 4_294_967_279,
+            1_000_000_007 * 1_000_000_009,
+            999_999_000_001 * 1_000_003,
+        ] {
+            check(n);
+        }
+        assert_eq!(factorize(4_294_967_291 * 4_294_967_291), vec![(4_294_967_291, 2)]);
+        assert_eq!(big_factorize(&BigUint::from(0u32)), vec![]);
+        assert_eq!(big_factorize(&BigUint::from(360u32)), vec![(BigUint::from(2u32), 3), (BigUint::from(3u32), 2), (BigUint::from(5u32), 1)]);
+    }
+
+    #[test]
+    fn test_fib_with_mod_crt() {
+        for modulo in 1..1_000 {
+            for &n in &[0, 1, 2, 99, u64::MAX] {
+                assert_eq!(fib_with_mod_crt(n, modulo), crate::fib_with_mod(n, modulo), "n = {}, modulo = {}", n, modulo);
+            }
+        }
+        let modulo = 4_294_967_291 * 65_521;
+        assert_eq!(fib_with_mod_crt(u64::MAX, modulo), crate::fib_with_mod(u64::MAX, modulo));
+        assert_eq!(try_fib_with_mod_crt(1, 0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_pisano_period_of_semiprime() {
+        // Trial division would take 2^31 steps.
+        let modulo = 4_294_967_291 * 4_294_967_279;
+        assert_eq!(pisano_period(modulo), pisano_period_of_product(&[(4_294_967_291, 1), (4_294_967_279, 1)]));
+    }
+}
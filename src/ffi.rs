@@ -0,0 +1,163 @@
+//! C bindings, with the `ffi` feature. See `include/fast_fibonacci.h` for the C declarations.
+//!
+//! Every function takes an `error` out-parameter, which is set to one of the `FAST_FIBONACCI_*`
+//! codes, and may be null. Big numbers are passed as NUL terminated decimal strings, and results
+//! returned as strings are owned by the caller, who frees them with `fast_fibonacci_string_free`.
+
+use num_bigint::BigUint;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic;
+use std::ptr;
+
+use crate::FibError;
+
+
+/// Success.
+pub const FAST_FIBONACCI_OK: c_int = 0;
+/// The modulo was 0, see `FibError::ZeroModulus`.
+pub const FAST_FIBONACCI_ZERO_MODULUS: c_int = 1;
+/// The result would overflow, see `FibError::OverflowRisk`.
+pub const FAST_FIBONACCI_OVERFLOW: c_int = 2;
+/// An argument was out of range, a null pointer, or not a decimal string.
+pub const FAST_FIBONACCI_INVALID_ARGUMENT: c_int = 3;
+/// Something panicked. Never expected, but it's caught rather than unwinding into C.
+pub const FAST_FIBONACCI_PANIC: c_int = 4;
+/// The computation was cancelled, see `FibError::Cancelled`.
+pub const FAST_FIBONACCI_CANCELLED: c_int = 5;
+/// The computation ran out of time, see `FibError::Timeout`.
+pub const FAST_FIBONACCI_TIMEOUT: c_int = 6;
+
+
+/// Finds the nth fibonacci number with modulo, see `fib_with_mod`. Returns 0 on error.
+///
+/// # Safety
+///
+/// `error` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn fast_fibonacci_fib_with_mod(n: u64, modulo: u64, error: *mut c_int) -> u64 {
+    let result = catch(|| crate::try_fib_with_mod(n, modulo).map_err(error_code));
+    set_error(error, result.as_ref().err());
+    result.unwrap_or(0)
+}
+
+
+/// Finds the nth fibonacci number with modulo, for decimal strings n and modulo, see
+/// `bigfib_with_mod`. Returns the result as a decimal string, or null on error.
+///
+/// # Safety
+///
+/// `n` and `modulo` must be null or NUL terminated strings, and `error` must be null or valid for
+/// writes. The result must be freed with `fast_fibonacci_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn fast_fibonacci_bigfib_with_mod(
+    n: *const c_char,
+    modulo: *const c_char,
+    error: *mut c_int
+) -> *mut c_char {
+    let result = catch(|| {
+        let n = parse_decimal(n)?;
+        let modulo = parse_decimal(modulo)?;
+        let fib = crate::try_bigfib_with_mod(&n, &modulo).map_err(error_code)?;
+        // Decimal digits never contain NUL.
+        Ok(CString::new(fib.to_str_radix(10)).unwrap().into_raw())
+    });
+    set_error(error, result.as_ref().err());
+    result.unwrap_or(ptr::null_mut())
+}
+
+
+/// Frees a string returned by this library. Does nothing for null.
+///
+/// # Safety
+///
+/// `string` must be null or a string returned by this library, not already freed.
+#[no_mangle]
+pub unsafe extern "C" fn fast_fibonacci_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+
+unsafe fn parse_decimal(digits: *const c_char) -> Result<BigUint, c_int> {
+    if digits.is_null() {
+        return Err(FAST_FIBONACCI_INVALID_ARGUMENT);
+    }
+    let digits = CStr::from_ptr(digits).to_bytes();
+    if digits.is_empty() || !digits.iter().all(|b| b.is_ascii_digit()) {
+        return Err(FAST_FIBONACCI_INVALID_ARGUMENT);
+    }
+    BigUint::parse_bytes(digits, 10).ok_or(FAST_FIBONACCI_INVALID_ARGUMENT)
+}
+
+
+fn catch<T, F: FnOnce() -> Result<T, c_int>>(f: F) -> Result<T, c_int> {
+    panic::catch_unwind(panic::AssertUnwindSafe(f)).unwrap_or(Err(FAST_FIBONACCI_PANIC))
+}
+
+
+unsafe fn set_error(error: *mut c_int, code: Option<&c_int>) {
+    if !error.is_null() {
+        *error = code.copied().unwrap_or(FAST_FIBONACCI_OK);
+    }
+}
+
+
+fn error_code(err: FibError) -> c_int {
+    match err {
+        FibError::ZeroModulus => FAST_FIBONACCI_ZERO_MODULUS,
+        FibError::OverflowRisk => FAST_FIBONACCI_OVERFLOW,
+        FibError::InvalidArgument(_) => FAST_FIBONACCI_INVALID_ARGUMENT,
+        FibError::Cancelled => FAST_FIBONACCI_CANCELLED,
+        FibError::Timeout => FAST_FIBONACCI_TIMEOUT,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::ffi::*;
+
+    #[test]
+    fn test_fib_with_mod() {
+        let mut error = -1;
+        unsafe {
+            assert_eq!(fast_fibonacci_fib_with_mod(10, 100, &mut error), 55);
+            assert_eq!(error, FAST_FIBONACCI_OK);
+            assert_eq!(fast_fibonacci_fib_with_mod(10, 0, &mut error), 0);
+            assert_eq!(error, FAST_FIBONACCI_ZERO_MODULUS);
+            assert_eq!(fast_fibonacci_fib_with_mod(10, 100, ptr::null_mut()), 55);
+        }
+    }
+
+    #[test]
+    fn test_bigfib_with_mod() {
+        let mut error = -1;
+        let big = |n: &str, modulo: &str, error: &mut c_int| unsafe {
+            let n = CString::new(n).unwrap();
+            let modulo = CString::new(modulo).unwrap();
+            let result = fast_fibonacci_bigfib_with_mod(n.as_ptr(), modulo.as_ptr(), error);
+            if result.is_null() {
+                return None;
+            }
+            let string = CStr::from_ptr(result).to_str().unwrap().to_string();
+            fast_fibonacci_string_free(result);
+            Some(string)
+        };
+        assert_eq!(big("10", "100", &mut error), Some("55".to_string()));
+        assert_eq!(error, FAST_FIBONACCI_OK);
+        assert_eq!(big("1000000000000000", "1000000", &mut error), Some("546875".to_string()));
+        assert_eq!(big("10", "0", &mut error), None);
+        assert_eq!(error, FAST_FIBONACCI_ZERO_MODULUS);
+        assert_eq!(big("-10", "100", &mut error), None);
+        assert_eq!(error, FAST_FIBONACCI_INVALID_ARGUMENT);
+
+        unsafe {
+            let result = fast_fibonacci_bigfib_with_mod(ptr::null(), ptr::null(), &mut error);
+            assert!(result.is_null());
+            assert_eq!(error, FAST_FIBONACCI_INVALID_ARGUMENT);
+            fast_fibonacci_string_free(result);
+        }
+    }
+}
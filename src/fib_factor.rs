@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use num::{One, Zero};
+use num_bigint::BigUint;
+
+use crate::nt::{big_is_probable_prime, big_prime_factors, factorize, is_prime};
+use crate::FibError;
+
+// Trial division by primitive prime candidates k * d ± 1 stops at this bound.
+const CANDIDATE_LIMIT: u64 = 1 << 20;
+
+
+/// Factors fib(n) into (prime, exponent) pairs, in increasing order of the primes.
+/// Runtime about O(sqrt(q)) multiplications of n bit numbers, for q the second largest prime factor
+///
+/// fib(d) divides fib(n) for every d dividing n, so fib(n) splits into the primitive parts
+/// P(d) = ∏ fib(e)^μ(d / e) over the divisors e of d, one for each divisor d of n. Apart from
+/// factors of d itself, every prime dividing P(d) has rank of apparition d, and so is ±1 modulo
+/// d, which trial division only needs to try. What's left goes to Pollard's rho.
+///
+/// Fine for n up to a few hundred; past that, some fib(n) hold two primes of 20 or more digits,
+/// which take Pollard's rho very long to separate.
+///
+/// # Examples
+/// ```
+/// use num_bigint::BigUint;
+///
+/// // fib(60) = 1548008755920
+/// let factors: Vec<(u64, u32)> = fast_fibonacci::factor_fib(60)
+///     .into_iter()
+///     .map(|(prime, exponent)| (num::ToPrimitive::to_u64(&prime).unwrap(), exponent))
+///     .collect();
+/// assert_eq!(vec![(2, 4), (3, 2), (5, 1), (11, 1), (31, 1), (41, 1), (61, 1), (2521, 1)], factors);
+/// ```
+pub fn factor_fib(n: u64) -> Vec<(BigUint, u32)> {
+    try_factor_fib(n).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `factor_fib`, but returns an error instead of panicking when n is 0.
+pub fn try_factor_fib(n: u64) -> Result<Vec<(BigUint, u32)>, FibError> {
+    if n == 0 {
+        return Err(FibError::InvalidArgument("fib(0) = 0 has no factorization"));
+    }
+    let n_factors = factorize(n);
+    let divisors = divisors(&n_factors);
+    let fibs: HashMap<u64, BigUint> = divisors.iter().map(|&d| (d, crate::exact_bigfib_pair(d).0)).collect();
+
+    let mut counts: HashMap<BigUint, u32> = HashMap::new();
+    for &d in &divisors {
+        let d_factors = factorize(d);
+        let mut part = primitive_part(d, &d_factors, &fibs);
+        let mut primes = Vec::new();
+        // Factors of d, and then the candidates for primitive factors.
+        for &(prime, _) in &d_factors {
+            divide_out(&mut part, prime, &mut primes);
+        }
+        let mut done = part.is_one() || big_is_probable_prime(&part);
+        let mut k = 1;
+        while !done && k * d <= CANDIDATE_LIMIT {
+            for candidate in [k * d - 1, k * d + 1] {
+                if candidate > 1 && (&part % candidate).is_zero() && is_prime(candidate) {
+                    divide_out(&mut part, candidate, &mut primes);
+                    done = part.is_one() || big_is_probable_prime(&part);
+                }
+            }
+            k += 1;
+        }
+        big_prime_factors(part, &mut primes);
+        for prime in primes {
+            *counts.entry(prime).or_insert(0) += 1;
+        }
+    }
+    let mut factors: Vec<(BigUint, u32)> = counts.into_iter().collect();
+    factors.sort();
+    Ok(factors)
+}
+
+
+// The divisors of a number from its factorization, in increasing order.
+fn divisors(factors: &[(u64, u32)]) -> Vec<u64> {
+    let mut divisors = vec![1];
+    for &(prime, exponent) in factors {
+        let previous = divisors.clone();
+        let mut power = 1;
+        for _ in 0..exponent {
+            power *= prime;
+            divisors.extend(previous.iter().map(|&d| d * power));
+        }
+    }
+    divisors.sort_unstable();
+    divisors
+}
+
+
+// ∏ fib(d / e)^μ(e) over the squarefree divisors e of d, where μ(e) is ±1.
+fn primitive_part(d: u64, d_factors: &[(u64, u32)], fibs: &HashMap<u64, BigUint>) -> BigUint {
+    let (mut numerator, mut denominator) = (BigUint::one(), BigUint::one());
+    for mask in 0..1u32 << d_factors.len() {
+        let e: u64 = (0..d_factors.len()).filter(|&i| mask >> i & 1 == 1).map(|i| d_factors[i].0).product();
+        if mask.count_ones() % 2 == 0 {
+            numerator *= &fibs[&(d / e)];
+        } else {
+            denominator *= &fibs[&(d / e)];
+        }
+    }
+    numerator / denominator
+}
+
+
+// Divides every factor of divisor out of n, recording it once per division. Only ever called
+// with primes, or with divisors whose prime factors were divided out before.
+fn divide_out(n: &mut BigUint, divisor: u64, primes: &mut Vec<BigUint>) {
+    if divisor < 2 {
+        return;
+    }
+    let divisor = BigUint::from(divisor);
+    while (&*n % &divisor).is_zero() {
+        *n /= &divisor;
+        primes.push(divisor.clone());
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::nt::big_is_probable_prime;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_factors_multiply_back() {
+        for n in 1..=150 {
+            let factors = factor_fib(n);
+            let product = factors.iter().fold(BigUint::from(1u32), |product, (prime, exponent)| {
+                assert!(big_is_probable_prime(prime), "fib({}) factor {}", n, prime);
+                product * num::pow(prime.clone(), *exponent as usize)
+            });
+            assert_eq!(product, exact_bigfib_pair(n).0, "n = {}", n);
+            assert!(factors.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        }
+        assert_eq!(factor_fib(1), vec![]);
+        assert_eq!(factor_fib(2), vec![]);
+        assert_eq!(try_factor_fib(0), Err(FibError::InvalidArgument("fib(0) = 0 has no factorization")));
+    }
+
+    #[test]
+    fn test_prime_powers() {
+        // fib(12) = 144 = 2^4 * 3^2
+        assert_eq!(factor_fib(12), vec![(BigUint::from(2u32), 4), (BigUint::from(3u32), 2)]);
+        // 5^k exactly divides fib(5^k)
+        assert_eq!(factor_fib(125).first(), Some(&(BigUint::from(5u32), 3)));
+    }
+}
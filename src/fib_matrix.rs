@@ -0,0 +1,137 @@
+use core::fmt;
+use core::ops::Mul;
+
+use crate::{fib_add_indices, sub_mod, FibError};
+
+
+/// The matrix Q^n = [[fib(n - 1), fib(n)], [fib(n), fib(n + 1)]] with modulo, for
+/// Q = [[0, 1], [1, 1]].
+///
+/// Multiplying Q^m by Q^n gives Q^(m + n) in O(1), so jumps computed once can be combined freely.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::FibMatrix;
+///
+/// let jump = FibMatrix::new(1_000_000, 1_000_000_007);
+/// let q = FibMatrix::q(1_000_000_007);
+/// assert_eq!(FibMatrix::new(3_000_001, 1_000_000_007), jump.pow(3) * q);
+/// assert_eq!("[[34, 55], [55, 89]] mod 100", FibMatrix::new(10, 100).to_string());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FibMatrix {
+    f_n: u64,
+    f_n1: u64,
+    modulo: u64,
+}
+
+impl FibMatrix {
+    /// Q^n with modulo. Runtime O(log(n))
+    pub fn new(n: u64, modulo: u64) -> FibMatrix {
+        FibMatrix::try_new(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `new`, but returns an error instead of panicking.
+    pub fn try_new(n: u64, modulo: u64) -> Result<FibMatrix, FibError> {
+        let (f_n, f_n1) = crate::try_fib_pair_with_mod(n, modulo)?;
+        Ok(FibMatrix { f_n, f_n1, modulo })
+    }
+
+    /// The identity matrix Q^0 with modulo.
+    pub fn identity(modulo: u64) -> FibMatrix {
+        FibMatrix::new(0, modulo)
+    }
+
+    /// Q itself with modulo.
+    pub fn q(modulo: u64) -> FibMatrix {
+        FibMatrix::new(1, modulo)
+    }
+
+    /// fib(n - 1), the top left entry.
+    pub fn fib_prev(&self) -> u64 {
+        sub_mod(self.f_n1, self.f_n, self.modulo)
+    }
+
+    /// fib(n), the off diagonal entries.
+    pub fn fib(&self) -> u64 {
+        self.f_n
+    }
+
+    /// fib(n + 1), the bottom right entry.
+    pub fn fib_next(&self) -> u64 {
+        self.f_n1
+    }
+
+    pub fn modulo(&self) -> u64 {
+        self.modulo
+    }
+
+    /// The entries as [[fib(n - 1), fib(n)], [fib(n), fib(n + 1)]].
+    pub fn to_array(&self) -> [[u64; 2]; 2] {
+        [[self.fib_prev(), self.f_n], [self.f_n, self.f_n1]]
+    }
+
+    /// Q^(n * exponent). Runtime O(log(exponent))
+    pub fn pow(&self, mut exponent: u64) -> FibMatrix {
+        let mut result = FibMatrix::identity(self.modulo);
+        let mut base = *self;
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent /= 2;
+        }
+        result
+    }
+}
+
+/// Q^m * Q^n = Q^(m + n). Panics if the moduli differ.
+impl Mul for FibMatrix {
+    type Output = FibMatrix;
+
+    fn mul(self, other: FibMatrix) -> FibMatrix {
+        assert_eq!(self.modulo, other.modulo, "moduli must match");
+        let (f_n, f_n1) = fib_add_indices((self.f_n, self.f_n1), (other.f_n, other.f_n1), self.modulo);
+        FibMatrix { f_n, f_n1, modulo: self.modulo }
+    }
+}
+
+impl fmt::Display for FibMatrix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "[[{}, {}], [{}, {}]] mod {}",
+            self.fib_prev(), self.f_n, self.f_n, self.f_n1, self.modulo
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::fib_matrix::*;
+
+    #[test]
+    fn test_matches_fib_pair() {
+        for &modulo in &[1, 2, 10, 1_000_000_007, u64::MAX] {
+            let q = FibMatrix::q(modulo);
+            for &n in &[0, 1, 2, 10, 1_000_000_000_000_000] {
+                let matrix = FibMatrix::new(n, modulo);
+                assert_eq!((matrix.fib(), matrix.fib_next()), crate::fib_pair_with_mod(n, modulo));
+                assert_eq!(matrix * q, FibMatrix::new(n + 1, modulo));
+                assert_eq!(matrix.pow(3), FibMatrix::new(3 * n, modulo));
+            }
+            assert_eq!(q.pow(0), FibMatrix::identity(modulo));
+        }
+        assert_eq!(FibMatrix::new(0, 100).to_array(), [[1, 0], [0, 1]]);
+        assert_eq!(FibMatrix::new(10, 100).to_array(), [[34, 55], [55, 89]]);
+        assert_eq!(FibMatrix::try_new(10, 0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    #[should_panic(expected = "moduli must match")]
+    fn test_mismatched_moduli() {
+        let _ = FibMatrix::q(10) * FibMatrix::q(100);
+    }
+}
@@ -0,0 +1,112 @@
+use std::ops::RangeInclusive;
+
+use crate::nt::{big_is_probable_prime, is_prime};
+
+// The largest factor tried by trial division before building fib(n) for a probable prime test.
+const TRIAL_FACTOR_LIMIT: u64 = 1 << 20;
+
+
+/// Checks whether fib(n) is prime, probably: with the Baillie-PSW test once fib(n) outgrows
+/// u64, see `nt::big_is_probable_prime`. Runtime O(n^3)
+///
+/// fib(d) divides fib(n) for every d dividing n, so apart from fib(4) = 3, fib(n) can only be
+/// prime when n is, and composite n return at once. For a prime n > 5, every prime factor of
+/// fib(n) is 1 mod 4 and ±1 mod n, so those up to 2^20 are tried with `fib_with_mod` before
+/// computing fib(n) exactly.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::is_fib_prime;
+///
+/// let indices: Vec<u64> = (0..100).filter(|&n| is_fib_prime(n)).collect();
+/// assert_eq!(vec![3, 4, 5, 7, 11, 13, 17, 23, 29, 43, 47, 83], indices);
+/// assert!(!is_fib_prime(1_000_000_000));
+/// ```
+pub fn is_fib_prime(n: u64) -> bool {
+    if n == 4 {
+        return true;
+    }
+    if !is_prime(n) {
+        return false;
+    }
+    // fib(93) is the last to fit in a u64.
+    if n <= 93 {
+        return is_prime(crate::fib_with_mod(n, u64::MAX));
+    }
+    if has_small_factor(n) {
+        return false;
+    }
+    big_is_probable_prime(&crate::exact_bigfib_pair(n).0)
+}
+
+
+/// The indices n up to max_index for which fib(n) is prime, probably, in increasing order, see
+/// `is_fib_prime`.
+///
+/// # Examples
+/// ```
+/// let indices: Vec<u64> = fast_fibonacci::fib_prime_indices(200).collect();
+/// assert_eq!(vec![3, 4, 5, 7, 11, 13, 17, 23, 29, 43, 47, 83, 131, 137], indices);
+/// ```
+pub fn fib_prime_indices(max_index: u64) -> FibPrimeIndices {
+    FibPrimeIndices { candidates: 0..=max_index }
+}
+
+
+/// Iterator returned by `fib_prime_indices`.
+#[derive(Clone, Debug)]
+pub struct FibPrimeIndices {
+    candidates: RangeInclusive<u64>,
+}
+
+impl Iterator for FibPrimeIndices {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        self.candidates.find(|&n| is_fib_prime(n))
+    }
+}
+
+
+// Whether a prime q = 2kn ± 1 = 1 mod 4 up to TRIAL_FACTOR_LIMIT divides fib(n), for a prime
+// n > 93, so that fib(n) > q.
+fn has_small_factor(n: u64) -> bool {
+    let step = match n.checked_mul(2) {
+        Some(step) if step < TRIAL_FACTOR_LIMIT => step,
+        _ => return false,
+    };
+    (step..TRIAL_FACTOR_LIMIT)
+        .step_by(step as usize)
+        .flat_map(|multiple| [multiple - 1, multiple + 1])
+        .any(|q| q % 4 == 1 && is_prime(q) && crate::fib_with_mod(n, q) == 0)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use crate::nt::{big_is_probable_prime, is_prime};
+
+    #[test]
+    fn test_known_fibonacci_primes() {
+        // OEIS A001605
+        let expected = [3, 4, 5, 7, 11, 13, 17, 23, 29, 43, 47, 83, 131, 137, 359, 431, 433, 449, 509, 569, 571];
+        let indices: Vec<u64> = (0..600).filter(|&n| is_fib_prime(n)).collect();
+        assert_eq!(indices, expected);
+        assert_eq!(fib_prime_indices(600).collect::<Vec<u64>>(), expected);
+    }
+
+    #[test]
+    fn test_small_factors_divide() {
+        for n in (97..1_000).filter(|&n| is_prime(n)) {
+            if fib_prime::has_small_factor(n) {
+                assert!(!big_is_probable_prime(&exact_bigfib_pair(n).0), "n = {}", n);
+            }
+        }
+        // fib(137) and fib(359) are prime.
+        assert!(!fib_prime::has_small_factor(137));
+        assert!(!fib_prime::has_small_factor(359));
+        // 2 * 97 - 1 = 193 divides fib(97).
+        assert!(fib_prime::has_small_factor(97));
+    }
+}
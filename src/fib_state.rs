@@ -0,0 +1,112 @@
+use crate::{add_mod, fib_add_indices, FibError};
+
+
+/// The pair (fib(n), fib(n + 1)) with modulo, moving forward through the sequence.
+///
+/// `step` moves to n + 1 in O(1), and `advance` jumps by any delta in O(log(delta)). The jump for
+/// the last delta is kept, so advancing by the same delta again, as simulations with a fixed time
+/// step do, is O(1).
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::FibState;
+///
+/// let mut state = FibState::new(10, 1_000);
+/// assert_eq!((55, 89), (state.fib(), state.fib_next()));
+/// state.step();
+/// assert_eq!(89, state.fib());
+/// state.advance(1_000_000);
+/// state.advance(1_000_000);
+/// assert_eq!(fast_fibonacci::fib_with_mod(2_000_011, 1_000), state.fib());
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct FibState {
+    f_n: u64,
+    f_n1: u64,
+    modulo: u64,
+    // The last delta advanced by, and (fib(delta), fib(delta + 1)).
+    jump: Option<(u64, (u64, u64))>,
+}
+
+impl FibState {
+    /// The state at n with modulo. Runtime O(log(n))
+    pub fn new(n: u64, modulo: u64) -> FibState {
+        FibState::try_new(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `new`, but returns an error instead of panicking.
+    pub fn try_new(n: u64, modulo: u64) -> Result<FibState, FibError> {
+        let (f_n, f_n1) = crate::try_fib_pair_with_mod(n, modulo)?;
+        Ok(FibState { f_n, f_n1, modulo, jump: None })
+    }
+
+    /// fib(n)
+    pub fn fib(&self) -> u64 {
+        self.f_n
+    }
+
+    /// fib(n + 1)
+    pub fn fib_next(&self) -> u64 {
+        self.f_n1
+    }
+
+    pub fn modulo(&self) -> u64 {
+        self.modulo
+    }
+
+    /// Moves from n to n + 1. Runtime O(1)
+    pub fn step(&mut self) {
+        let next = add_mod(self.f_n, self.f_n1, self.modulo);
+        self.f_n = self.f_n1;
+        self.f_n1 = next;
+    }
+
+    /// Moves from n to n + delta. Runtime O(1) when delta is the same as last time, and
+    /// O(log(delta)) otherwise.
+    pub fn advance(&mut self, delta: u64) {
+        let jump = match self.jump {
+            Some((last, jump)) if last == delta => jump,
+            _ => {
+                let jump = crate::fib_pair_with_mod(delta, self.modulo);
+                self.jump = Some((delta, jump));
+                jump
+            },
+        };
+        let (f_n, f_n1) = fib_add_indices((self.f_n, self.f_n1), jump, self.modulo);
+        self.f_n = f_n;
+        self.f_n1 = f_n1;
+    }
+}
+
+/// States are equal at the same pair with the same modulo, whatever jump they keep.
+impl PartialEq for FibState {
+    fn eq(&self, other: &FibState) -> bool {
+        (self.f_n, self.f_n1, self.modulo) == (other.f_n, other.f_n1, other.modulo)
+    }
+}
+
+impl Eq for FibState {}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::fib_state::*;
+    use crate::fib_pair_with_mod;
+
+    #[test]
+    fn test_matches_fib_pair() {
+        for &modulo in &[1, 2, 10, 1_000_000_007, u64::MAX] {
+            let mut state = FibState::new(0, modulo);
+            let mut n = 0;
+            for &delta in &[1, 1, 0, 7, 7, 7, 1_000_000_000_000, 1_000_000_000_000, 3] {
+                state.advance(delta);
+                n += delta;
+                assert_eq!((state.fib(), state.fib_next()), fib_pair_with_mod(n, modulo), "n = {}", n);
+                state.step();
+                n += 1;
+                assert_eq!(state, FibState::new(n, modulo), "n = {}", n);
+            }
+        }
+        assert_eq!(FibState::try_new(10, 0), Err(FibError::ZeroModulus));
+    }
+}
@@ -0,0 +1,193 @@
+use crate::nt::mod_inverse;
+use crate::{add_mod, mul_mod, sub_mod, FibError, GoldenInt};
+
+
+/// Finds fib(a) + fib(a + d) + ... + fib(a + (count - 1)d) with modulo. Runtime O(log(count) +
+/// log(a) + log(d))
+///
+/// The terms x_i = fib(a + id) satisfy x_(i+1) = lucas(d) x_i - (-1)^d x_(i-1), which sums to
+/// the closed form
+///
+/// (1 - lucas(d) + (-1)^d) * sum = (1 - lucas(d)) x_0 + x_1 - x_count + (-1)^d x_(count-1).
+///
+/// When the factor on the left has no inverse modulo `modulo`, e.g. for d = 3, where it's -4, and
+/// an even modulo, the sum is a geometric series φ^a (1 + φ^d + ... + φ^((count - 1)d)) in Z[φ]
+/// instead, summed by doubling.
+///
+/// # Examples
+/// ```
+/// // fib(2) + fib(5) + fib(8) + fib(11) = 1 + 5 + 21 + 89
+/// assert_eq!(116, fast_fibonacci::fib_ap_sum_with_mod(2, 3, 4, 1_000));
+/// // The sum of the first n fibonacci numbers is fib(n + 1) - 1.
+/// assert_eq!(
+///     fast_fibonacci::fib_with_mod(1_000_001, 1_000_000_007) - 1,
+///     fast_fibonacci::fib_ap_sum_with_mod(0, 1, 1_000_000, 1_000_000_007)
+/// );
+/// ```
+pub fn fib_ap_sum_with_mod(a: u64, d: u64, count: u64, modulo: u64) -> u64 {
+    try_fib_ap_sum_with_mod(a, d, count, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_ap_sum_with_mod`, but returns an error instead of panicking.
+pub fn try_fib_ap_sum_with_mod(a: u64, d: u64, count: u64, modulo: u64) -> Result<u64, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    if count == 0 {
+        return Ok(0);
+    }
+    let phi = GoldenInt::phi(modulo);
+    let (start, ratio) = (phi.pow(a), phi.pow(d));
+    // φ^d = fib(d - 1) + fib(d)φ, and lucas(d) = fib(d - 1) + fib(d + 1).
+    let lucas = add_mod(add_mod(ratio.a(), ratio.a(), modulo), ratio.b(), modulo);
+    let sign = if d.is_multiple_of(2) { 1 % modulo } else { modulo - 1 };
+    let one_minus_lucas = sub_mod(1 % modulo, lucas, modulo);
+
+    let inverse = match mod_inverse(add_mod(one_minus_lucas, sign, modulo), modulo) {
+        Some(inverse) => inverse,
+        None => return Ok((start * geometric_sum(ratio, count)).b()),
+    };
+    let second_last = start * ratio.pow(count - 1);
+    let (x_0, x_1) = (start.b(), (start * ratio).b());
+    let (x_last, x_count) = (second_last.b(), (second_last * ratio).b());
+    let right = add_mod(mul_mod(one_minus_lucas, x_0, modulo), sub_mod(x_1, x_count, modulo), modulo);
+    let right = add_mod(right, mul_mod(sign, x_last, modulo), modulo);
+    Ok(mul_mod(inverse, right, modulo))
+}
+
+
+/// Finds fib(a)fib(a + k) + fib(a + 1)fib(a + 1 + k) + ... + fib(a + count - 1)fib(a + count - 1 + k)
+/// with modulo. Runtime O(log(a) + log(k) + log(count))
+///
+/// The product-to-sum identity 5fib(i)fib(i + k) = lucas(2i + k) - (-1)^i lucas(k) would divide
+/// by 5, so instead the products telescope with h(i) = fib(i - 1)fib(i + k). d'Ocagne's identity
+/// gives h(i + 1) - h(i) = fib(i)fib(i + k) - (-1)^i fib(k), so
+///
+/// sum = h(a + count) - h(a) + fib(k) * ((-1)^a + ... + (-1)^(a + count - 1)),
+///
+/// which works for any modulo.
+///
+/// # Examples
+/// ```
+/// // 1 * 2 + 1 * 3 + 2 * 5 + 3 * 8
+/// assert_eq!(39, fast_fibonacci::fib_product_sum_with_mod(1, 2, 4, 1_000));
+/// // The sum of the first n squares is fib(n - 1)fib(n).
+/// let (f_n, f_n1) = fast_fibonacci::fib_pair_with_mod(1_000_000, 1_000_000_007);
+/// assert_eq!(
+///     (f_n1 + 1_000_000_007 - f_n) * f_n % 1_000_000_007,
+///     fast_fibonacci::fib_product_sum_with_mod(0, 0, 1_000_000, 1_000_000_007)
+/// );
+/// ```
+pub fn fib_product_sum_with_mod(a: u64, k: u64, count: u64, modulo: u64) -> u64 {
+    try_fib_product_sum_with_mod(a, k, count, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_product_sum_with_mod`, but returns an error instead of panicking.
+pub fn try_fib_product_sum_with_mod(a: u64, k: u64, count: u64, modulo: u64) -> Result<u64, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    let phi = GoldenInt::phi(modulo);
+    // φ^i = fib(i - 1) + fib(i)φ, and multiplying the powers keeps a + count + k from overflowing.
+    let (start, shift) = (phi.pow(a), phi.pow(k));
+    let h = |power: GoldenInt| mul_mod(power.a(), (power * shift).b(), modulo);
+    let sum = sub_mod(h(start * phi.pow(count)), h(start), modulo);
+    if count.is_multiple_of(2) {
+        return Ok(sum);
+    }
+    // The signs cancel in pairs, leaving (-1)^a.
+    let f_k = shift.b();
+    Ok(if a.is_multiple_of(2) { add_mod(sum, f_k, modulo) } else { sub_mod(sum, f_k, modulo) })
+}
+
+
+// 1 + ratio + ... + ratio^(count - 1), over the bits of count from the top, keeping the sum and
+// ratio^j for the prefix j of count.
+fn geometric_sum(ratio: GoldenInt, count: u64) -> GoldenInt {
+    let one = GoldenInt::new(1, 0, ratio.modulo());
+    let (mut sum, mut power) = (GoldenInt::new(0, 0, ratio.modulo()), one);
+    for bit in (0..64 - count.leading_zeros()).rev() {
+        // j to 2j
+        sum = sum * (one + power);
+        power = power * power;
+        if (count >> bit) & 1 == 1 {
+            // j to j + 1
+            sum = sum + power;
+            power = power * ratio;
+        }
+    }
+    sum
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::fib_sum::*;
+
+    #[test]
+    fn test_matches_naive() {
+        let fibs: Vec<u128> = (0..150).fold(vec![0, 1], |mut fibs, i| {
+            fibs.push(fibs[i] + fibs[i + 1]);
+            fibs
+        });
+        for &modulo in &[1, 2, 3, 4, 5, 8, 10, 11, 12, 100, 1_000_000_007, u64::MAX] {
+            for a in 0..8 {
+                for d in 0..8 {
+                    let mut sum = 0;
+                    for count in 0..20 {
+                        assert_eq!(
+                            fib_ap_sum_with_mod(a, d, count, modulo),
+                            (sum % modulo as u128) as u64,
+                            "a = {}, d = {}, count = {}, modulo = {}", a, d, count, modulo
+                        );
+                        sum += fibs[(a + count * d) as usize];
+                    }
+                }
+            }
+        }
+        assert_eq!(try_fib_ap_sum_with_mod(1, 1, 1, 0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_product_sum_matches_naive() {
+        let fibs: Vec<u128> = (0..80).fold(vec![0, 1], |mut fibs, i| {
+            fibs.push(fibs[i] + fibs[i + 1]);
+            fibs
+        });
+        for &modulo in &[1, 2, 5, 10, 1_000_000_007, u64::MAX] {
+            for a in 0..10 {
+                for k in 0..10 {
+                    let mut sum = 0u128;
+                    for count in 0..20 {
+                        assert_eq!(
+                            fib_product_sum_with_mod(a, k, count, modulo),
+                            (sum % modulo as u128) as u64,
+                            "a = {}, k = {}, count = {}, modulo = {}", a, k, count, modulo
+                        );
+                        let i = (a + count) as usize;
+                        sum += fibs[i] * fibs[i + k as usize];
+                    }
+                }
+            }
+        }
+        // Splitting the range at any point gives the same sum.
+        let modulo = 999_999_937;
+        let whole = fib_product_sum_with_mod(u64::MAX - 5_000, u64::MAX, 5_000, modulo);
+        let head = fib_product_sum_with_mod(u64::MAX - 5_000, u64::MAX, 1_234, modulo);
+        let tail = fib_product_sum_with_mod(u64::MAX - 5_000 + 1_234, u64::MAX, 5_000 - 1_234, modulo);
+        assert_eq!(whole, (head + tail) % modulo);
+        assert_eq!(try_fib_product_sum_with_mod(1, 1, 1, 0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_geometric_sum_matches_closed_form() {
+        let modulo = 1_000_000_007;
+        for &(a, d, count) in &[(0, 1, 1_000_000), (5, 7, 123_456_789), (u64::MAX, u64::MAX, u64::MAX)] {
+            let phi = GoldenInt::phi(modulo);
+            let geometric = (phi.pow(a) * geometric_sum(phi.pow(d), count)).b();
+            assert_eq!(geometric, fib_ap_sum_with_mod(a, d, count, modulo), "a = {}, d = {}, count = {}", a, d, count);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::combinatorics::{binomial_mod_prime, check_prime};
+use crate::{add_mod, fib_with_mod, mul_mod, pow_mod, FibError};
+
+
+/// Finds the fibonorial fib(1) * fib(2) * ... * fib(n) with modulo. Runtime O(min(n, 6 * modulo))
+///
+/// Streams through consecutive fibonacci numbers, so each factor costs one addition. Every
+/// modulo divides some fib(i) with i <= 6 * modulo, after which the product stays 0, so the
+/// iteration stops early for large n.
+///
+/// # Examples
+/// ```
+/// assert_eq!(1, fast_fibonacci::fibonorial_with_mod(0, 1_000));
+/// // 1 * 1 * 2 * 3 * 5 * 8 = 240
+/// assert_eq!(240, fast_fibonacci::fibonorial_with_mod(6, 1_000));
+/// assert_eq!(0, fast_fibonacci::fibonorial_with_mod(1_000_000_000_000_000, 1_000_000));
+/// ```
+pub fn fibonorial_with_mod(n: u64, modulo: u64) -> u64 {
+    try_fibonorial_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fibonorial_with_mod`, but returns an error instead of panicking.
+pub fn try_fibonorial_with_mod(n: u64, modulo: u64) -> Result<u64, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    let mut product = 1 % modulo;
+    let (mut f_i, mut f_i1) = (1 % modulo, 1 % modulo);
+    for _ in 0..n {
+        if product == 0 {
+            break;
+        }
+        product = mul_mod(product, f_i, modulo);
+        let f_i2 = add_mod(f_i, f_i1, modulo);
+        f_i = f_i1;
+        f_i1 = f_i2;
+    }
+    Ok(product)
+}
+
+
+/// Finds the fibonomial coefficient [n choose k]_F = n!_F / (k!_F * (n - k)!_F) with a prime
+/// modulo, where n!_F is the fibonorial. Runtime O(sqrt(prime) + prime)
+///
+/// Factors of the fibonorials can be divisible by the prime, so they can't simply be inverted.
+/// Instead, this splits n and k around the prime's rank of apparition α (the first index with
+/// prime | fib(α)), in the spirit of Lucas' theorem: the multiples of α contribute an ordinary
+/// binomial coefficient, and the rest only involves invertible factors.
+///
+/// modulo must be prime.
+///
+/// # Examples
+/// ```
+/// // [6 choose 3]_F = 240 / (2 * 2) = 60
+/// assert_eq!(60, fast_fibonacci::fibonomial_with_mod(6, 3, 1_000_000_007));
+/// assert_eq!(60 % 7, fast_fibonacci::fibonomial_with_mod(6, 3, 7));
+/// assert_eq!(0, fast_fibonacci::fibonomial_with_mod(3, 4, 7));
+/// ```
+pub fn fibonomial_with_mod(n: u64, k: u64, prime: u64) -> u64 {
+    try_fibonomial_with_mod(n, k, prime).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fibonomial_with_mod`, but checks that the modulo is prime, and returns an error instead of
+/// panicking. Runtime O(sqrt(prime) + prime)
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::FibError;
+///
+/// assert_eq!(Ok(60), fast_fibonacci::try_fibonomial_with_mod(6, 3, 1_000_000_007));
+/// assert_eq!(
+///     Err(FibError::InvalidArgument("modulo must be prime")),
+///     fast_fibonacci::try_fibonomial_with_mod(6, 3, 10)
+/// );
+/// ```
+pub fn try_fibonomial_with_mod(n: u64, k: u64, prime: u64) -> Result<u64, FibError> {
+    check_prime(prime)?;
+    Ok(unchecked_fibonomial(n, k, prime))
+}
+
+
+// fibonomial_with_mod for a modulo already known to be prime.
+fn unchecked_fibonomial(n: u64, k: u64, prime: u64) -> u64 {
+    if k > n {
+        return 0;
+    }
+    let a = crate::pisano::rank_of_apparition(prime);
+    let (n1, n0) = (n / a, n % a);
+    let (k1, k0) = (k / a, k % a);
+    let (m1, m0) = ((n - k) / a, (n - k) % a);
+    if k0 > n0 {
+        // Carrying means the numerator holds one more multiple of fib(α) than the denominator.
+        return 0;
+    }
+
+    // fib(jα + r) = fib(α - 1)^j * fib(r) modulo the prime, which leaves this power of
+    // fib(α - 1) over after canceling.
+    let c = fib_with_mod(a - 1, prime);
+    let order = prime as u128 - 1;
+    let exponent = if order == 0 {
+        0
+    } else {
+        let (a, k1, m1, k0, m0) = (a as u128, k1 as u128, m1 as u128, k0 as u128, m0 as u128);
+        (a % order * (k1 % order) % order * (m1 % order) + k1 * m0 % order + m1 * k0 % order) % order
+    };
+
+    let result = mul_mod(pow_mod(c, exponent as u64, prime), small_fibonomial(n0, k0, prime), prime);
+    mul_mod(result, binomial_mod_prime(n1 as u128, k1 as u128, prime), prime)
+}
+
+
+// [n choose k]_F for n below the rank of apparition, where every factor is invertible.
+fn small_fibonomial(n: u64, k: u64, prime: u64) -> u64 {
+    let (mut numerator, mut denominator) = (1 % prime, 1 % prime);
+    let (mut f_i, mut f_i1) = (1 % prime, 1 % prime);
+    for i in 1..=n {
+        if i <= k {
+            denominator = mul_mod(denominator, f_i, prime);
+        }
+        if i > n - k {
+            numerator = mul_mod(numerator, f_i, prime);
+        }
+        let f_i2 = add_mod(f_i, f_i1, prime);
+        f_i = f_i1;
+        f_i1 = f_i2;
+    }
+    mul_mod(numerator, pow_mod(denominator, prime - 2, prime), prime)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_fibonorial_small() {
+        let expected = [1u64, 1, 1, 2, 6, 30, 240, 3_120, 65_520, 2_227_680, 122_522_400];
+        for (n, &value) in expected.iter().enumerate() {
+            assert_eq!(fibonorial_with_mod(n as u64, u64::MAX), value);
+            assert_eq!(fibonorial_with_mod(n as u64, 1_000), value % 1_000);
+        }
+    }
+
+    #[test]
+    fn test_fibonorial_hits_zero() {
+        // 11 first divides fib(10) = 55
+        assert_ne!(fibonorial_with_mod(9, 11), 0);
+        assert_eq!(fibonorial_with_mod(10, 11), 0);
+        assert_eq!(fibonorial_with_mod(u64::MAX, 11), 0);
+        assert_eq!(fibonorial_with_mod(5, 1), 0);
+    }
+
+    #[test]
+    fn test_fibonomial_matches_exact() {
+        let mut fibonorials = vec![BigUint::from(1u32)];
+        for i in 1..60 {
+            let next = &fibonorials[i - 1] * exact_bigfib_pair(i as u64).0;
+            fibonorials.push(next);
+        }
+
+        for n in 0..60 {
+            for k in 0..=n {
+                let exact = &fibonorials[n] / (&fibonorials[k] * &fibonorials[n - k]);
+                for &prime in &[2u64, 3, 5, 7, 11, 13, 47, 1_000_003] {
+                    assert_eq!(
+                        BigUint::from(fibonomial_with_mod(n as u64, k as u64, prime)),
+                        &exact % BigUint::from(prime),
+                        "n = {}, k = {}, prime = {}", n, k, prime
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//! The finite fields GF(p^k), for recurrences over extension fields rather than the integers
+//! modulo m.
+//!
+//! Elements are polynomials of degree below k over the integers modulo the prime p, as their
+//! coefficients from the constant term up, and multiply modulo a monic irreducible polynomial of
+//! degree k. `GaloisField` is a `Semiring`, so `semiring::linear_recurrence_in` evaluates any
+//! linear recurrence with coefficients and initial terms in the field through the same matrix
+//! power as `fib_with_mod`.
+//!
+//! # Examples
+//! ```
+//! use fast_fibonacci::galois::GaloisField;
+//! use fast_fibonacci::semiring::linear_recurrence_in;
+//!
+//! // GF(4) as polynomials over GF(2) modulo a^2 + a + 1.
+//! let field = GaloisField::new(2, &[1, 1, 1]);
+//! let (zero, one, a) = (field.element(&[0]), field.element(&[1]), field.element(&[0, 1]));
+//! // x(i) = x(i - 1) + a * x(i - 2) from 0, 1: 0, 1, 1, 1 + a, 1, ...
+//! let (coefficients, initial) = ([one.clone(), a], [zero, one]);
+//! let terms: Vec<Vec<u64>> = (0..5).map(|n| linear_recurrence_in(&field, &coefficients, &initial, n)).collect();
+//! assert_eq!(vec![vec![0, 0], vec![1, 0], vec![1, 0], vec![1, 1], vec![1, 0]], terms);
+//! ```
+
+use crate::nt::{factorize, is_prime, mod_inverse};
+use crate::semiring::Semiring;
+use crate::{add_mod, mul_mod, FibError};
+
+
+/// GF(p^k), the polynomials over the integers modulo p, modulo a monic irreducible polynomial of
+/// degree k. Elements are `Vec<u64>`s of their k coefficients from the constant term up, which
+/// `Semiring::add` and `Semiring::mul` expect reduced, as from `element`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GaloisField {
+    prime: u64,
+    // The coefficients of the modulus from the constant term up, ending in 1.
+    modulus: Vec<u64>,
+}
+
+impl GaloisField {
+    /// GF(prime^k) for a monic irreducible modulus of degree k, as its coefficients from the
+    /// constant term up. Runtime O(k^3 * log(prime) * log(k))
+    pub fn new(prime: u64, modulus: &[u64]) -> GaloisField {
+        GaloisField::try_new(prime, modulus).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `new`, but returns an error instead of panicking when prime isn't prime, or the
+    /// modulus isn't monic, of degree at least 1 and irreducible.
+    ///
+    /// # Examples
+    /// ```
+    /// use fast_fibonacci::galois::GaloisField;
+    /// use fast_fibonacci::FibError;
+    ///
+    /// assert!(GaloisField::try_new(3, &[1, 0, 1]).is_ok());
+    /// // x^2 + 1 = (x + 1)^2 over GF(2)
+    /// assert_eq!(Err(FibError::InvalidArgument("modulus must be irreducible")), GaloisField::try_new(2, &[1, 0, 1]));
+    /// ```
+    pub fn try_new(prime: u64, modulus: &[u64]) -> Result<GaloisField, FibError> {
+        if !is_prime(prime) {
+            return Err(FibError::InvalidArgument("characteristic must be prime"));
+        }
+        let modulus: Vec<u64> = modulus.iter().map(|&c| c % prime).collect();
+        if modulus.len() < 2 || modulus[modulus.len() - 1] != 1 {
+            return Err(FibError::InvalidArgument("modulus must be monic with degree at least 1"));
+        }
+        let field = GaloisField { prime, modulus };
+        if !field.is_irreducible() {
+            return Err(FibError::InvalidArgument("modulus must be irreducible"));
+        }
+        Ok(field)
+    }
+
+    pub fn prime(&self) -> u64 {
+        self.prime
+    }
+
+    /// k, for the prime^k elements.
+    pub fn degree(&self) -> usize {
+        self.modulus.len() - 1
+    }
+
+    /// The element for a polynomial of any degree, with any coefficients. Runtime O(d * k) for
+    /// degree d
+    pub fn element(&self, coefficients: &[u64]) -> Vec<u64> {
+        self.reduce(coefficients.iter().map(|&c| c % self.prime).collect())
+    }
+
+    /// The element to the power pow. Runtime O(k^2 * log(pow))
+    ///
+    /// # Examples
+    /// ```
+    /// use fast_fibonacci::galois::GaloisField;
+    ///
+    /// // The nonzero elements of GF(9) form a group of order 8.
+    /// let field = GaloisField::new(3, &[1, 0, 1]);
+    /// assert_eq!(field.element(&[1]), field.pow(&field.element(&[2, 1]), 8));
+    /// ```
+    pub fn pow(&self, element: &[u64], pow: u64) -> Vec<u64> {
+        let mut power = self.one();
+        for bit in (0..64 - pow.leading_zeros()).rev() {
+            power = self.mul(&power, &power);
+            if pow >> bit & 1 == 1 {
+                power = self.mul(&power, &element.to_vec());
+            }
+        }
+        power
+    }
+
+    // Rabin's test: x^(p^k) = x, and x^(p^(k / q)) - x is coprime to the modulus for every prime
+    // q dividing k.
+    fn is_irreducible(&self) -> bool {
+        let k = self.degree();
+        let x = self.element(&[0, 1]);
+        // x^(p^i) for i = 0..=k, by raising to the pth power again and again.
+        let mut frobenius = vec![x.clone()];
+        for i in 0..k {
+            frobenius.push(self.pow(&frobenius[i], self.prime));
+        }
+        if frobenius[k] != x {
+            return false;
+        }
+        factorize(k as u64).into_iter().all(|(q, _)| {
+            let difference: Vec<u64> = frobenius[k / q as usize]
+                .iter()
+                .zip(&x)
+                .map(|(&a, &b)| add_mod(a, self.prime - b, self.prime))
+                .collect();
+            self.gcd_degree(difference) == 0
+        })
+    }
+
+    // The degree of gcd(a, modulus), for a reduced a that isn't 0.
+    fn gcd_degree(&self, a: Vec<u64>) -> usize {
+        let (mut a, mut b) = (self.modulus.clone(), trim(a));
+        while !b.is_empty() {
+            let remainder = self.remainder(a, &b);
+            a = b;
+            b = remainder;
+        }
+        a.len().saturating_sub(1)
+    }
+
+    // a modulo b, for b without trailing zeros.
+    fn remainder(&self, mut a: Vec<u64>, b: &[u64]) -> Vec<u64> {
+        let p = self.prime;
+        // b's leading coefficient is nonzero modulo a prime, so invertible.
+        let inverse = mod_inverse(b[b.len() - 1], p).unwrap();
+        while a.len() >= b.len() {
+            let factor = mul_mod(a[a.len() - 1], inverse, p);
+            let shift = a.len() - b.len();
+            for (i, &c) in b.iter().enumerate() {
+                a[shift + i] = add_mod(a[shift + i], p - mul_mod(factor, c, p), p);
+            }
+            a.pop();
+            a = trim(a);
+        }
+        a
+    }
+
+    // a modulo the monic modulus, padded to k coefficients.
+    fn reduce(&self, mut a: Vec<u64>) -> Vec<u64> {
+        let (p, k) = (self.prime, self.degree());
+        while a.len() > k {
+            let top = a.pop().unwrap();
+            let shift = a.len() - k;
+            for i in 0..k {
+                a[shift + i] = add_mod(a[shift + i], p - mul_mod(top, self.modulus[i], p), p);
+            }
+        }
+        a.resize(k, 0);
+        a
+    }
+}
+
+impl Semiring for GaloisField {
+    type Element = Vec<u64>;
+
+    fn zero(&self) -> Vec<u64> {
+        vec![0; self.degree()]
+    }
+
+    fn one(&self) -> Vec<u64> {
+        self.element(&[1])
+    }
+
+    fn add(&self, a: &Vec<u64>, b: &Vec<u64>) -> Vec<u64> {
+        a.iter().zip(b).map(|(&x, &y)| add_mod(x, y, self.prime)).collect()
+    }
+
+    fn mul(&self, a: &Vec<u64>, b: &Vec<u64>) -> Vec<u64> {
+        let p = self.prime;
+        let mut product = vec![0; a.len() + b.len() - 1];
+        for (i, &x) in a.iter().enumerate() {
+            for (j, &y) in b.iter().enumerate() {
+                product[i + j] = add_mod(product[i + j], mul_mod(x, y, p), p);
+            }
+        }
+        self.reduce(product)
+    }
+}
+
+
+// Without trailing zero coefficients, so the last one is the leading one.
+fn trim(mut a: Vec<u64>) -> Vec<u64> {
+    while a.last() == Some(&0) {
+        a.pop();
+    }
+    a
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::galois::*;
+    use crate::semiring::{linear_recurrence_in, Modular};
+    use std::num::NonZeroU64;
+
+    // Whether a monic polynomial of degree 2 or 3 over GF(p) is irreducible, which for those
+    // degrees means it has no root.
+    fn irreducible_brute_force(p: u64, modulus: &[u64]) -> bool {
+        (0..p).all(|x| modulus.iter().rev().fold(0, |value, &c| (value * x + c) % p) != 0)
+    }
+
+    #[test]
+    fn test_irreducibility() {
+        for &p in &[2u64, 3, 5, 7] {
+            for k in 2..=3 {
+                for low in 0..p.pow(k as u32) {
+                    let mut modulus: Vec<u64> = (0..k).map(|i| low / p.pow(i as u32) % p).collect();
+                    modulus.push(1);
+                    let expected = irreducible_brute_force(p, &modulus);
+                    assert_eq!(GaloisField::try_new(p, &modulus).is_ok(), expected, "p = {}, modulus = {:?}", p, modulus);
+                }
+            }
+        }
+        // The AES field, GF(2^8) modulo x^8 + x^4 + x^3 + x + 1, and a reducible square.
+        assert!(GaloisField::try_new(2, &[1, 1, 0, 1, 1, 0, 0, 0, 1]).is_ok());
+        let square = [1, 0, 1, 0, 1];
+        assert_eq!(GaloisField::try_new(2, &square), Err(FibError::InvalidArgument("modulus must be irreducible")));
+        assert_eq!(GaloisField::try_new(4, &[1, 1, 1]), Err(FibError::InvalidArgument("characteristic must be prime")));
+        for modulus in [&[1][..], &[], &[1, 2]] {
+            let err = FibError::InvalidArgument("modulus must be monic with degree at least 1");
+            assert_eq!(GaloisField::try_new(3, modulus), Err(err), "modulus = {:?}", modulus);
+        }
+    }
+
+    #[test]
+    fn test_field_axioms() {
+        let field = GaloisField::new(2, &[1, 1, 0, 1, 1, 0, 0, 0, 1]);
+        let elements: Vec<Vec<u64>> = (0..256u64).map(|bits| field.element(&(0..8).map(|i| bits >> i & 1).collect::<Vec<u64>>())).collect();
+        // Every nonzero element has an inverse, a^254.
+        for a in &elements[1..] {
+            assert_eq!(field.mul(a, &field.pow(a, 254)), field.one(), "a = {:?}", a);
+        }
+        // 0x53 * 0xca = 1 in the AES field.
+        assert_eq!(field.mul(&elements[0x53], &elements[0xca]), field.one());
+        assert_eq!(field.element(&[1, 0, 0, 0, 0, 0, 0, 0, 1]), field.element(&[0, 1, 0, 1, 1]));
+    }
+
+    #[test]
+    fn test_degree_one_matches_modular() {
+        // GF(p) itself, where fibonacci is fib_with_mod.
+        let field = GaloisField::new(1_000_000_007, &[0, 1]);
+        let modular = Modular(NonZeroU64::new(1_000_000_007).unwrap());
+        let (zero, one) = (field.zero(), field.one());
+        for &n in &[0, 1, 2, 50, u64::MAX] {
+            let fib = linear_recurrence_in(&field, &[one.clone(), one.clone()], &[zero.clone(), one.clone()], n);
+            assert_eq!(fib, vec![crate::fib_with_mod(n, 1_000_000_007)]);
+            assert_eq!(linear_recurrence_in(&modular, &[1, 1], &[0, 1], n), fib[0]);
+        }
+    }
+
+    #[test]
+    fn test_recurrence_matches_naive() {
+        let field = GaloisField::new(5, &[2, 0, 1, 1]);
+        let coefficients = [field.element(&[1, 2]), field.element(&[0, 0, 3]), field.element(&[4])];
+        let initial = [field.element(&[1]), field.element(&[0, 1]), field.element(&[2, 2, 2])];
+        let mut terms = initial.to_vec();
+        for i in 3..200 {
+            let term = (0..3).fold(field.zero(), |sum, j| field.add(&sum, &field.mul(&coefficients[j], &terms[i - 1 - j])));
+            terms.push(term);
+        }
+        for (n, term) in terms.iter().enumerate() {
+            assert_eq!(&linear_recurrence_in(&field, &coefficients, &initial, n as u64), term, "n = {}", n);
+        }
+    }
+}
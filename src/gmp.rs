@@ -0,0 +1,97 @@
+//! bigfib_with_mod on GMP through rug, with the `rug` feature. GMP multiplies operands of 10^5
+//! bits and more many times faster than num-bigint.
+
+use num_bigint::BigUint;
+use rug::integer::Order;
+use rug::Integer;
+
+use crate::bignum::{self, BigNum};
+
+
+impl BigNum for Integer {
+    fn from_u64(n: u64) -> Self {
+        Integer::from(n)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.cmp0() == std::cmp::Ordering::Equal
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        Integer::from(self + other)
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        Integer::from(self - other)
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        Integer::from(self * other)
+    }
+
+    fn rem(&self, modulo: &Self) -> Self {
+        Integer::from(self % modulo)
+    }
+
+    fn to_bytes_le(&self) -> Vec<u8> {
+        self.to_digits(Order::Lsf)
+    }
+
+    fn square(&self) -> Self {
+        Integer::from(self.square_ref())
+    }
+}
+
+
+// fib(n) with a positive modulo, by `bignum::fib_with_mod` on GMP integers.
+pub(crate) fn bigfib_with_mod(n: &BigUint, modulo: &BigUint) -> BigUint {
+    to_biguint(&bignum::fib_with_mod(&to_integer(n), &to_integer(modulo)))
+}
+
+
+// fib(n) exactly, by GMP's own fibonacci function, or `bignum::fib` on GMP integers past it.
+pub(crate) fn fib(n: u64) -> BigUint {
+    if n <= u64::from(u32::MAX) {
+        return to_biguint(&Integer::from(Integer::fibonacci(n as u32)));
+    }
+    to_biguint(&bignum::fib(n))
+}
+
+
+// fib(n) in a radix from 2 to 36, by GMP's own fibonacci function and conversion.
+pub(crate) fn fib_to_string_radix(n: u32, radix: u32) -> String {
+    Integer::from(Integer::fibonacci(n)).to_string_radix(radix as i32)
+}
+
+
+fn to_integer(n: &BigUint) -> Integer {
+    Integer::from_digits(&n.to_bytes_le(), Order::Lsf)
+}
+
+
+fn to_biguint(n: &Integer) -> BigUint {
+    BigUint::from_bytes_le(&n.to_digits::<u8>(Order::Lsf))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::gmp::*;
+
+    #[test]
+    fn test_matches_num_bigint() {
+        let modulo = (BigUint::from(1u32) << 2_000) - 1u32;
+        for n in &[BigUint::from(0u32), BigUint::from(1u32), BigUint::from(10u32), &modulo * 12_345u32] {
+            for m in &[BigUint::from(1u32), BigUint::from(1_000_000_007u32), modulo.clone()] {
+                let mut computation = crate::computation::BigFibComputation::new(n, m).unwrap();
+                assert_eq!(bigfib_with_mod(n, m), computation.run());
+            }
+        }
+        assert_eq!(to_biguint(&to_integer(&modulo)), modulo);
+        for &n in &[0, 1, 2, 1_000, 65_537] {
+            assert_eq!(fib(n), bignum::fib::<BigUint>(n), "n = {}", n);
+            assert_eq!(bignum::fib::<Integer>(n), to_integer(&fib(n)), "n = {}", n);
+        }
+        assert_eq!(bignum::try_fib_with_mod(&Integer::from(10), &Integer::new()), Err(crate::FibError::ZeroModulus));
+    }
+}
@@ -0,0 +1,169 @@
+//! Rational approximations of the golden ratio φ = (1 + √5) / 2.
+//!
+//! The ratios fib(n + 1) / fib(n) are the convergents of φ's continued fraction [1; 1, 1, 1, ...],
+//! and since every partial quotient is 1, they are also its best rational approximations.
+
+use num_bigint::BigUint;
+use num::FromPrimitive;
+
+use crate::FibError;
+
+
+/// Finds the nth convergent of φ, fib(n + 1) / fib(n), as an exact (numerator, denominator) pair.
+/// Runtime O(log(n))
+///
+/// n must be at least 1, since fib(0) = 0.
+///
+/// # Examples
+/// ```
+/// use num::FromPrimitive;
+///
+/// let (numerator, denominator) = fast_fibonacci::golden::convergent(10);
+/// assert_eq!(FromPrimitive::from_u64(89), Some(numerator));
+/// assert_eq!(FromPrimitive::from_u64(55), Some(denominator));
+/// ```
+pub fn convergent(n: u64) -> (BigUint, BigUint) {
+    try_convergent(n).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `convergent`, but returns an error instead of panicking when n is 0.
+pub fn try_convergent(n: u64) -> Result<(BigUint, BigUint), FibError> {
+    if n == 0 {
+        return Err(FibError::InvalidArgument("fib(0) = 0 can't be a denominator"));
+    }
+    let (f_n, f_n1) = crate::exact_bigfib_pair(n);
+    Ok((f_n1, f_n))
+}
+
+
+/// Iterates over the convergents of φ, fib(n + 1) / fib(n) for n = 1, 2, 3, ...
+///
+/// # Examples
+/// ```
+/// use num::ToPrimitive;
+///
+/// let first: Vec<(u64, u64)> = fast_fibonacci::golden::convergents()
+///     .take(5)
+///     .map(|(p, q)| (p.to_u64().unwrap(), q.to_u64().unwrap()))
+///     .collect();
+/// assert_eq!(vec![(1, 1), (2, 1), (3, 2), (5, 3), (8, 5)], first);
+/// ```
+pub fn convergents() -> Convergents {
+    Convergents {
+        f_n: FromPrimitive::from_u64(1).unwrap(),
+        f_n1: FromPrimitive::from_u64(1).unwrap(),
+    }
+}
+
+
+/// Iterator over the convergents of φ, created by `convergents`.
+#[derive(Clone, Debug)]
+pub struct Convergents {
+    f_n: BigUint,
+    f_n1: BigUint,
+}
+
+impl Iterator for Convergents {
+    type Item = (BigUint, BigUint);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let f_n2 = &self.f_n + &self.f_n1;
+        let f_n = std::mem::replace(&mut self.f_n, std::mem::replace(&mut self.f_n1, f_n2));
+        Some((self.f_n.clone(), f_n))
+    }
+}
+
+
+/// Finds the best rational approximation of φ with a denominator of at most `max_denominator`,
+/// as a (numerator, denominator) pair. Runtime O(log(max_denominator)) big additions.
+///
+/// This is fib(n + 1) / fib(n) for the largest n with fib(n) <= max_denominator.
+///
+/// # Examples
+/// ```
+/// use num::FromPrimitive;
+///
+/// let (numerator, denominator) = fast_fibonacci::golden::best_approximation(
+///     &FromPrimitive::from_u64(100).unwrap()
+/// );
+/// assert_eq!(FromPrimitive::from_u64(144), Some(numerator));
+/// assert_eq!(FromPrimitive::from_u64(89), Some(denominator));
+/// ```
+pub fn best_approximation(max_denominator: &BigUint) -> (BigUint, BigUint) {
+    try_best_approximation(max_denominator).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `best_approximation`, but returns an error instead of panicking when max_denominator is 0.
+pub fn try_best_approximation(max_denominator: &BigUint) -> Result<(BigUint, BigUint), FibError> {
+    let one: BigUint = FromPrimitive::from_u64(1).unwrap();
+    if max_denominator < &one {
+        return Err(FibError::InvalidArgument("denominator must be at least 1"));
+    }
+
+    // Start from 2 / 1, which beats 1 / 1.
+    let mut f_n = one.clone();
+    let mut f_n1: BigUint = FromPrimitive::from_u64(2).unwrap();
+    while &f_n1 <= max_denominator {
+        let f_n2 = &f_n + &f_n1;
+        f_n = std::mem::replace(&mut f_n1, f_n2);
+    }
+    Ok((f_n1, f_n))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::golden::*;
+    use num::ToPrimitive;
+
+    fn small(pair: (BigUint, BigUint)) -> (u64, u64) {
+        (pair.0.to_u64().unwrap(), pair.1.to_u64().unwrap())
+    }
+
+    #[test]
+    fn test_try_errors() {
+        assert!(try_convergent(0).is_err());
+        assert!(try_best_approximation(&FromPrimitive::from_u64(0).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_convergent() {
+        assert_eq!(small(convergent(1)), (1, 1));
+        assert_eq!(small(convergent(2)), (2, 1));
+        assert_eq!(small(convergent(3)), (3, 2));
+        assert_eq!(small(convergent(90)), (4_660_046_610_375_530_309, 2_880_067_194_370_816_120));
+    }
+
+    #[test]
+    fn test_convergents_match_convergent() {
+        for (n, pair) in (1..100).zip(convergents()) {
+            assert_eq!(pair, convergent(n));
+        }
+    }
+
+    #[test]
+    fn test_best_approximation() {
+        let best = |d: u64| small(best_approximation(&FromPrimitive::from_u64(d).unwrap()));
+        assert_eq!(best(1), (2, 1));
+        assert_eq!(best(2), (3, 2));
+        assert_eq!(best(4), (5, 3));
+        assert_eq!(best(5), (8, 5));
+        assert_eq!(best(88), (89, 55));
+        assert_eq!(best(89), (144, 89));
+    }
+
+    #[test]
+    fn test_best_approximation_is_best() {
+        let phi = (1.0 + 5f64.sqrt()) / 2.0;
+        for d in 1..200u64 {
+            let (p, q) = small(best_approximation(&FromPrimitive::from_u64(d).unwrap()));
+            let error = (p as f64 / q as f64 - phi).abs();
+            for q2 in 1..=d {
+                let p2 = (phi * q2 as f64).round();
+                assert!(error <= (p2 / q2 as f64 - phi).abs() + 1e-15, "d = {}, q2 = {}", d, q2);
+            }
+        }
+    }
+}
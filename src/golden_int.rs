@@ -0,0 +1,177 @@
+use core::fmt;
+use core::ops::{Add, Mul, Neg, Sub};
+
+use crate::{add_mod, mul_mod, sub_mod, FibError};
+
+
+/// a + bφ in Z[φ] with modulo, where φ^2 = φ + 1.
+///
+/// φ^n = fib(n - 1) + fib(n)φ, so fib(n) is the φ coefficient of `GoldenInt::phi(modulo).pow(n)`.
+/// A product takes 4 multiplications, against 8 for a 2x2 matrix.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::GoldenInt;
+///
+/// let phi = GoldenInt::phi(1_000_000_007);
+/// assert_eq!(55, phi.pow(10).b());
+/// assert_eq!(phi * phi, phi + GoldenInt::new(1, 0, 1_000_000_007));
+/// // The norm (a + bφ)(a + bψ) of a unit is ±1.
+/// assert_eq!(1_000_000_006, phi.norm());
+/// assert_eq!("34 + 55φ mod 100", GoldenInt::phi(100).pow(10).to_string());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GoldenInt {
+    a: u64,
+    b: u64,
+    modulo: u64,
+}
+
+impl GoldenInt {
+    /// a + bφ with modulo, reducing a and b.
+    pub fn new(a: u64, b: u64, modulo: u64) -> GoldenInt {
+        GoldenInt::try_new(a, b, modulo).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `new`, but returns an error instead of panicking.
+    pub fn try_new(a: u64, b: u64, modulo: u64) -> Result<GoldenInt, FibError> {
+        if modulo == 0 {
+            return Err(FibError::ZeroModulus);
+        }
+        Ok(GoldenInt { a: a % modulo, b: b % modulo, modulo })
+    }
+
+    /// φ itself with modulo.
+    pub fn phi(modulo: u64) -> GoldenInt {
+        GoldenInt::new(0, 1, modulo)
+    }
+
+    /// The rational part a.
+    pub fn a(&self) -> u64 {
+        self.a
+    }
+
+    /// The φ coefficient b.
+    pub fn b(&self) -> u64 {
+        self.b
+    }
+
+    pub fn modulo(&self) -> u64 {
+        self.modulo
+    }
+
+    /// a + bψ, where ψ = 1 - φ is the other root of x^2 = x + 1.
+    pub fn conjugate(&self) -> GoldenInt {
+        GoldenInt { a: add_mod(self.a, self.b, self.modulo), b: sub_mod(0, self.b, self.modulo), modulo: self.modulo }
+    }
+
+    /// (a + bφ)(a + bψ) = a^2 + ab - b^2, which is multiplicative.
+    pub fn norm(&self) -> u64 {
+        let (a, b, modulo) = (self.a, self.b, self.modulo);
+        sub_mod(mul_mod(a, add_mod(a, b, modulo), modulo), mul_mod(b, b, modulo), modulo)
+    }
+
+    /// self^exponent. Runtime O(log(exponent))
+    pub fn pow(&self, mut exponent: u64) -> GoldenInt {
+        let mut result = GoldenInt::new(1, 0, self.modulo);
+        let mut base = *self;
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent /= 2;
+        }
+        result
+    }
+}
+
+/// Panics if the moduli differ.
+impl Add for GoldenInt {
+    type Output = GoldenInt;
+
+    fn add(self, other: GoldenInt) -> GoldenInt {
+        assert_eq!(self.modulo, other.modulo, "moduli must match");
+        let modulo = self.modulo;
+        GoldenInt { a: add_mod(self.a, other.a, modulo), b: add_mod(self.b, other.b, modulo), modulo }
+    }
+}
+
+/// Panics if the moduli differ.
+impl Sub for GoldenInt {
+    type Output = GoldenInt;
+
+    fn sub(self, other: GoldenInt) -> GoldenInt {
+        assert_eq!(self.modulo, other.modulo, "moduli must match");
+        let modulo = self.modulo;
+        GoldenInt { a: sub_mod(self.a, other.a, modulo), b: sub_mod(self.b, other.b, modulo), modulo }
+    }
+}
+
+/// (a + bφ)(c + dφ) = (ac + bd) + (ad + bc + bd)φ. Panics if the moduli differ.
+impl Mul for GoldenInt {
+    type Output = GoldenInt;
+
+    fn mul(self, other: GoldenInt) -> GoldenInt {
+        assert_eq!(self.modulo, other.modulo, "moduli must match");
+        let modulo = self.modulo;
+        let bd = mul_mod(self.b, other.b, modulo);
+        let a = add_mod(mul_mod(self.a, other.a, modulo), bd, modulo);
+        let ad_bc = add_mod(mul_mod(self.a, other.b, modulo), mul_mod(self.b, other.a, modulo), modulo);
+        GoldenInt { a, b: add_mod(ad_bc, bd, modulo), modulo }
+    }
+}
+
+impl Neg for GoldenInt {
+    type Output = GoldenInt;
+
+    fn neg(self) -> GoldenInt {
+        GoldenInt { a: sub_mod(0, self.a, self.modulo), b: sub_mod(0, self.b, self.modulo), modulo: self.modulo }
+    }
+}
+
+impl fmt::Display for GoldenInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} + {}φ mod {}", self.a, self.b, self.modulo)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::golden_int::*;
+
+    #[test]
+    fn test_powers_of_phi() {
+        for &modulo in &[1, 2, 10, 1_000_000_007, u64::MAX] {
+            let phi = GoldenInt::phi(modulo);
+            for &n in &[0, 1, 2, 10, 1_000_000_000_000_000] {
+                let power = phi.pow(n);
+                let (f_n, f_n1) = crate::fib_pair_with_mod(n, modulo);
+                assert_eq!(power, GoldenInt::new(sub_mod(f_n1, f_n, modulo), f_n, modulo));
+                assert_eq!(power * phi, phi.pow(n + 1));
+                // ψ^n = fib(n + 1) - fib(n)φ
+                assert_eq!(power.conjugate(), GoldenInt::new(f_n1, 0, modulo) - GoldenInt::new(0, f_n, modulo));
+            }
+        }
+        assert_eq!(GoldenInt::try_new(1, 1, 0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_ring() {
+        let modulo = 1_000_003;
+        let x = GoldenInt::new(123_456, 654_321, modulo);
+        let y = GoldenInt::new(999_999, 42, modulo);
+        assert_eq!(x * y, y * x);
+        assert_eq!((x + y) * x, x * x + y * x);
+        assert_eq!(x - x, -x + x);
+        assert_eq!(mul_mod(x.norm(), y.norm(), modulo), (x * y).norm());
+        assert_eq!(x.conjugate().conjugate(), x);
+    }
+
+    #[test]
+    #[should_panic(expected = "moduli must match")]
+    fn test_mismatched_moduli() {
+        let _ = GoldenInt::phi(10) * GoldenInt::phi(100);
+    }
+}
@@ -0,0 +1,172 @@
+//! fib_with_mod for huge batches of u64 queries on the GPU through wgpu, with the experimental
+//! `gpu` feature.
+//!
+//! Every query runs fast doubling in its own invocation of a compute shader, `gpu.wgsl`, which
+//! emulates u64 modular arithmetic on pairs of u32 since WGSL has no 64-bit integers. Uploading
+//! the queries and reading back the results costs a round trip to the device, so this pays off
+//! for millions of queries; smaller batches are faster with `batch::fib_with_mod_batch`.
+
+use std::convert::TryInto;
+use wgpu::util::DeviceExt;
+
+use crate::FibError;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+// The bytes of one query, n and modulo as four little endian u32.
+const QUERY_SIZE: u64 = 16;
+
+
+/// A GPU device with the fibonacci shader compiled for it. Set it up once and reuse it for every
+/// batch.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::gpu::GpuBatch;
+///
+/// // None on machines without a usable GPU.
+/// if let Some(gpu) = GpuBatch::new() {
+///     let queries = [(10, 100), (1_000_000_000_000_000, 1_000_000), (90, u64::MAX)];
+///     assert_eq!(vec![55, 546_875, 2_880_067_194_370_816_120], gpu.fib_with_mod_batch(&queries));
+/// }
+/// ```
+#[derive(Debug)]
+pub struct GpuBatch {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl GpuBatch {
+    /// Sets up the default GPU, or returns None when there is no usable one.
+    pub fn new() -> Option<GpuBatch> {
+        let instance = wgpu::Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .ok()?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).ok()?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("fib_with_mod"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("gpu.wgsl").into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("fib_with_mod"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        Some(GpuBatch { device, queue, pipeline })
+    }
+
+    /// Finds fib(n) with modulo for every (n, modulo) query. Runtime O(log(n) * log(modulo)) per
+    /// query, since the shader multiplies by doubling and adding, spread over the GPU's cores.
+    pub fn fib_with_mod_batch(&self, queries: &[(u64, u64)]) -> Vec<u64> {
+        self.try_fib_with_mod_batch(queries).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `fib_with_mod_batch`, but returns an error instead of panicking.
+    pub fn try_fib_with_mod_batch(&self, queries: &[(u64, u64)]) -> Result<Vec<u64>, FibError> {
+        if queries.iter().any(|&(_, modulo)| modulo == 0) {
+            return Err(FibError::ZeroModulus);
+        }
+        let mut results = Vec::with_capacity(queries.len());
+        for chunk in queries.chunks(self.max_dispatch()) {
+            results.extend(self.dispatch(chunk));
+        }
+        Ok(results)
+    }
+
+    // The most queries one dispatch can take, limited by the workgroup count and buffer sizes.
+    fn max_dispatch(&self) -> usize {
+        let limits = self.device.limits();
+        let by_workgroups = u64::from(limits.max_compute_workgroups_per_dimension) * u64::from(WORKGROUP_SIZE);
+        let by_buffers = limits.max_storage_buffer_binding_size.min(limits.max_buffer_size) / QUERY_SIZE;
+        by_workgroups.min(by_buffers) as usize
+    }
+
+    // Runs the shader on up to `max_dispatch` queries.
+    fn dispatch(&self, queries: &[(u64, u64)]) -> Vec<u64> {
+        if queries.is_empty() {
+            return Vec::new();
+        }
+        let mut bytes = Vec::with_capacity(QUERY_SIZE as usize * queries.len());
+        for &(n, modulo) in queries {
+            bytes.extend_from_slice(&n.to_le_bytes());
+            bytes.extend_from_slice(&modulo.to_le_bytes());
+        }
+        let input = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("queries"),
+            contents: &bytes,
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let size = 8 * queries.len() as u64;
+        let output = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("results"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("staging"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: input.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: output.as_entire_binding() },
+            ],
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups((queries.len() as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&output, 0, &staging, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = staging.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |mapped| mapped.expect("couldn't read the results from the GPU"));
+        self.device.poll(wgpu::PollType::wait_indefinitely()).expect("GPU stopped responding");
+        let results = slice
+            .get_mapped_range()
+            .expect("couldn't read the results from the GPU")
+            .chunks_exact(8)
+            .map(|result| u64::from_le_bytes(result.try_into().unwrap()))
+            .collect();
+        staging.unmap();
+        results
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::gpu::*;
+
+    #[test]
+    fn test_matches_fib_with_mod() {
+        // Skipped without a GPU.
+        let gpu = match GpuBatch::new() {
+            Some(gpu) => gpu,
+            None => return,
+        };
+        let moduli = [1, 2, 10, 1_000_000_007, (1 << 32) - 1, 1 << 32, 1 << 63, u64::MAX - 58, u64::MAX];
+        let ns = [0, 1, 2, 3, 10, 93, 1 << 32, 1_000_000_000_000_000, u64::MAX];
+        let queries: Vec<(u64, u64)> = moduli.iter().flat_map(|&m| ns.iter().map(move |&n| (n, m))).collect();
+        let expected: Vec<u64> = queries.iter().map(|&(n, m)| crate::fib_with_mod(n, m)).collect();
+        assert_eq!(gpu.fib_with_mod_batch(&queries), expected);
+        assert_eq!(gpu.fib_with_mod_batch(&[]), Vec::<u64>::new());
+        assert_eq!(gpu.try_fib_with_mod_batch(&[(10, 100), (10, 0)]), Err(FibError::ZeroModulus));
+    }
+}
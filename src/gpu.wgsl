@@ -0,0 +1,85 @@
+// fib(n) mod modulo for one (n, modulo) query per invocation, by fast doubling. WGSL has no
+// 64-bit integers, so u64 values are vec2<u32>(low, high) pairs.
+
+@group(0) @binding(0) var<storage, read> queries: array<vec4<u32>>;
+@group(0) @binding(1) var<storage, read_write> results: array<vec2<u32>>;
+
+fn geq(a: vec2<u32>, b: vec2<u32>) -> bool {
+    return a.y > b.y || (a.y == b.y && a.x >= b.x);
+}
+
+// a - b, wrapping around 2^64.
+fn sub(a: vec2<u32>, b: vec2<u32>) -> vec2<u32> {
+    let borrow = select(0u, 1u, a.x < b.x);
+    return vec2<u32>(a.x - b.x, a.y - b.y - borrow);
+}
+
+// The number of bits of a, 0 for 0.
+fn bit_length(a: vec2<u32>) -> u32 {
+    if (a.y != 0u) {
+        return 64u - countLeadingZeros(a.y);
+    }
+    return 32u - countLeadingZeros(a.x);
+}
+
+fn bit(a: vec2<u32>, i: u32) -> bool {
+    let word = select(a.x, a.y, i >= 32u);
+    return ((word >> (i & 31u)) & 1u) == 1u;
+}
+
+// The residues a and b are below m.
+fn add_mod(a: vec2<u32>, b: vec2<u32>, m: vec2<u32>) -> vec2<u32> {
+    let low = a.x + b.x;
+    let high = a.y + b.y;
+    let sum = vec2<u32>(low, high + select(0u, 1u, low < a.x));
+    // Past 2^64, the wrapping subtraction still gives a + b - m.
+    let overflow = high < a.y || sum.y < high;
+    if (overflow || geq(sum, m)) {
+        return sub(sum, m);
+    }
+    return sum;
+}
+
+fn sub_mod(a: vec2<u32>, b: vec2<u32>, m: vec2<u32>) -> vec2<u32> {
+    if (geq(a, b)) {
+        return sub(a, b);
+    }
+    return sub(m, sub(b, a));
+}
+
+// a * b mod m by doubling and adding, as there is no 128-bit product.
+fn mul_mod(a: vec2<u32>, b: vec2<u32>, m: vec2<u32>) -> vec2<u32> {
+    var product = vec2<u32>(0u, 0u);
+    for (var i = bit_length(b); i > 0u; i = i - 1u) {
+        product = add_mod(product, product, m);
+        if (bit(b, i - 1u)) {
+            product = add_mod(product, a, m);
+        }
+    }
+    return product;
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if (index >= arrayLength(&queries)) {
+        return;
+    }
+    let n = queries[index].xy;
+    let m = queries[index].zw;
+    var a = vec2<u32>(0u, 0u);
+    var b = select(vec2<u32>(1u, 0u), vec2<u32>(0u, 0u), m.x == 1u && m.y == 0u);
+    for (var i = bit_length(n); i > 0u; i = i - 1u) {
+        // fib(2k) = fib(k) * (2 * fib(k + 1) - fib(k)), fib(2k + 1) = fib(k)^2 + fib(k + 1)^2
+        let even = mul_mod(a, sub_mod(add_mod(b, b, m), a, m), m);
+        let odd = add_mod(mul_mod(a, a, m), mul_mod(b, b, m), m);
+        if (bit(n, i - 1u)) {
+            a = odd;
+            b = add_mod(even, odd, m);
+        } else {
+            a = even;
+            b = odd;
+        }
+    }
+    results[index] = a;
+}
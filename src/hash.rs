@@ -0,0 +1,93 @@
+//! Fibonacci hashing: multiplying a key by 2^64 / φ and keeping the top bits, which spreads
+//! consecutive and patterned keys evenly over a table of 2^bits slots.
+//!
+//! The functions are `const fn`, so slot indices of known keys can be computed at compile time.
+
+/// 2^64 / φ, rounded to the nearest odd integer.
+pub const GOLDEN_RATIO_64: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// 2^32 / φ, rounded to the nearest odd integer.
+pub const GOLDEN_RATIO_32: u32 = 0x9E37_79B9;
+
+
+/// Hashes a key to a slot in a table of 2^bits slots, for bits up to 64. 0 bits means a single
+/// slot, 0. Runtime O(1)
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::hash::fib_hash;
+///
+/// // Consecutive keys land far apart.
+/// assert_eq!(0, fib_hash(0, 10));
+/// assert_eq!(632, fib_hash(1, 10));
+/// assert_eq!(241, fib_hash(2, 10));
+///
+/// const SLOT: u64 = fib_hash(42, 16);
+/// assert_eq!(SLOT, fib_hash(42, 16));
+/// ```
+pub const fn fib_hash(key: u64, bits: u32) -> u64 {
+    assert!(bits <= 64, "bits must be at most 64");
+    if bits == 0 {
+        return 0;
+    }
+    key.wrapping_mul(GOLDEN_RATIO_64) >> (64 - bits)
+}
+
+
+/// `fib_hash` for usize keys, using the golden ratio multiplier of the target's pointer width.
+/// bits must be at most usize::BITS. Runtime O(1)
+///
+/// # Examples
+/// ```
+/// let slot = fast_fibonacci::hash::fib_hash_usize(12_345, 8);
+/// assert!(slot < 256);
+/// ```
+pub const fn fib_hash_usize(key: usize, bits: u32) -> usize {
+    #[cfg(target_pointer_width = "64")]
+    return fib_hash(key as u64, bits) as usize;
+    #[cfg(not(target_pointer_width = "64"))]
+    return fib_hash_32(key as u32, bits) as usize;
+}
+
+
+/// `fib_hash` for u32 keys, for bits up to 32. Runtime O(1)
+pub const fn fib_hash_32(key: u32, bits: u32) -> u32 {
+    assert!(bits <= 32, "bits must be at most 32");
+    if bits == 0 {
+        return 0;
+    }
+    key.wrapping_mul(GOLDEN_RATIO_32) >> (32 - bits)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::hash::*;
+
+    #[test]
+    fn test_fib_hash() {
+        assert_eq!(fib_hash(1, 64), GOLDEN_RATIO_64);
+        assert_eq!(fib_hash(u64::MAX, 0), 0);
+        assert_eq!(fib_hash_32(1, 32), GOLDEN_RATIO_32);
+        assert_eq!(fib_hash_32(1, 8), GOLDEN_RATIO_32 >> 24);
+        assert_eq!(fib_hash_usize(1, 8), 0x9E);
+        // The multipliers are the top bits of 1 / φ.
+        assert_eq!(GOLDEN_RATIO_32, (GOLDEN_RATIO_64 >> 32) as u32);
+    }
+
+    #[test]
+    fn test_spreads_consecutive_keys() {
+        // The first 1024 keys fill 1024 slots with no slot taking more than 2.
+        let mut counts = [0u32; 1024];
+        for key in 0..1024 {
+            counts[fib_hash(key, 10) as usize] += 1;
+        }
+        assert!(counts.iter().all(|&count| count <= 2));
+    }
+
+    #[test]
+    #[should_panic(expected = "bits must be at most 64")]
+    fn test_too_many_bits() {
+        fib_hash(1, 65);
+    }
+}
@@ -0,0 +1,152 @@
+//! Classic fibonacci identities, evaluated with modulo.
+//!
+//! Each function returns (left hand side, right hand side) of its identity, with the left side
+//! computed from fibonacci numbers and the right side from the identity's closed form. The two
+//! always agree, so the right side is a cheap shortcut, and comparing both sides is a self-check
+//! for the fibonacci computations.
+
+use crate::{fib_pair_with_mod, mul_mod, sub_mod, FibError};
+
+
+/// Cassini's identity: fib(n - 1) * fib(n + 1) - fib(n)^2 = (-1)^n, with modulo.
+/// Runtime O(log(n))
+///
+/// # Examples
+/// ```
+/// assert_eq!((1, 1), fast_fibonacci::identities::cassini_with_mod(10, 100));
+/// assert_eq!((99, 99), fast_fibonacci::identities::cassini_with_mod(11, 100));
+/// ```
+pub fn cassini_with_mod(n: u64, modulo: u64) -> (u64, u64) {
+    try_cassini_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `cassini_with_mod`, but returns an error instead of panicking.
+pub fn try_cassini_with_mod(n: u64, modulo: u64) -> Result<(u64, u64), FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    let (f_n, f_n1) = fib_pair_with_mod(n, modulo);
+    let f_n_minus_1 = sub_mod(f_n1, f_n, modulo);
+    let lhs = sub_mod(mul_mod(f_n_minus_1, f_n1, modulo), mul_mod(f_n, f_n, modulo), modulo);
+    Ok((lhs, minus_one_pow(n, modulo)))
+}
+
+
+/// Catalan's identity: fib(n)^2 - fib(n - r) * fib(n + r) = (-1)^(n - r) * fib(r)^2, with modulo.
+/// Runtime O(log(n))
+///
+/// r must be at most n.
+///
+/// # Examples
+/// ```
+/// // 55^2 - 8 * 377 = 9
+/// assert_eq!((9, 9), fast_fibonacci::identities::catalan_identity_with_mod(10, 4, 1_000));
+/// ```
+pub fn catalan_identity_with_mod(n: u64, r: u64, modulo: u64) -> (u64, u64) {
+    try_catalan_identity_with_mod(n, r, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `catalan_identity_with_mod`, but returns an error instead of panicking.
+pub fn try_catalan_identity_with_mod(n: u64, r: u64, modulo: u64) -> Result<(u64, u64), FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    if r > n {
+        return Err(FibError::InvalidArgument("r must be at most n"));
+    }
+    let n_plus_r = n.checked_add(r).ok_or(FibError::OverflowRisk)?;
+    let f_n = fib_pair_with_mod(n, modulo).0;
+    let f_n_minus_r = fib_pair_with_mod(n - r, modulo).0;
+    let f_n_plus_r = fib_pair_with_mod(n_plus_r, modulo).0;
+    let f_r = fib_pair_with_mod(r, modulo).0;
+
+    let lhs = sub_mod(mul_mod(f_n, f_n, modulo), mul_mod(f_n_minus_r, f_n_plus_r, modulo), modulo);
+    let rhs = mul_mod(minus_one_pow(n - r, modulo), mul_mod(f_r, f_r, modulo), modulo);
+    Ok((lhs, rhs))
+}
+
+
+/// d'Ocagne's identity: fib(m) * fib(n + 1) - fib(m + 1) * fib(n) = (-1)^n * fib(m - n), with modulo.
+/// Runtime O(log(m))
+///
+/// n must be at most m.
+///
+/// # Examples
+/// ```
+/// // 55 * 5 - 89 * 3 = 8
+/// assert_eq!((8, 8), fast_fibonacci::identities::d_ocagne_with_mod(10, 4, 1_000));
+/// ```
+pub fn d_ocagne_with_mod(m: u64, n: u64, modulo: u64) -> (u64, u64) {
+    try_d_ocagne_with_mod(m, n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `d_ocagne_with_mod`, but returns an error instead of panicking.
+pub fn try_d_ocagne_with_mod(m: u64, n: u64, modulo: u64) -> Result<(u64, u64), FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    if n > m {
+        return Err(FibError::InvalidArgument("n must be at most m"));
+    }
+    let (f_m, f_m1) = fib_pair_with_mod(m, modulo);
+    let (f_n, f_n1) = fib_pair_with_mod(n, modulo);
+    let f_m_minus_n = fib_pair_with_mod(m - n, modulo).0;
+
+    let lhs = sub_mod(mul_mod(f_m, f_n1, modulo), mul_mod(f_m1, f_n, modulo), modulo);
+    let rhs = mul_mod(minus_one_pow(n, modulo), f_m_minus_n, modulo);
+    Ok((lhs, rhs))
+}
+
+
+fn minus_one_pow(n: u64, modulo: u64) -> u64 {
+    if n % 2 == 1 {
+        modulo - 1
+    } else {
+        1 % modulo
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::identities::*;
+
+    #[test]
+    fn test_try_errors() {
+        assert_eq!(try_cassini_with_mod(5, 0), Err(FibError::ZeroModulus));
+        assert_eq!(try_catalan_identity_with_mod(5, 6, 10), Err(FibError::InvalidArgument("r must be at most n")));
+        assert_eq!(try_catalan_identity_with_mod(u64::MAX, 1, 10), Err(FibError::OverflowRisk));
+        assert_eq!(try_d_ocagne_with_mod(5, 6, 10), Err(FibError::InvalidArgument("n must be at most m")));
+    }
+
+    #[test]
+    fn test_identities_hold() {
+        for &modulo in &[1, 2, 10, 1_000_000_007, u64::MAX] {
+            for &n in &[0, 1, 2, 3, 10, 11, 1_000_000_000_000] {
+                let (lhs, rhs) = cassini_with_mod(n, modulo);
+                assert_eq!(lhs, rhs, "cassini n = {}, modulo = {}", n, modulo);
+
+                for &r in &[0, 1, 7, n / 2, n] {
+                    if r > n {
+                        continue;
+                    }
+                    let (lhs, rhs) = catalan_identity_with_mod(n, r, modulo);
+                    assert_eq!(lhs, rhs, "catalan n = {}, r = {}, modulo = {}", n, r, modulo);
+                    let (lhs, rhs) = d_ocagne_with_mod(n, r, modulo);
+                    assert_eq!(lhs, rhs, "d'ocagne m = {}, n = {}, modulo = {}", n, r, modulo);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_small_values() {
+        assert_eq!(cassini_with_mod(0, 10), (1, 1));
+        assert_eq!(cassini_with_mod(5, 10), (9, 9));
+        assert_eq!(catalan_identity_with_mod(5, 5, 1_000), (25, 25));
+        assert_eq!(d_ocagne_with_mod(7, 7, 10), (0, 0));
+    }
+}
@@ -0,0 +1,99 @@
+use core::ops::RangeInclusive;
+
+// A u64 has at most 20 decimal digits.
+const MAX_DIGITS: usize = 20;
+
+
+/// Checks whether x is a Keith number, or repfigit: a number of d >= 2 digits that appears in the
+/// d-bonacci-like sequence x(i) = x(i - 1) + ... + x(i - d) started from its own digits.
+/// Runtime O(d * log(x))
+///
+/// The terms at least double every d steps once they're all positive, so the sequence passes x
+/// within O(d * log(x)) terms.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::is_keith_number;
+///
+/// // 1, 9, 7, 17, 33, 57, 107, 197
+/// assert!(is_keith_number(197));
+/// assert!(!is_keith_number(198));
+/// // Single digits don't count.
+/// assert!(!is_keith_number(7));
+/// ```
+pub fn is_keith_number(x: u64) -> bool {
+    if x < 10 {
+        return false;
+    }
+    // The last d terms as a ring buffer, oldest at start, summed up in u128 so that they can pass
+    // u64::MAX.
+    let mut terms = [0u128; MAX_DIGITS];
+    let mut d = 0;
+    let mut rest = x;
+    while rest > 0 {
+        terms[d] = (rest % 10) as u128;
+        rest /= 10;
+        d += 1;
+    }
+    terms[..d].reverse();
+    let mut sum: u128 = terms[..d].iter().sum();
+    let mut start = 0;
+    while sum < x as u128 {
+        let oldest = terms[start];
+        terms[start] = sum;
+        start = (start + 1) % d;
+        sum = 2 * sum - oldest;
+    }
+    sum == x as u128
+}
+
+
+/// The Keith numbers up to bound, in increasing order, by checking every number from 10.
+/// Runtime O(bound * log(bound))
+///
+/// # Examples
+/// ```
+/// let keith: Vec<u64> = fast_fibonacci::keith_numbers(1_000).collect();
+/// assert_eq!(vec![14, 19, 28, 47, 61, 75, 197, 742], keith);
+/// ```
+pub fn keith_numbers(bound: u64) -> KeithNumbers {
+    KeithNumbers { candidates: 10..=bound }
+}
+
+
+/// Iterator returned by `keith_numbers`.
+#[derive(Clone, Debug)]
+pub struct KeithNumbers {
+    candidates: RangeInclusive<u64>,
+}
+
+impl Iterator for KeithNumbers {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        self.candidates.find(|&x| is_keith_number(x))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::keith::*;
+
+    #[test]
+    fn test_known_keith_numbers() {
+        // OEIS A007629
+        let expected = [
+            14, 19, 28, 47, 61, 75, 197, 742, 1_104, 1_537, 2_208, 2_580, 3_684, 4_788, 7_385, 7_647, 7_909, 31_331,
+            34_285, 34_348, 55_604, 62_662, 86_935, 93_993, 120_284, 129_106, 147_640, 156_146, 174_680, 183_186,
+            298_320, 355_419, 694_280, 925_993,
+        ];
+        assert_eq!(keith_numbers(1_000_000).collect::<Vec<u64>>(), expected);
+        assert!(is_keith_number(1_084_051));
+        assert!(is_keith_number(44_121_607));
+        assert!(is_keith_number(129_572_008));
+        assert!(!is_keith_number(u64::MAX));
+        let top = KeithNumbers { candidates: u64::MAX - 1_000..=u64::MAX };
+        assert_eq!(top.count(), 0);
+    }
+}
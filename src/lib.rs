@@ -3,15 +3,174 @@
 //! `fast-fibonacci` uses linear recurrence to quickly find fib(n, mod) in O(log(n)) time.
 //!
 //! Adapted from http://fusharblog.com/solving-linear-recurrence-for-programming-contest/
+//!
+//! With default features off, only the u64 functions are compiled, without heap allocation and
+//! without `std`, `ndarray` or `num-bigint`.
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+#[cfg(feature = "std")]
 use ndarray::arr2;
+#[cfg(feature = "std")]
 use ndarray::Array2;
-use num_bigint::BigUint;
+#[cfg(feature = "std")]
+use num_bigint::{BigInt, BigUint, Sign};
+#[cfg(feature = "std")]
 use num::FromPrimitive;
+#[cfg(feature = "std")]
+use num::{Integer, Signed};
+#[cfg(feature = "std")]
+use core::convert::Infallible;
+use core::num::{NonZeroU64, Wrapping};
+
+#[cfg(feature = "std")]
+mod algorithm;
+#[cfg(feature = "std")]
+mod approx;
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod bignum;
+#[cfg(feature = "std")]
+mod catalan;
+#[cfg(feature = "std")]
+pub mod combinatorics;
+#[cfg(feature = "std")]
+pub mod computation;
+#[cfg(feature = "std")]
+pub mod continued_fraction;
+#[cfg(feature = "std")]
+mod convert;
+#[cfg(feature = "constant-time")]
+pub mod ct;
+#[cfg(feature = "std")]
+pub mod diagnostics;
+mod error;
+#[cfg(feature = "std")]
+mod exact;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "factor")]
+pub mod factor;
+#[cfg(feature = "std")]
+mod fib_factor;
+mod fib_matrix;
+#[cfg(feature = "std")]
+mod fib_prime;
+mod fib_state;
+mod fib_sum;
+#[cfg(feature = "std")]
+mod fibonorial;
+#[cfg(feature = "std")]
+pub mod galois;
+#[cfg(feature = "rug")]
+mod gmp;
+#[cfg(feature = "std")]
+pub mod golden;
+mod golden_int;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod hash;
+pub mod identities;
+mod keith;
+pub mod lucas;
+pub mod matrix;
+mod mersenne;
+#[cfg(feature = "node")]
+pub mod node;
+#[cfg(feature = "tokio")]
+pub mod nonblocking;
+#[cfg(feature = "std")]
+mod nonzero;
+pub mod nt;
+#[cfg(feature = "std")]
+pub mod numeration;
+#[cfg(feature = "std")]
+pub mod parallel;
+pub mod pell;
+#[cfg(feature = "std")]
+pub mod period_cache;
+#[cfg(feature = "persist")]
+pub mod persist;
+#[cfg(feature = "std")]
+pub mod pisano;
+pub mod polynomial;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "std")]
+pub mod random;
+pub mod retracement;
+#[cfg(feature = "std")]
+pub mod rns;
+#[cfg(feature = "std")]
+pub mod sampling;
+pub mod search;
+#[cfg(feature = "std")]
+pub mod semiring;
+#[cfg(feature = "std")]
+pub mod sequence;
+#[cfg(feature = "std")]
+mod shared_cache;
+#[cfg(feature = "std")]
+pub mod series;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "std")]
+mod wipe;
+pub mod word;
+
+#[cfg(feature = "std")]
+pub use algorithm::{
+    bigverify, fib_with_mod_using, verify, Algorithm, Config, Crossover, Dispatcher, Hints, DEFAULT_CROSSOVERS,
+    MAX_TRACKED_MODULI,
+};
+#[cfg(feature = "std")]
+pub use approx::{fib_approx, fib_approx_sci};
+#[cfg(feature = "bigfloat")]
+pub use approx::bigfib_leading_digits;
+#[cfg(feature = "std")]
+pub use convert::{
+    bigfib_with_mod_bytes, bigfib_with_mod_of, fib_with_mod_str, parse_biguint, try_bigfib_with_mod_bytes,
+    try_bigfib_with_mod_of, try_fib_with_mod_str, IntoBigUint, MAX_DECIMAL_EXPONENT,
+};
+#[cfg(feature = "std")]
+pub use catalan::{catalan_with_mod, try_catalan_with_mod};
+#[cfg(feature = "std")]
+pub use exact::{bigfib, fib_to_string, fib_to_string_radix, try_fib_to_string_radix, write_fib_decimal, write_fib_radix};
+pub use error::FibError;
+#[cfg(feature = "macros")]
+pub use fast_fibonacci_macros::fib;
+#[cfg(feature = "std")]
+pub use fib_factor::{factor_fib, try_factor_fib};
+pub use fib_matrix::FibMatrix;
+#[cfg(feature = "std")]
+pub use fib_prime::{fib_prime_indices, is_fib_prime, FibPrimeIndices};
+pub use fib_state::FibState;
+pub use fib_sum::{fib_ap_sum_with_mod, fib_product_sum_with_mod, try_fib_ap_sum_with_mod, try_fib_product_sum_with_mod};
+pub use golden_int::GoldenInt;
+pub use keith::{is_keith_number, keith_numbers, KeithNumbers};
+pub use mersenne::{fib_mod_mersenne, try_fib_mod_mersenne};
+#[cfg(feature = "std")]
+pub use fibonorial::{fibonomial_with_mod, fibonorial_with_mod, try_fibonomial_with_mod, try_fibonorial_with_mod};
+#[cfg(feature = "std")]
+pub use nonzero::NonZeroBigUint;
+#[cfg(feature = "std")]
+pub use semiring::{mat_pow_mod, try_mat_pow_mod};
+#[cfg(feature = "std")]
+pub use shared_cache::SharedFibCache;
+
+// [[a, b], [c, d]], kept on the stack.
+type Matrix = matrix::Matrix2<u64>;
+
+/// The n below which `fib_with_mod` adds up fib(0), fib(1), ..., fib(n) instead of raising a
+/// matrix, whose u128 divisions cost as much as dozens of additions. The default
+/// `Config::iteration_threshold`.
+pub const ITERATION_THRESHOLD: u64 = 128;
 
 /// Finds the nth fibonacci number with modulo. Runtime O(log(n))
 ///
-/// Uses linear recurrence under the covers.
+/// Uses linear recurrence under the covers, plain additions for small n, and shifts instead of
+/// divisions for Mersenne moduli 2^k - 1.
 /// # Examples
 ///
 /// ```
@@ -23,30 +182,176 @@ use num::FromPrimitive;
 /// assert_eq!(875, fast_fibonacci::fib_with_mod(1_000_000_000_000_000, 1_000));
 /// ```
 pub fn fib_with_mod(n: u64, modulo: u64) -> u64 {
+    try_fib_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Finds the nth fibonacci number with modulo at compile time. Runtime O(log(n))
+///
+/// Uses fast doubling, fib(2k) = fib(k) * (2 * fib(k + 1) - fib(k)) and
+/// fib(2k + 1) = fib(k)^2 + fib(k + 1)^2, over the bits of n with u128 arithmetic, so it works in
+/// const contexts. Panics (or fails to compile) if modulo is 0.
+///
+/// # Examples
+///
+/// ```
+/// const X: u64 = fast_fibonacci::fib_const(10_000, 1_000_000_007);
+/// assert_eq!(fast_fibonacci::fib_with_mod(10_000, 1_000_000_007), X);
+/// assert_eq!(55, fast_fibonacci::fib_const(10, 100));
+/// ```
+pub const fn fib_const(n: u64, modulo: u64) -> u64 {
+    assert!(modulo > 0, "modulo must be positive");
+    let m = modulo as u128;
+    let (mut a, mut b) = (0, 1 % m);
+    let mut bit = 64;
+    while bit > 0 {
+        bit -= 1;
+        let c = a * ((2 * b + m - a) % m) % m;
+        let d = (a * a % m + b * b % m) % m;
+        if (n >> bit) & 1 == 1 {
+            a = d;
+            b = (c + d) % m;
+        } else {
+            a = c;
+            b = d;
+        }
+    }
+    a as u64
+}
+
+
+/// Finds the nth fibonacci number modulo the const generic M. Runtime O(log(n))
+///
+/// With the modulo known at compile time, divisions by M below 2^32, whose products fit in a u64,
+/// compile to multiplications and shifts, and the loop inlines into hot paths. Larger moduli
+/// reduce as `fib_with_mod` does. M = 0 fails to compile.
+///
+/// # Examples
+///
+/// ```
+/// use fast_fibonacci::fib_with_mod_const;
+///
+/// let n = 1_000_000_000_000_000;
+/// assert_eq!(fast_fibonacci::fib_with_mod(n, 1_000_000_007), fib_with_mod_const::<1_000_000_007>(n));
+/// assert_eq!(55, fib_with_mod_const::<100>(10));
+/// ```
+#[inline]
+pub fn fib_with_mod_const<const M: u64>(n: u64) -> u64 {
+    const { assert!(M > 0, "modulo must be positive") };
+    if M > u32::MAX as u64 {
+        return fib_with_mod(n, M);
+    }
+    let (mut a, mut b) = (0, 1 % M);
+    for bit in (0..64 - n.leading_zeros()).rev() {
+        // fib(2k) = fib(k) * (2 * fib(k + 1) - fib(k)), fib(2k + 1) = fib(k)^2 + fib(k + 1)^2
+        let even = a * ((2 * b + M - a) % M) % M;
+        let odd = (a * a % M + b * b % M) % M;
+        if (n >> bit) & 1 == 1 {
+            a = odd;
+            b = (even + odd) % M;
+        } else {
+            a = even;
+            b = odd;
+        }
+    }
+    a
+}
+
+
+/// Finds the nth fibonacci number modulo 2^64, a modulo fib_with_mod can't express.
+/// Runtime O(log(n))
+///
+/// Uses fast doubling on `Wrapping<u64>`, so the reduction is free.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(2_880_067_194_370_816_120, fast_fibonacci::fib_mod_2_64(90));
+/// // fib(94) is the first past u64::MAX.
+/// assert_eq!(1_293_530_146_158_671_551, fast_fibonacci::fib_mod_2_64(94));
+/// ```
+pub fn fib_mod_2_64(n: u64) -> u64 {
+    let (mut a, mut b) = (Wrapping(0u64), Wrapping(1u64));
+    for bit in (0..64 - n.leading_zeros()).rev() {
+        let even = a * (b + b - a);
+        let odd = a * a + b * b;
+        if (n >> bit) & 1 == 1 {
+            a = odd;
+            b = even + odd;
+        } else {
+            a = even;
+            b = odd;
+        }
+    }
+    a.0
+}
+
+
+/// Finds the nth fibonacci number with modulo, or an error instead of panicking. Runtime O(log(n))
+///
+/// # Examples
+///
+/// ```
+/// use fast_fibonacci::FibError;
+///
+/// assert_eq!(Ok(55), fast_fibonacci::try_fib_with_mod(10, 100));
+/// assert_eq!(Err(FibError::ZeroModulus), fast_fibonacci::try_fib_with_mod(5, 0));
+/// ```
+pub fn try_fib_with_mod(n: u64, modulo: u64) -> Result<u64, FibError> {
+    fib_with_mod_inner(n, modulo).map(|(fib, _)| fib)
+}
+
+
+// How `try_fib_with_mod` found its result, for `diagnostics::fib_with_mod_report`.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct FibTrace {
+    // Fast doubling for a Mersenne modulo, rather than the matrix power.
+    pub(crate) doubling: bool,
+    pub(crate) pisano_reduced: bool,
+    pub(crate) matrix_multiplications: u64,
+}
+
+
+// try_fib_with_mod, and how it found the result.
+pub(crate) fn fib_with_mod_inner(n: u64, modulo: u64) -> Result<(u64, FibTrace), FibError> {
+    let mut trace = FibTrace::default();
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
     if n == 0 {
-        return 0;
+        return Ok((0, trace));
     }
     if n == 1 {
-        return 1;
+        return Ok((1 % modulo, trace));
+    }
+    if n < ITERATION_THRESHOLD {
+        return Ok((fib_by_iteration(n, modulo), trace));
+    }
+    if let Some(exponent) = mersenne::mersenne_exponent(modulo) {
+        trace.doubling = true;
+        return Ok((mersenne::fib_pair_mersenne(n, exponent).0, trace));
     }
 
-    let f = vec![0, 1];
-    let t = arr2(&[
+    let f = [0, 1];
+    let t = [
         [0, 1], 
         [1, 1]
-    ]);
-    let power_t = matrix_power_with_mod(&t, n, modulo);
+    ];
+    let (power_t, multiplications) = matrix::power_with_mod_u64(&t, n, modulo);
+    trace.matrix_multiplications = multiplications;
     let mut answer = 0;
-    for i in 0..2 {
-        answer = (answer + (power_t[[0, i]] * f[i])) % modulo;
+    for (i, f_i) in f.iter().enumerate() {
+        answer = (answer + (power_t[0][i] * f_i)) % modulo;
     }
-    answer
+    Ok((answer, trace))
 }
 
 
 /// BigUint version of fib_with_mod. Finds the nth fibonacci number with modulo. Runtime O(log(n))
 ///
-/// Uses linear recurrence under the covers.
+/// Uses linear recurrence under the covers, or fast doubling on GMP integers with the `rug`
+/// feature.
 ///
 /// # Examples
 /// ```
@@ -79,57 +384,408 @@ pub fn fib_with_mod(n: u64, modulo: u64) -> u64 {
 ///     expected_result
 /// );
 /// ```
+#[cfg(feature = "std")]
 pub fn bigfib_with_mod(n: &BigUint, modulo: &BigUint) -> BigUint {
-    let ZERO: BigUint = FromPrimitive::from_u64(0).unwrap();
-    let ONE: BigUint = FromPrimitive::from_u64(1).unwrap();
-    if n == &ZERO || n == &ONE {
-        return n.clone();
+    try_bigfib_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// BigUint version of try_fib_with_mod. Finds the nth fibonacci number with modulo, or an error
+/// instead of panicking. Runtime O(log(n))
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::FibError;
+/// use num::FromPrimitive;
+/// use num_bigint::BigUint;
+///
+/// let n: BigUint = FromPrimitive::from_u64(10).unwrap();
+/// assert_eq!(
+///     Ok(FromPrimitive::from_u64(55).unwrap()),
+///     fast_fibonacci::try_bigfib_with_mod(&n, &FromPrimitive::from_u64(100).unwrap())
+/// );
+/// assert_eq!(
+///     Err(FibError::ZeroModulus),
+///     fast_fibonacci::try_bigfib_with_mod(&n, &FromPrimitive::from_u64(0).unwrap())
+/// );
+/// ```
+#[cfg(feature = "std")]
+pub fn try_bigfib_with_mod(n: &BigUint, modulo: &BigUint) -> Result<BigUint, FibError> {
+    let zero: BigUint = FromPrimitive::from_u64(0).unwrap();
+    let one: BigUint = FromPrimitive::from_u64(1).unwrap();
+    if modulo == &zero {
+        return Err(FibError::ZeroModulus);
+    }
+    if n == &zero || n == &one {
+        return Ok(n % modulo);
     }
+    #[cfg(feature = "rug")]
+    let fib = gmp::bigfib_with_mod(n, modulo);
+    #[cfg(not(feature = "rug"))]
+    let fib = matrix_bigfib_with_mod(n, modulo);
+    Ok(fib)
+}
+
 
-    let f: Vec<BigUint> = vec![ZERO.clone(), ONE.clone()];
+// try_bigfib_with_mod on num-bigint, for n >= 2 and a positive modulo.
+#[cfg(feature = "std")]
+#[cfg_attr(feature = "rug", allow(dead_code))]
+fn matrix_bigfib_with_mod(n: &BigUint, modulo: &BigUint) -> BigUint {
+    let zero: BigUint = FromPrimitive::from_u64(0).unwrap();
+    let one: BigUint = FromPrimitive::from_u64(1).unwrap();
+    let f: [BigUint; 2] = [zero.clone(), one.clone()];
     let t: Array2<BigUint> = arr2(&[
-        [ZERO.clone(), ONE.clone()],
-        [ONE.clone(), ONE.clone()]
+        [zero.clone(), one.clone()],
+        [one.clone(), one]
     ]);
-    let power_t = bigfib_matrix_power(&t, n, modulo);
-    let mut answer: BigUint = ZERO.clone();
-    for i in 0..2 {
-        answer = (answer + (&power_t[[0, i]] * &f[i])) % modulo;
+    let mut power_t = bigfib_matrix_power(&t, n, modulo);
+    let mut answer: BigUint = zero;
+    for (i, f_i) in f.iter().enumerate() {
+        answer = (answer + (&power_t[[0, i]] * f_i)) % modulo;
     }
-    return answer;
+    wipe::wipe_matrix(&mut power_t);
+    answer
 }
 
 
+/// Finds the nth fibonacci number with a modulo that can't be 0. Runtime O(log(n))
+///
+/// # Examples
+///
+/// ```
+/// use std::num::NonZeroU64;
+///
+/// let modulo = NonZeroU64::new(100).unwrap();
+/// assert_eq!(55, fast_fibonacci::fib_with_nonzero_mod(10, modulo));
+/// ```
+pub fn fib_with_nonzero_mod(n: u64, modulo: NonZeroU64) -> u64 {
+    fib_with_mod(n, modulo.get())
+}
+
+
+/// BigUint version of fib_with_nonzero_mod. Finds the nth fibonacci number with a modulo that
+/// can't be 0. Runtime O(log(n))
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::NonZeroBigUint;
+/// use num::FromPrimitive;
+/// use num_bigint::BigUint;
+///
+/// let n: BigUint = FromPrimitive::from_u64(10).unwrap();
+/// let modulo = NonZeroBigUint::new(FromPrimitive::from_u64(100).unwrap()).unwrap();
+/// assert_eq!(
+///     BigUint::from_u64(55).unwrap(),
+///     fast_fibonacci::bigfib_with_nonzero_mod(&n, &modulo)
+/// );
+/// ```
+#[cfg(feature = "std")]
+pub fn bigfib_with_nonzero_mod(n: &BigUint, modulo: &NonZeroBigUint) -> BigUint {
+    bigfib_with_mod(n, modulo.get())
+}
+
+
+/// Finds the nth fibonacci number with modulo for a signed index. Runtime O(log(|n|))
+///
+/// The sequence extends to negative indices with fib(n - 1) = fib(n + 1) - fib(n), which gives
+/// the negafibonacci numbers fib(-n) = (-1)^(n + 1) fib(n). They're returned in [0, modulo).
+///
+/// # Examples
+/// ```
+/// use num_bigint::{BigInt, BigUint};
+///
+/// let modulo = BigUint::from(1_000u32);
+/// // fib(-10) = -55 and fib(-9) = 34
+/// assert_eq!(BigUint::from(945u32), fast_fibonacci::bigfib_signed_with_mod(&BigInt::from(-10), &modulo));
+/// assert_eq!(BigUint::from(34u32), fast_fibonacci::bigfib_signed_with_mod(&BigInt::from(-9), &modulo));
+/// ```
+#[cfg(feature = "std")]
+pub fn bigfib_signed_with_mod(n: &BigInt, modulo: &BigUint) -> BigUint {
+    try_bigfib_signed_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `bigfib_signed_with_mod`, but returns an error instead of panicking.
+#[cfg(feature = "std")]
+pub fn try_bigfib_signed_with_mod(n: &BigInt, modulo: &BigUint) -> Result<BigUint, FibError> {
+    let magnitude = n.abs().to_biguint().unwrap();
+    let fib = try_bigfib_with_mod(&magnitude, modulo)?;
+    if n.sign() == Sign::Minus && magnitude.is_even() && !num::Zero::is_zero(&fib) {
+        return Ok(modulo - fib);
+    }
+    Ok(fib)
+}
+
+
+/// Finds the last k decimal digits of the nth fibonacci number. Runtime O(log(n))
+///
+/// The digits are zero-padded to a length of k. If fib(n) has fewer than k digits,
+/// fib(n) is returned as is, without padding.
+///
+/// # Examples
+/// ```
+/// assert_eq!("", fast_fibonacci::last_k_digits(10, 0));
+/// assert_eq!("55", fast_fibonacci::last_k_digits(10, 5));
+/// assert_eq!("875", fast_fibonacci::last_k_digits(1_000_000_000_000_000, 3));
+/// assert_eq!("0546875", fast_fibonacci::last_k_digits(1_000_000_000_000_000, 7));
+/// ```
+#[cfg(feature = "std")]
+pub fn last_k_digits(n: u64, k: u32) -> String {
+    if k == 0 {
+        return String::new();
+    }
+
+    let ten: BigUint = FromPrimitive::from_u64(10).unwrap();
+    let modulo: BigUint = num::pow(ten, k as usize);
+    let digits = bigfib_with_mod(&FromPrimitive::from_u64(n).unwrap(), &modulo).to_str_radix(10);
+    if fib_is_below(n, &modulo) {
+        return digits;
+    }
+    format!("{:0>width$}", digits, width = k as usize)
+}
+
+
+// Whether fib(n) < bound. Stops as soon as the sequence passes bound, so only
+// O(log(bound)) additions are needed regardless of n.
+#[cfg(feature = "std")]
+fn fib_is_below(n: u64, bound: &BigUint) -> bool {
+    let mut a: BigUint = FromPrimitive::from_u64(0).unwrap();
+    let mut b: BigUint = FromPrimitive::from_u64(1).unwrap();
+    for _ in 0..n {
+        if &a >= bound {
+            return false;
+        }
+        let next = &a + &b;
+        a = b;
+        b = next;
+    }
+    &a < bound
+}
+
+
+/// Finds the pair (fib(n), fib(n + 1)) with modulo. Runtime O(log(n))
+///
+/// Pairs can be combined with `fib_add_indices` without another exponentiation.
+///
+/// # Examples
+/// ```
+/// assert_eq!((0, 1), fast_fibonacci::fib_pair_with_mod(0, 10));
+/// assert_eq!((55, 89), fast_fibonacci::fib_pair_with_mod(10, 100));
+/// assert_eq!((875, 501), fast_fibonacci::fib_pair_with_mod(1_000_000_000_000_000, 1_000));
+/// ```
+pub fn fib_pair_with_mod(n: u64, modulo: u64) -> (u64, u64) {
+    try_fib_pair_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Finds the pair (fib(n), fib(n + 1)) with modulo, or an error instead of panicking.
+/// Runtime O(log(n))
+///
+/// # Examples
+/// ```
+/// assert_eq!(Ok((55, 89)), fast_fibonacci::try_fib_pair_with_mod(10, 100));
+/// assert!(fast_fibonacci::try_fib_pair_with_mod(10, 0).is_err());
+/// ```
+pub fn try_fib_pair_with_mod(n: u64, modulo: u64) -> Result<(u64, u64), FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    if n == 0 {
+        return Ok((0, 1 % modulo));
+    }
+    let t = [
+        [0, 1],
+        [1, 1]
+    ];
+    let (power_t, _) = matrix::power_with_mod_u64(&t, n, modulo);
+    Ok((power_t[0][1] % modulo, power_t[1][1] % modulo))
+}
+
+
+/// Finds the pair (fib(n), lucas(n)) with modulo from one exponentiation. Runtime O(log(n))
+///
+/// Uses lucas(n) = fib(n - 1) + fib(n + 1) = 2 * fib(n + 1) - fib(n).
+///
+/// # Examples
+/// ```
+/// assert_eq!((0, 2), fast_fibonacci::fib_lucas_with_mod(0, 10));
+/// assert_eq!((55, 123), fast_fibonacci::fib_lucas_with_mod(10, 1_000));
+/// ```
+pub fn fib_lucas_with_mod(n: u64, modulo: u64) -> (u64, u64) {
+    try_fib_lucas_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_lucas_with_mod`, but returns an error instead of panicking.
+pub fn try_fib_lucas_with_mod(n: u64, modulo: u64) -> Result<(u64, u64), FibError> {
+    let (f, f1) = try_fib_pair_with_mod(n, modulo)?;
+    Ok((f, sub_mod(add_mod(f1, f1, modulo), f, modulo)))
+}
+
+
+/// Combines (fib(m), fib(m + 1)) and (fib(n), fib(n + 1)) into (fib(m + n), fib(m + n + 1)) with
+/// modulo. Runtime O(1)
+///
+/// Uses the addition formula fib(m + n) = fib(m) * fib(n + 1) + fib(m - 1) * fib(n).
+///
+/// # Examples
+/// ```
+/// let m = fast_fibonacci::fib_pair_with_mod(1_000_000, 1_000_000_007);
+/// let n = fast_fibonacci::fib_pair_with_mod(2_345_678, 1_000_000_007);
+/// assert_eq!(
+///     fast_fibonacci::fib_pair_with_mod(3_345_678, 1_000_000_007),
+///     fast_fibonacci::fib_add_indices(m, n, 1_000_000_007)
+/// );
+/// ```
+pub fn fib_add_indices(m_pair: (u64, u64), n_pair: (u64, u64), modulo: u64) -> (u64, u64) {
+    try_fib_add_indices(m_pair, n_pair, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Combines (fib(m), fib(m + 1)) and (fib(n), fib(n + 1)) into (fib(m + n), fib(m + n + 1)) with
+/// modulo, or an error instead of panicking. Runtime O(1)
+///
+/// # Examples
+/// ```
+/// // fib(4) = 3 and fib(5) = 5 give fib(9) = 34
+/// assert_eq!(Ok((34, 55)), fast_fibonacci::try_fib_add_indices((3, 5), (5, 8), 100));
+/// assert!(fast_fibonacci::try_fib_add_indices((3, 5), (5, 8), 0).is_err());
+/// ```
+pub fn try_fib_add_indices(
+    m_pair: (u64, u64),
+    n_pair: (u64, u64),
+    modulo: u64
+) -> Result<(u64, u64), FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    let (f_m, f_m1) = (m_pair.0 % modulo, m_pair.1 % modulo);
+    let (f_n, f_n1) = (n_pair.0 % modulo, n_pair.1 % modulo);
+    let f_m_minus_1 = sub_mod(f_m1, f_m, modulo);
+
+    let f_sum = add_mod(mul_mod(f_m, f_n1, modulo), mul_mod(f_m_minus_1, f_n, modulo), modulo);
+    let f_sum1 = add_mod(mul_mod(f_m1, f_n1, modulo), mul_mod(f_m, f_n, modulo), modulo);
+    Ok((f_sum, f_sum1))
+}
+
+
+/// Finds gcd(fib(a), fib(b)) with modulo. Runtime O(log(a) + log(b))
+///
+/// Uses the identity gcd(fib(a), fib(b)) = fib(gcd(a, b)).
+///
+/// # Examples
+/// ```
+/// // gcd(fib(12), fib(18)) = gcd(144, 2584) = 8 = fib(6)
+/// assert_eq!(8, fast_fibonacci::fib_gcd_with_mod(12, 18, 1_000));
+/// assert_eq!(
+///     fast_fibonacci::fib_with_mod(5_000_000_000, 1_000_000_007),
+///     fast_fibonacci::fib_gcd_with_mod(3_000_000_000_000_000_000, 5_000_000_000, 1_000_000_007)
+/// );
+/// ```
+pub fn fib_gcd_with_mod(a: u64, b: u64, modulo: u64) -> u64 {
+    fib_with_mod(nt::gcd(a as u128, b as u128) as u64, modulo)
+}
+
+
+/// Finds gcd(fib(a), fib(b)) with modulo, or an error instead of panicking.
+/// Runtime O(log(a) + log(b))
+///
+/// # Examples
+/// ```
+/// assert_eq!(Ok(8), fast_fibonacci::try_fib_gcd_with_mod(12, 18, 1_000));
+/// assert!(fast_fibonacci::try_fib_gcd_with_mod(12, 18, 0).is_err());
+/// ```
+pub fn try_fib_gcd_with_mod(a: u64, b: u64, modulo: u64) -> Result<u64, FibError> {
+    try_fib_with_mod(nt::gcd(a as u128, b as u128) as u64, modulo)
+}
+
+
+/// BigUint version of fib_gcd_with_mod. Finds gcd(fib(a), fib(b)) with modulo.
+/// Runtime O(log(a) + log(b))
+///
+/// Uses the identity gcd(fib(a), fib(b)) = fib(gcd(a, b)).
+///
+/// # Examples
+/// ```
+/// use num::FromPrimitive;
+/// use num_bigint::BigUint;
+///
+/// let a: BigUint = BigUint::from_slice(&[0, 0, 0, 12]);
+/// let b: BigUint = BigUint::from_slice(&[0, 0, 0, 18]);
+/// let modulo: BigUint = FromPrimitive::from_u64(1_000_000_007).unwrap();
+/// assert_eq!(
+///     fast_fibonacci::bigfib_with_mod(&BigUint::from_slice(&[0, 0, 0, 6]), &modulo),
+///     fast_fibonacci::bigfib_gcd_with_mod(&a, &b, &modulo)
+/// );
+/// ```
+#[cfg(feature = "std")]
+pub fn bigfib_gcd_with_mod(a: &BigUint, b: &BigUint, modulo: &BigUint) -> BigUint {
+    bigfib_with_mod(&a.gcd(b), modulo)
+}
+
+
+/// BigUint version of try_fib_gcd_with_mod. Finds gcd(fib(a), fib(b)) with modulo, or an error
+/// instead of panicking. Runtime O(log(a) + log(b))
+#[cfg(feature = "std")]
+pub fn try_bigfib_gcd_with_mod(a: &BigUint, b: &BigUint, modulo: &BigUint) -> Result<BigUint, FibError> {
+    try_bigfib_with_mod(&a.gcd(b), modulo)
+}
+
+
+// Exact (fib(n), fib(n + 1)), via the matrix power with a modulo too large to ever apply.
+#[cfg(feature = "std")]
+pub(crate) fn exact_bigfib_pair(n: u64) -> (BigUint, BigUint) {
+    let zero: BigUint = FromPrimitive::from_u64(0).unwrap();
+    let one: BigUint = FromPrimitive::from_u64(1).unwrap();
+    if n == 0 {
+        return (zero, one);
+    }
+
+    // fib(n + 1) < 2^(0.7 * (n + 1))
+    let modulo = one.clone() << ((n as usize + 1) * 7 / 10 + 1);
+    let t: Array2<BigUint> = arr2(&[
+        [zero, one.clone()],
+        [one.clone(), one]
+    ]);
+    let power_t = bigfib_matrix_power(&t, &FromPrimitive::from_u64(n).unwrap(), &modulo);
+    (power_t[[0, 1]].clone(), power_t[[1, 1]].clone())
+}
+
+
+#[cfg(feature = "std")]
 fn bigfib_matrix_power(mat: &Array2<BigUint>, pow: &BigUint, modulo: &BigUint) -> Array2<BigUint> {
-    let ONE: BigUint = FromPrimitive::from_u64(1).unwrap();
-    let TWO: BigUint = FromPrimitive::from_u64(2).unwrap();
-    if pow == &ONE {
-        return mat.clone();
-    }
-    if pow % &TWO == ONE {
-        return bigfib_multiply(
-            &mat, 
-            &bigfib_matrix_power(mat, &(pow - ONE), modulo),
-            modulo
-        );
+    // Over the bits of pow from the top, iteratively, since indices can have millions of bits.
+    let pow_bytes = pow.to_bytes_le();
+    let mut power = mat.clone();
+    for bit in (0..pow.bits() - 1).rev() {
+        let mut x = power;
+        power = bigfib_multiply(&x, &x, modulo);
+        wipe::wipe_matrix(&mut x);
+        if pow_bytes[bit / 8] >> (bit % 8) & 1 == 1 {
+            let mut x = power;
+            power = bigfib_multiply(mat, &x, modulo);
+            wipe::wipe_matrix(&mut x);
+        }
     }
-    let x = bigfib_matrix_power(mat, &(pow / TWO), modulo);
-    bigfib_multiply(&x, &x, modulo)
+    power
 }
 
 
+#[cfg(feature = "std")]
 fn bigfib_multiply(a: &Array2<BigUint>, b: &Array2<BigUint>, modulo: &BigUint) -> Array2<BigUint> {
-    let ZERO: BigUint = FromPrimitive::from_u64(0).unwrap();
+    let zero: BigUint = FromPrimitive::from_u64(0).unwrap();
     let mut return_mat: Array2<BigUint> = arr2(&[
-        [ZERO.clone(), ZERO.clone()],
-        [ZERO.clone(), ZERO.clone()]
+        [zero.clone(), zero.clone()],
+        [zero.clone(), zero]
     ]);
 
     for i in 0..2 {
         for j in 0..2 {
             for k in 0..2 {
-                let big_val: BigUint = &return_mat[[i, j]] + (&a[[i, k]] * &b[[k, j]]);
-                return_mat[[i, j]] = big_val % modulo;
+                let mut big_val: BigUint = &return_mat[[i, j]] + (&a[[i, k]] * &b[[k, j]]);
+                wipe::wipe(&mut return_mat[[i, j]]);
+                return_mat[[i, j]] = &big_val % modulo;
+                wipe::wipe(&mut big_val);
             }
         }
     }
@@ -137,64 +793,188 @@ fn bigfib_multiply(a: &Array2<BigUint>, b: &Array2<BigUint>, modulo: &BigUint) -
 }
 
 
-fn multiply_with_mod(a: &Array2<u64>, b: &Array2<u64>, modulo: u64) -> Array2<u64> {
-    let mut return_mat: Array2<u64> = Array2::zeros((2, 2));
+#[cfg(not(target_pointer_width = "32"))]
+fn mul_mod(a: u64, b: u64, modulo: u64) -> u64 {
+    ((a as u128 * b as u128) % modulo as u128) as u64
+}
 
-    let big_mod: BigUint = FromPrimitive::from_u64(modulo).unwrap();
-    for i in 0..2 {
-        for j in 0..2 {
-            for k in 0..2 {
-                let mat_ij: BigUint = FromPrimitive::from_u64(return_mat[[i, j]]).unwrap();
-                let a_ik: BigUint = FromPrimitive::from_u64(a[[i, k]]).unwrap();
-                let b_kj: BigUint = FromPrimitive::from_u64(b[[k, j]]).unwrap();
 
-                let big_val: BigUint = (mat_ij + (
-                    a_ik * b_kj
-                )) % &big_mod;
+#[cfg(not(target_pointer_width = "32"))]
+fn add_mod(a: u64, b: u64, modulo: u64) -> u64 {
+    ((a as u128 + b as u128) % modulo as u128) as u64
+}
 
-                return_mat[[i, j]] = small_big_int_to_u64(&big_val);
-            }
+
+// On 32-bit targets, such as wasm32 and microcontrollers, u128 division is a slow library call.
+#[cfg(target_pointer_width = "32")]
+fn mul_mod(a: u64, b: u64, modulo: u64) -> u64 {
+    mul_mod_narrow(a, b, modulo)
+}
+
+
+#[cfg(target_pointer_width = "32")]
+fn add_mod(a: u64, b: u64, modulo: u64) -> u64 {
+    add_mod_narrow(a % modulo, b % modulo, modulo)
+}
+
+
+// a * b % modulo without u128: in u64 for moduli below 2^32, where the product fits, and by double
+// and add over the bits of b otherwise.
+#[cfg_attr(not(target_pointer_width = "32"), allow(dead_code))]
+fn mul_mod_narrow(a: u64, b: u64, modulo: u64) -> u64 {
+    let (mut a, mut b) = (a % modulo, b % modulo);
+    if modulo <= u32::MAX as u64 {
+        return a * b % modulo;
+    }
+    let mut product = 0;
+    while b > 0 {
+        if b & 1 == 1 {
+            product = add_mod_narrow(product, a, modulo);
         }
+        a = add_mod_narrow(a, a, modulo);
+        b >>= 1;
     }
-    return_mat
+    product
 }
 
 
-fn matrix_power_with_mod(mat: &Array2<u64>, pow: u64, modulo: u64) -> Array2<u64> {
-    if pow == 1 {
-        return mat.clone();
+// fib(n) with a positive modulo by n additions, for n below ITERATION_THRESHOLD.
+pub(crate) fn fib_by_iteration(n: u64, modulo: u64) -> u64 {
+    let (mut a, mut b) = (0, 1 % modulo);
+    for _ in 0..n {
+        let next = add_mod_narrow(a, b, modulo);
+        a = b;
+        b = next;
     }
-    if pow % 2 == 1 {
-        return multiply_with_mod(
-            &mat, 
-            &matrix_power_with_mod(
-                mat, 
-                pow - 1, 
-                modulo
-            ), 
-            modulo
-        );
+    a
+}
+
+
+// fib(0), ..., fib(len - 1) with a positive modulo.
+#[cfg(feature = "std")]
+pub(crate) fn small_fib_table(modulo: u64, len: usize) -> Vec<u64> {
+    let mut table = vec![0, 1 % modulo];
+    while table.len() < len {
+        let next = add_mod_narrow(table[table.len() - 2], table[table.len() - 1], modulo);
+        table.push(next);
+    }
+    table.truncate(len);
+    table
+}
+
+
+// a and b must already be reduced. Never overflows, unlike a + b.
+fn add_mod_narrow(a: u64, b: u64, modulo: u64) -> u64 {
+    if a >= modulo - b {
+        a - (modulo - b)
+    } else {
+        a + b
+    }
+}
+
+
+fn pow_mod(base: u64, mut exponent: u64, modulo: u64) -> u64 {
+    let mut result = 1 % modulo;
+    let mut base = base % modulo;
+    while exponent > 0 {
+        if exponent % 2 == 1 {
+            result = mul_mod(result, base, modulo);
+        }
+        base = mul_mod(base, base, modulo);
+        exponent /= 2;
     }
-    let x = matrix_power_with_mod(mat, pow / 2, modulo);
-    multiply_with_mod(&x, &x, modulo)
+    result
 }
 
 
-fn small_big_int_to_u64(big_int: &BigUint) -> u64 {
-    let mut result: u64 = 0;
+// a and b must already be reduced.
+fn sub_mod(a: u64, b: u64, modulo: u64) -> u64 {
+    if a >= b {
+        a - b
+    } else {
+        modulo - (b - a)
+    }
+}
+
 
-    let digits = big_int.to_radix_be(10);
-	for i in 0..digits.len() - 1 {
-		result = result + digits[i] as u64;
-		result = result * 10;
-	}
-	result + digits[digits.len() - 1] as u64
+// (fib(n), fib(n + 1)) with modulo by fast doubling over the bits of n, for indices up to u128.
+#[cfg(feature = "std")]
+pub(crate) fn fib_pair_u128(n: u128, modulo: u64) -> (u64, u64) {
+    match fib_pair_doubling(n, modulo, || Ok::<(), Infallible>(())) {
+        Ok(pair) => pair,
+        Err(never) => match never {},
+    }
 }
 
-#[cfg(test)]
+
+// Like `fib_pair_u128`, but calls `before_step` before every doubling step, and stops with the
+// first error it returns.
+#[cfg(feature = "std")]
+pub(crate) fn fib_pair_doubling<E, F: FnMut() -> Result<(), E>>(
+    n: u128,
+    modulo: u64,
+    mut before_step: F,
+) -> Result<(u64, u64), E> {
+    let (mut a, mut b) = (0, 1 % modulo);
+    for bit in (0..128 - n.leading_zeros()).rev() {
+        before_step()?;
+        // fib(2k) = fib(k) * (2 * fib(k + 1) - fib(k)), fib(2k + 1) = fib(k)^2 + fib(k + 1)^2
+        let even = mul_mod(a, sub_mod(add_mod(b, b, modulo), a, modulo), modulo);
+        let odd = add_mod(mul_mod(a, a, modulo), mul_mod(b, b, modulo), modulo);
+        if (n >> bit) & 1 == 1 {
+            a = odd;
+            b = add_mod(even, odd, modulo);
+        } else {
+            a = even;
+            b = odd;
+        }
+    }
+    Ok((a, b))
+}
+
+
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use crate::*;
     
+    #[test]
+    fn test_try_errors() {
+        assert_eq!(try_fib_pair_with_mod(10, 0), Err(FibError::ZeroModulus));
+        assert_eq!(try_fib_add_indices((0, 1), (1, 1), 0), Err(FibError::ZeroModulus));
+        assert_eq!(try_fib_gcd_with_mod(12, 18, 0), Err(FibError::ZeroModulus));
+        assert_eq!(try_fibonorial_with_mod(6, 0), Err(FibError::ZeroModulus));
+        assert_eq!(try_fibonomial_with_mod(6, 3, 0), Err(FibError::ZeroModulus));
+        for &modulo in &[1, 4, 1_000_000_007u64 * 3] {
+            assert!(try_fibonomial_with_mod(6, 3, modulo).is_err());
+        }
+        assert_eq!(try_fib_pair_with_mod(u64::MAX, u64::MAX), Ok(fib_pair_with_mod(u64::MAX, u64::MAX)));
+    }
+
+    #[test]
+    fn test_fib_with_mod_const() {
+        fn check<const M: u64>() {
+            for &n in &[0, 1, 2, 93, 127, 128, 1 << 40, 1_000_000_000_000_000, u64::MAX] {
+                assert_eq!(fib_with_mod_const::<M>(n), fib_with_mod(n, M), "n = {}, modulo = {}", n, M);
+            }
+        }
+        check::<1>();
+        check::<2>();
+        check::<1_000_000_007>();
+        check::<{ u32::MAX as u64 }>();
+        check::<{ u32::MAX as u64 + 1 }>();
+        check::<{ (1 << 61) - 1 }>();
+        check::<{ u64::MAX }>();
+    }
+
+    #[test]
+    fn test_iteration_matches_doubling() {
+        for &modulo in &[1, 2, 10, 1_000_000_007, u64::MAX - 1, u64::MAX] {
+            for n in 0..2 * ITERATION_THRESHOLD {
+                assert_eq!(fib_with_mod(n, modulo), fib_pair_with_mod(n, modulo).0, "n = {}, modulo = {}", n, modulo);
+            }
+        }
+    }
+
     #[test]
     fn test_first_few() {
         assert_eq!(fib_with_mod(0, 10), 0);
@@ -205,6 +985,89 @@ mod tests {
         assert_eq!(fib_with_mod(5, 10), 5);
     }
 
+    #[test]
+    fn test_zero_modulus() {
+        let zero: BigUint = FromPrimitive::from_u64(0).unwrap();
+        for n in 0..3 {
+            assert_eq!(try_fib_with_mod(n, 0), Err(FibError::ZeroModulus));
+            assert_eq!(
+                try_bigfib_with_mod(&FromPrimitive::from_u64(n).unwrap(), &zero),
+                Err(FibError::ZeroModulus)
+            );
+        }
+        assert_eq!(try_fib_with_mod(1, 1), Ok(0));
+    }
+
+    #[test]
+    fn test_nonzero_mod() {
+        use std::convert::TryFrom;
+
+        let zero: BigUint = FromPrimitive::from_u64(0).unwrap();
+        assert_eq!(NonZeroBigUint::try_from(zero), Err(FibError::ZeroModulus));
+        for &modulo in &[1, 2, 10, 1_000_000_007] {
+            let nonzero = NonZeroU64::new(modulo).unwrap();
+            let big_nonzero = NonZeroBigUint::new(FromPrimitive::from_u64(modulo).unwrap()).unwrap();
+            for n in 0..50 {
+                let expected = fib_with_mod(n, modulo);
+                assert_eq!(fib_with_nonzero_mod(n, nonzero), expected);
+                assert_eq!(
+                    bigfib_with_nonzero_mod(&FromPrimitive::from_u64(n).unwrap(), &big_nonzero),
+                    FromPrimitive::from_u64(expected).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "modulo must be positive")]
+    fn test_zero_modulus_panics() {
+        fib_with_mod(5, 0);
+    }
+
+    #[test]
+    fn test_fib_const() {
+        const F_90: u64 = fib_const(90, u64::MAX);
+        assert_eq!(F_90, 2_880_067_194_370_816_120);
+        for &modulo in &[1, 2, 10, 1_000_000_007, u64::MAX] {
+            for &n in &[0, 1, 2, 3, 10, 1_000_000_000_000_000, u64::MAX] {
+                assert_eq!(fib_const(n, modulo), fib_with_mod(n, modulo), "n = {}, modulo = {}", n, modulo);
+            }
+        }
+    }
+
+    #[test]
+    fn test_narrow_mod_arithmetic() {
+        for &modulo in &[1, 2, 10, u32::MAX as u64, u32::MAX as u64 + 1, 1_000_000_000_039, u64::MAX - 58, u64::MAX] {
+            for &(a, b) in &[(0, 0), (1, u64::MAX), (u64::MAX, u64::MAX), (12_345, 67_890), (1 << 40, 1 << 33)] {
+                let expected = ((a as u128 * b as u128) % modulo as u128) as u64;
+                assert_eq!(mul_mod_narrow(a, b, modulo), expected, "{} * {} % {}", a, b, modulo);
+                let (a, b) = (a % modulo, b % modulo);
+                assert_eq!(add_mod_narrow(a, b, modulo), ((a as u128 + b as u128) % modulo as u128) as u64);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fib_mod_2_64() {
+        let modulo = BigUint::from(1u32) << 64;
+        for &n in &[0, 1, 2, 3, 10, 93, 94, 1_000, 1_000_000_000_000_000, u64::MAX] {
+            let expected = bigfib_with_mod(&BigUint::from(n), &modulo);
+            assert_eq!(BigUint::from(fib_mod_2_64(n)), expected, "n = {}", n);
+            assert_eq!(fib_mod_2_64(n) % (1 << 63), fib_with_mod(n, 1 << 63));
+        }
+    }
+
+    #[cfg(feature = "macros")]
+    #[test]
+    fn test_fib_macro() {
+        const X: u64 = fib!(1_000_000_000_000_000, 1_000_000u64);
+        assert_eq!(X, fib_with_mod(1_000_000_000_000_000, 1_000_000));
+        // Past u64, so check against the index reduced by the pisano period.
+        let period = pisano::pisano_period(1_000_000_007) as u128;
+        let reduced = (100_000_000_000_000_000_000u128 % period) as u64;
+        assert_eq!(fib!(100_000_000_000_000_000_000u128, 1_000_000_007), fib_with_mod(reduced, 1_000_000_007));
+    }
+
     #[test]
     fn test_modulo() {
         assert_eq!(fib_with_mod(100, 1_000_000_000), 261_915_075);
@@ -221,9 +1084,9 @@ mod tests {
 
     #[test]
     fn test_bigfib() {
-        let ns = vec![0, 1, 2, 3, 10, 1_000_000_000_000_000, 1_955_995_342_096_516];
-        let modulos = vec![10, 10, 20, 30, 100, 1_000_000, u64::MAX];
-        let expected_results = vec![0, 1, 1, 2, 55, 546_875, 2_886_946_313_980_141_317];
+        let ns = [0, 1, 2, 3, 10, 1_000_000_000_000_000, 1_955_995_342_096_516];
+        let modulos = [10, 10, 20, 30, 100, 1_000_000, u64::MAX];
+        let expected_results = [0, 1, 1, 2, 55, 546_875, 2_886_946_313_980_141_317];
 
         for i in 0..ns.len() {
             assert_eq!(
@@ -236,6 +1099,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_huge_index_bigfib() {
+        // Used to recurse twice per bit of n and overflow the stack.
+        let n = (BigUint::from(1u32) << 200_000) - 1u32;
+        let modulo: BigUint = FromPrimitive::from_u64(1_000_000_007).unwrap();
+        let expected = computation::BigFibComputation::new(&n, &modulo).unwrap().run();
+        assert_eq!(try_bigfib_with_mod(&n, &modulo), Ok(expected));
+    }
+
+    #[test]
+    fn test_bigfib_signed() {
+        // Extending fib(n - 1) = fib(n + 1) - fib(n) to negative n.
+        let (mut fib, mut next) = (0i64, 1i64);
+        for n in 0..=80i64 {
+            for &modulo in &[1u64, 2, 10, 1_000_000_007, u64::MAX] {
+                let modulo = BigUint::from(modulo);
+                let expected = (BigInt::from(fib) % BigInt::from(modulo.clone()) + BigInt::from(modulo.clone()))
+                    % BigInt::from(modulo.clone());
+                let actual = bigfib_signed_with_mod(&BigInt::from(-n), &modulo);
+                assert_eq!(BigInt::from(actual), expected, "n = {}", -n);
+                let positive = bigfib_signed_with_mod(&BigInt::from(n), &modulo);
+                assert_eq!(positive, bigfib_with_mod(&BigUint::from(n as u64), &modulo));
+            }
+            let previous = next - fib;
+            next = fib;
+            fib = previous;
+        }
+        assert_eq!(try_bigfib_signed_with_mod(&BigInt::from(-3), &BigUint::from(0u32)), Err(FibError::ZeroModulus));
+    }
+
     #[test]
     fn test_large_bigfib() {
         let n: BigUint = BigUint::from_slice(&[100u32, 100, 100, 100, 15129, 12319]);
@@ -246,4 +1139,61 @@ mod tests {
             expected_result
         );
     }
+
+    #[test]
+    fn test_last_k_digits() {
+        assert_eq!(last_k_digits(0, 3), "0");
+        assert_eq!(last_k_digits(12, 3), "144");
+        assert_eq!(last_k_digits(13, 3), "233");
+        assert_eq!(last_k_digits(15, 3), "610");
+        assert_eq!(last_k_digits(30, 4), "2040");
+        assert_eq!(last_k_digits(100, 9), "261915075");
+        assert_eq!(last_k_digits(40, 8), "02334155");
+        assert_eq!(last_k_digits(5, 3), "5");
+        assert_eq!(last_k_digits(100, 30), "354224848179261915075");
+    }
+
+    #[test]
+    fn test_fib_gcd() {
+        assert_eq!(fib_gcd_with_mod(0, 0, 10), 0);
+        assert_eq!(fib_gcd_with_mod(0, 7, 100), 13);
+        assert_eq!(fib_gcd_with_mod(13, 21, 100), 1);
+        assert_eq!(fib_gcd_with_mod(12, 18, 1_000), 8);
+        assert_eq!(fib_gcd_with_mod(24, 36, 1_000_000), 144);
+        assert_eq!(
+            bigfib_gcd_with_mod(
+                &FromPrimitive::from_u64(24).unwrap(),
+                &FromPrimitive::from_u64(36).unwrap(),
+                &FromPrimitive::from_u64(1_000_000).unwrap()
+            ),
+            FromPrimitive::from_u64(144).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_fib_add_indices() {
+        let modulo = u64::MAX;
+        for &(m, n) in &[(0, 0), (0, 5), (1, 1), (7, 13), (1_000_000_007, 998_244_353)] {
+            assert_eq!(
+                fib_add_indices(fib_pair_with_mod(m, modulo), fib_pair_with_mod(n, modulo), modulo),
+                fib_pair_with_mod(m + n, modulo)
+            );
+        }
+        assert_eq!(fib_add_indices((3, 5), (5, 8), 1_000), (34, 55));
+        assert_eq!(fib_add_indices((3, 5), (5, 8), 10), (4, 5));
+    }
+
+    #[test]
+    fn test_fib_lucas_with_mod() {
+        let (mut fib, mut lucas) = ((0u128, 1u128), (2u128, 1u128));
+        for n in 0..150 {
+            for &modulo in &[1, 2, 5, 1_000, u64::MAX] {
+                let expected = ((fib.0 % modulo as u128) as u64, (lucas.0 % modulo as u128) as u64);
+                assert_eq!(fib_lucas_with_mod(n, modulo), expected, "n = {}, modulo = {}", n, modulo);
+            }
+            fib = (fib.1, fib.0 + fib.1);
+            lucas = (lucas.1, lucas.0 + lucas.1);
+        }
+        assert_eq!(try_fib_lucas_with_mod(10, 0), Err(FibError::ZeroModulus));
+    }
 }
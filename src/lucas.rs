@@ -0,0 +1,160 @@
+//! Conversions between pairs of fibonacci and lucas numbers with modulo.
+//!
+//! lucas(n) = 2 * fib(n + 1) - fib(n) and lucas(n + 1) = 2 * fib(n) + fib(n + 1), so lucas numbers
+//! follow from fibonacci pairs under any modulo. Going back divides by 2, or by 5, which only
+//! works when the modulo is coprime to it. `fib_lucas_with_mod` finds (fib(n), lucas(n)) directly.
+
+use crate::nt::mod_inverse;
+use crate::{add_mod, mul_mod, sub_mod, FibError};
+
+
+/// Turns (fib(n), fib(n + 1)) into (fib(n), lucas(n)) with modulo. Runtime O(1)
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::lucas::fib_pair_to_fib_lucas;
+///
+/// assert_eq!((55, 123), fib_pair_to_fib_lucas((55, 89), 1_000));
+/// ```
+pub fn fib_pair_to_fib_lucas(pair: (u64, u64), modulo: u64) -> (u64, u64) {
+    try_fib_pair_to_fib_lucas(pair, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_pair_to_fib_lucas`, but returns an error instead of panicking.
+pub fn try_fib_pair_to_fib_lucas((f_n, f_n1): (u64, u64), modulo: u64) -> Result<(u64, u64), FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    let (f_n, f_n1) = (f_n % modulo, f_n1 % modulo);
+    Ok((f_n, sub_mod(add_mod(f_n1, f_n1, modulo), f_n, modulo)))
+}
+
+
+/// Turns (fib(n), lucas(n)) into (fib(n), fib(n + 1)) with an odd modulo, with
+/// fib(n + 1) = (fib(n) + lucas(n)) / 2. Runtime O(1)
+///
+/// With an even modulo, fib(n + 1) is only known modulo half of it, so it panics, see
+/// `try_fib_lucas_to_fib_pair`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::lucas::fib_lucas_to_fib_pair;
+///
+/// assert_eq!((55, 89), fib_lucas_to_fib_pair((55, 123), 999));
+/// ```
+pub fn fib_lucas_to_fib_pair(pair: (u64, u64), modulo: u64) -> (u64, u64) {
+    try_fib_lucas_to_fib_pair(pair, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_lucas_to_fib_pair`, but returns an error instead of panicking.
+pub fn try_fib_lucas_to_fib_pair((f_n, l_n): (u64, u64), modulo: u64) -> Result<(u64, u64), FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    if modulo.is_multiple_of(2) {
+        return Err(FibError::InvalidArgument("modulo must be odd"));
+    }
+    let (f_n, l_n) = (f_n % modulo, l_n % modulo);
+    // 2 * (modulo + 1) / 2 ≡ 1
+    let half = modulo / 2 + 1;
+    Ok((f_n, mul_mod(add_mod(f_n, l_n, modulo), half % modulo, modulo)))
+}
+
+
+/// Turns (fib(n), fib(n + 1)) into (lucas(n), lucas(n + 1)) with modulo. Runtime O(1)
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::lucas::fib_pair_to_lucas_pair;
+///
+/// assert_eq!((123, 199), fib_pair_to_lucas_pair((55, 89), 1_000));
+/// ```
+pub fn fib_pair_to_lucas_pair(pair: (u64, u64), modulo: u64) -> (u64, u64) {
+    try_fib_pair_to_lucas_pair(pair, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_pair_to_lucas_pair`, but returns an error instead of panicking.
+pub fn try_fib_pair_to_lucas_pair((f_n, f_n1): (u64, u64), modulo: u64) -> Result<(u64, u64), FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    let (f_n, f_n1) = (f_n % modulo, f_n1 % modulo);
+    Ok((
+        sub_mod(add_mod(f_n1, f_n1, modulo), f_n, modulo),
+        add_mod(add_mod(f_n, f_n, modulo), f_n1, modulo),
+    ))
+}
+
+
+/// Turns (lucas(n), lucas(n + 1)) into (fib(n), fib(n + 1)) with a modulo coprime to 5, with
+/// fib(n) = (2 * lucas(n + 1) - lucas(n)) / 5 and fib(n + 1) = (lucas(n + 1) + 2 * lucas(n)) / 5.
+/// Runtime O(log(modulo))
+///
+/// With a multiple of 5 as modulo, several fibonacci pairs share each lucas pair, e.g. modulo 5,
+/// lucas numbers repeat every 4 and fibonacci numbers every 20, so it panics, see
+/// `try_lucas_pair_to_fib_pair`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::lucas::lucas_pair_to_fib_pair;
+///
+/// assert_eq!((55, 89), lucas_pair_to_fib_pair((123, 199), 1_001));
+/// ```
+pub fn lucas_pair_to_fib_pair(pair: (u64, u64), modulo: u64) -> (u64, u64) {
+    try_lucas_pair_to_fib_pair(pair, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `lucas_pair_to_fib_pair`, but returns an error instead of panicking.
+pub fn try_lucas_pair_to_fib_pair((l_n, l_n1): (u64, u64), modulo: u64) -> Result<(u64, u64), FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    let fifth = mod_inverse(5, modulo).ok_or(FibError::InvalidArgument("modulo must be coprime to 5"))?;
+    let (l_n, l_n1) = (l_n % modulo, l_n1 % modulo);
+    let f_n = sub_mod(add_mod(l_n1, l_n1, modulo), l_n, modulo);
+    let f_n1 = add_mod(add_mod(l_n, l_n, modulo), l_n1, modulo);
+    Ok((mul_mod(f_n, fifth, modulo), mul_mod(f_n1, fifth, modulo)))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::lucas::*;
+    use crate::{fib_lucas_with_mod, fib_pair_with_mod};
+
+    #[test]
+    fn test_round_trips() {
+        for &modulo in &[1, 3, 7, 999, 1_000_000_007, u64::MAX - 58, u64::MAX] {
+            for &n in &[0, 1, 2, 10, 1_000_000_000_000, u64::MAX - 1] {
+                let fib = fib_pair_with_mod(n, modulo);
+                let (f_n, l_n) = fib_lucas_with_mod(n, modulo);
+                let lucas = (l_n, fib_lucas_with_mod(n + 1, modulo).1);
+                assert_eq!(fib_pair_to_fib_lucas(fib, modulo), (f_n, l_n), "n = {}, modulo = {}", n, modulo);
+                assert_eq!(fib_pair_to_lucas_pair(fib, modulo), lucas, "n = {}, modulo = {}", n, modulo);
+                if modulo % 2 == 1 {
+                    assert_eq!(fib_lucas_to_fib_pair((f_n, l_n), modulo), fib, "n = {}, modulo = {}", n, modulo);
+                }
+                if modulo % 5 != 0 {
+                    assert_eq!(lucas_pair_to_fib_pair(lucas, modulo), fib, "n = {}, modulo = {}", n, modulo);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_errors() {
+        assert_eq!(try_fib_pair_to_fib_lucas((0, 1), 0), Err(FibError::ZeroModulus));
+        assert_eq!(try_fib_pair_to_lucas_pair((0, 1), 0), Err(FibError::ZeroModulus));
+        assert_eq!(try_fib_lucas_to_fib_pair((0, 2), 10), Err(FibError::InvalidArgument("modulo must be odd")));
+        assert_eq!(
+            try_lucas_pair_to_fib_pair((2, 1), 10),
+            Err(FibError::InvalidArgument("modulo must be coprime to 5"))
+        );
+        // Unreduced inputs
+        assert_eq!(fib_pair_to_fib_lucas((55 + 100, 89 + 300), 100), (55, 23));
+    }
+}
@@ -0,0 +1,169 @@
+//! The 2x2 matrix arithmetic behind `fib_with_mod`, for u64 and BigUint entries.
+//!
+//! fib(n) is entry [0][1] of [[0, 1], [1, 1]]^n, and any two term recurrence
+//! x(n + 2) = p * x(n + 1) + q * x(n) is a power of [[0, 1], [q, p]] the same way. For square
+//! matrices of any size, see `mat_pow_mod`, and for other element types, `semiring`.
+//!
+//! # Examples
+//! ```
+//! use fast_fibonacci::matrix::matrix_power_with_mod;
+//!
+//! // pell(n + 2) = 2 * pell(n + 1) + pell(n)
+//! let power = matrix_power_with_mod(&[[0, 1], [1, 2]], 10, &1_000_000);
+//! assert_eq!(2_378, power[0][1]);
+//! ```
+
+#[cfg(feature = "std")]
+use crate::semiring::{ModElement, Semiring};
+#[cfg(feature = "std")]
+use crate::FibError;
+use crate::{add_mod, mul_mod};
+
+/// A 2x2 matrix, as its rows.
+pub type Matrix2<T> = [[T; 2]; 2];
+
+
+/// Multiplies two 2x2 matrices with modulo. The entries don't need to be reduced. Runtime O(1)
+/// multiplications
+///
+/// Panics if modulo is zero, see `try_multiply_with_mod`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::matrix::multiply_with_mod;
+/// use num_bigint::BigUint;
+///
+/// let q = [[BigUint::from(0u32), BigUint::from(1u32)], [BigUint::from(1u32), BigUint::from(1u32)]];
+/// let squared = multiply_with_mod(&q, &q, &BigUint::from(100u32));
+/// assert_eq!(BigUint::from(2u32), squared[1][1]);
+/// ```
+#[cfg(feature = "std")]
+pub fn multiply_with_mod<T: ModElement>(a: &Matrix2<T>, b: &Matrix2<T>, modulo: &T) -> Matrix2<T> {
+    try_multiply_with_mod(a, b, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `multiply_with_mod`, but returns an error instead of panicking.
+#[cfg(feature = "std")]
+pub fn try_multiply_with_mod<T: ModElement>(a: &Matrix2<T>, b: &Matrix2<T>, modulo: &T) -> Result<Matrix2<T>, FibError> {
+    let semiring = T::semiring(modulo).ok_or(FibError::ZeroModulus)?;
+    let (add, mul) = (|a: &T, b: &T| semiring.add(a, b), |a: &T, b: &T| semiring.mul(a, b));
+    Ok(multiply(&reduce(a, modulo), &reduce(b, modulo), &add, &mul))
+}
+
+
+/// Raises a 2x2 matrix to the power pow with modulo, the identity for pow = 0. The entries don't
+/// need to be reduced. Runtime O(log(pow)) multiplications
+///
+/// Panics if modulo is zero, see `try_matrix_power_with_mod`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::matrix::matrix_power_with_mod;
+///
+/// let power = matrix_power_with_mod(&[[0, 1], [1, 1]], 1_000_000_000_000_000, &1_000_000);
+/// assert_eq!(fast_fibonacci::fib_with_mod(1_000_000_000_000_000, 1_000_000), power[0][1]);
+/// ```
+#[cfg(feature = "std")]
+pub fn matrix_power_with_mod<T: ModElement>(mat: &Matrix2<T>, pow: u64, modulo: &T) -> Matrix2<T> {
+    try_matrix_power_with_mod(mat, pow, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `matrix_power_with_mod`, but returns an error instead of panicking.
+#[cfg(feature = "std")]
+pub fn try_matrix_power_with_mod<T: ModElement>(mat: &Matrix2<T>, pow: u64, modulo: &T) -> Result<Matrix2<T>, FibError> {
+    let semiring = T::semiring(modulo).ok_or(FibError::ZeroModulus)?;
+    let identity = [[semiring.one(), semiring.zero()], [semiring.zero(), semiring.one()]];
+    let (add, mul) = (|a: &T, b: &T| semiring.add(a, b), |a: &T, b: &T| semiring.mul(a, b));
+    Ok(power(&reduce(mat, modulo), pow, identity, &add, &mul).0)
+}
+
+
+// matrix_power_with_mod for u64 entries, without the heap, for fib_with_mod and the other u64
+// functions, and the matrix multiplications it took. The entries are only reduced if pow > 1.
+pub(crate) fn power_with_mod_u64(mat: &Matrix2<u64>, pow: u64, modulo: u64) -> (Matrix2<u64>, u64) {
+    let identity = [[1 % modulo, 0], [0, 1 % modulo]];
+    let add = |a: &u64, b: &u64| add_mod(*a, *b, modulo);
+    let mul = |a: &u64, b: &u64| mul_mod(*a, *b, modulo);
+    power(mat, pow, identity, &add, &mul)
+}
+
+
+#[cfg(feature = "std")]
+fn reduce<T: ModElement>(mat: &Matrix2<T>, modulo: &T) -> Matrix2<T> {
+    [
+        [mat[0][0].reduce(modulo), mat[0][1].reduce(modulo)],
+        [mat[1][0].reduce(modulo), mat[1][1].reduce(modulo)],
+    ]
+}
+
+
+// mat^pow by squaring over the bits of pow after the leading one, with the entries' add and mul,
+// and the matrix multiplications that took.
+fn power<T: Clone, A: Fn(&T, &T) -> T, M: Fn(&T, &T) -> T>(
+    mat: &Matrix2<T>,
+    pow: u64,
+    identity: Matrix2<T>,
+    add: &A,
+    mul: &M,
+) -> (Matrix2<T>, u64) {
+    if pow == 0 {
+        return (identity, 0);
+    }
+    let (mut power, mut multiplications) = (mat.clone(), 0);
+    for bit in (0..63 - pow.leading_zeros()).rev() {
+        power = multiply(&power, &power, add, mul);
+        multiplications += 1;
+        if pow >> bit & 1 == 1 {
+            power = multiply(&power, mat, add, mul);
+            multiplications += 1;
+        }
+    }
+    (power, multiplications)
+}
+
+
+fn multiply<T, A: Fn(&T, &T) -> T, M: Fn(&T, &T) -> T>(a: &Matrix2<T>, b: &Matrix2<T>, add: &A, mul: &M) -> Matrix2<T> {
+    let entry = |i: usize, j: usize| add(&mul(&a[i][0], &b[0][j]), &mul(&a[i][1], &b[1][j]));
+    [[entry(0, 0), entry(0, 1)], [entry(1, 0), entry(1, 1)]]
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::matrix::*;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_matches_mat_pow_mod() {
+        let mats = [[[0, 1], [1, 1]], [[3, 7], [u64::MAX, 2]], [[0, 0], [0, 0]]];
+        for mat in &mats {
+            let array = ndarray::arr2(mat);
+            for &modulo in &[1, 2, 1_000_000_007, u64::MAX] {
+                for &pow in &[1, 2, 3, 100, u64::MAX] {
+                    let expected = crate::mat_pow_mod(&array, pow, &modulo);
+                    let power = matrix_power_with_mod(mat, pow, &modulo);
+                    assert_eq!(ndarray::arr2(&power), expected, "pow = {}, modulo = {}", pow, modulo);
+                    assert_eq!(power_with_mod_u64(&reduce(mat, &modulo), pow, modulo).0, power, "pow = {}, modulo = {}", pow, modulo);
+                }
+                assert_eq!(ndarray::arr2(&multiply_with_mod(mat, mat, &modulo)), crate::mat_pow_mod(&array, 2, &modulo));
+                assert_eq!(matrix_power_with_mod(mat, 0, &modulo), [[1 % modulo, 0], [0, 1 % modulo]]);
+            }
+        }
+        assert_eq!(try_matrix_power_with_mod(&mats[0], 1, &0), Err(FibError::ZeroModulus));
+        assert_eq!(try_multiply_with_mod(&mats[0], &mats[0], &0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_big_matrix_power() {
+        let (zero, one) = (BigUint::from(0u32), BigUint::from(1u32));
+        let q = [[zero.clone(), one.clone()], [one.clone(), one]];
+        let modulo = (BigUint::from(1u32) << 127) - 1u32;
+        for &pow in &[0, 1, 2, 1_000, u64::MAX] {
+            let fib = crate::bigfib_with_mod(&BigUint::from(pow), &modulo);
+            assert_eq!(matrix_power_with_mod(&q, pow, &modulo)[0][1], fib, "pow = {}", pow);
+        }
+        assert_eq!(try_matrix_power_with_mod(&q, 1, &zero), Err(FibError::ZeroModulus));
+    }
+}
@@ -0,0 +1,98 @@
+use crate::FibError;
+
+
+/// Finds fib(n) modulo the Mersenne number 2^exponent - 1, such as 2^61 - 1, by fast doubling
+/// with reductions by shifts and additions instead of divisions. Runtime O(log(n))
+///
+/// x = hi * 2^exponent + lo is congruent to hi + lo, so a product folds down in two steps.
+/// `fib_with_mod` takes this path by itself for Mersenne moduli.
+///
+/// Panics if exponent isn't between 1 and 64, see `try_fib_mod_mersenne`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::fib_mod_mersenne;
+///
+/// let modulo = (1 << 61) - 1;
+/// assert_eq!(fast_fibonacci::fib_with_mod(1_000_000_000_000_000, modulo), fib_mod_mersenne(1_000_000_000_000_000, 61));
+/// assert_eq!(2_880_067_194_370_816_120, fib_mod_mersenne(90, 64));
+/// ```
+pub fn fib_mod_mersenne(n: u64, exponent: u32) -> u64 {
+    try_fib_mod_mersenne(n, exponent).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_mod_mersenne`, but returns an error instead of panicking.
+pub fn try_fib_mod_mersenne(n: u64, exponent: u32) -> Result<u64, FibError> {
+    if exponent == 0 || exponent > 64 {
+        return Err(FibError::InvalidArgument("exponent must be between 1 and 64"));
+    }
+    Ok(fib_pair_mersenne(n, exponent).0)
+}
+
+
+// k for a modulo of the form 2^k - 1 with k >= 1.
+pub(crate) fn mersenne_exponent(modulo: u64) -> Option<u32> {
+    if modulo != 0 && modulo & modulo.wrapping_add(1) == 0 {
+        Some(modulo.count_ones())
+    } else {
+        None
+    }
+}
+
+
+// (fib(n), fib(n + 1)) modulo 2^exponent - 1 for 1 <= exponent <= 64, by fast doubling.
+pub(crate) fn fib_pair_mersenne(n: u64, exponent: u32) -> (u64, u64) {
+    let modulo = u64::MAX >> (64 - exponent);
+    let reduce = |x: u128| {
+        // At most 2^(exponent + 1) - 2 after one fold for x below 2^(2 * exponent), and at most
+        // modulo after the second.
+        let x = (x & modulo as u128) + (x >> exponent);
+        let x = (x & modulo as u128) + (x >> exponent);
+        let x = x as u64;
+        if x >= modulo {
+            x - modulo
+        } else {
+            x
+        }
+    };
+    let (mut a, mut b) = (0, reduce(1));
+    for bit in (0..64 - n.leading_zeros()).rev() {
+        // fib(2k) = fib(k) * (2 * fib(k + 1) - fib(k)), fib(2k + 1) = fib(k)^2 + fib(k + 1)^2
+        let twice_b_minus_a = reduce(2 * b as u128 + (modulo - a) as u128);
+        let even = reduce(a as u128 * twice_b_minus_a as u128);
+        // The sum of the squares could pass u128 for exponent 64, so they're reduced first.
+        let odd = reduce(reduce(a as u128 * a as u128) as u128 + reduce(b as u128 * b as u128) as u128);
+        if (n >> bit) & 1 == 1 {
+            a = odd;
+            b = reduce(even as u128 + odd as u128);
+        } else {
+            a = even;
+            b = odd;
+        }
+    }
+    (a, b)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::mersenne::*;
+
+    #[test]
+    fn test_matches_fib_pair() {
+        for exponent in 1..=64 {
+            let modulo = u64::MAX >> (64 - exponent);
+            assert_eq!(mersenne_exponent(modulo), Some(exponent));
+            for &n in &[0, 1, 2, 3, 93, 94, 1 << 40, 1_000_000_000_000_000, u64::MAX - 1, u64::MAX] {
+                let expected = crate::fib_pair_with_mod(n, modulo);
+                assert_eq!(fib_pair_mersenne(n, exponent), expected, "n = {}, exponent = {}", n, exponent);
+            }
+        }
+        for &modulo in &[0, 2, 6, 10, 1 << 61, u64::MAX - 1] {
+            assert_eq!(mersenne_exponent(modulo), None, "modulo = {}", modulo);
+        }
+        assert!(try_fib_mod_mersenne(1, 0).is_err());
+        assert!(try_fib_mod_mersenne(1, 65).is_err());
+    }
+}
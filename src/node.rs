@@ -0,0 +1,97 @@
+//! Node.js bindings through napi-rs, with the `node` feature. Build the addon with
+//! `cargo rustc --release --features node --crate-type cdylib`, and rename the library to
+//! `fast_fibonacci.node`.
+//!
+//! Arguments and results are JavaScript BigInts of any size. Inputs that fit in a u64 take the
+//! u64 path, and anything larger goes through BigUint.
+
+use napi::bindgen_prelude::BigInt;
+use napi::{Error, Result};
+use napi_derive::napi;
+use num_bigint::BigUint;
+
+use crate::FibError;
+
+
+/// `fibWithMod(n: bigint, modulo: bigint): bigint` in JavaScript, see `fib_with_mod` and
+/// `bigfib_with_mod`.
+#[napi(js_name = "fibWithMod")]
+pub fn fib_with_mod(n: BigInt, modulo: BigInt) -> Result<BigInt> {
+    let n = to_biguint(&n)?;
+    let modulo = to_biguint(&modulo)?;
+    let fib = crate::try_bigfib_with_mod(&n, &modulo).map_err(to_napi_error)?;
+    Ok(to_bigint(&fib))
+}
+
+
+/// `pisanoPeriod(modulo: bigint): bigint` in JavaScript, see `pisano::pisano_period`.
+#[napi(js_name = "pisanoPeriod")]
+pub fn pisano_period(modulo: BigInt) -> Result<BigInt> {
+    let (sign_bit, modulo, lossless) = modulo.get_u64();
+    if sign_bit || !lossless {
+        return Err(Error::from_reason("modulo must fit in a u64"));
+    }
+    let period = crate::pisano::try_pisano_period(modulo).map_err(to_napi_error)?;
+    Ok(BigInt::from(period))
+}
+
+
+// BigInt words are little endian u64s.
+fn to_biguint(n: &BigInt) -> Result<BigUint> {
+    if n.sign_bit && n.words.iter().any(|&word| word != 0) {
+        return Err(Error::from_reason("expected a non-negative bigint"));
+    }
+    let bytes: Vec<u8> = n.words.iter().flat_map(|word| word.to_le_bytes().to_vec()).collect();
+    Ok(BigUint::from_bytes_le(&bytes))
+}
+
+
+fn to_bigint(n: &BigUint) -> BigInt {
+    let mut bytes = n.to_bytes_le();
+    bytes.resize(bytes.len().div_ceil(8) * 8, 0);
+    let words = bytes
+        .chunks(8)
+        .map(|chunk| {
+            let mut word = [0; 8];
+            word.copy_from_slice(chunk);
+            u64::from_le_bytes(word)
+        })
+        .collect();
+    BigInt { sign_bit: false, words }
+}
+
+
+fn to_napi_error(err: FibError) -> Error {
+    Error::from_reason(err.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::node::*;
+
+    #[test]
+    fn test_fib_with_mod() {
+        let fib = fib_with_mod(BigInt::from(10u64), BigInt::from(100u64)).unwrap();
+        assert_eq!((fib.sign_bit, fib.words), (false, vec![55]));
+
+        // fib(10^20) mod 2^100, and its words past the first u64.
+        let n = BigInt { sign_bit: false, words: vec![7_766_279_631_452_241_920, 5] };
+        let modulo = BigInt { sign_bit: false, words: vec![0, 1 << 36] };
+        let fib = fib_with_mod(n, modulo).unwrap();
+        let expected = crate::bigfib_with_mod(
+            &BigUint::parse_bytes(b"100000000000000000000", 10).unwrap(),
+            &(BigUint::from_bytes_le(&[1]) << 100)
+        );
+        assert_eq!(to_biguint(&fib).unwrap(), expected);
+
+        assert!(fib_with_mod(BigInt::from(10u64), BigInt::from(0u64)).is_err());
+        assert!(fib_with_mod(BigInt::from(-10i64), BigInt::from(100u64)).is_err());
+    }
+
+    #[test]
+    fn test_pisano_period() {
+        assert_eq!(pisano_period(BigInt::from(10u64)).unwrap().words, vec![60]);
+        assert!(pisano_period(BigInt { sign_bit: false, words: vec![0, 1] }).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! Async versions of the heavy BigUint computations, with the `tokio` feature.
+//!
+//! Each function moves its arguments onto tokio's blocking pool with `spawn_blocking` and returns
+//! a `FibTask`, a future of the result. Dropping the `FibTask` before it finishes, or calling
+//! `FibTask::cancel`, cancels the computation between two doubling steps, so abandoned requests
+//! don't keep a blocking thread busy. The functions must be called from within a tokio runtime.
+
+use num_bigint::BigUint;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+use crate::computation::{bigfib_with_mod_cancellable, finish, BigFibComputation, CHECK_INTERVAL};
+use crate::wipe::wipe;
+use crate::FibError;
+
+
+/// A computation running on tokio's blocking pool, see the module documentation.
+#[derive(Debug)]
+pub struct FibTask<T> {
+    handle: JoinHandle<Result<T, FibError>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl<T: Send + 'static> FibTask<T> {
+    fn spawn<F: FnOnce(&AtomicBool) -> Result<T, FibError> + Send + 'static>(f: F) -> FibTask<T> {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&cancel);
+        FibTask {
+            handle: tokio::task::spawn_blocking(move || f(&flag)),
+            cancel,
+        }
+    }
+}
+
+impl<T> FibTask<T> {
+    /// Asks the computation to stop at its next check, like dropping the task, but keeps the task
+    /// to await what it stopped with.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+impl<T> Future for FibTask<T> {
+    type Output = Result<T, FibError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        match Pin::new(&mut self.handle).poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(result)) => Poll::Ready(result),
+            Poll::Ready(Err(err)) if err.is_panic() => std::panic::resume_unwind(err.into_panic()),
+            // The runtime is shutting down.
+            Poll::Ready(Err(_)) => Poll::Ready(Err(FibError::Cancelled)),
+        }
+    }
+}
+
+impl<T> Drop for FibTask<T> {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+
+/// Async version of `try_bigfib_with_mod`. Runtime O(log(n)), off the async threads.
+///
+/// # Examples
+/// ```
+/// use num_bigint::BigUint;
+///
+/// let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// let n = BigUint::parse_bytes(b"1000000000000000", 10).unwrap();
+/// let modulo = BigUint::parse_bytes(b"1000000", 10).unwrap();
+/// let fib = runtime.block_on(async { fast_fibonacci::nonblocking::bigfib_with_mod(&n, &modulo).await });
+/// assert_eq!(Ok(BigUint::parse_bytes(b"546875", 10).unwrap()), fib);
+/// ```
+pub fn bigfib_with_mod(n: &BigUint, modulo: &BigUint) -> FibTask<BigUint> {
+    let (mut n, mut modulo) = (n.clone(), modulo.clone());
+    FibTask::spawn(move |cancel| {
+        let fib = bigfib_with_mod_cancellable(&n, &modulo, |_| {}, cancel);
+        wipe(&mut n);
+        wipe(&mut modulo);
+        fib
+    })
+}
+
+
+/// Async version of `computation::bigfib_with_mod_timeout`. Runtime O(log(n)), off the async
+/// threads.
+///
+/// The budget only counts time spent computing, not time waiting for a blocking thread.
+pub fn bigfib_with_mod_timeout(n: &BigUint, modulo: &BigUint, budget: Duration) -> FibTask<BigUint> {
+    let (mut n, mut modulo) = (n.clone(), modulo.clone());
+    FibTask::spawn(move |cancel| {
+        let deadline = Instant::now().checked_add(budget);
+        let fib = BigFibComputation::new(&n, &modulo)
+            .and_then(|computation| finish(computation, |_| {}, Some(cancel), deadline));
+        wipe(&mut n);
+        wipe(&mut modulo);
+        fib
+    })
+}
+
+
+/// Async version of `BigFibComputation::run`, finishing a possibly resumed computation.
+/// Runtime O(log(n)), off the async threads.
+///
+/// Resolves to the computation itself after `FibTask::cancel`, so it can be saved with
+/// `to_bytes`.
+pub fn run_computation(mut computation: BigFibComputation) -> FibTask<Result<BigUint, BigFibComputation>> {
+    FibTask::spawn(move |cancel| {
+        while !computation.step(CHECK_INTERVAL) {
+            if cancel.load(Ordering::Relaxed) {
+                return Ok(Err(computation));
+            }
+        }
+        Ok(Ok(computation.run()))
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::nonblocking::*;
+    use std::time::Instant;
+
+    fn big(digits: &str) -> BigUint {
+        BigUint::parse_bytes(digits.as_bytes(), 10).unwrap()
+    }
+
+    #[test]
+    fn test_bigfib_with_mod() {
+        let runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+        let n = big("123456789012345678901234567890");
+        let modulo = big("1000000007");
+        let expected = crate::bigfib_with_mod(&n, &modulo);
+        runtime.block_on(async {
+            assert_eq!(bigfib_with_mod(&n, &modulo).await, Ok(expected.clone()));
+            assert_eq!(bigfib_with_mod(&n, &big("0")).await, Err(FibError::ZeroModulus));
+            assert_eq!(bigfib_with_mod_timeout(&n, &modulo, Duration::from_secs(3600)).await, Ok(expected.clone()));
+            assert_eq!(bigfib_with_mod_timeout(&n, &modulo, Duration::from_secs(0)).await, Err(FibError::Timeout));
+
+            let computation = BigFibComputation::new(&n, &modulo).unwrap();
+            assert_eq!(run_computation(computation).await, Ok(Ok(expected)));
+        });
+    }
+
+    #[test]
+    fn test_cancel_computation() {
+        let runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+        // Takes minutes unless cancelled.
+        let n = BigUint::from(1u32) << 4_000_000;
+        let modulo = (BigUint::from(1u32) << 4_000) - 1u32;
+        let task = {
+            let _guard = runtime.enter();
+            run_computation(BigFibComputation::new(&n, &modulo).unwrap())
+        };
+        std::thread::sleep(Duration::from_millis(200));
+        task.cancel();
+        let computation = match runtime.block_on(task) {
+            Ok(Err(computation)) => computation,
+            other => panic!("expected a cancelled computation, got {:?}", other.map(|result| result.is_ok())),
+        };
+        assert!(!computation.is_done());
+        assert_eq!(BigFibComputation::from_bytes(&computation.to_bytes()), Ok(computation));
+    }
+
+    #[test]
+    fn test_cancel_on_drop() {
+        // Each of these takes minutes unless cancelled.
+        let n = BigUint::from(1u32) << 4_000_000;
+        let modulo = (BigUint::from(1u32) << 4_000) - 1u32;
+        let spawners: [&dyn Fn() -> Box<dyn Send>; 3] = [
+            &|| Box::new(bigfib_with_mod(&n, &modulo)),
+            &|| Box::new(bigfib_with_mod_timeout(&n, &modulo, Duration::from_secs(3600))),
+            &|| Box::new(run_computation(BigFibComputation::new(&n, &modulo).unwrap())),
+        ];
+        for spawn in spawners.iter() {
+            let runtime = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+            let task = {
+                let _guard = runtime.enter();
+                spawn()
+            };
+            // Let the blocking thread get well into the computation.
+            std::thread::sleep(Duration::from_millis(200));
+            let start = Instant::now();
+            drop(task);
+            // Shutting down waits for the blocking thread, which only stops early if it was cancelled.
+            drop(runtime);
+            assert!(start.elapsed() < Duration::from_secs(10));
+        }
+    }
+}
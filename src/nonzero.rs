@@ -0,0 +1,83 @@
+use crate::FibError;
+use num_bigint::BigUint;
+use num::Zero;
+use std::convert::TryFrom;
+use std::fmt;
+
+
+/// A BigUint that is known to be non-zero, the BigUint counterpart of `std::num::NonZeroU64`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::NonZeroBigUint;
+/// use num::FromPrimitive;
+/// use num_bigint::BigUint;
+///
+/// assert!(NonZeroBigUint::new(FromPrimitive::from_u64(0).unwrap()).is_none());
+/// let modulo = NonZeroBigUint::new(FromPrimitive::from_u64(100).unwrap()).unwrap();
+/// assert_eq!(&BigUint::from_u64(100).unwrap(), modulo.get());
+/// ```
+///
+/// With the `serde` feature, it serializes as the BigUint, and refuses to deserialize a zero.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "BigUint", into = "BigUint"))]
+pub struct NonZeroBigUint(BigUint);
+
+impl NonZeroBigUint {
+    /// Wraps `n`, or returns None if it is zero.
+    pub fn new(n: BigUint) -> Option<NonZeroBigUint> {
+        if n.is_zero() {
+            None
+        } else {
+            Some(NonZeroBigUint(n))
+        }
+    }
+
+    /// The wrapped value.
+    pub fn get(&self) -> &BigUint {
+        &self.0
+    }
+
+    /// Unwraps the value.
+    pub fn into_inner(self) -> BigUint {
+        self.0
+    }
+}
+
+impl TryFrom<BigUint> for NonZeroBigUint {
+    type Error = FibError;
+
+    fn try_from(n: BigUint) -> Result<NonZeroBigUint, FibError> {
+        NonZeroBigUint::new(n).ok_or(FibError::ZeroModulus)
+    }
+}
+
+impl From<NonZeroBigUint> for BigUint {
+    fn from(n: NonZeroBigUint) -> BigUint {
+        n.0
+    }
+}
+
+impl fmt::Display for NonZeroBigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use crate::nonzero::*;
+
+    #[test]
+    fn test_serde() {
+        let n = NonZeroBigUint::new(BigUint::from(55u32)).unwrap();
+        let json = serde_json::to_string(&n).unwrap();
+        assert_eq!(json, serde_json::to_string(&BigUint::from(55u32)).unwrap());
+        assert_eq!(serde_json::from_str::<NonZeroBigUint>(&json).unwrap(), n);
+
+        let zero = serde_json::to_string(&BigUint::from(0u32)).unwrap();
+        assert!(serde_json::from_str::<NonZeroBigUint>(&zero).is_err());
+    }
+}
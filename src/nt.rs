@@ -0,0 +1,776 @@
+//! Number theory with modulo: modular inverses, the Chinese remainder theorem, square roots
+//! modulo a prime by Tonelli-Shanks, Jacobi symbols and primality, for u64 and, with std,
+//! BigUint.
+//!
+//! These are the building blocks of the pisano period and index searches, of
+//! `Algorithm::FiniteFieldBinet` and of splitting a modulo into coprime parts.
+
+// Without std, the internal helpers are only partly used.
+#![cfg_attr(not(feature = "std"), allow(dead_code))]
+
+#[cfg(feature = "std")]
+use num::{Integer, One, Zero};
+#[cfg(feature = "std")]
+use num_bigint::{BigInt, BigUint, Sign};
+
+use crate::{mul_mod, pow_mod, FibError};
+
+
+// Prime factorization, as (prime, exponent) pairs in increasing order, by trial division unless
+// the `factor` feature brings in Pollard's rho.
+#[cfg(feature = "std")]
+pub(crate) fn factorize(n: u64) -> Vec<(u64, u32)> {
+    #[cfg(feature = "factor")]
+    return crate::factor::factorize(n);
+    #[cfg(not(feature = "factor"))]
+    trial_division(n)
+}
+
+
+#[cfg(all(feature = "std", not(feature = "factor")))]
+fn trial_division(mut n: u64) -> Vec<(u64, u32)> {
+    let mut factors = Vec::new();
+    let mut p = 2;
+    while p <= n / p {
+        if n.is_multiple_of(p) {
+            let mut exponent = 0;
+            while n.is_multiple_of(p) {
+                n /= p;
+                exponent += 1;
+            }
+            factors.push((p, exponent));
+        }
+        p += if p == 2 { 1 } else { 2 };
+    }
+    if n > 1 {
+        factors.push((n, 1));
+    }
+    factors
+}
+
+
+// Euler's totient φ(n), the count of 1..=n coprime to n.
+#[cfg(feature = "std")]
+pub(crate) fn totient(n: u64) -> u64 {
+    factorize(n).into_iter().fold(n, |phi, (prime, _)| phi / prime * (prime - 1))
+}
+
+
+pub(crate) fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+
+pub(crate) fn lcm(a: u128, b: u128) -> u128 {
+    a / gcd(a, b) * b
+}
+
+
+/// Finds the inverse of a with modulo, the x in 0..modulo with a * x = 1, or None when a and
+/// modulo aren't coprime. Runtime O(log(modulo))
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::nt::mod_inverse;
+///
+/// assert_eq!(Some(4), mod_inverse(3, 11));
+/// assert_eq!(None, mod_inverse(4, 10));
+/// ```
+pub fn mod_inverse(a: u64, modulo: u64) -> Option<u64> {
+    try_mod_inverse(a, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `mod_inverse`, but returns an error instead of panicking.
+pub fn try_mod_inverse(a: u64, modulo: u64) -> Result<Option<u64>, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    let (mut old_r, mut r) = (a as i128 % modulo as i128, modulo as i128);
+    let (mut old_s, mut s) = (1i128, 0i128);
+    while r != 0 {
+        let q = old_r / r;
+        let next_r = old_r - q * r;
+        old_r = core::mem::replace(&mut r, next_r);
+        let next_s = old_s - q * s;
+        old_s = core::mem::replace(&mut s, next_s);
+    }
+    if old_r != 1 {
+        return Ok(if modulo == 1 { Some(0) } else { None });
+    }
+    Ok(Some(old_s.rem_euclid(modulo as i128) as u64))
+}
+
+
+/// Combines x = r1 (mod m1) and x = r2 (mod m2) into the x in 0..m1 * m2, for coprime m1 and m2.
+/// Runtime O(log(m2))
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::nt::crt;
+///
+/// // 23 is 2 mod 3 and 3 mod 5 (and 2 mod 7).
+/// assert_eq!(8, crt(2, 3, 3, 5));
+/// assert_eq!(23, crt(8, 15, 2, 7));
+/// ```
+pub fn crt(r1: u64, m1: u64, r2: u64, m2: u64) -> u64 {
+    try_crt(r1, m1, r2, m2).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `crt`, but returns an error instead of panicking when a modulus is 0, the moduli aren't
+/// coprime or their product overflows u64.
+pub fn try_crt(r1: u64, m1: u64, r2: u64, m2: u64) -> Result<u64, FibError> {
+    if m1 == 0 || m2 == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    let modulo = m1.checked_mul(m2).ok_or(FibError::OverflowRisk)?;
+    let inverse = try_mod_inverse(m1 % m2, m2)?.ok_or(FibError::InvalidArgument("moduli must be coprime"))?;
+    // x = r1 + m1 * ((r2 - r1) / m1 mod m2)
+    let diff = (r2 % m2 + m2 - r1 % m2) % m2;
+    let t = mul_mod(diff, inverse, m2);
+    Ok(((r1 as u128 % m1 as u128 + m1 as u128 * t as u128) % modulo as u128) as u64)
+}
+
+
+/// Finds a square root of c modulo a prime, by Tonelli-Shanks, or None when c isn't a square.
+/// The other root is prime minus it. Runtime O(log(prime)^2)
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::nt::sqrt_mod_prime;
+///
+/// let root = sqrt_mod_prime(5, 1_000_000_009).unwrap();
+/// assert_eq!(5, root * root % 1_000_000_009);
+/// // 5 is a square modulo p exactly when p is ±1 mod 5.
+/// assert_eq!(None, sqrt_mod_prime(5, 1_000_000_007));
+/// ```
+pub fn sqrt_mod_prime(c: u64, prime: u64) -> Option<u64> {
+    try_sqrt_mod_prime(c, prime).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `sqrt_mod_prime`, but returns an error instead of panicking when the modulo isn't prime.
+pub fn try_sqrt_mod_prime(c: u64, prime: u64) -> Result<Option<u64>, FibError> {
+    if prime == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    if !is_prime(prime) {
+        return Err(FibError::InvalidArgument("modulo must be prime"));
+    }
+    let c = c % prime;
+    if c == 0 || prime == 2 {
+        return Ok(Some(c));
+    }
+    if pow_mod(c, (prime - 1) / 2, prime) != 1 {
+        return Ok(None);
+    }
+
+    // prime - 1 = q * 2^s
+    let (mut q, mut s) = (prime - 1, 0);
+    while q % 2 == 0 {
+        q /= 2;
+        s += 1;
+    }
+    let mut z = 2;
+    while pow_mod(z, (prime - 1) / 2, prime) != prime - 1 {
+        z += 1;
+    }
+
+    let mut m = s;
+    let mut c_pow = pow_mod(z, q, prime);
+    let mut t = pow_mod(c, q, prime);
+    let mut root = pow_mod(c, q.div_ceil(2), prime);
+    while t != 1 {
+        let mut i = 0;
+        let mut t_pow = t;
+        while t_pow != 1 {
+            t_pow = mul_mod(t_pow, t_pow, prime);
+            i += 1;
+        }
+        let b = pow_mod(c_pow, 1 << (m - i - 1), prime);
+        m = i;
+        c_pow = mul_mod(b, b, prime);
+        t = mul_mod(t, c_pow, prime);
+        root = mul_mod(root, b, prime);
+    }
+    Ok(Some(root))
+}
+
+
+/// Finds the Jacobi symbol (a / n) for an odd n: 0 when a and n share a factor, and otherwise
+/// the product of the Legendre symbols over the prime factors of n, which for a prime n is 1
+/// exactly when a is a square modulo n. Runtime O(log(n))
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::nt::jacobi;
+///
+/// assert_eq!(1, jacobi(4, 7));
+/// assert_eq!(-1, jacobi(3, 7));
+/// assert_eq!(0, jacobi(6, 9));
+/// // 2 isn't a square modulo 15, but (2 / 15) = (2 / 3)(2 / 5) = 1.
+/// assert_eq!(1, jacobi(2, 15));
+/// ```
+pub fn jacobi(a: u64, n: u64) -> i32 {
+    try_jacobi(a, n).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `jacobi`, but returns an error instead of panicking when n is even.
+pub fn try_jacobi(mut a: u64, mut n: u64) -> Result<i32, FibError> {
+    if n.is_multiple_of(2) {
+        return Err(FibError::InvalidArgument("n must be odd"));
+    }
+    a %= n;
+    let mut result = 1;
+    while a != 0 {
+        while a.is_multiple_of(2) {
+            a /= 2;
+            if n % 8 == 3 || n % 8 == 5 {
+                result = -result;
+            }
+        }
+        // Quadratic reciprocity.
+        core::mem::swap(&mut a, &mut n);
+        if a % 4 == 3 && n % 4 == 3 {
+            result = -result;
+        }
+        a %= n;
+    }
+    Ok(if n == 1 { result } else { 0 })
+}
+
+
+/// Checks whether n is prime, by Miller-Rabin with the first 12 primes as bases, which has no
+/// exceptions below 2^64. Runtime O(log(n))
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::nt::is_prime;
+///
+/// assert!(is_prime(1_000_000_007));
+/// assert!(!is_prime(1_000_000_007 * 3));
+/// assert!(is_prime(18_446_744_073_709_551_557));
+/// ```
+pub fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    if let Some(&base) = BASES.iter().find(|&&base| n.is_multiple_of(base)) {
+        return n == base;
+    }
+    // n - 1 = d * 2^s
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    BASES.iter().all(|&base| {
+        let mut x = pow_mod(base, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+
+/// `mod_inverse` for BigUint. Runtime O(log(modulo)^2)
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::nt::big_mod_inverse;
+/// use num_bigint::BigUint;
+///
+/// let modulo = (BigUint::from(1u32) << 127) - 1u32;
+/// let inverse = big_mod_inverse(&BigUint::from(3u32), &modulo).unwrap();
+/// assert_eq!(BigUint::from(1u32), inverse * 3u32 % &modulo);
+/// ```
+#[cfg(feature = "std")]
+pub fn big_mod_inverse(a: &BigUint, modulo: &BigUint) -> Option<BigUint> {
+    try_big_mod_inverse(a, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `big_mod_inverse`, but returns an error instead of panicking.
+#[cfg(feature = "std")]
+pub fn try_big_mod_inverse(a: &BigUint, modulo: &BigUint) -> Result<Option<BigUint>, FibError> {
+    if modulo.is_zero() {
+        return Err(FibError::ZeroModulus);
+    }
+    let signed_modulo = BigInt::from_biguint(Sign::Plus, modulo.clone());
+    let (mut old_r, mut r) = (BigInt::from_biguint(Sign::Plus, a % modulo), signed_modulo.clone());
+    let (mut old_s, mut s) = (BigInt::one(), BigInt::zero());
+    while !r.is_zero() {
+        let q = &old_r / &r;
+        let next_r = &old_r - &q * &r;
+        old_r = std::mem::replace(&mut r, next_r);
+        let next_s = &old_s - &q * &s;
+        old_s = std::mem::replace(&mut s, next_s);
+    }
+    if !old_r.is_one() {
+        return Ok(if modulo.is_one() { Some(BigUint::zero()) } else { None });
+    }
+    Ok(old_s.mod_floor(&signed_modulo).to_biguint())
+}
+
+
+/// `crt` for BigUint, with no bound on the product of the moduli. Runtime O(log(m2)^2)
+#[cfg(feature = "std")]
+pub fn big_crt(r1: &BigUint, m1: &BigUint, r2: &BigUint, m2: &BigUint) -> BigUint {
+    try_big_crt(r1, m1, r2, m2).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `big_crt`, but returns an error instead of panicking when a modulus is 0 or the moduli
+/// aren't coprime.
+#[cfg(feature = "std")]
+pub fn try_big_crt(r1: &BigUint, m1: &BigUint, r2: &BigUint, m2: &BigUint) -> Result<BigUint, FibError> {
+    if m1.is_zero() || m2.is_zero() {
+        return Err(FibError::ZeroModulus);
+    }
+    let inverse = try_big_mod_inverse(&(m1 % m2), m2)?.ok_or(FibError::InvalidArgument("moduli must be coprime"))?;
+    let diff = (r2 % m2 + m2 - r1 % m2) % m2;
+    let t = diff * inverse % m2;
+    Ok((r1 % m1 + m1 * t) % (m1 * m2))
+}
+
+
+/// `sqrt_mod_prime` for BigUint. Only checks that the modulo is a probable prime, see
+/// `big_is_probable_prime`. Runtime O(log(prime)^4)
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::nt::big_sqrt_mod_prime;
+/// use num_bigint::BigUint;
+///
+/// let prime = (BigUint::from(1u32) << 127) - 1u32;
+/// let root = big_sqrt_mod_prime(&BigUint::from(2u32), &prime).unwrap();
+/// assert_eq!(BigUint::from(2u32), &root * &root % &prime);
+/// assert_eq!(None, big_sqrt_mod_prime(&BigUint::from(5u32), &prime));
+/// ```
+#[cfg(feature = "std")]
+pub fn big_sqrt_mod_prime(c: &BigUint, prime: &BigUint) -> Option<BigUint> {
+    try_big_sqrt_mod_prime(c, prime).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `big_sqrt_mod_prime`, but returns an error instead of panicking when the modulo isn't a
+/// probable prime.
+#[cfg(feature = "std")]
+pub fn try_big_sqrt_mod_prime(c: &BigUint, prime: &BigUint) -> Result<Option<BigUint>, FibError> {
+    if prime.is_zero() {
+        return Err(FibError::ZeroModulus);
+    }
+    if !big_is_probable_prime(prime) {
+        return Err(FibError::InvalidArgument("modulo must be prime"));
+    }
+    let one = BigUint::one();
+    let c = c % prime;
+    if c.is_zero() || prime == &BigUint::from(2u32) {
+        return Ok(Some(c));
+    }
+    let minus_one = prime - 1u32;
+    let half = &minus_one >> 1;
+    if c.modpow(&half, prime) != one {
+        return Ok(None);
+    }
+
+    // prime - 1 = q * 2^s
+    let mut s = 0;
+    let mut q = minus_one.clone();
+    while q.is_even() {
+        q >>= 1;
+        s += 1;
+    }
+    let mut z = BigUint::from(2u32);
+    while z.modpow(&half, prime) != minus_one {
+        z += 1u32;
+    }
+
+    let mut m = s;
+    let mut c_pow = z.modpow(&q, prime);
+    let mut t = c.modpow(&q, prime);
+    let mut root = c.modpow(&((&q + 1u32) >> 1), prime);
+    while t != one {
+        let mut i = 0;
+        let mut t_pow = t.clone();
+        while t_pow != one {
+            // Only a composite modulo keeps t from reaching 1 in under m squarings.
+            if i + 1 >= m {
+                return Err(FibError::InvalidArgument("modulo must be prime"));
+            }
+            t_pow = &t_pow * &t_pow % prime;
+            i += 1;
+        }
+        let b = c_pow.modpow(&(BigUint::one() << (m - i - 1)), prime);
+        m = i;
+        c_pow = &b * &b % prime;
+        t = t * &c_pow % prime;
+        root = root * b % prime;
+    }
+    Ok(Some(root))
+}
+
+
+/// `jacobi` for BigUint. Runtime O(log(n)^2)
+#[cfg(feature = "std")]
+pub fn big_jacobi(a: &BigUint, n: &BigUint) -> i32 {
+    try_big_jacobi(a, n).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `big_jacobi`, but returns an error instead of panicking when n is even.
+#[cfg(feature = "std")]
+pub fn try_big_jacobi(a: &BigUint, n: &BigUint) -> Result<i32, FibError> {
+    if n.is_even() {
+        return Err(FibError::InvalidArgument("n must be odd"));
+    }
+    let (mut a, mut n) = (a % n, n.clone());
+    let mut result = 1;
+    let low_bits = |x: &BigUint, mask: u32| (x % (mask + 1)).to_u32_digits().first().copied().unwrap_or(0);
+    while !a.is_zero() {
+        let zeros = big_trailing_zeros(&a);
+        a >>= zeros;
+        if zeros % 2 == 1 && matches!(low_bits(&n, 7), 3 | 5) {
+            result = -result;
+        }
+        std::mem::swap(&mut a, &mut n);
+        if low_bits(&a, 3) == 3 && low_bits(&n, 3) == 3 {
+            result = -result;
+        }
+        a %= &n;
+    }
+    Ok(if n.is_one() { result } else { 0 })
+}
+
+
+// The number of trailing zero bits of a nonzero x.
+#[cfg(feature = "std")]
+fn big_trailing_zeros(x: &BigUint) -> usize {
+    let digits = x.to_u32_digits();
+    let zero_digits = digits.iter().take_while(|&&digit| digit == 0).count();
+    zero_digits * 32 + digits.get(zero_digits).map_or(0, |digit| digit.trailing_zeros() as usize)
+}
+
+
+/// Checks whether n is a probable prime by the Baillie-PSW test: trial division, a Miller-Rabin
+/// round to base 2 and a strong Lucas test. No composite is known to pass it, and none exists
+/// below 2^64. Runtime O(log(n)^3)
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::nt::big_is_probable_prime;
+/// use num_bigint::BigUint;
+///
+/// assert!(big_is_probable_prime(&((BigUint::from(1u32) << 521) - 1u32)));
+/// assert!(!big_is_probable_prime(&((BigUint::from(1u32) << 523) - 1u32)));
+/// ```
+#[cfg(feature = "std")]
+pub fn big_is_probable_prime(n: &BigUint) -> bool {
+    if n < &BigUint::from(2u32) {
+        return false;
+    }
+    for divisor in (3..1_000u32).step_by(2).chain(core::iter::once(2)) {
+        if (n % divisor).is_zero() {
+            return n == &BigUint::from(divisor);
+        }
+    }
+    if n < &BigUint::from(1_000_000u32) {
+        return true;
+    }
+    is_strong_probable_prime(n, &BigUint::from(2u32)) && is_strong_lucas_probable_prime(n)
+}
+
+
+// Miller-Rabin to one base, for an odd n > base.
+#[cfg(feature = "std")]
+fn is_strong_probable_prime(n: &BigUint, base: &BigUint) -> bool {
+    let minus_one = n - 1u32;
+    let s = big_trailing_zeros(&minus_one);
+    let d = &minus_one >> s;
+    let mut x = base.modpow(&d, n);
+    if x.is_one() || x == minus_one {
+        return true;
+    }
+    for _ in 1..s {
+        x = &x * &x % n;
+        if x == minus_one {
+            return true;
+        }
+    }
+    false
+}
+
+
+// The strong Lucas test with Selfridge's parameters: the first D of 5, -7, 9, -11, ... with
+// (D / n) = -1, P = 1 and Q = (1 - D) / 4. For an odd n without small factors.
+#[cfg(feature = "std")]
+fn is_strong_lucas_probable_prime(n: &BigUint) -> bool {
+    // Squares have no such D.
+    let root = n.sqrt();
+    if &(&root * &root) == n {
+        return false;
+    }
+    let mut d = 5i64;
+    let reduce = |x: i64| {
+        let magnitude = BigUint::from(x.unsigned_abs()) % n;
+        if x < 0 && !magnitude.is_zero() { n - magnitude } else { magnitude }
+    };
+    while try_big_jacobi(&reduce(d), n) != Ok(-1) {
+        d = if d > 0 { -(d + 2) } else { -d + 2 };
+    }
+    let (d_mod, q) = (reduce(d), reduce((1 - d) / 4));
+    let half = |x: BigUint| if x.is_even() { x >> 1 } else { (x + n) >> 1 };
+
+    // n + 1 = k * 2^s, with U(k), V(k) and Q^k from the top bit of k down, for P = 1.
+    let plus_one = n + 1u32;
+    let s = big_trailing_zeros(&plus_one);
+    let k = &plus_one >> s;
+    let (mut u, mut v, mut q_k) = (BigUint::one(), BigUint::one(), q.clone());
+    for bit in (0..k.bits() - 1).rev() {
+        // U(2j) = U(j) V(j), V(2j) = V(j)^2 - 2Q^j
+        u = &u * &v % n;
+        v = (&v * &v + n + n - (&q_k + &q_k) % n) % n;
+        q_k = &q_k * &q_k % n;
+        if (&k >> bit).is_odd() {
+            // U(j + 1) = (U(j) + V(j)) / 2, V(j + 1) = (D U(j) + V(j)) / 2
+            let next_u = half((&u + &v) % n);
+            v = half((&d_mod * &u + &v) % n);
+            u = next_u;
+            q_k = &q_k * &q % n;
+        }
+    }
+    if u.is_zero() || v.is_zero() {
+        return true;
+    }
+    for _ in 1..s {
+        v = (&v * &v + n + n - (&q_k + &q_k) % n) % n;
+        if v.is_zero() {
+            return true;
+        }
+        q_k = &q_k * &q_k % n;
+    }
+    false
+}
+
+
+// Prime factorization of a positive n by trial division and Pollard's rho, as (prime, exponent)
+// pairs in increasing order.
+#[cfg(feature = "std")]
+pub(crate) fn big_factorize(n: &BigUint) -> Vec<(BigUint, u32)> {
+    let mut primes = Vec::new();
+    big_prime_factors(n.clone(), &mut primes);
+    primes.sort();
+    let mut factors: Vec<(BigUint, u32)> = Vec::new();
+    for prime in primes {
+        match factors.last_mut() {
+            Some((last, exponent)) if *last == prime => *exponent += 1,
+            _ => factors.push((prime, 1)),
+        }
+    }
+    factors
+}
+
+
+// The prime factors of a positive n with repetition, in no particular order.
+#[cfg(feature = "std")]
+pub(crate) fn big_prime_factors(mut n: BigUint, primes: &mut Vec<BigUint>) {
+    for prime in 2..1_000u32 {
+        while (&n % prime).is_zero() {
+            n /= prime;
+            primes.push(BigUint::from(prime));
+        }
+    }
+    let mut stack = vec![n];
+    while let Some(n) = stack.pop() {
+        if n.is_one() {
+            continue;
+        }
+        if big_is_probable_prime(&n) {
+            primes.push(n);
+            continue;
+        }
+        let factor = pollard_rho(&n);
+        stack.push(&n / &factor);
+        stack.push(factor);
+    }
+}
+
+
+// A nontrivial factor of a composite n without factors below 1000, by Brent's variant of
+// Pollard's rho, trying x^2 + c for c = 1, 2, ... until one splits n.
+#[cfg(feature = "std")]
+fn pollard_rho(n: &BigUint) -> BigUint {
+    const BATCH: u64 = 128;
+    let distance = |a: &BigUint, b: &BigUint| if a > b { a - b } else { b - a };
+    for c in 1u32.. {
+        let step = |x: &BigUint| (x * x + c) % n;
+        let (mut x, mut y, mut batch_start) = (BigUint::from(2u32), BigUint::from(2u32), BigUint::from(2u32));
+        let (mut product, mut factor) = (BigUint::one(), BigUint::one());
+        let mut length = 1;
+        while factor.is_one() {
+            x = y.clone();
+            for _ in 0..length {
+                y = step(&y);
+            }
+            let mut k = 0;
+            while k < length && factor.is_one() {
+                // One gcd per batch of differences multiplied together.
+                batch_start = y.clone();
+                for _ in 0..BATCH.min(length - k) {
+                    y = step(&y);
+                    product = product * distance(&x, &y) % n;
+                }
+                factor = product.gcd(n);
+                k += BATCH;
+            }
+            length *= 2;
+        }
+        if &factor == n {
+            // The batch multiplied in every factor at once, so retrace it one step at a time.
+            loop {
+                batch_start = step(&batch_start);
+                factor = distance(&x, &batch_start).gcd(n);
+                if !factor.is_one() {
+                    break;
+                }
+            }
+        }
+        if &factor != n {
+            return factor;
+        }
+    }
+    unreachable!()
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::nt::*;
+
+    #[test]
+    fn test_factorize() {
+        assert_eq!(factorize(1), vec![]);
+        assert_eq!(factorize(2), vec![(2, 1)]);
+        assert_eq!(factorize(360), vec![(2, 3), (3, 2), (5, 1)]);
+        assert_eq!(factorize(1_000_000_007), vec![(1_000_000_007, 1)]);
+        assert_eq!(factorize(2_000_000_014), vec![(2, 1), (1_000_000_007, 1)]);
+    }
+
+    #[test]
+    fn test_mod_inverse_and_crt() {
+        assert_eq!(mod_inverse(3, 7), Some(5));
+        assert_eq!(mod_inverse(6, 9), None);
+        assert_eq!(mod_inverse(5, 1), Some(0));
+        assert_eq!(crt(2, 3, 3, 5), 8);
+        assert_eq!(crt(0, 1, 4, 7), 4);
+        assert_eq!(try_mod_inverse(3, 0), Err(FibError::ZeroModulus));
+        assert_eq!(try_crt(1, 4, 1, 6), Err(FibError::InvalidArgument("moduli must be coprime")));
+        assert_eq!(try_crt(1, 1 << 32, 1, (1 << 32) + 1), Err(FibError::OverflowRisk));
+        assert_eq!(try_crt(1, 0, 1, 3), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_is_prime() {
+        let sieve: Vec<bool> = (0..10_000u64).map(|n| n >= 2 && (2..n).take_while(|d| d * d <= n).all(|d| n % d != 0)).collect();
+        for n in 0..10_000u64 {
+            assert_eq!(is_prime(n), sieve[n as usize], "n = {}", n);
+            assert_eq!(big_is_probable_prime(&BigUint::from(n)), sieve[n as usize], "n = {}", n);
+        }
+        // Strong pseudoprimes to the smaller bases, and a Carmichael number.
+        for &n in &[2_047u64, 1_373_653, 3_215_031_751, 3_825_123_056_546_413_051, 561] {
+            assert!(!is_prime(n), "n = {}", n);
+        }
+        assert!(is_prime(u64::MAX - 58));
+        assert!(!is_prime(u64::MAX));
+    }
+
+    #[test]
+    fn test_baillie_psw() {
+        for n in (1_000_000_000_000..1_000_000_020_000).chain(u64::MAX - 20_000..=u64::MAX) {
+            assert_eq!(big_is_probable_prime(&BigUint::from(n)), is_prime(n), "n = {}", n);
+        }
+        // Strong pseudoprimes to base 2, one of them a square.
+        for &n in &[1_194_649u128, 3_825_123_056_546_413_051, 318_665_857_834_031_151_167_461] {
+            assert!(!big_is_probable_prime(&BigUint::from(n)), "n = {}", n);
+        }
+        let mersenne = |p: usize| (BigUint::from(1u32) << p) - 1u32;
+        for p in 2..300 {
+            let expected = [2, 3, 5, 7, 13, 17, 19, 31, 61, 89, 107, 127].contains(&p);
+            assert_eq!(big_is_probable_prime(&mersenne(p)), expected, "2^{} - 1", p);
+        }
+        assert!(!big_is_probable_prime(&(mersenne(61) * mersenne(89))));
+    }
+
+    #[test]
+    fn test_jacobi() {
+        for n in (1..200u64).step_by(2) {
+            for a in 0..200u64 {
+                // The product of Legendre symbols, each by Euler's criterion.
+                let expected = factorize(n).iter().fold(1, |product, &(prime, exponent)| {
+                    let legendre: i32 = match pow_mod(a, (prime - 1) / 2, prime) {
+                        0 => 0,
+                        1 => 1,
+                        _ => -1,
+                    };
+                    product * legendre.pow(exponent)
+                });
+                assert_eq!(jacobi(a, n), expected, "({} / {})", a, n);
+                assert_eq!(big_jacobi(&BigUint::from(a), &BigUint::from(n)), expected, "({} / {})", a, n);
+            }
+        }
+        assert_eq!(try_jacobi(1, 10), Err(FibError::InvalidArgument("n must be odd")));
+        assert_eq!(try_big_jacobi(&BigUint::from(1u32), &BigUint::from(0u32)), Err(FibError::InvalidArgument("n must be odd")));
+    }
+
+    #[test]
+    fn test_big_variants() {
+        let prime = (BigUint::from(1u32) << 127) - 1u32;
+        let other = (BigUint::from(1u32) << 89) - 1u32;
+        for c in 0..50u32 {
+            let c = BigUint::from(c);
+            match big_sqrt_mod_prime(&c, &prime) {
+                Some(root) => assert_eq!(&root * &root % &prime, c),
+                None => assert_eq!(big_jacobi(&c, &prime), -1),
+            }
+            if !c.is_zero() {
+                assert_eq!(big_mod_inverse(&c, &prime).unwrap() * &c % &prime, BigUint::one());
+            }
+        }
+        let x = big_crt(&BigUint::from(12_345u32), &prime, &BigUint::from(678u32), &other);
+        assert_eq!((&x % &prime, &x % &other), (BigUint::from(12_345u32), BigUint::from(678u32)));
+        for &(a, modulo) in &[(3u64, 7u64), (6, 9), (5, 1), (10, 1_000_000_007)] {
+            let expected = mod_inverse(a, modulo).map(BigUint::from);
+            assert_eq!(big_mod_inverse(&BigUint::from(a), &BigUint::from(modulo)), expected);
+        }
+        for &prime in &[2u64, 3, 13, 1_000_000_007] {
+            for c in 0..20 {
+                let root = big_sqrt_mod_prime(&BigUint::from(c), &BigUint::from(prime));
+                assert_eq!(root.is_some(), sqrt_mod_prime(c, prime).is_some());
+            }
+        }
+        assert_eq!(try_big_sqrt_mod_prime(&BigUint::from(4u32), &(&prime * &other)), Err(FibError::InvalidArgument("modulo must be prime")));
+        assert_eq!(try_big_mod_inverse(&prime, &BigUint::zero()), Err(FibError::ZeroModulus));
+        assert_eq!(try_big_crt(&prime, &prime, &other, &prime), Err(FibError::InvalidArgument("moduli must be coprime")));
+        assert_eq!(try_sqrt_mod_prime(4, 15), Err(FibError::InvalidArgument("modulo must be prime")));
+        assert_eq!(sqrt_mod_prime(3, 2), Some(1));
+    }
+
+    #[test]
+    fn test_sqrt_mod_prime() {
+        for &prime in &[3u64, 5, 7, 13, 17, 41, 1_000_000_007] {
+            for c in 0..50 {
+                match sqrt_mod_prime(c, prime) {
+                    Some(root) => assert_eq!(mul_mod(root, root, prime), c % prime),
+                    None => assert!((0..prime.min(1_000)).all(|x| mul_mod(x, x, prime) != c % prime)),
+                }
+            }
+        }
+    }
+}
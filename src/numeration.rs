@@ -0,0 +1,464 @@
+//! Writing integers as sums of fibonacci numbers.
+//!
+//! Every positive integer is a sum of non-consecutive fibonacci numbers fib(i), i >= 2, in
+//! exactly one way, its Zeckendorf representation. Its digits, from the largest fib(i) down to
+//! fib(2), are the fibonacci coding of the integer. The iterators here find them one at a time
+//! from the top, holding only the rest of the integer and two fibonacci numbers, so integers of
+//! millions of digits don't need a table of every fibonacci number below them.
+//!
+//! The lucas numbers L(0) = 2, L(1) = 1, L(i) = L(i - 1) + L(i - 2) do the same, with every
+//! positive integer a sum of non-consecutive lucas numbers in exactly one way that doesn't use
+//! both L(0) and L(2).
+//!
+//! Base φ writes integers as sums of non-consecutive powers of the golden ratio instead, such as
+//! 2 = φ + φ^-2, written 10.01. Every nonnegative integer has exactly one such standard form,
+//! with finitely many digits.
+
+use num::{One, Signed, Zero};
+use num_bigint::{BigInt, BigUint};
+
+use crate::{exact_bigfib_pair, FibError};
+
+
+/// The indices i of the fibonacci numbers fib(i) in the Zeckendorf representation of n, from
+/// the largest down. They're at least 2 and never consecutive, and there are none for 0.
+/// Runtime O(log(n)) per index, using O(log(n)) memory
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::numeration::zeckendorf_indices;
+/// use num_bigint::BigUint;
+///
+/// // 100 = fib(11) + fib(6) + fib(4) = 89 + 8 + 3
+/// assert_eq!(vec![11, 6, 4], zeckendorf_indices(&BigUint::from(100u32)).collect::<Vec<u64>>());
+/// ```
+pub fn zeckendorf_indices(n: &BigUint) -> ZeckendorfIndices {
+    ZeckendorfIndices { digits: zeckendorf_digits(n) }
+}
+
+
+/// The Zeckendorf digits of n, whether each of fib(k), fib(k - 1), ..., fib(2) is in its
+/// representation, for the largest fib(k) <= n. The first digit is always true, and there are
+/// none for 0. Runtime O(log(n)) per digit, using O(log(n)) memory
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::numeration::zeckendorf_digits;
+/// use num_bigint::BigUint;
+///
+/// // 100 = 89 + 8 + 3, from fib(11) = 89 down to fib(2) = 1
+/// let digits: String = zeckendorf_digits(&BigUint::from(100u32)).map(|digit| if digit { '1' } else { '0' }).collect();
+/// assert_eq!("1000010100", digits);
+/// ```
+pub fn zeckendorf_digits(n: &BigUint) -> ZeckendorfDigits {
+    if n.is_zero() {
+        return ZeckendorfDigits { rest: BigUint::zero(), index: 1, fib: BigUint::one(), next: BigUint::one() };
+    }
+    // fib(k) >= φ^(k - 2) > n for k >= bits * log_φ(2) + 2, a few steps above the largest fib(k) <= n.
+    let mut index = (n.bits() as f64 * 1.4405).ceil() as u64 + 2;
+    let (mut fib, mut next) = exact_bigfib_pair(index);
+    while fib > *n {
+        let previous = next - &fib;
+        next = fib;
+        fib = previous;
+        index -= 1;
+    }
+    ZeckendorfDigits { rest: n.clone(), index, fib, next }
+}
+
+
+/// The integer with these Zeckendorf digits, from fib(k) down to fib(2), as from
+/// `zeckendorf_digits`. Consecutive true digits are fine too. Runtime O(k * log(n))
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::numeration::{from_zeckendorf_digits, zeckendorf_digits};
+/// use num_bigint::BigUint;
+///
+/// let n = num::pow(BigUint::from(10u32), 1_000);
+/// assert_eq!(n, from_zeckendorf_digits(zeckendorf_digits(&n)));
+/// ```
+pub fn from_zeckendorf_digits<I: IntoIterator<Item = bool>>(digits: I) -> BigUint {
+    // With the digits so far ending at fib(2), their sum and the sum with every index one lower.
+    // Another digit moves every index up one, as fib(i + 1) = fib(i) + fib(i - 1).
+    let (mut sum, mut lower) = (BigUint::zero(), BigUint::zero());
+    for digit in digits {
+        let shifted = &sum + &lower;
+        lower = sum;
+        sum = shifted;
+        if digit {
+            sum += 1u32;
+            lower += 1u32;
+        }
+    }
+    sum
+}
+
+
+/// The indices i of the lucas numbers L(i) in the lucas representation of n, from the largest
+/// down. They're never consecutive, 0 and 2 aren't both there, and there are none for 0.
+/// Runtime O(log(n)^2)
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::numeration::lucas_indices;
+/// use num_bigint::BigUint;
+///
+/// // 100 = L(9) + L(6) + L(3) + L(0) = 76 + 18 + 4 + 2
+/// assert_eq!(vec![9, 6, 3, 0], lucas_indices(&BigUint::from(100u32)));
+/// ```
+pub fn lucas_indices(n: &BigUint) -> Vec<u64> {
+    let mut indices = Vec::new();
+    if n.is_zero() {
+        return indices;
+    }
+    // L(k) > φ^k - 1 >= n for k > bits * log_φ(2), with (lucas, above) = (L(k), L(k + 1)) from
+    // L(k) = 2fib(k + 1) - fib(k) and L(k + 1) = 2fib(k) + fib(k + 1).
+    let mut index = (n.bits() as f64 * 1.4405).ceil() as u64 + 1;
+    let (fib, next) = exact_bigfib_pair(index);
+    let (mut lucas, mut above) = ((&next << 1) - &fib, (&fib << 1) + next);
+    let mut rest = n.clone();
+    // Greedily down to L(2) = 3, which leaves less than L(k - 1) after taking L(k), and then
+    // less than 3 at the end for L(1) = 1 or L(0) = 2.
+    while index >= 2 && !rest.is_zero() {
+        if lucas <= rest {
+            rest -= &lucas;
+            indices.push(index);
+        }
+        let below = &above - &lucas;
+        above = std::mem::replace(&mut lucas, below);
+        index -= 1;
+    }
+    if rest == BigUint::one() {
+        indices.push(1);
+    } else if !rest.is_zero() {
+        indices.push(0);
+    }
+    indices
+}
+
+
+/// The sum of the lucas numbers L(i) for these indices, in any order and not necessarily a lucas
+/// representation. Runtime O(k * log(L)) for k indices up to L
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::numeration::{from_lucas_indices, lucas_indices};
+/// use num_bigint::BigUint;
+///
+/// let n = num::pow(BigUint::from(10u32), 1_000);
+/// assert_eq!(n, from_lucas_indices(&lucas_indices(&n)));
+/// ```
+pub fn from_lucas_indices(indices: &[u64]) -> BigUint {
+    indices.iter().fold(BigUint::zero(), |sum, &index| {
+        let (fib, next) = exact_bigfib_pair(index);
+        sum + (next << 1) - fib
+    })
+}
+
+
+/// The exponents k of the powers φ^k in the standard base φ representation of n, from the
+/// largest down. They're never consecutive, the negative ones are the digits after the point,
+/// and there are none for 0. Runtime O(log(n)^2)
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::numeration::base_phi_exponents;
+/// use num_bigint::BigUint;
+///
+/// // 5 = φ^3 + φ^-1 + φ^-4
+/// assert_eq!(vec![3, -1, -4], base_phi_exponents(&BigUint::from(5u32)));
+/// ```
+pub fn base_phi_exponents(n: &BigUint) -> Vec<i64> {
+    let mut exponents = Vec::new();
+    if n.is_zero() {
+        return exponents;
+    }
+    // φ^k > 2^bits > n for k > bits * log_φ(2), with (power, above) = (φ^k, φ^(k + 1)).
+    let mut exponent = (n.bits() as f64 * 1.4405).ceil() as i64 + 1;
+    let (fib, next) = exact_bigfib_pair(exponent as u64 - 1);
+    let (fib, next) = (BigInt::from(fib), BigInt::from(next));
+    let mut power = (fib.clone(), next.clone());
+    let mut above = (next.clone(), fib + next);
+    let mut rest = (BigInt::from(n.clone()), BigInt::zero());
+    // Greedily, which leaves less than φ^(k - 1) after taking φ^k, so the exponents can't be
+    // consecutive, and ends for integers.
+    while !rest.0.is_zero() || !rest.1.is_zero() {
+        if golden_sign(&(&rest.0 - &power.0), &(&rest.1 - &power.1)) >= 0 {
+            rest = (&rest.0 - &power.0, &rest.1 - &power.1);
+            exponents.push(exponent);
+        }
+        let below = (&above.0 - &power.0, &above.1 - &power.1);
+        above = std::mem::replace(&mut power, below);
+        exponent -= 1;
+    }
+    exponents
+}
+
+
+/// The standard base φ representation of n, with a point before the digits of negative powers.
+/// Runtime O(log(n)^2)
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::numeration::to_base_phi;
+/// use num_bigint::BigUint;
+///
+/// let digits: Vec<String> = (0..6u32).map(|n| to_base_phi(&BigUint::from(n))).collect();
+/// assert_eq!(vec!["0", "1", "10.01", "100.01", "101.01", "1000.1001"], digits);
+/// ```
+pub fn to_base_phi(n: &BigUint) -> String {
+    let exponents = base_phi_exponents(n);
+    let (top, bottom) = match (exponents.first(), exponents.last()) {
+        (Some(&top), Some(&bottom)) => (top, bottom.min(0)),
+        _ => return String::from("0"),
+    };
+    let mut digits = String::new();
+    let mut used = exponents.iter().peekable();
+    for exponent in (bottom..=top).rev() {
+        if exponent == -1 {
+            digits.push('.');
+        }
+        digits.push(if used.next_if_eq(&&exponent).is_some() { '1' } else { '0' });
+    }
+    digits
+}
+
+
+/// The integer with these base φ digits, which may have one point, and needn't be in standard
+/// form, so `to_base_phi(&from_base_phi(digits)?)` is its standard form. Runtime
+/// O(digits^2)
+///
+/// Returns an error if there are other characters or no digits, or the digits don't add up to
+/// an integer.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::numeration::{from_base_phi, to_base_phi};
+/// use fast_fibonacci::FibError;
+/// use num_bigint::BigUint;
+///
+/// assert_eq!(Ok(BigUint::from(5u32)), from_base_phi("1000.1001"));
+/// // φ^-1 + φ^-2 = 1
+/// assert_eq!("1", to_base_phi(&from_base_phi("0.11").unwrap()));
+/// assert_eq!(Err(FibError::InvalidArgument("base φ digits must add up to an integer")), from_base_phi("10"));
+/// ```
+pub fn from_base_phi(digits: &str) -> Result<BigUint, FibError> {
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let all_digits = whole.bytes().chain(fraction.bytes());
+    if whole.len() + fraction.len() == 0 || !all_digits.clone().all(|digit| digit == b'0' || digit == b'1') {
+        return Err(FibError::InvalidArgument("base φ digits must be 0s and 1s with at most one point"));
+    }
+    // The digits as a whole number a + bφ in Horner's rule, since (a + bφ)φ = b + (a + b)φ.
+    let (mut a, mut b) = (BigInt::zero(), BigInt::zero());
+    for digit in all_digits {
+        let shifted = (b.clone(), a + &b);
+        a = shifted.0 + u32::from(digit - b'0');
+        b = shifted.1;
+    }
+    // Then times φ^-f for f digits after the point, φ^-f = fib(-f - 1) + fib(-f)φ.
+    let f = fraction.len() as u64;
+    let (fib, next) = exact_bigfib_pair(f);
+    let sign = if f.is_multiple_of(2) { BigInt::one() } else { -BigInt::one() };
+    let (c, d) = (&sign * BigInt::from(next), -&sign * BigInt::from(fib));
+    // (a + bφ)(c + dφ) = ac + bd + (ad + bc + bd)φ, as φ^2 = φ + 1.
+    let (whole, irrational) = (&a * &c + &b * &d, &a * &d + &b * &c + &b * &d);
+    if !irrational.is_zero() {
+        return Err(FibError::InvalidArgument("base φ digits must add up to an integer"));
+    }
+    Ok(whole.to_biguint().unwrap())
+}
+
+
+// The sign of x + yφ = (2x + y + y√5) / 2.
+fn golden_sign(x: &BigInt, y: &BigInt) -> i32 {
+    let rational: BigInt = x * 2 + y;
+    let sign = |value: &BigInt| if value.is_positive() { 1 } else if value.is_negative() { -1 } else { 0 };
+    let (p, q) = (sign(&rational), sign(y));
+    if p == q || q == 0 {
+        return p;
+    }
+    if p == 0 {
+        return q;
+    }
+    // Opposite signs, so whichever of the two parts is larger wins.
+    let (rational_squared, root_squared) = (&rational * &rational, y * y * 5);
+    if rational_squared > root_squared { p } else { q }
+}
+
+
+/// Iterator returned by `zeckendorf_digits`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZeckendorfDigits {
+    rest: BigUint,
+    // The digit of fib(index) comes next, with next = fib(index + 1).
+    index: u64,
+    fib: BigUint,
+    next: BigUint,
+}
+
+impl Iterator for ZeckendorfDigits {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        if self.index < 2 {
+            return None;
+        }
+        // Once nothing is left, the rest are zeros without stepping the fibonacci numbers down.
+        let digit = !self.rest.is_zero() && self.fib <= self.rest;
+        if digit {
+            self.rest -= &self.fib;
+        }
+        if !self.rest.is_zero() {
+            let previous = &self.next - &self.fib;
+            self.next = std::mem::replace(&mut self.fib, previous);
+        }
+        self.index -= 1;
+        Some(digit)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.index.saturating_sub(1) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for ZeckendorfDigits {}
+
+
+/// Iterator returned by `zeckendorf_indices`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZeckendorfIndices {
+    digits: ZeckendorfDigits,
+}
+
+impl Iterator for ZeckendorfIndices {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        while !self.digits.rest.is_zero() {
+            let index = self.digits.index;
+            if self.digits.next()? {
+                return Some(index);
+            }
+        }
+        None
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::numeration::*;
+
+    // The greedy representation from a table of every fibonacci number up to n.
+    fn naive_indices(mut n: u128) -> Vec<u64> {
+        let mut fibs = vec![0u128, 1];
+        while let Some(next) = fibs[fibs.len() - 1].checked_add(fibs[fibs.len() - 2]).filter(|&next| next <= n) {
+            fibs.push(next);
+        }
+        let mut indices = Vec::new();
+        for i in (2..fibs.len()).rev() {
+            if fibs[i] <= n {
+                n -= fibs[i];
+                indices.push(i as u64);
+            }
+        }
+        indices
+    }
+
+    #[test]
+    fn test_matches_naive() {
+        let big = (0..3_000u128).chain((u128::MAX - 1_000..=u128::MAX).step_by(7)).chain([1 << 100, (1 << 100) - 1]);
+        for n in big {
+            let indices: Vec<u64> = zeckendorf_indices(&BigUint::from(n)).collect();
+            assert_eq!(indices, naive_indices(n), "n = {}", n);
+            let digits: Vec<bool> = zeckendorf_digits(&BigUint::from(n)).collect();
+            assert_eq!(digits.len(), indices.first().map_or(0, |&top| top as usize - 1), "n = {}", n);
+            assert!(digits.windows(2).all(|pair| !(pair[0] && pair[1])), "n = {}", n);
+            assert_eq!(from_zeckendorf_digits(digits), BigUint::from(n), "n = {}", n);
+        }
+    }
+
+    #[test]
+    fn test_huge() {
+        let n = num::pow(BigUint::from(7u32), 20_000) - 1u32;
+        let digits = zeckendorf_digits(&n);
+        assert_eq!(digits.len(), 80_876);
+        assert_eq!(from_zeckendorf_digits(digits), n);
+        // A fibonacci number is its own representation.
+        let (fib, _) = exact_bigfib_pair(50_000);
+        assert_eq!(zeckendorf_indices(&fib).collect::<Vec<u64>>(), vec![50_000]);
+        assert_eq!(zeckendorf_indices(&(fib - 1u32)).next(), Some(49_999));
+    }
+
+    #[test]
+    fn test_lucas_representation() {
+        let mut lucas = vec![2u128, 1];
+        while lucas.len() < 100 {
+            lucas.push(lucas[lucas.len() - 1] + lucas[lucas.len() - 2]);
+        }
+        // Every representation by brute force, and each n must have exactly one.
+        let mut representations = vec![Vec::new(); 2_000];
+        for subset in 1u32..1 << 16 {
+            let indices: Vec<u64> = (0..16).rev().filter(|&i| subset >> i & 1 == 1).collect();
+            if subset & subset >> 1 != 0 || subset & 0b101 == 0b101 {
+                continue;
+            }
+            let sum: u128 = indices.iter().map(|&i| lucas[i as usize]).sum();
+            if sum < 2_000 {
+                assert!(representations[sum as usize].is_empty(), "n = {}", sum);
+                representations[sum as usize] = indices;
+            }
+        }
+        for (n, expected) in representations.iter().enumerate() {
+            assert_eq!(&lucas_indices(&BigUint::from(n)), expected, "n = {}", n);
+        }
+        for n in (u128::MAX - 1_000..=u128::MAX).chain(lucas[90..].iter().flat_map(|&l| [l - 1, l, l + 1])) {
+            let indices = lucas_indices(&BigUint::from(n));
+            assert!(indices.windows(2).all(|pair| pair[0] >= pair[1] + 2), "n = {}", n);
+            assert!(!(indices.ends_with(&[2, 0])), "n = {}", n);
+            assert_eq!(from_lucas_indices(&indices), BigUint::from(n), "n = {}", n);
+        }
+    }
+
+    #[test]
+    fn test_base_phi() {
+        for n in (0..3_000u64).chain([u64::MAX - 1, u64::MAX]) {
+            let n = BigUint::from(n);
+            let exponents = base_phi_exponents(&n);
+            assert!(exponents.windows(2).all(|pair| pair[0] >= pair[1] + 2), "n = {}", n);
+            let digits = to_base_phi(&n);
+            assert!(!digits.replace('.', "").contains("11"), "n = {}", n);
+            assert_eq!(from_base_phi(&digits), Ok(n.clone()), "n = {}", n);
+        }
+        let n = num::pow(BigUint::from(10u32), 200);
+        assert_eq!(from_base_phi(&to_base_phi(&n)), Ok(n));
+        // φ^k + φ^-k is the lucas number L(k) for even k.
+        assert_eq!(to_base_phi(&BigUint::from(123u32)), "10000000000.0000000001");
+    }
+
+    #[test]
+    fn test_base_phi_canonicalization() {
+        // Consecutive digits, 011 = 100, and extra zeros.
+        for (digits, standard) in [("0.11", "1"), ("1.11", "10.01"), ("0011.01", "100.01"), ("1", "1"), ("0.", "0")] {
+            assert_eq!(to_base_phi(&from_base_phi(digits).unwrap()), standard, "digits = {}", digits);
+        }
+        for digits in ["", ".", "12", "1.0.1", "-1", "1 0"] {
+            let err = FibError::InvalidArgument("base φ digits must be 0s and 1s with at most one point");
+            assert_eq!(from_base_phi(digits), Err(err), "digits = {:?}", digits);
+        }
+        for digits in ["10", "0.1", "11"] {
+            let err = FibError::InvalidArgument("base φ digits must add up to an integer");
+            assert_eq!(from_base_phi(digits), Err(err), "digits = {:?}", digits);
+        }
+    }
+
+    #[test]
+    fn test_non_canonical_digits() {
+        // fib(5) + fib(4) + fib(3) + fib(2) = 5 + 3 + 2 + 1
+        assert_eq!(from_zeckendorf_digits(vec![true; 4]), BigUint::from(11u32));
+        assert_eq!(from_zeckendorf_digits(vec![false, false, true]), BigUint::from(1u32));
+        assert_eq!(from_zeckendorf_digits(Vec::new()), BigUint::zero());
+    }
+}
@@ -0,0 +1,150 @@
+//! fib(n) with a huge modulo on several cores, for a single query rather than a batch.
+//!
+//! Splitting n = a + b and finding the pairs for a and b on separate threads doesn't help by
+//! itself: a and b are about as long as n, so each thread still does about log(n) doublings.
+//! What does help is the addition formula with a = b, F(2k) = F(k) * (2F(k + 1) - F(k)) and
+//! F(2k + 1) = F(k)^2 + F(k + 1)^2, whose three products are independent. Every doubling runs
+//! them on three threads, which pays off once the modulo is long enough for the products to
+//! outweigh starting the threads, from `MIN_PARALLEL_BITS`.
+//!
+//! `bigfib` does the same for exact fib(n), whose doubling steps take two independent squarings.
+//!
+//! # Examples
+//! ```
+//! use fast_fibonacci::parallel;
+//! use num_bigint::BigUint;
+//!
+//! let n = num::pow(BigUint::from(10u32), 30);
+//! let modulo = (BigUint::from(1u32) << 127) - 1u32;
+//! assert_eq!(fast_fibonacci::bigfib_with_mod(&n, &modulo), parallel::bigfib_with_mod(&n, &modulo));
+//! ```
+
+use std::thread;
+
+use num::{One, Zero};
+use num_bigint::BigUint;
+
+use crate::{bignum, FibError};
+
+/// The bit length of the modulo from which `bigfib_with_mod` uses more than one thread.
+pub const MIN_PARALLEL_BITS: usize = 1 << 14;
+
+
+/// `crate::bigfib_with_mod` by fast doubling, running the products of every step on three threads
+/// for moduli of at least `MIN_PARALLEL_BITS` bits, and one after another below. Runtime
+/// O(log(n)) steps, each taking as long as its longest product
+///
+/// Panics if modulo is zero, see `try_bigfib_with_mod`.
+pub fn bigfib_with_mod(n: &BigUint, modulo: &BigUint) -> BigUint {
+    try_bigfib_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `bigfib_with_mod`, but returns an error instead of panicking.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::{parallel, FibError};
+/// use num_bigint::BigUint;
+///
+/// let n = BigUint::from(10u32);
+/// assert_eq!(Ok(BigUint::from(55u32)), parallel::try_bigfib_with_mod(&n, &BigUint::from(100u32)));
+/// assert_eq!(Err(FibError::ZeroModulus), parallel::try_bigfib_with_mod(&n, &BigUint::from(0u32)));
+/// ```
+pub fn try_bigfib_with_mod(n: &BigUint, modulo: &BigUint) -> Result<BigUint, FibError> {
+    if modulo.is_zero() {
+        return Err(FibError::ZeroModulus);
+    }
+    Ok(parallel_fib_pair(n, modulo).0)
+}
+
+
+/// `crate::bigfib` with the two squarings of every doubling step on two threads once the
+/// operands have `MIN_PARALLEL_BITS` bits, see `bignum::fib`. Runtime O(M(n)) for M(n) the time to
+/// multiply two n bit numbers
+///
+/// The last step is a single product, which stays on one thread.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::parallel;
+///
+/// assert_eq!(fast_fibonacci::bigfib(1_000_000), parallel::bigfib(1_000_000));
+/// ```
+pub fn bigfib(n: u64) -> BigUint {
+    bignum::fib_by_squares(n, |a: &BigUint, b: &BigUint| {
+        if a.bits() < MIN_PARALLEL_BITS {
+            return (a * a, b * b);
+        }
+        thread::scope(|scope| {
+            let b_squared = scope.spawn(|| b * b);
+            let a_squared = a * a;
+            (a_squared, b_squared.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+        })
+    })
+}
+
+
+// (fib(n), fib(n + 1)) with a positive modulo, by fast doubling over the bits of n with the
+// three products of each step on their own threads, from MIN_PARALLEL_BITS.
+fn parallel_fib_pair(n: &BigUint, modulo: &BigUint) -> (BigUint, BigUint) {
+    let parallel = modulo.bits() >= MIN_PARALLEL_BITS;
+    let n_bytes = n.to_bytes_le();
+    let (mut a, mut b) = (BigUint::zero(), BigUint::one() % modulo);
+    for bit in (0..n.bits()).rev() {
+        let twice_b_minus_a = (&b + &b + modulo - &a) % modulo;
+        let (even, odd) = if !parallel {
+            (&a * &twice_b_minus_a % modulo, (&a * &a + &b * &b) % modulo)
+        } else {
+            thread::scope(|scope| {
+                let a_squared = scope.spawn(|| &a * &a);
+                let b_squared = scope.spawn(|| &b * &b);
+                let even = &a * &twice_b_minus_a % modulo;
+                let a_squared = a_squared.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                let b_squared = b_squared.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                (even, (a_squared + b_squared) % modulo)
+            })
+        };
+        if n_bytes[bit / 8] >> (bit % 8) & 1 == 1 {
+            b = (&even + &odd) % modulo;
+            a = odd;
+        } else {
+            a = even;
+            b = odd;
+        }
+    }
+    (a, b)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::parallel::*;
+
+    #[test]
+    fn test_parallel_fib_pair() {
+        let moduli = [BigUint::from(1u32), BigUint::from(1_000_000u32), (BigUint::from(1u32) << 521) - 1u32];
+        for modulo in &moduli {
+            for n in (0..50u32).map(BigUint::from).chain(std::iter::once(num::pow(BigUint::from(7u32), 200))) {
+                let expected = (crate::bigfib_with_mod(&n, modulo), crate::bigfib_with_mod(&(&n + 1u32), modulo));
+                assert_eq!(parallel_fib_pair(&n, modulo), expected, "n = {}, modulo = {}", n, modulo);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bigfib_with_mod() {
+        let modulo = (BigUint::from(1u32) << MIN_PARALLEL_BITS) - 1u32;
+        for n in &[BigUint::from(1_000u32), BigUint::from(u64::MAX)] {
+            assert_eq!(bigfib_with_mod(n, &modulo), crate::bigfib_with_mod(n, &modulo));
+        }
+        assert_eq!(try_bigfib_with_mod(&BigUint::one(), &BigUint::zero()), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_bigfib() {
+        for n in (0..100).chain(vec![100_000, 100_001]) {
+            assert_eq!(bigfib(n), crate::bigfib(n), "n = {}", n);
+        }
+    }
+}
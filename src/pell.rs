@@ -0,0 +1,236 @@
+//! Solutions of the Pell equations x^2 - d * y^2 = ±4 that Lucas sequences solve.
+//!
+//! The Lucas sequences U(0) = 0, U(1) = 1 and V(0) = 2, V(1) = p of
+//! x(i) = p * x(i - 1) - q * x(i - 2) satisfy V(k)^2 - d * U(k)^2 = 4 * q^k for d = p^2 - 4q.
+//! With q = -1 and d = p^2 + 4, or q = 1 and d = p^2 - 4, (V(k), U(k)) for k = 0, 1, ... are all
+//! the solutions in nonnegative integers, powers of the fundamental solution (p, 1). p = 1 gives
+//! x^2 - 5y^2 = ±4, solved by the lucas and fibonacci numbers (lucas(k), fib(k)).
+//!
+//! # Examples
+//! ```
+//! use fast_fibonacci::pell::PellEquation;
+//!
+//! let pell = PellEquation::FIBONACCI;
+//! assert_eq!(5, pell.discriminant());
+//! // 123^2 - 5 * 55^2 = 4
+//! assert_eq!((123, 55), pell.solution_with_mod(10, 1_000));
+//! assert_eq!(4, pell.norm(10));
+//!
+//! // x^2 - 21y^2 = 4, from 5^2 - 21 * 1^2
+//! let pell = PellEquation::from_discriminant(21).unwrap();
+//! assert_eq!((5, 1), pell.fundamental_solution());
+//! assert_eq!(vec![(2, 0), (5, 1), (23, 5), (110, 24)], pell.solutions_with_mod(1_000).take(4).collect::<Vec<_>>());
+//! ```
+
+#[cfg(feature = "std")]
+use num_bigint::{BigInt, BigUint};
+
+use crate::matrix::power_with_mod_u64;
+use crate::{add_mod, mul_mod, sub_mod, FibError, Matrix};
+
+
+/// x^2 - d * y^2 = ±4 for d = p^2 + 4, or x^2 - d * y^2 = 4 for d = p^2 - 4, solved by the Lucas
+/// sequences of p and q = -1 or q = 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PellEquation {
+    p: u64,
+    // q = -1 rather than q = 1.
+    negative_q: bool,
+}
+
+impl PellEquation {
+    /// x^2 - 5y^2 = ±4, solved by (lucas(k), fib(k)).
+    pub const FIBONACCI: PellEquation = PellEquation { p: 1, negative_q: true };
+
+    /// Looks up the equation x^2 - d * y^2 = ±4 with a fundamental solution (p, 1), for
+    /// d = p^2 + 4 with p >= 1 or d = p^2 - 4 with p >= 3. Runtime O(1)
+    ///
+    /// Other d have no solution with y = 1, and give None.
+    ///
+    /// # Examples
+    /// ```
+    /// use fast_fibonacci::pell::PellEquation;
+    ///
+    /// assert_eq!(Some(PellEquation::FIBONACCI), PellEquation::from_discriminant(5));
+    /// // x^2 - 8y^2 = ±4 from 2^2 - 8 * 1^2 = -4, with the pell numbers as y.
+    /// assert_eq!(Some((2, 1)), PellEquation::from_discriminant(8).map(|pell| pell.fundamental_solution()));
+    /// assert_eq!(None, PellEquation::from_discriminant(7));
+    /// ```
+    pub fn from_discriminant(d: u64) -> Option<PellEquation> {
+        if d >= 5 {
+            let p = (d - 4).isqrt();
+            if p >= 1 && p * p == d - 4 {
+                return Some(PellEquation { p, negative_q: true });
+            }
+        }
+        let p = d.checked_add(4)?.isqrt();
+        if p >= 3 && p * p == d + 4 {
+            return Some(PellEquation { p, negative_q: false });
+        }
+        None
+    }
+
+    /// d = p^2 - 4q.
+    pub fn discriminant(&self) -> u64 {
+        if self.negative_q {
+            self.p * self.p + 4
+        } else {
+            self.p * self.p - 4
+        }
+    }
+
+    /// (p, 1), the solution with the smallest positive y, whose powers give all the others.
+    pub fn fundamental_solution(&self) -> (u64, u64) {
+        (self.p, 1)
+    }
+
+    /// x^2 - d * y^2 for the kth solution, 4 * q^k: 4, or -4 for odd k with q = -1.
+    pub fn norm(&self, k: u64) -> i64 {
+        if self.negative_q && k % 2 == 1 {
+            -4
+        } else {
+            4
+        }
+    }
+
+    /// The kth solution (V(k), U(k)) with modulo. Runtime O(log(k))
+    ///
+    /// # Examples
+    /// ```
+    /// use fast_fibonacci::pell::PellEquation;
+    ///
+    /// let pell = PellEquation::from_discriminant(8).unwrap();
+    /// // 2 * 3363 and the pell number 2378: (2 * 3363)^2 - 8 * 2378^2 = 4
+    /// assert_eq!((6_726, 2_378), pell.solution_with_mod(10, 1_000_000));
+    /// ```
+    pub fn solution_with_mod(&self, k: u64, modulo: u64) -> (u64, u64) {
+        self.try_solution_with_mod(k, modulo).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `solution_with_mod`, but returns an error instead of panicking.
+    pub fn try_solution_with_mod(&self, k: u64, modulo: u64) -> Result<(u64, u64), FibError> {
+        if modulo == 0 {
+            return Err(FibError::ZeroModulus);
+        }
+        if k == 0 {
+            return Ok((2 % modulo, 0));
+        }
+        // [[0, 1], [-q, p]]^k, whose second column is (U(k), U(k + 1)).
+        let minus_q = if self.negative_q { 1 % modulo } else { modulo - 1 % modulo };
+        let t: Matrix = [
+            [0, 1 % modulo],
+            [minus_q % modulo, self.p % modulo]
+        ];
+        let power_t = power_with_mod_u64(&t, k, modulo).0;
+        Ok(self.solution_from(power_t[0][1], power_t[1][1], modulo))
+    }
+
+    /// The solutions (V(k), U(k)) for k = 0, 1, 2, ... with modulo, O(1) each.
+    pub fn solutions_with_mod(&self, modulo: u64) -> PellSolutions {
+        self.try_solutions_with_mod(modulo).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `solutions_with_mod`, but returns an error instead of panicking.
+    pub fn try_solutions_with_mod(&self, modulo: u64) -> Result<PellSolutions, FibError> {
+        if modulo == 0 {
+            return Err(FibError::ZeroModulus);
+        }
+        Ok(PellSolutions { pell: *self, modulo, u: 0, u_next: 1 % modulo })
+    }
+
+    /// The kth solution (V(k), U(k)) exactly. Runtime O(M(k) * log(k)) for M(k) the time to
+    /// multiply two k bit numbers
+    ///
+    /// # Examples
+    /// ```
+    /// use fast_fibonacci::pell::PellEquation;
+    ///
+    /// let (x, y) = PellEquation::FIBONACCI.solution(100);
+    /// assert_eq!(fast_fibonacci::bigfib(100), y);
+    /// assert_eq!(x.clone() * x, y.clone() * y * 5u32 + 4u32);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn solution(&self, k: u64) -> (BigUint, BigUint) {
+        use crate::semiring::linear_recurrence;
+
+        let p = BigInt::from(self.p);
+        let coefficients = [p.clone(), BigInt::from(if self.negative_q { 1 } else { -1 })];
+        let v = linear_recurrence(&coefficients, &[BigInt::from(2), p], k);
+        let u = linear_recurrence(&coefficients, &[BigInt::from(0), BigInt::from(1)], k);
+        // Both sequences are nonnegative, and increasing from k = 1.
+        (v.to_biguint().unwrap(), u.to_biguint().unwrap())
+    }
+
+    // (V(k), U(k)) from (U(k), U(k + 1)), with V(k) = 2 * U(k + 1) - p * U(k).
+    fn solution_from(&self, u: u64, u_next: u64, modulo: u64) -> (u64, u64) {
+        let v = sub_mod(add_mod(u_next, u_next, modulo), mul_mod(self.p % modulo, u, modulo), modulo);
+        (v, u)
+    }
+}
+
+
+/// The solutions of a `PellEquation` with modulo, from `PellEquation::solutions_with_mod`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PellSolutions {
+    pell: PellEquation,
+    modulo: u64,
+    // U(k) and U(k + 1) for the next k.
+    u: u64,
+    u_next: u64,
+}
+
+impl Iterator for PellSolutions {
+    type Item = (u64, u64);
+
+    fn next(&mut self) -> Option<(u64, u64)> {
+        let modulo = self.modulo;
+        let solution = self.pell.solution_from(self.u, self.u_next, modulo);
+        // U(k + 2) = p * U(k + 1) - q * U(k)
+        let p_u = mul_mod(self.pell.p % modulo, self.u_next, modulo);
+        let u_after = if self.pell.negative_q { add_mod(p_u, self.u, modulo) } else { sub_mod(p_u, self.u, modulo) };
+        self.u = self.u_next;
+        self.u_next = u_after;
+        Some(solution)
+    }
+}
+
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::pell::*;
+    use num::ToPrimitive;
+
+    #[test]
+    fn test_solutions() {
+        for d in 0..2_000 {
+            let pell = match PellEquation::from_discriminant(d) {
+                Some(pell) => pell,
+                None => continue,
+            };
+            assert_eq!(pell.discriminant(), d);
+            let (x, y) = pell.fundamental_solution();
+            assert_eq!((x * x) as i64 - (d * y * y) as i64, pell.norm(1), "d = {}", d);
+            let mut solutions = pell.solutions_with_mod(1_000_000_007);
+            for k in 0..40 {
+                let (x, y) = pell.solution(k);
+                let norm = BigInt::from(x.clone() * &x) - BigInt::from(y.clone() * &y * d);
+                assert_eq!(norm, BigInt::from(pell.norm(k)), "d = {}, k = {}", d, k);
+                let reduced = ((x % 1_000_000_007u32).to_u64().unwrap(), (y % 1_000_000_007u32).to_u64().unwrap());
+                assert_eq!(pell.solution_with_mod(k, 1_000_000_007), reduced, "d = {}, k = {}", d, k);
+                assert_eq!(solutions.next(), Some(reduced), "d = {}, k = {}", d, k);
+            }
+        }
+        for k in 0..200 {
+            assert_eq!(PellEquation::FIBONACCI.solution_with_mod(k, 1_000), {
+                let (fib, lucas) = crate::fib_lucas_with_mod(k, 1_000);
+                (lucas, fib)
+            });
+        }
+        assert_eq!(PellEquation::FIBONACCI.solution_with_mod(u64::MAX, u64::MAX).1, crate::fib_with_mod(u64::MAX, u64::MAX));
+        for &d in &[0, 1, 2, 3, 4, 6, 7, u64::MAX] {
+            assert_eq!(PellEquation::from_discriminant(d), None, "d = {}", d);
+        }
+        assert_eq!(PellEquation::FIBONACCI.try_solution_with_mod(1, 0), Err(FibError::ZeroModulus));
+    }
+}
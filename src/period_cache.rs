@@ -0,0 +1,206 @@
+//! An opt-in process wide cache of pisano periods and ranks of apparition.
+//!
+//! Long running services tend to query the same few moduli over and over, and each of their
+//! periods costs a factorization. Once `enable`d, the periods found here are kept for every
+//! thread, and `Algorithm::PisanoReduce` and `Dispatcher` look them up before finding them again.
+//! Past `MAX_CACHED_MODULI` moduli, the cache starts over rather than growing without bound.
+//!
+//! # Examples
+//! ```
+//! use fast_fibonacci::period_cache;
+//!
+//! period_cache::enable();
+//! assert_eq!(Ok(1_500), period_cache::pisano_period(1_000));
+//! // Found once, then kept for every thread.
+//! assert_eq!(1, period_cache::len());
+//! ```
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{OnceLock, RwLock};
+
+use crate::pisano::{try_pisano_period, try_rank_of_apparition};
+use crate::FibError;
+
+/// How many moduli the cache keeps at most.
+pub const MAX_CACHED_MODULI: usize = 10_000;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PERIODS: Cache = OnceLock::new();
+static RANKS: Cache = OnceLock::new();
+
+type Cache = OnceLock<RwLock<HashMap<u64, u64>>>;
+
+
+/// Starts keeping the periods and ranks found through this module.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+
+/// Stops keeping periods and ranks, and forgets the ones kept.
+pub fn disable() {
+    ENABLED.store(false, Ordering::Relaxed);
+    clear();
+}
+
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+
+/// Forgets every kept period and rank.
+pub fn clear() {
+    for cache in [&PERIODS, &RANKS] {
+        if let Some(lock) = cache.get() {
+            lock.write().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
+        }
+    }
+}
+
+
+/// How many periods and ranks are kept.
+pub fn len() -> usize {
+    [&PERIODS, &RANKS]
+        .iter()
+        .filter_map(|cache| cache.get())
+        .map(|lock| lock.read().unwrap_or_else(|poisoned| poisoned.into_inner()).len())
+        .sum()
+}
+
+
+/// `pisano::try_pisano_period`, from the cache when it's enabled. Errors aren't kept.
+/// Runtime O(1) for a kept modulo, and O(sqrt(modulo)) otherwise
+pub fn pisano_period(modulo: u64) -> Result<u64, FibError> {
+    cached(modulo, &PERIODS, try_pisano_period)
+}
+
+
+/// `pisano::try_rank_of_apparition`, the first n with modulo dividing fib(n), from the cache when
+/// it's enabled. Errors aren't kept. Runtime O(1) for a kept modulo, and O(sqrt(modulo)) otherwise
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::period_cache;
+///
+/// period_cache::enable();
+/// assert_eq!(Ok(750), period_cache::rank_of_apparition(1_000));
+/// ```
+pub fn rank_of_apparition(modulo: u64) -> Result<u64, FibError> {
+    cached(modulo, &RANKS, try_rank_of_apparition)
+}
+
+
+// Every kept (modulo, period), for `persist`.
+#[cfg(feature = "persist")]
+pub(crate) fn period_entries() -> Vec<(u64, u64)> {
+    sorted_entries(&PERIODS)
+}
+
+
+// Every kept (modulo, rank), for `persist`.
+#[cfg(feature = "persist")]
+pub(crate) fn rank_entries() -> Vec<(u64, u64)> {
+    sorted_entries(&RANKS)
+}
+
+
+// Keeps the given periods and ranks, as if they were found here.
+#[cfg(feature = "persist")]
+pub(crate) fn insert_entries(periods: &[(u64, u64)], ranks: &[(u64, u64)]) {
+    for (cache, entries) in [(&PERIODS, periods), (&RANKS, ranks)] {
+        let lock = cache.get_or_init(|| RwLock::new(HashMap::new()));
+        let mut values = lock.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+        for &(modulo, value) in entries {
+            if values.len() >= MAX_CACHED_MODULI {
+                values.clear();
+            }
+            values.insert(modulo, value);
+        }
+    }
+}
+
+// modulo's value in the cache, or find's value, kept when enabled. A panic can't leave the cache
+// inconsistent, only without some entries, so poisoned locks are used anyway.
+fn cached<F>(modulo: u64, cache: &Cache, find: F) -> Result<u64, FibError>
+where
+    F: FnOnce(u64) -> Result<u64, FibError>,
+{
+    if !is_enabled() {
+        return find(modulo);
+    }
+    let lock = cache.get_or_init(|| RwLock::new(HashMap::new()));
+    if let Some(&value) = lock.read().unwrap_or_else(|poisoned| poisoned.into_inner()).get(&modulo) {
+        return Ok(value);
+    }
+    // Found without holding the lock, so lookups of other moduli aren't held up meanwhile.
+    let value = find(modulo)?;
+    let mut values = lock.write().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if values.len() >= MAX_CACHED_MODULI {
+        values.clear();
+    }
+    values.insert(modulo, value);
+    Ok(value)
+}
+
+
+#[cfg(feature = "persist")]
+fn sorted_entries(cache: &Cache) -> Vec<(u64, u64)> {
+    cache.get().map_or(Vec::new(), |lock| {
+        let values = lock.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut entries: Vec<(u64, u64)> = values.iter().map(|(&modulo, &value)| (modulo, value)).collect();
+        entries.sort_unstable();
+        entries
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::period_cache::*;
+    use crate::pisano::{pisano_period as uncached_period, rank_of_apparition as uncached_rank};
+    use std::thread;
+
+    // The cache is process wide, so everything that toggles it is in one test.
+    #[test]
+    fn test_cache() {
+        disable();
+        assert_eq!(pisano_period(10), Ok(60));
+        assert_eq!(len(), 0);
+
+        enable();
+        let threads: Vec<_> = (0..4u64)
+            .map(|i| {
+                thread::spawn(move || {
+                    for modulo in 1..=200 {
+                        let modulo = modulo * (i + 1);
+                        assert_eq!(pisano_period(modulo), Ok(uncached_period(modulo)), "modulo = {}", modulo);
+                        assert_eq!(rank_of_apparition(modulo), Ok(uncached_rank(modulo)), "modulo = {}", modulo);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert!(is_enabled());
+        assert!(len() >= 200);
+        assert_eq!(pisano_period(0), Err(FibError::ZeroModulus));
+
+        // PisanoReduce reads the cache.
+        for &modulo in &[7, 1_000, 1_000_003] {
+            let fib = crate::fib_with_mod_using(crate::Algorithm::PisanoReduce, u64::MAX, modulo);
+            assert_eq!(fib, Ok(crate::fib_with_mod(u64::MAX, modulo)));
+        }
+        assert!(PERIODS.get().unwrap().read().unwrap().contains_key(&1_000_003));
+
+        for modulo in 1..=MAX_CACHED_MODULI as u64 + 1 {
+            let _ = rank_of_apparition(modulo);
+        }
+        assert!(len() <= 2 * MAX_CACHED_MODULI);
+        clear();
+        disable();
+        assert!(!is_enabled());
+        assert_eq!(len(), 0);
+    }
+}
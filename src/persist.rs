@@ -0,0 +1,210 @@
+//! Saving precomputed tables to files, so they survive process restarts.
+//!
+//! A `PisanoTable` for a large bound, or a `period_cache` warmed up with large moduli, can take
+//! minutes to compute again. The files are "FFPS", a format version byte, a byte for what they
+//! hold, and then the contents in postcard. Loading a file of another version or kind fails with
+//! `io::ErrorKind::InvalidData` instead of misreading it, and so does loading a period that
+//! doesn't check out.
+//!
+//! # Examples
+//! ```
+//! use fast_fibonacci::persist::{load_pisano_table, save_pisano_table};
+//! use fast_fibonacci::pisano::PisanoTable;
+//!
+//! let path = std::env::temp_dir().join("fast_fibonacci_doc_table.bin");
+//! let table = PisanoTable::new(1_000);
+//! save_pisano_table(&table, &path).unwrap();
+//! assert_eq!(table, load_pisano_table(&path).unwrap());
+//! # std::fs::remove_file(&path).unwrap();
+//! ```
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::nt::factorize;
+use crate::pisano::PisanoTable;
+use crate::{fib_pair_u128, period_cache};
+
+const MAGIC: [u8; 4] = *b"FFPS";
+// Bumped whenever the contents change shape, so older files are refused.
+const VERSION: u8 = 1;
+const PISANO_TABLE: u8 = 1;
+const PERIOD_CACHE: u8 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntries {
+    periods: Vec<(u64, u64)>,
+    ranks: Vec<(u64, u64)>,
+}
+
+
+/// Writes a pisano table to a file, replacing it if it exists. Runtime O(bound)
+pub fn save_pisano_table<P: AsRef<Path>>(table: &PisanoTable, path: P) -> io::Result<()> {
+    save(path.as_ref(), PISANO_TABLE, table)
+}
+
+
+/// Reads a pisano table written by `save_pisano_table`. Runtime O(bound)
+pub fn load_pisano_table<P: AsRef<Path>>(path: P) -> io::Result<PisanoTable> {
+    let table: PisanoTable = load(path.as_ref(), PISANO_TABLE)?;
+    if !table.is_consistent() {
+        return Err(invalid("pisano table with differently sized periods and ranks"));
+    }
+    Ok(table)
+}
+
+
+/// Writes every period and rank `period_cache` keeps to a file, replacing it if it exists, and
+/// returns how many there were. Runtime O(k * log(k)) for k entries
+pub fn save_period_cache<P: AsRef<Path>>(path: P) -> io::Result<usize> {
+    let (periods, ranks) = (period_cache::period_entries(), period_cache::rank_entries());
+    let count = periods.len() + ranks.len();
+    save(path.as_ref(), PERIOD_CACHE, &CacheEntries { periods, ranks })?;
+    Ok(count)
+}
+
+
+/// Adds the periods and ranks in a file written by `save_period_cache` to `period_cache`, and
+/// enables it. Returns how many there were. Nothing is added unless every period is the least
+/// positive index with fib(period) = 0 and fib(period + 1) = 1, and every rank the least with
+/// fib(rank) = 0, since their multiples would pass as well. Runtime O(k * sqrt(modulo)) for k
+/// entries, dominated by factoring them
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::{period_cache, persist};
+///
+/// let path = std::env::temp_dir().join("fast_fibonacci_doc_cache.bin");
+/// period_cache::enable();
+/// period_cache::pisano_period(1_000_000_007).unwrap();
+/// persist::save_period_cache(&path).unwrap();
+///
+/// // After a restart
+/// period_cache::disable();
+/// assert_eq!(1, persist::load_period_cache(&path).unwrap());
+/// assert_eq!(1, period_cache::len());
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+pub fn load_period_cache<P: AsRef<Path>>(path: P) -> io::Result<usize> {
+    let entries: CacheEntries = load(path.as_ref(), PERIOD_CACHE)?;
+    let periods_hold = entries.periods.iter().all(|&(modulo, period)| {
+        modulo > 0 && is_least(period, |i| fib_pair_u128(i as u128, modulo) == (0, 1 % modulo))
+    });
+    let ranks_hold = entries.ranks.iter().all(|&(modulo, rank)| {
+        modulo > 0 && is_least(rank, |i| fib_pair_u128(i as u128, modulo).0 == 0)
+    });
+    if !periods_hold || !ranks_hold {
+        return Err(invalid("period cache with a wrong period or rank"));
+    }
+    period_cache::insert_entries(&entries.periods, &entries.ranks);
+    period_cache::enable();
+    Ok(entries.periods.len() + entries.ranks.len())
+}
+
+
+// Whether index is the least positive one that holds, for a property that holds exactly for the
+// multiples of the least: it holds for index, but not for index / q for any prime q dividing it.
+fn is_least<F: Fn(u64) -> bool>(index: u64, holds: F) -> bool {
+    index > 0 && holds(index) && factorize(index).iter().all(|&(q, _)| !holds(index / q))
+}
+
+
+fn save<T: Serialize>(path: &Path, kind: u8, value: &T) -> io::Result<()> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(VERSION);
+    bytes.push(kind);
+    let bytes = postcard::to_extend(value, bytes).map_err(postcard_error)?;
+    fs::write(path, bytes)
+}
+
+
+fn load<T: DeserializeOwned>(path: &Path, kind: u8) -> io::Result<T> {
+    let bytes = fs::read(path)?;
+    if bytes.len() < 6 || bytes[..4] != MAGIC[..] {
+        return Err(invalid("not a fast-fibonacci file"));
+    }
+    if bytes[4] != VERSION {
+        return Err(invalid("file from another version of fast-fibonacci"));
+    }
+    if bytes[5] != kind {
+        return Err(invalid("file holds something else"));
+    }
+    postcard::from_bytes(&bytes[6..]).map_err(postcard_error)
+}
+
+
+fn invalid(reason: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+
+fn postcard_error(err: postcard::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::persist::*;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("fast_fibonacci_test_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_pisano_table_round_trip() {
+        let path = temp_path("table");
+        for &bound in &[0, 1, 10_000] {
+            let table = PisanoTable::new(bound);
+            save_pisano_table(&table, &path).unwrap();
+            assert_eq!(load_pisano_table(&path).unwrap(), table);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_stale_and_foreign_files() {
+        let path = temp_path("stale");
+        save_pisano_table(&PisanoTable::new(100), &path).unwrap();
+        let bytes = fs::read(&path).unwrap();
+
+        let mut stale = bytes.clone();
+        stale[4] = VERSION + 1;
+        fs::write(&path, &stale).unwrap();
+        assert_eq!(load_pisano_table(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // A table isn't a cache.
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(load_period_cache(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        for broken in [&bytes[..3], &bytes[..bytes.len() - 1], b"not a table at all"] {
+            fs::write(&path, broken).unwrap();
+            assert_eq!(load_pisano_table(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+
+        // A wrong period, multiples of the right ones, and a multiple of the rank.
+        let wrong = [
+            CacheEntries { periods: vec![(10, 60), (11, 11)], ranks: vec![] },
+            CacheEntries { periods: vec![(10, 120)], ranks: vec![] },
+            CacheEntries { periods: vec![(1, 2)], ranks: vec![] },
+            CacheEntries { periods: vec![(10, 60)], ranks: vec![(10, 30)] },
+        ];
+        for entries in &wrong {
+            save(&path, PERIOD_CACHE, entries).unwrap();
+            assert_eq!(load_period_cache(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        }
+        // Checked without loading, which would enable the process wide cache.
+        let period_of_10 = |i: u64| fib_pair_u128(i as u128, 10) == (0, 1);
+        assert!(is_least(60, period_of_10));
+        assert!(!is_least(0, period_of_10));
+        assert!(is_least(15, |i| fib_pair_u128(i as u128, 10).0 == 0));
+        assert!(is_least(1, |i| fib_pair_u128(i as u128, 1) == (0, 0)));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(load_pisano_table(&path).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,1690 @@
+//! Pisano periods, and the questions they answer about fibonacci numbers modulo m.
+//!
+//! fib(n) modulo m is periodic for every m, and its period π(m) is the pisano period. The period
+//! of a prime p divides p - 1 or 2(p + 1), the period of a prime power p^e is p^(e-1) * π(p), and
+//! the period of any other modulo is the lcm of the periods of its prime power factors. A
+//! Wall-Sun-Sun prime, with p^2 | fib(π(p)), would break the rule for prime powers; none is known,
+//! but they're checked for all the same.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read};
+
+use num::{Integer, One, ToPrimitive, Zero};
+use num_bigint::BigUint;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::nt::{
+    big_factorize, big_is_probable_prime, crt, factorize, is_prime, lcm, mod_inverse, sqrt_mod_prime, totient, try_crt,
+};
+use crate::{add_mod, fib_add_indices, fib_pair_u128, mul_mod, pow_mod, sub_mod, FibError};
+
+// Below this, walking through the whole period beats anything clever.
+const LINEAR_SCAN_LIMIT: u64 = 4_096;
+
+// The moduli whose periods build.rs bakes into SMALL_PERIODS.
+const SMALL_PERIODS_BOUND: u64 = 4_096;
+
+// π(m) at index m for m <= SMALL_PERIODS_BOUND, and 0 at index 0, computed at build time.
+static SMALL_PERIODS: [u32; SMALL_PERIODS_BOUND as usize + 1] =
+    include!(concat!(env!("OUT_DIR"), "/small_pisano_periods.rs"));
+
+
+/// Finds the pisano period π(modulo), the period of fib(n) modulo `modulo`.
+/// Runtime O(sqrt(modulo)), dominated by factoring.
+///
+/// Moduli up to 4096 are looked up in a table computed at build time instead.
+///
+/// Panics if modulo is 0, or if the period doesn't fit in a u64, which is only possible for
+/// moduli above u64::MAX / 6. See `try_pisano_period`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::pisano_period;
+///
+/// assert_eq!(60, pisano_period(10));
+/// assert_eq!(1_500, pisano_period(1_000));
+/// assert_eq!(2_000_000_016, pisano_period(1_000_000_007));
+/// ```
+pub fn pisano_period(modulo: u64) -> u64 {
+    try_pisano_period(modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Finds the pisano period π(modulo), or an error instead of panicking.
+/// Runtime O(sqrt(modulo)), dominated by factoring.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::FibError;
+/// use fast_fibonacci::pisano::try_pisano_period;
+///
+/// assert_eq!(Ok(60), try_pisano_period(10));
+/// assert_eq!(Err(FibError::ZeroModulus), try_pisano_period(0));
+/// ```
+pub fn try_pisano_period(modulo: u64) -> Result<u64, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    if modulo <= SMALL_PERIODS_BOUND {
+        return Ok(SMALL_PERIODS[modulo as usize] as u64);
+    }
+    period_of_factors(&factorize(modulo))
+}
+
+
+/// Finds the pisano period π(m) of m = p1^e1 * p2^e2 * ..., given as [(p1, e1), (p2, e2), ...],
+/// as the lcm of the periods of the prime powers. Runtime O(sqrt(p)) for the largest prime p
+///
+/// m itself can be far past u64, as long as its period isn't. Repeated primes multiply together,
+/// so [(2, 1), (2, 3)] is 2^4, and the empty product is 1.
+///
+/// Panics if a factor isn't prime or the period doesn't fit in a u64, see
+/// `try_pisano_period_of_product`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::{pisano_period, pisano_period_of_product};
+///
+/// assert_eq!(pisano_period(1_000), pisano_period_of_product(&[(2, 3), (5, 3)]));
+/// // fib(47) * fib(83) is about 3 * 10^26, and the period of an odd indexed fibonacci prime
+/// // fib(k) is 4k.
+/// assert_eq!(4 * 47 * 83, pisano_period_of_product(&[(2_971_215_073, 1), (99_194_853_094_755_497, 1)]));
+/// ```
+pub fn pisano_period_of_product(factors: &[(u64, u32)]) -> u64 {
+    try_pisano_period_of_product(factors).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `pisano_period_of_product`, but returns an error instead of panicking.
+pub fn try_pisano_period_of_product(factors: &[(u64, u32)]) -> Result<u64, FibError> {
+    period_of_factors(&merge_factors(factors)?)
+}
+
+
+/// Finds the pisano period π(prime^exponent) = prime^(exponent - 1) * π(prime) without factoring
+/// the prime power, or searching through it. Runtime O(sqrt(prime) + exponent * log(prime)^2)
+///
+/// A Wall-Sun-Sun prime, with prime^2 | fib(π(prime)), would keep the period of prime for higher
+/// powers too. None is known, but each exponent is checked, so the period is right either way.
+///
+/// Panics if prime isn't prime or the period doesn't fit in a u64, see
+/// `try_pisano_period_prime_power`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::pisano_period_prime_power;
+///
+/// assert_eq!(3 * 2u64.pow(19), pisano_period_prime_power(2, 20));
+/// // 10^9 + 7 squared is past u64
+/// assert_eq!(2_000_000_016 * 1_000_000_007, pisano_period_prime_power(1_000_000_007, 2));
+/// ```
+pub fn pisano_period_prime_power(prime: u64, exponent: u32) -> u64 {
+    try_pisano_period_prime_power(prime, exponent).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `pisano_period_prime_power`, but returns an error instead of panicking.
+pub fn try_pisano_period_prime_power(prime: u64, exponent: u32) -> Result<u64, FibError> {
+    try_pisano_period_of_product(&[(prime, exponent)])
+}
+
+
+/// `pisano_period` for BigUint moduli, which it factors by Pollard's rho.
+/// Runtime about O(q^(1/4)) multiplications for q the second largest prime factor of the modulo,
+/// or of p ± 1 for its prime factors p
+///
+/// The period of a prime p divides p - 1 or 2(p + 1), so only those need factoring to find it.
+///
+/// Panics if modulo is 0, see `try_big_pisano_period`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::big_pisano_period;
+/// use num_bigint::BigUint;
+///
+/// // π(10^k) = 15 * 10^(k - 1) for k >= 3
+/// let modulo = num::pow(BigUint::from(10u32), 30);
+/// assert_eq!(BigUint::from(15u32) * num::pow(BigUint::from(10u32), 29), big_pisano_period(&modulo));
+/// ```
+pub fn big_pisano_period(modulo: &BigUint) -> BigUint {
+    try_big_pisano_period(modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `big_pisano_period`, but returns an error instead of panicking.
+pub fn try_big_pisano_period(modulo: &BigUint) -> Result<BigUint, FibError> {
+    if modulo.is_zero() {
+        return Err(FibError::ZeroModulus);
+    }
+    Ok(big_period_of_factors(&big_factorize(modulo)))
+}
+
+
+/// `pisano_period_of_product` for BigUint primes, with no limit on the period. Runtime as
+/// `big_pisano_period` for the primes alone
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::big_pisano_period_of_product;
+/// use num_bigint::BigUint;
+///
+/// // 2^127 - 1 is 2 modulo 5, so its period divides 2 * 2^127.
+/// let prime = (BigUint::from(1u32) << 127) - 1u32;
+/// let period = big_pisano_period_of_product(&[(prime, 2)]);
+/// assert_eq!(((BigUint::from(1u32) << 127) - 1u32) << 128, period);
+/// ```
+pub fn big_pisano_period_of_product(factors: &[(BigUint, u32)]) -> BigUint {
+    try_big_pisano_period_of_product(factors).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `big_pisano_period_of_product`, but returns an error instead of panicking when a factor
+/// isn't prime.
+pub fn try_big_pisano_period_of_product(factors: &[(BigUint, u32)]) -> Result<BigUint, FibError> {
+    if factors.iter().any(|(prime, _)| !big_is_probable_prime(prime)) {
+        return Err(FibError::InvalidArgument("factors must be prime"));
+    }
+    let mut sorted = factors.to_vec();
+    sorted.sort();
+    let mut merged: Vec<(BigUint, u32)> = Vec::with_capacity(sorted.len());
+    for (prime, exponent) in sorted {
+        match merged.last_mut() {
+            Some(last) if last.0 == prime => {
+                last.1 = last.1.checked_add(exponent).ok_or(FibError::OverflowRisk)?;
+            },
+            _ => merged.push((prime, exponent)),
+        }
+    }
+    Ok(big_period_of_factors(&merged))
+}
+
+
+/// Finds the rank of apparition α(modulo), the smallest n >= 1 with modulo | fib(n).
+/// Runtime O(sqrt(modulo)), dominated by factoring.
+///
+/// modulo divides fib(n) exactly when α(modulo) divides n.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::rank_of_apparition;
+///
+/// assert_eq!(15, rank_of_apparition(10));
+/// assert_eq!(10, rank_of_apparition(11));
+/// assert_eq!(750, rank_of_apparition(1_000));
+/// ```
+pub fn rank_of_apparition(modulo: u64) -> u64 {
+    pisano_info(modulo).rank
+}
+
+
+/// Like `rank_of_apparition`, but returns an error instead of panicking.
+pub fn try_rank_of_apparition(modulo: u64) -> Result<u64, FibError> {
+    Ok(try_pisano_info(modulo)?.rank)
+}
+
+
+/// Everything about the pisano period of a modulo, see `pisano_info`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PisanoInfo {
+    /// The modulo the rest describe.
+    pub modulo: u64,
+    /// The pisano period π(modulo).
+    pub period: u64,
+    /// The rank of apparition α(modulo), the smallest n >= 1 with modulo | fib(n).
+    pub rank: u64,
+    /// How many n in 0..π(modulo) have modulo | fib(n). Always 1, 2 or 4.
+    pub zeros_per_period: u64,
+    /// fib(α(modulo) + 1) modulo `modulo`. fib(α + n) ≡ multiplier * fib(n), so the sequence
+    /// restarts after each zero scaled by this, and its multiplicative order is zeros_per_period.
+    pub multiplier: u64,
+}
+
+
+/// Finds the pisano period, rank of apparition, zeros per period and multiplier of a modulo together.
+/// Runtime O(sqrt(modulo)), dominated by factoring.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::pisano_info;
+///
+/// let info = pisano_info(10);
+/// assert_eq!(60, info.period);
+/// assert_eq!(15, info.rank);
+/// assert_eq!(4, info.zeros_per_period);
+/// assert_eq!(7, info.multiplier);
+/// ```
+pub fn pisano_info(modulo: u64) -> PisanoInfo {
+    try_pisano_info(modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `pisano_info`, but returns an error instead of panicking.
+pub fn try_pisano_info(modulo: u64) -> Result<PisanoInfo, FibError> {
+    let period = try_pisano_period(modulo)?;
+
+    // modulo | fib(n) exactly for the multiples of the rank, so the rank is the smallest divisor
+    // of the period that still has fib ≡ 0.
+    let mut rank = period;
+    for prime in period_primes(modulo) {
+        while rank.is_multiple_of(prime) && fib_pair_u128((rank / prime) as u128, modulo).0 == 0 {
+            rank /= prime;
+        }
+    }
+
+    Ok(PisanoInfo {
+        modulo,
+        period,
+        rank,
+        zeros_per_period: period / rank,
+        multiplier: fib_pair_u128(rank as u128, modulo).1,
+    })
+}
+
+
+/// Pisano periods and ranks of apparition of every modulo up to a bound, for batch jobs that
+/// query many moduli. With the `serde` feature, it serializes, so the tables can be computed once
+/// and loaded on later runs. A `Dispatcher` given a table reduces n with its periods.
+///
+/// Deserializing trusts the tables; wrong entries give wrong results, but never panics.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::PisanoTable;
+///
+/// let table = PisanoTable::new(1_000);
+/// assert_eq!(Some(60), table.period(10));
+/// assert_eq!(Some(750), table.rank(1_000));
+/// assert_eq!(None, table.period(1_001));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PisanoTable {
+    periods: Vec<u64>,
+    ranks: Vec<u64>,
+}
+
+impl PisanoTable {
+    /// Tables of π(m) and α(m) for 1 <= m <= bound. Runtime O(bound * log(bound)), memory
+    /// O(bound)
+    ///
+    /// Each entry is the lcm of the entries of its prime power factors, and each prime power
+    /// p^e has the entries of p^(e-1), or p times them.
+    pub fn new(bound: u64) -> PisanoTable {
+        PisanoTable::try_new(bound).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `new`, but returns an error instead of panicking when the tables can't be indexed by
+    /// usize.
+    pub fn try_new(bound: u64) -> Result<PisanoTable, FibError> {
+        if bound >= usize::MAX as u64 / 2 {
+            return Err(FibError::OverflowRisk);
+        }
+        let bound = bound as usize;
+        let smallest = smallest_prime_factors(bound + 1);
+        let (mut periods, mut ranks) = (vec![0; bound + 1], vec![0; bound + 1]);
+        if bound >= 1 {
+            periods[1] = 1;
+            ranks[1] = 1;
+        }
+        for m in 2..=bound {
+            let prime = smallest[m];
+            let mut power = prime;
+            while (m / power).is_multiple_of(prime) {
+                power *= prime;
+            }
+            let (period, rank) = if m != power {
+                let rest = m / power;
+                (
+                    lcm(periods[power] as u128, periods[rest] as u128),
+                    lcm(ranks[power] as u128, ranks[rest] as u128),
+                )
+            } else if m == prime {
+                prime_entries(prime as u64, &smallest)
+            } else {
+                let (mut period, mut rank) = (periods[m / prime] as u128, ranks[m / prime] as u128);
+                if fib_pair_u128(period, m as u64) != (0, 1) {
+                    period *= prime as u128;
+                }
+                if fib_pair_u128(rank, m as u64).0 != 0 {
+                    rank *= prime as u128;
+                }
+                (period, rank)
+            };
+            periods[m] = period as u64;
+            ranks[m] = rank as u64;
+        }
+        Ok(PisanoTable { periods, ranks })
+    }
+
+    /// The largest modulo in the table.
+    pub fn bound(&self) -> u64 {
+        self.periods.len().saturating_sub(1) as u64
+    }
+
+    /// π(modulo), or None for 0 and moduli past the bound.
+    pub fn period(&self, modulo: u64) -> Option<u64> {
+        table_entry(&self.periods, modulo)
+    }
+
+    /// α(modulo), or None for 0 and moduli past the bound.
+    pub fn rank(&self, modulo: u64) -> Option<u64> {
+        table_entry(&self.ranks, modulo)
+    }
+
+    // Whether a deserialized table has a rank for every period.
+    #[cfg(feature = "persist")]
+    pub(crate) fn is_consistent(&self) -> bool {
+        self.periods.len() == self.ranks.len()
+    }
+}
+
+
+// Sorts the factors and adds up the exponents of repeated primes, checking that they're prime.
+fn merge_factors(factors: &[(u64, u32)]) -> Result<Vec<(u64, u32)>, FibError> {
+    if factors.iter().any(|&(prime, _)| !is_prime(prime)) {
+        return Err(FibError::InvalidArgument("factors must be prime"));
+    }
+    let mut merged: Vec<(u64, u32)> = Vec::with_capacity(factors.len());
+    let mut sorted = factors.to_vec();
+    sorted.sort_unstable();
+    for (prime, exponent) in sorted {
+        match merged.last_mut() {
+            Some(last) if last.0 == prime => {
+                last.1 = last.1.checked_add(exponent).ok_or(FibError::OverflowRisk)?;
+            },
+            _ => merged.push((prime, exponent)),
+        }
+    }
+    Ok(merged)
+}
+
+
+// A multiple of the pisano period of a prime.
+pub(crate) fn prime_period_multiple(prime: u64) -> u128 {
+    match prime {
+        2 => 3,
+        5 => 20,
+        _ if prime % 5 == 1 || prime % 5 == 4 => prime as u128 - 1,
+        _ => 2 * (prime as u128 + 1),
+    }
+}
+
+fn table_entry(entries: &[u64], modulo: u64) -> Option<u64> {
+    if modulo == 0 || modulo > usize::MAX as u64 {
+        return None;
+    }
+    entries.get(modulo as usize).copied()
+}
+
+
+/// Finds the pisano period of every modulo up to a bound, as a Vec with π(m) at index m and 0
+/// at index 0. Runtime O(bound * log(bound)), memory O(bound)
+///
+/// A sieve of smallest prime factors splits every modulo into prime powers, whose periods come
+/// first, each prime on its own, and then every other period is their lcm. With the `rayon`
+/// feature, both steps run in parallel.
+///
+/// Panics if the Vec can't be indexed by usize, see `try_pisano_periods`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::{pisano_period, pisano_periods};
+///
+/// let periods = pisano_periods(1_000);
+/// assert_eq!(&[0, 1, 3, 8, 6, 20, 24, 16, 12, 24, 60], &periods[..=10]);
+/// assert!((1..=1_000).all(|modulo| periods[modulo as usize] == pisano_period(modulo)));
+/// ```
+pub fn pisano_periods(bound: u64) -> Vec<u64> {
+    try_pisano_periods(bound).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `pisano_periods`, but returns an error instead of panicking.
+pub fn try_pisano_periods(bound: u64) -> Result<Vec<u64>, FibError> {
+    if bound >= usize::MAX as u64 / 2 {
+        return Err(FibError::OverflowRisk);
+    }
+    let bound = bound as usize;
+    let smallest = smallest_prime_factors(bound + 1);
+
+    // π(p^e) is π(p^(e-1)), or p times it.
+    let prime_power_periods = |prime: usize| {
+        let mut period = sieve_prime_period(prime as u64, &smallest).0;
+        let mut periods = vec![(prime, period as u64)];
+        let mut power = prime;
+        while power <= bound / prime {
+            power *= prime;
+            if fib_pair_u128(period, power as u64) != (0, 1) {
+                period *= prime as u128;
+            }
+            periods.push((power, period as u64));
+        }
+        periods
+    };
+    let primes = (2..=bound).filter(|&n| smallest[n] == n);
+    #[cfg(feature = "rayon")]
+    let prime_powers: Vec<(usize, u64)> =
+        primes.collect::<Vec<usize>>().into_par_iter().flat_map_iter(prime_power_periods).collect();
+    #[cfg(not(feature = "rayon"))]
+    let prime_powers: Vec<(usize, u64)> = primes.flat_map(prime_power_periods).collect();
+    let mut periods = vec![0; bound + 1];
+    for (power, period) in prime_powers {
+        periods[power] = period;
+    }
+
+    let period = |modulo: usize| {
+        match modulo {
+            0 => return 0,
+            1 => return 1,
+            _ if periods[modulo] != 0 => return periods[modulo],
+            _ => {},
+        }
+        let (mut rest, mut period) = (modulo, 1);
+        while rest > 1 {
+            let (prime, mut power) = (smallest[rest], 1);
+            while rest.is_multiple_of(prime) {
+                rest /= prime;
+                power *= prime;
+            }
+            period = lcm(period, periods[power] as u128);
+        }
+        period as u64
+    };
+    #[cfg(feature = "rayon")]
+    return Ok((0..=bound).into_par_iter().map(period).collect());
+    #[cfg(not(feature = "rayon"))]
+    Ok((0..=bound).map(period).collect())
+}
+
+
+/// Finds the smallest index x with fib(x) ≡ residue (mod modulo), or None if fib never hits that
+/// residue. Runtime O(sqrt(π(modulo))) time and memory, plus factoring modulo.
+///
+/// The fibonacci analog of a discrete logarithm. By Cassini's identity, fib(x + 1) is a root of
+/// b^2 - a * b - a^2 = ±1 with a = fib(x), so every candidate pair (fib(x), fib(x + 1)) is found
+/// with modular square roots, and then located within one pisano period by baby-step giant-step.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::fib_index_with_mod;
+///
+/// assert_eq!(Some(10), fib_index_with_mod(55, 1_000));
+/// assert_eq!(None, fib_index_with_mod(4, 11));
+///
+/// let x = fib_index_with_mod(123_456_789, 1_000_000_007).unwrap();
+/// assert_eq!(123_456_789, fast_fibonacci::fib_with_mod(x, 1_000_000_007));
+/// ```
+pub fn fib_index_with_mod(residue: u64, modulo: u64) -> Option<u64> {
+    try_fib_index_with_mod(residue, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_index_with_mod`, but returns an error instead of panicking.
+pub fn try_fib_index_with_mod(residue: u64, modulo: u64) -> Result<Option<u64>, FibError> {
+    let period = try_pisano_period(modulo)?;
+    Ok(period_solutions(residue, modulo, period).first().copied())
+}
+
+
+/// Iterates over every index x <= max_index with fib(x) ≡ residue (mod modulo), in increasing order.
+/// Runtime O(sqrt(π(modulo))) to set up, then O(1) per index.
+///
+/// The solutions within one pisano period are found as in `fib_index_with_mod`, and then repeated
+/// every period.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::fib_indices_with_mod;
+///
+/// // fib(n) mod 10 has period 60, and hits 5 at 5, 10, 20, 25, 35, 40, 50 and 55 within it.
+/// let indices: Vec<u64> = fib_indices_with_mod(5, 10, 90).collect();
+/// assert_eq!(vec![5, 10, 20, 25, 35, 40, 50, 55, 65, 70, 80, 85], indices);
+///
+/// assert_eq!(0, fib_indices_with_mod(4, 11, 1_000_000).count());
+/// ```
+pub fn fib_indices_with_mod(residue: u64, modulo: u64, max_index: u64) -> FibIndices {
+    try_fib_indices_with_mod(residue, modulo, max_index).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_indices_with_mod`, but returns an error instead of panicking.
+pub fn try_fib_indices_with_mod(residue: u64, modulo: u64, max_index: u64) -> Result<FibIndices, FibError> {
+    let period = try_pisano_period(modulo)?;
+    Ok(FibIndices {
+        solutions: period_solutions(residue, modulo, period),
+        period,
+        max_index,
+        base: Some(0),
+        position: 0,
+    })
+}
+
+
+/// Iterator over the indices with a given fibonacci residue, created by `fib_indices_with_mod`.
+#[derive(Clone, Debug)]
+pub struct FibIndices {
+    solutions: Vec<u64>,
+    period: u64,
+    max_index: u64,
+    // None once the next period would overflow u64.
+    base: Option<u64>,
+    position: usize,
+}
+
+impl Iterator for FibIndices {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let index = self.base?.checked_add(*self.solutions.get(self.position)?)?;
+        if index > self.max_index {
+            return None;
+        }
+        self.position += 1;
+        if self.position == self.solutions.len() {
+            self.position = 0;
+            self.base = self.base.and_then(|base| base.checked_add(self.period));
+        }
+        Some(index)
+    }
+}
+
+
+/// Lists fib(k) modulo `modulo` for k in 0..π(modulo), one full pisano period, so that
+/// fib(n) ≡ table[n % table.len()]. Runtime O(π(modulo)) additions, plus factoring modulo
+///
+/// Panics if modulo is 0, or the period doesn't fit in a usize, see `try_fib_period_table`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::fib_period_table;
+///
+/// let table = fib_period_table(10);
+/// assert_eq!(60, table.len());
+/// assert_eq!(&[0, 1, 1, 2, 3, 5, 8, 3, 1, 4], &table[..10]);
+/// assert_eq!(fast_fibonacci::fib_with_mod(1_000_000, 10), table[1_000_000 % 60]);
+/// ```
+pub fn fib_period_table(modulo: u64) -> Vec<u64> {
+    try_fib_period_table(modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_period_table`, but returns an error instead of panicking.
+pub fn try_fib_period_table(modulo: u64) -> Result<Vec<u64>, FibError> {
+    let period = try_pisano_period(modulo)?;
+    if period > usize::MAX as u64 {
+        return Err(FibError::OverflowRisk);
+    }
+    let mut table = Vec::with_capacity(period as usize);
+    table.extend(period_iter(modulo).take(period as usize));
+    Ok(table)
+}
+
+
+/// Iterates over fib(k) modulo `modulo` for k in 0..π(modulo), like `fib_period_table` without
+/// the memory. Runtime O(sqrt(modulo)) to set up, then O(1) per value.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::fib_period_iter;
+///
+/// assert_eq!(vec![0, 1, 1, 2, 0, 2, 2, 1], fib_period_iter(3).collect::<Vec<u64>>());
+/// assert_eq!(1_500, fib_period_iter(1_000).count());
+/// ```
+pub fn fib_period_iter(modulo: u64) -> FibPeriod {
+    try_fib_period_iter(modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_period_iter`, but returns an error instead of panicking.
+pub fn try_fib_period_iter(modulo: u64) -> Result<FibPeriod, FibError> {
+    let remaining = try_pisano_period(modulo)?;
+    Ok(FibPeriod { pair: (0, 1 % modulo), modulo, remaining })
+}
+
+
+/// Iterator over one pisano period of fibonacci residues, created by `fib_period_iter`.
+#[derive(Clone, Debug)]
+pub struct FibPeriod {
+    pair: (u64, u64),
+    modulo: u64,
+    remaining: u64,
+}
+
+impl Iterator for FibPeriod {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let f = self.pair.0;
+        self.pair = (self.pair.1, add_mod(self.pair.0, self.pair.1, self.modulo));
+        Some(f)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.remaining.min(usize::MAX as u64) as usize;
+        (remaining, if self.remaining > usize::MAX as u64 { None } else { Some(remaining) })
+    }
+}
+
+
+/// Finds which residues fib(n) attains modulo `modulo`, as a bitmap indexed by residue.
+/// Runtime O(π(modulo)), memory O(modulo)
+///
+/// Walks through one full pisano period.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::attained_residues;
+///
+/// let attained = attained_residues(11);
+/// let missing: Vec<usize> = (0..11).filter(|&residue| !attained[residue]).collect();
+/// assert_eq!(vec![4, 6, 7, 9], missing);
+/// ```
+pub fn attained_residues(modulo: u64) -> Vec<bool> {
+    try_attained_residues(modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `attained_residues`, but returns an error instead of panicking, including when the bitmap
+/// can't be indexed by usize.
+pub fn try_attained_residues(modulo: u64) -> Result<Vec<bool>, FibError> {
+    let period = try_pisano_period(modulo)?;
+    if modulo > usize::MAX as u64 || period > usize::MAX as u64 {
+        return Err(FibError::OverflowRisk);
+    }
+    let mut attained = vec![false; modulo as usize];
+    for f in period_iter(modulo).take(period as usize) {
+        attained[f as usize] = true;
+    }
+    Ok(attained)
+}
+
+
+/// Checks whether fib(n) ≡ residue (mod modulo) for some n. Runtime O(sqrt(π(modulo)))
+///
+/// Doesn't walk through the period, see `fib_index_with_mod`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::is_attained;
+///
+/// assert!(is_attained(3, 11));
+/// assert!(!is_attained(4, 11));
+/// assert!(is_attained(999_999_999, 1_000_000_007));
+/// ```
+pub fn is_attained(residue: u64, modulo: u64) -> bool {
+    fib_index_with_mod(residue, modulo).is_some()
+}
+
+
+/// Like `is_attained`, but returns an error instead of panicking.
+pub fn try_is_attained(residue: u64, modulo: u64) -> Result<bool, FibError> {
+    Ok(try_fib_index_with_mod(residue, modulo)?.is_some())
+}
+
+
+/// Finds fib(n) with modulo m = p1^e1 * p2^e2 * ..., given as [(p1, e1), (p2, e2), ...], without
+/// factoring anything. Runtime O(k * log(p)) for k prime powers
+///
+/// The period of each prime power p^e divides p^(e-1) times p - 1 or 2(p + 1), so n is reduced by
+/// that multiple for each of them, and the results combine by the chinese remainder theorem.
+/// Repeated primes multiply together, and the empty product is 1.
+///
+/// Panics if a factor isn't prime or m doesn't fit in a u64, see `try_fib_with_mod_factored`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::fib_with_mod_factored;
+///
+/// // 10^6 = 2^6 * 5^6
+/// assert_eq!(546_875, fib_with_mod_factored(1_000_000_000_000_000, &[(2, 6), (5, 6)]));
+/// assert_eq!(
+///     fast_fibonacci::fib_with_mod(u64::MAX, 999_999_000_001),
+///     fib_with_mod_factored(u64::MAX, &[(999_999_000_001, 1)])
+/// );
+/// ```
+pub fn fib_with_mod_factored(n: u64, factors: &[(u64, u32)]) -> u64 {
+    try_fib_with_mod_factored(n, factors).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_with_mod_factored`, but returns an error instead of panicking.
+pub fn try_fib_with_mod_factored(n: u64, factors: &[(u64, u32)]) -> Result<u64, FibError> {
+    let (mut residue, mut modulo) = (0, 1u64);
+    for (prime, exponent) in merge_factors(factors)? {
+        if exponent == 0 {
+            continue;
+        }
+        let lower_power = prime.checked_pow(exponent - 1).ok_or(FibError::OverflowRisk)?;
+        let power = lower_power.checked_mul(prime).ok_or(FibError::OverflowRisk)?;
+        let period_multiple = lower_power as u128 * prime_period_multiple(prime);
+        let fib = fib_pair_u128(n as u128 % period_multiple, power).0;
+        let product = modulo.checked_mul(power).ok_or(FibError::OverflowRisk)?;
+        residue = try_crt(residue, modulo, fib, power)?;
+        modulo = product;
+    }
+    Ok(residue)
+}
+
+
+/// Finds fib(n) with modulo for an n the caller already reduced modulo the pisano period, e.g.
+/// with periods they keep themselves, skipping the reduction. Runtime O(log(modulo))
+///
+/// fib(n) ≡ fib(n mod π(modulo)), so `fib_with_mod_reduced(n % period, modulo)` is fib(n).
+/// Debug builds check that n_reduced is below the period, finding it in O(sqrt(modulo)). Release
+/// builds take it on trust, and still return fib(n_reduced) for an index that isn't reduced.
+///
+/// Panics if modulo is 0, see `try_fib_with_mod_reduced`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::fib_with_mod_reduced;
+///
+/// // π(10^6) = 1.5 * 10^6
+/// let n_reduced = 1_000_000_000_000_000 % 1_500_000;
+/// assert_eq!(546_875, fib_with_mod_reduced(n_reduced, 1_000_000));
+/// ```
+pub fn fib_with_mod_reduced(n_reduced: u64, modulo: u64) -> u64 {
+    try_fib_with_mod_reduced(n_reduced, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_with_mod_reduced`, but returns an error instead of panicking when modulo is 0. The
+/// debug check still panics.
+pub fn try_fib_with_mod_reduced(n_reduced: u64, modulo: u64) -> Result<u64, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    // Periods past u64 can't be exceeded.
+    debug_assert!(
+        try_pisano_period(modulo).map_or(true, |period| n_reduced < period),
+        "n_reduced = {} isn't reduced modulo the pisano period of {}",
+        n_reduced,
+        modulo
+    );
+    Ok(fib_pair_u128(n_reduced as u128, modulo).0)
+}
+
+
+/// Finds fib(a1^(a2^(...^ak))) with modulo, for the power tower [a1, a2, ..., ak], without
+/// computing the tower. Runtime O(sqrt(modulo) * k), dominated by factoring.
+///
+/// The tower is reduced modulo the pisano period, with each exponent reduced modulo the totient
+/// of the modulus above it. `[a, b]` is a^b, and `[a]` is a itself.
+///
+/// Panics if modulo is 0, the tower is empty or the period doesn't fit in a u64, see
+/// `try_fib_of_power_tower_with_mod`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::fib_of_power_tower_with_mod;
+///
+/// // fib(2^10) = fib(1024)
+/// assert_eq!(fast_fibonacci::fib_with_mod(1_024, 100), fib_of_power_tower_with_mod(&[2, 10], 100));
+/// // fib(7^7^7), whose index has about 7 * 10^5 digits
+/// assert_eq!(983_596_201, fib_of_power_tower_with_mod(&[7, 7, 7], 1_000_000_007));
+/// ```
+pub fn fib_of_power_tower_with_mod(tower: &[u64], modulo: u64) -> u64 {
+    try_fib_of_power_tower_with_mod(tower, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_of_power_tower_with_mod`, but returns an error instead of panicking.
+pub fn try_fib_of_power_tower_with_mod(tower: &[u64], modulo: u64) -> Result<u64, FibError> {
+    if tower.is_empty() {
+        return Err(FibError::InvalidArgument("power tower must not be empty"));
+    }
+    let period = try_pisano_period(modulo)?;
+    Ok(fib_pair_u128(tower_mod(tower, period) as u128, modulo).0)
+}
+
+
+/// Finds fib(k!) with modulo, without computing k!. Runtime O(sqrt(modulo) + min(k, π(modulo)))
+///
+/// Panics if modulo is 0 or the period doesn't fit in a u64, see `try_fib_of_factorial_with_mod`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::fib_of_factorial_with_mod;
+///
+/// // fib(5!) = fib(120)
+/// assert_eq!(fast_fibonacci::fib_with_mod(120, 1_000), fib_of_factorial_with_mod(5, 1_000));
+/// // π(1_000) = 1_500 divides 1_000_000!, so fib(1_000_000!) ≡ fib(0)
+/// assert_eq!(0, fib_of_factorial_with_mod(1_000_000, 1_000));
+/// ```
+pub fn fib_of_factorial_with_mod(k: u64, modulo: u64) -> u64 {
+    try_fib_of_factorial_with_mod(k, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_of_factorial_with_mod`, but returns an error instead of panicking.
+pub fn try_fib_of_factorial_with_mod(k: u64, modulo: u64) -> Result<u64, FibError> {
+    let period = try_pisano_period(modulo)?;
+    // Once the product reaches 0, it stays there, which happens by k = period at the latest.
+    let mut index = 1 % period;
+    for i in 2..=k {
+        if index == 0 {
+            break;
+        }
+        index = mul_mod(index, i % period, period);
+    }
+    Ok(fib_pair_u128(index as u128, modulo).0)
+}
+
+
+/// Finds the nth fibonacci number with modulo, reading n in decimal from `reader`. Runtime O(length
+/// of n + sqrt(modulo)), in constant memory.
+///
+/// n is reduced modulo the pisano period as it's read, so it can have any number of digits, e.g. a
+/// file of gigabytes of them. `_` separators and surrounding whitespace are ignored. Anything else
+/// besides digits is an `InvalidData` error, and the errors of `try_pisano_period` come back as
+/// `InvalidInput` errors wrapping the `FibError`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::pisano::fib_with_mod_from_reader;
+///
+/// let n = "1_000_000_000_000_000\n";
+/// assert_eq!(546_875, fib_with_mod_from_reader(n.as_bytes(), 1_000_000).unwrap());
+/// ```
+pub fn fib_with_mod_from_reader<R: Read>(reader: R, modulo: u64) -> io::Result<u64> {
+    let period = try_pisano_period(modulo).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let mut reader = BufReader::new(reader);
+    let (mut index, mut digits, mut trailing_space) = (0u64, 0u64, false);
+    // Up to 19 digits at a time, which fit in a u64 whatever they are.
+    let (mut chunk, mut chunk_scale) = (0u64, 1u64);
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+        for &byte in buffer {
+            match byte {
+                b'0'..=b'9' if !trailing_space => {
+                    chunk = chunk * 10 + (byte - b'0') as u64;
+                    chunk_scale *= 10;
+                    digits += 1;
+                    if chunk_scale == 10_000_000_000_000_000_000 {
+                        index = ((index as u128 * chunk_scale as u128 + chunk as u128) % period as u128) as u64;
+                        chunk = 0;
+                        chunk_scale = 1;
+                    }
+                }
+                b'_' if !trailing_space => {}
+                _ if byte.is_ascii_whitespace() => trailing_space = digits > 0,
+                _ => return Err(invalid("expected a non-negative decimal integer")),
+            }
+        }
+        let length = buffer.len();
+        reader.consume(length);
+    }
+    if digits == 0 {
+        return Err(invalid("expected a non-negative decimal integer, found no digits"));
+    }
+    index = ((index as u128 * chunk_scale as u128 + chunk as u128) % period as u128) as u64;
+    Ok(fib_pair_u128(index as u128, modulo).0)
+}
+
+
+// The power tower modulo a positive modulo. a^e ≡ a^(e mod φ(m) + φ(m)) (mod m) for any
+// e >= log2(m), since no prime divides m more than log2(m) times.
+fn tower_mod(tower: &[u64], modulo: u64) -> u64 {
+    let base = tower[0];
+    if tower.len() == 1 || modulo == 1 {
+        return base % modulo;
+    }
+    let exponent = tower_capped(&tower[1..], 64);
+    if exponent < 64 {
+        return pow_mod(base, exponent, modulo);
+    }
+    let phi = totient(modulo);
+    let reduced = tower_mod(&tower[1..], phi);
+    mul_mod(pow_mod(base, reduced, modulo), pow_mod(base, phi, modulo), modulo)
+}
+
+
+// min(the power tower, cap), for a cap of at most 64.
+fn tower_capped(tower: &[u64], cap: u64) -> u64 {
+    let base = tower[0].min(cap);
+    if tower.len() == 1 {
+        return base;
+    }
+    let exponent = tower_capped(&tower[1..], cap);
+    match base {
+        0 if exponent == 0 => 1,
+        0 | 1 => base,
+        // 2^64 > cap, so the loop runs at most 64 times.
+        _ => (0..exponent).fold(1u64, |power, _| (power * base).min(cap)),
+    }
+}
+
+
+// Every x < period with fib(x) ≡ residue, in increasing order.
+fn period_solutions(residue: u64, modulo: u64, period: u64) -> Vec<u64> {
+    let residue = residue % modulo;
+    if period <= LINEAR_SCAN_LIMIT {
+        return period_iter(modulo)
+            .take(period as usize)
+            .enumerate()
+            .filter(|&(_, f)| f == residue)
+            .map(|(x, _)| x as u64)
+            .collect();
+    }
+
+    let factors = factorize(modulo);
+    let mut solutions = Vec::new();
+    for &cassini_sign in &[1, modulo - 1] {
+        for next in cassini_roots(residue, cassini_sign, &factors) {
+            solutions.extend(pair_index((residue, next), period, modulo));
+        }
+    }
+    solutions.sort_unstable();
+    solutions.dedup();
+    solutions
+}
+
+
+// fib(0), fib(1), fib(2), ... modulo `modulo`.
+fn period_iter(modulo: u64) -> impl Iterator<Item = u64> {
+    let mut pair = (0, 1 % modulo);
+    std::iter::from_fn(move || {
+        let f = pair.0;
+        pair = (pair.1, add_mod(pair.0, pair.1, modulo));
+        Some(f)
+    })
+}
+
+
+// Every prime that can divide π(modulo), possibly with some extras.
+fn period_primes(modulo: u64) -> Vec<u64> {
+    let mut primes = vec![2, 3, 5];
+    for (prime, _) in factorize(modulo) {
+        primes.push(prime);
+        primes.extend(factorize(prime - 1).into_iter().map(|(p, _)| p));
+        primes.extend(factorize(prime + 1).into_iter().map(|(p, _)| p));
+    }
+    primes.sort_unstable();
+    primes.dedup();
+    primes
+}
+
+
+// The lcm of the prime power periods, or OverflowRisk once it's past u64.
+fn period_of_factors(factors: &[(u64, u32)]) -> Result<u64, FibError> {
+    let mut period = 1;
+    for &(prime, exponent) in factors {
+        if exponent == 0 {
+            continue;
+        }
+        let prime_power_period = prime_power_period(prime, exponent).ok_or(FibError::OverflowRisk)?;
+        period = lcm(period, prime_power_period);
+        if period > u64::MAX as u128 {
+            return Err(FibError::OverflowRisk);
+        }
+    }
+    Ok(period as u64)
+}
+
+
+// None once the period is past u128.
+fn prime_power_period(prime: u64, exponent: u32) -> Option<u128> {
+    let period = prime_period(prime);
+    let unchanged = unchanged_exponent(&BigUint::from(prime), exponent, &BigUint::from(period));
+    (prime as u128).checked_pow(exponent - unchanged)?.checked_mul(period)
+}
+
+
+// The largest t <= exponent with π(prime^t) = π(prime), which is 1 unless prime is a
+// Wall-Sun-Sun prime. π(prime^exponent) = prime^(exponent - t) * π(prime).
+fn unchanged_exponent(prime: &BigUint, exponent: u32, period: &BigUint) -> u32 {
+    let identity = (BigUint::zero(), BigUint::one());
+    let mut unchanged = 1;
+    let mut power = prime * prime;
+    while unchanged < exponent && big_fib_pair(period, &power) == identity {
+        unchanged += 1;
+        power *= prime;
+    }
+    unchanged
+}
+
+
+fn prime_period(prime: u64) -> u128 {
+    match prime {
+        2 => return 3,
+        5 => return 20,
+        _ => {}
+    }
+    // T^multiple = I for the matrix T = [[0, 1], [1, 1]], so the period divides multiple.
+    let (multiple, mut factors) = match prime % 5 {
+        1 | 4 => (prime as u128 - 1, factorize(prime - 1)),
+        _ => (2 * (prime as u128 + 1), factorize(prime + 1)),
+    };
+    if multiple % 2 == 0 && !factors.iter().any(|&(p, _)| p == 2) {
+        factors.insert(0, (2, 1));
+    }
+
+    let mut period = multiple;
+    for (factor, _) in factors {
+        let factor = factor as u128;
+        while period % factor == 0 && fib_pair_u128(period / factor, prime) == (0, 1) {
+            period /= factor;
+        }
+    }
+    period
+}
+
+
+// The lcm of the prime power periods.
+fn big_period_of_factors(factors: &[(BigUint, u32)]) -> BigUint {
+    factors
+        .iter()
+        .filter(|&&(_, exponent)| exponent > 0)
+        .fold(BigUint::one(), |period, (prime, exponent)| {
+            let prime_period = big_prime_period(prime);
+            let unchanged = unchanged_exponent(prime, *exponent, &prime_period);
+            period.lcm(&(num::pow(prime.clone(), (exponent - unchanged) as usize) * prime_period))
+        })
+}
+
+
+// prime_period for BigUint, factoring p ± 1 with Pollard's rho.
+fn big_prime_period(prime: &BigUint) -> BigUint {
+    if let Some(prime) = prime.to_u64() {
+        return BigUint::from(prime_period(prime));
+    }
+    let multiple = match (prime % 5u32).to_u32() {
+        Some(1) | Some(4) => prime - 1u32,
+        _ => (prime + 1u32) * 2u32,
+    };
+    let identity = (BigUint::zero(), BigUint::one());
+    let mut period = multiple.clone();
+    for (factor, _) in big_factorize(&multiple) {
+        while (&period % &factor).is_zero() && big_fib_pair(&(&period / &factor), prime) == identity {
+            period /= &factor;
+        }
+    }
+    period
+}
+
+
+// (fib(n), fib(n + 1)) with a BigUint modulo, by fast doubling over the bits of n.
+fn big_fib_pair(n: &BigUint, modulo: &BigUint) -> (BigUint, BigUint) {
+    let n_bytes = n.to_bytes_le();
+    let (mut a, mut b) = (BigUint::zero(), BigUint::one() % modulo);
+    for bit in (0..n.bits()).rev() {
+        let even = &a * ((&b + &b + modulo - &a) % modulo) % modulo;
+        let odd = (&a * &a + &b * &b) % modulo;
+        if n_bytes[bit / 8] >> (bit % 8) & 1 == 1 {
+            b = (&even + &odd) % modulo;
+            a = odd;
+        } else {
+            a = even;
+            b = odd;
+        }
+    }
+    (a, b)
+}
+
+
+// The smallest prime factor of every n <= bound, with 0 for 0 and 1.
+fn smallest_prime_factors(bound: usize) -> Vec<usize> {
+    let mut smallest = vec![0; bound + 1];
+    for n in 2..=bound {
+        if smallest[n] != 0 {
+            continue;
+        }
+        for multiple in (n..=bound).step_by(n) {
+            if smallest[multiple] == 0 {
+                smallest[multiple] = n;
+            }
+        }
+    }
+    smallest
+}
+
+
+// π(prime) and α(prime), see sieve_prime_period.
+fn prime_entries(prime: u64, smallest: &[usize]) -> (u128, u128) {
+    let (period, primes) = sieve_prime_period(prime, smallest);
+    let mut rank = period;
+    for &factor in &primes {
+        while rank % factor == 0 && fib_pair_u128(rank / factor, prime).0 == 0 {
+            rank /= factor;
+        }
+    }
+    (period, rank)
+}
+
+
+// π(prime) like prime_period, but factoring prime ± 1 with the sieve, and the primes that can
+// divide it.
+fn sieve_prime_period(prime: u64, smallest: &[usize]) -> (u128, Vec<u128>) {
+    match prime {
+        2 => return (3, vec![3]),
+        5 => return (20, vec![2, 5]),
+        _ => {}
+    }
+    let (multiple, mut rest) = match prime % 5 {
+        1 | 4 => (prime as u128 - 1, prime as usize - 1),
+        _ => (2 * (prime as u128 + 1), prime as usize + 1),
+    };
+    let mut primes = vec![2];
+    while rest > 1 {
+        let factor = smallest[rest];
+        primes.push(factor as u128);
+        while rest.is_multiple_of(factor) {
+            rest /= factor;
+        }
+    }
+
+    let mut period = multiple;
+    for &factor in &primes {
+        while period % factor == 0 && fib_pair_u128(period / factor, prime) == (0, 1) {
+            period /= factor;
+        }
+    }
+    (period, primes)
+}
+
+
+// All b modulo the full modulo with b^2 - a * b - a^2 - sign ≡ 0, combining prime powers by CRT.
+fn cassini_roots(a: u64, sign: u64, factors: &[(u64, u32)]) -> Vec<u64> {
+    let mut roots = vec![0];
+    let mut modulo = 1;
+    for &(prime, exponent) in factors {
+        let prime_power = prime.pow(exponent);
+        let sign = if sign == 1 { 1 % prime_power } else { prime_power - 1 };
+        let local_roots = cassini_roots_prime_power(a % prime_power, sign, prime, exponent);
+        roots = roots
+            .iter()
+            .flat_map(|&root| local_roots.iter().map(move |&local| crt(root, modulo, local, prime_power)))
+            .collect();
+        modulo *= prime_power;
+    }
+    roots
+}
+
+
+// Roots modulo prime^exponent, found modulo prime and then lifted one power at a time.
+fn cassini_roots_prime_power(a: u64, sign: u64, prime: u64, exponent: u32) -> Vec<u64> {
+    let f = |b: u64, modulo: u64| {
+        let (a, b, sign) = (a % modulo, b % modulo, sign % modulo);
+        let b_terms = sub_mod(mul_mod(b, b, modulo), mul_mod(a, b, modulo), modulo);
+        sub_mod(sub_mod(b_terms, mul_mod(a, a, modulo), modulo), sign, modulo)
+    };
+
+    let mut roots: Vec<u64> = if prime < 64 {
+        (0..prime).filter(|&b| f(b, prime) == 0).collect()
+    } else {
+        // b = (a ± sqrt(5a^2 + 4 * sign)) / 2
+        let discriminant = add_mod(mul_mod(5, mul_mod(a, a, prime), prime), mul_mod(4, sign, prime), prime);
+        let half = prime.div_ceil(2);
+        match sqrt_mod_prime(discriminant, prime) {
+            Some(root) => {
+                let mut roots = vec![
+                    mul_mod(add_mod(a, root, prime), half, prime),
+                    mul_mod(sub_mod(a % prime, root, prime), half, prime),
+                ];
+                roots.dedup();
+                roots
+            },
+            None => vec![],
+        }
+    };
+
+    let mut modulo = prime;
+    for _ in 1..exponent {
+        let next_modulo = modulo * prime;
+        let mut lifted = Vec::new();
+        for &root in &roots {
+            let derivative = sub_mod(add_mod(root, root, prime), a % prime, prime);
+            if derivative != 0 {
+                // Newton step: root - f(root) / f'(root)
+                let full_derivative = sub_mod(add_mod(root, root, next_modulo), a % next_modulo, next_modulo);
+                let inverse = mod_inverse(full_derivative, next_modulo).unwrap();
+                lifted.push(sub_mod(root, mul_mod(f(root, next_modulo), inverse, next_modulo), next_modulo));
+            } else {
+                lifted.extend((0..prime).map(|t| root + t * modulo).filter(|&b| f(b, next_modulo) == 0));
+            }
+        }
+        lifted.sort_unstable();
+        lifted.dedup();
+        roots = lifted;
+        modulo = next_modulo;
+    }
+    roots
+}
+
+
+// The smallest x < period with (fib(x), fib(x + 1)) = pair, by baby-step giant-step.
+fn pair_index(pair: (u64, u64), period: u64, modulo: u64) -> Option<u64> {
+    let step = (period as f64).sqrt().ceil() as u64;
+
+    // Baby steps: T^j * pair, for x = i * step - j.
+    let mut baby_steps = HashMap::new();
+    let mut state = pair;
+    for j in 0..step {
+        baby_steps.insert(state, j);
+        state = (state.1, add_mod(state.0, state.1, modulo));
+    }
+
+    let giant_step = fib_pair_u128(step as u128, modulo);
+    let mut giant = (0, 1 % modulo);
+    for i in 0..=period / step + 1 {
+        if let Some(&j) = baby_steps.get(&giant) {
+            if i * step >= j && i * step - j < period {
+                return Some(i * step - j);
+            }
+        }
+        giant = fib_add_indices(giant, giant_step, modulo);
+    }
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::pisano::*;
+
+    fn naive_period(modulo: u64) -> u64 {
+        let mut pair = (0, 1 % modulo);
+        let mut period = 0;
+        loop {
+            pair = (pair.1, (pair.0 + pair.1) % modulo);
+            period += 1;
+            if pair == (0, 1 % modulo) {
+                return period;
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let info = pisano_info(10);
+        let json = serde_json::to_string(&info).unwrap();
+        assert_eq!(json, r#"{"modulo":10,"period":60,"rank":15,"zeros_per_period":4,"multiplier":7}"#);
+        assert_eq!(serde_json::from_str::<PisanoInfo>(&json).unwrap(), info);
+        let table = PisanoTable::new(5);
+        let json = serde_json::to_string(&table).unwrap();
+        assert_eq!(json, r#"{"periods":[0,1,3,8,6,20],"ranks":[0,1,3,4,6,5]}"#);
+        assert_eq!(serde_json::from_str::<PisanoTable>(&json).unwrap(), table);
+        assert_eq!(serde_json::to_string(&FibError::ZeroModulus).unwrap(), r#""ZeroModulus""#);
+    }
+
+    #[test]
+    fn test_try_errors() {
+        assert_eq!(try_rank_of_apparition(0), Err(FibError::ZeroModulus));
+        assert_eq!(try_pisano_info(0), Err(FibError::ZeroModulus));
+        assert_eq!(try_fib_index_with_mod(1, 0), Err(FibError::ZeroModulus));
+        assert!(try_fib_indices_with_mod(1, 0, 10).is_err());
+        assert_eq!(try_attained_residues(0), Err(FibError::ZeroModulus));
+        assert_eq!(try_is_attained(4, 11), Ok(false));
+        assert_eq!(try_fib_of_power_tower_with_mod(&[], 10), Err(FibError::InvalidArgument("power tower must not be empty")));
+        assert_eq!(try_fib_of_power_tower_with_mod(&[2, 3], 0), Err(FibError::ZeroModulus));
+        assert_eq!(try_fib_of_factorial_with_mod(5, 0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_fib_of_power_tower_matches_exact() {
+        // Towers small enough to evaluate, including 0^0 = 1 and exponents near the cap of 64.
+        let towers: &[(&[u64], u64)] = &[
+            (&[0], 0),
+            (&[12], 12),
+            (&[0, 0], 1),
+            (&[0, 5], 0),
+            (&[1, 1_000], 1),
+            (&[2, 63], 1 << 63),
+            (&[3, 40], 3u64.pow(40)),
+            (&[2, 2, 2, 2], 65_536),
+            (&[2, 3, 2], 512),
+            (&[5, 0, 7], 1),
+            (&[7, 1, 0], 7),
+        ];
+        for &(tower, n) in towers {
+            for &modulo in &[1, 2, 10, 64, 1_000, 1_024, 720, 1_000_000_007] {
+                assert_eq!(
+                    fib_of_power_tower_with_mod(tower, modulo),
+                    crate::fib_with_mod(n, modulo),
+                    "{:?}, modulo = {}", tower, modulo
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_tower_mod_matches_big_arithmetic() {
+        use num_bigint::BigUint;
+        use num::ToPrimitive;
+
+        // a^(b^c) with the exponent past u64, reduced with BigUint modpow.
+        for &(a, b, c) in &[(2, 10, 20), (3, 7, 23), (6, 12, 18), (10, 3, 41), (0, 2, 70), (7, 7, 7)] {
+            let exponent = num::pow(BigUint::from(b), c);
+            for &modulo in &[1u64, 2, 12, 1_000, 1_536, 2_000_000_016, 999_999_999_989] {
+                let expected = BigUint::from(a).modpow(&exponent, &BigUint::from(modulo)).to_u64().unwrap();
+                assert_eq!(tower_mod(&[a, b, c as u64], modulo), expected, "{}^{}^{} mod {}", a, b, c, modulo);
+            }
+        }
+    }
+
+    struct OneByteAtATime<'a>(&'a [u8]);
+
+    impl Read for OneByteAtATime<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let length = buf.len().min(self.0.len()).min(1);
+            buf[..length].copy_from_slice(&self.0[..length]);
+            self.0 = &self.0[length..];
+            Ok(length)
+        }
+    }
+
+    #[test]
+    fn test_fib_with_mod_from_reader() {
+        let digits = "1234567890".repeat(100);
+        let n = num_bigint::BigUint::parse_bytes(digits.as_bytes(), 10).unwrap();
+        for &modulo in &[1, 10, 1_000_000_007, 999_999_999_989] {
+            let expected = num::ToPrimitive::to_u64(&crate::bigfib_with_mod(&n, &modulo.into())).unwrap();
+            assert_eq!(fib_with_mod_from_reader(digits.as_bytes(), modulo).unwrap(), expected, "modulo = {}", modulo);
+            let padded = format!(" \n{}\r\n", digits);
+            assert_eq!(fib_with_mod_from_reader(OneByteAtATime(padded.as_bytes()), modulo).unwrap(), expected);
+        }
+        assert_eq!(fib_with_mod_from_reader("1_000_000_000_000_000".as_bytes(), 1_000_000).unwrap(), 546_875);
+        assert_eq!(fib_with_mod_from_reader("0".as_bytes(), 10).unwrap(), 0);
+
+        for &bad in &["", "  \n", "12a", "-1", "1 2", "1e5"] {
+            let err = fib_with_mod_from_reader(bad.as_bytes(), 10).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{:?}", bad);
+        }
+        let err = fib_with_mod_from_reader("10".as_bytes(), 0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.into_inner().unwrap().downcast::<FibError>().unwrap(), Box::new(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_fib_of_factorial() {
+        let mut factorial = 1u64;
+        for k in 0..=20 {
+            factorial *= k.max(1);
+            for &modulo in &[1, 7, 10, 1_000, 1_000_000_007] {
+                assert_eq!(fib_of_factorial_with_mod(k, modulo), crate::fib_with_mod(factorial, modulo));
+            }
+        }
+        assert_eq!(fib_of_factorial_with_mod(u64::MAX, 1_000_000_007), 0);
+    }
+
+    #[test]
+    fn test_pisano_period_matches_naive() {
+        for modulo in 1..2_000 {
+            assert_eq!(pisano_period(modulo), naive_period(modulo), "modulo = {}", modulo);
+        }
+        for &modulo in &[1_000_003, 1_000_033, 999_983 * 2, 7_u64.pow(7)] {
+            assert_eq!(pisano_period(modulo), naive_period(modulo), "modulo = {}", modulo);
+        }
+    }
+
+    #[test]
+    fn test_small_periods_match_factoring() {
+        assert_eq!(SMALL_PERIODS[0], 0);
+        for modulo in 1..=SMALL_PERIODS_BOUND + 1 {
+            let factored = period_of_factors(&factorize(modulo));
+            assert_eq!(try_pisano_period(modulo), factored, "modulo = {}", modulo);
+        }
+    }
+
+    #[test]
+    fn test_fib_with_mod_reduced() {
+        for &modulo in &[1, 2, 10, 1_000, 1_000_000_007, 18_446_744_073_709_551_557] {
+            let period = pisano_period(modulo);
+            for &n in &[0, 1, 1_000, 1 << 45, u64::MAX] {
+                let expected = crate::fib_with_mod(n, modulo);
+                assert_eq!(fib_with_mod_reduced(n % period, modulo), expected, "n = {}, modulo = {}", n, modulo);
+            }
+            assert_eq!(fib_with_mod_reduced(period - 1, modulo), crate::fib_with_mod(period - 1, modulo));
+        }
+        assert_eq!(try_fib_with_mod_reduced(1, 0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "isn't reduced")]
+    fn test_fib_with_mod_reduced_checks_index() {
+        fib_with_mod_reduced(60, 10);
+    }
+
+    #[test]
+    fn test_pisano_period_of_product() {
+        for modulo in 1..1_000 {
+            assert_eq!(pisano_period_of_product(&factorize(modulo)), pisano_period(modulo), "modulo = {}", modulo);
+        }
+        assert_eq!(pisano_period_of_product(&[]), 1);
+        assert_eq!(pisano_period_of_product(&[(5, 2), (2, 1), (5, 1), (3, 0)]), pisano_period(250));
+        assert_eq!(try_pisano_period_of_product(&[(4, 1)]), Err(FibError::InvalidArgument("factors must be prime")));
+        assert_eq!(try_pisano_period_of_product(&[(1_000_000_007, 3)]), Err(FibError::OverflowRisk));
+        assert_eq!(try_pisano_period_of_product(&[(2, 200)]), Err(FibError::OverflowRisk));
+    }
+
+    #[test]
+    fn test_fib_with_mod_factored() {
+        for modulo in 1..1_000 {
+            for &n in &[0, 1, 2, 99, 1_000_000, u64::MAX] {
+                let fib = fib_with_mod_factored(n, &factorize(modulo));
+                assert_eq!(fib, crate::fib_with_mod(n, modulo), "n = {}, modulo = {}", n, modulo);
+            }
+        }
+        assert_eq!(fib_with_mod_factored(u64::MAX, &[]), 0);
+        assert_eq!(fib_with_mod_factored(100, &[(5, 2), (2, 1), (5, 1), (3, 0)]), crate::fib_with_mod(100, 250));
+        let modulo = 4_294_967_291 * 65_521;
+        assert_eq!(fib_with_mod_factored(u64::MAX, &[(4_294_967_291, 1), (65_521, 1)]), crate::fib_with_mod(u64::MAX, modulo));
+        assert_eq!(try_fib_with_mod_factored(1, &[(4, 1)]), Err(FibError::InvalidArgument("factors must be prime")));
+        assert_eq!(try_fib_with_mod_factored(1, &[(1_000_000_007, 3)]), Err(FibError::OverflowRisk));
+        assert_eq!(try_fib_with_mod_factored(1, &[(2, 40), (3, 40)]), Err(FibError::OverflowRisk));
+    }
+
+    #[test]
+    fn test_big_pisano_period() {
+        for modulo in 1..500 {
+            assert_eq!(big_pisano_period(&BigUint::from(modulo)), BigUint::from(pisano_period(modulo)), "modulo = {}", modulo);
+        }
+        // Primes past u64, each 1, 2, 3 or 4 modulo 5.
+        for &prime in &[(1u128 << 127) - 1, (1 << 89) - 1, 18_446_744_073_709_551_629, 18_446_744_073_709_551_653] {
+            let prime = BigUint::from(prime);
+            let period = big_pisano_period(&prime);
+            assert_eq!(big_fib_pair(&period, &prime), (BigUint::zero(), BigUint::one()));
+            let residue = (&prime % 5u32).to_u32().unwrap();
+            let multiple = if residue == 1 || residue == 4 { &prime - 1u32 } else { (&prime + 1u32) * 2u32 };
+            assert!((&multiple % &period).is_zero());
+            for (factor, _) in big_factorize(&period) {
+                assert_ne!(big_fib_pair(&(&period / &factor), &prime), (BigUint::zero(), BigUint::one()));
+            }
+        }
+        let product = [(BigUint::from(1_000_000_007u32), 2), (BigUint::from(10u32), 0), (BigUint::from(3u32), 1)];
+        assert_eq!(
+            try_big_pisano_period_of_product(&product),
+            Err(FibError::InvalidArgument("factors must be prime"))
+        );
+        assert_eq!(
+            big_pisano_period_of_product(&product[..1]),
+            BigUint::from(pisano_period_of_product(&[(1_000_000_007, 2)]))
+        );
+        assert_eq!(try_big_pisano_period(&BigUint::zero()), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_pisano_period_prime_power() {
+        for &prime in &[2u64, 3, 5, 7, 11, 13] {
+            for exponent in 0..=5 {
+                let modulo = prime.pow(exponent);
+                assert_eq!(pisano_period_prime_power(prime, exponent), naive_period(modulo), "modulo = {}", modulo);
+            }
+        }
+        assert_eq!(try_pisano_period_prime_power(9, 2), Err(FibError::InvalidArgument("factors must be prime")));
+        assert_eq!(try_pisano_period_prime_power(1_000_000_007, 3), Err(FibError::OverflowRisk));
+    }
+
+    #[test]
+    fn test_unchanged_exponent() {
+        // No known prime has π(p^2) = π(p).
+        for &prime in &[2u32, 5, 1_000_000_007] {
+            let prime = BigUint::from(prime);
+            let period = big_prime_period(&prime);
+            assert_eq!(unchanged_exponent(&prime, 5, &period), 1);
+        }
+        // Passing a multiple of π(p^3) as the period acts like a prime whose period stays put.
+        let period = BigUint::from(pisano_period(125));
+        assert_eq!(unchanged_exponent(&BigUint::from(5u32), 5, &period), 3);
+    }
+
+    #[test]
+    fn test_pisano_table_matches_pisano_info() {
+        let table = PisanoTable::new(3_000);
+        assert_eq!(table.bound(), 3_000);
+        for modulo in 1..=3_000 {
+            let info = pisano_info(modulo);
+            assert_eq!(table.period(modulo), Some(info.period), "modulo = {}", modulo);
+            assert_eq!(table.rank(modulo), Some(info.rank), "modulo = {}", modulo);
+        }
+        assert_eq!((table.period(0), table.rank(3_001)), (None, None));
+        assert_eq!(PisanoTable::new(0).period(1), None);
+        assert_eq!(PisanoTable::new(1).period(1), Some(1));
+    }
+
+    #[test]
+    fn test_pisano_periods_matches_table() {
+        let table = PisanoTable::new(20_000);
+        let periods = pisano_periods(20_000);
+        assert_eq!(periods.len(), 20_001);
+        assert_eq!(periods[0], 0);
+        for modulo in 1..=20_000 {
+            assert_eq!(Some(periods[modulo as usize]), table.period(modulo), "modulo = {}", modulo);
+        }
+        assert_eq!(pisano_periods(0), vec![0]);
+        assert_eq!(pisano_periods(1), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_fib_period_table() {
+        for modulo in 1..300 {
+            let table = fib_period_table(modulo);
+            assert_eq!(table.len() as u64, naive_period(modulo));
+            assert!(fib_period_iter(modulo).eq(table.iter().copied()));
+            for n in (0..10_000).step_by(37) {
+                assert_eq!(table[(n % table.len() as u64) as usize], crate::fib_with_mod(n, modulo));
+            }
+        }
+        assert_eq!(try_fib_period_table(0), Err(FibError::ZeroModulus));
+        assert!(try_fib_period_iter(0).is_err());
+        let mut iter = fib_period_iter(3);
+        assert_eq!(iter.size_hint(), (8, Some(8)));
+        iter.nth(6);
+        assert_eq!((iter.size_hint(), iter.next(), iter.next()), ((1, Some(1)), Some(1), None));
+    }
+
+    #[test]
+    fn test_pisano_period_large() {
+        let prime = 1_000_000_000_039;
+        let period = pisano_period(prime);
+        assert_eq!((prime - 1) % period, 0);
+        assert_eq!(fib_pair_u128(period as u128, prime), (0, 1));
+    }
+
+    #[test]
+    fn test_fib_index_matches_naive() {
+        for &modulo in &[1, 2, 10, 11, 100, 1_000, 4_181, 10_007, 20_014, 10_007 * 10_009, 3_u64.pow(12)] {
+            let period = pisano_period(modulo);
+            let mut first_seen = HashMap::new();
+            for (x, f) in period_iter(modulo).take(period as usize).enumerate() {
+                first_seen.entry(f).or_insert(x as u64);
+            }
+            for residue in (0..modulo).step_by((modulo / 500).max(1) as usize) {
+                assert_eq!(
+                    fib_index_with_mod(residue, modulo),
+                    first_seen.get(&residue).copied(),
+                    "residue = {}, modulo = {}", residue, modulo
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fib_indices_matches_naive() {
+        for &modulo in &[1, 2, 10, 11, 89, 10_007, 3_u64.pow(9)] {
+            let max_index = 3 * pisano_period(modulo) + 17;
+            let values: Vec<u64> = period_iter(modulo).take(max_index as usize + 1).collect();
+            for residue in (0..modulo).step_by((modulo / 50).max(1) as usize) {
+                let expected: Vec<u64> = (0..=max_index).filter(|&x| values[x as usize] == residue).collect();
+                assert_eq!(
+                    fib_indices_with_mod(residue, modulo, max_index).collect::<Vec<u64>>(),
+                    expected,
+                    "residue = {}, modulo = {}", residue, modulo
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fib_indices_stops_at_u64_max() {
+        let indices = FibIndices {
+            solutions: vec![0, 5],
+            period: u64::MAX - 2,
+            max_index: u64::MAX,
+            base: Some(0),
+            position: 0,
+        };
+        assert_eq!(indices.collect::<Vec<u64>>(), vec![0, 5, u64::MAX - 2]);
+    }
+
+    #[test]
+    fn test_attained_residues() {
+        assert_eq!(attained_residues(1), vec![true]);
+        assert_eq!(attained_residues(8), vec![true, true, true, true, false, true, false, true]);
+        for &modulo in &[11, 29, 10_007, 20_000] {
+            let attained = attained_residues(modulo);
+            for residue in (0..modulo).step_by((modulo / 300).max(1) as usize) {
+                assert_eq!(attained[residue as usize], is_attained(residue, modulo), "residue = {}", residue);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pisano_info_matches_naive() {
+        for modulo in 1..1_000 {
+            let period = naive_period(modulo);
+            let values: Vec<u64> = period_iter(modulo).take(period as usize + 2).collect();
+            let rank = (1..).find(|&n| values[n] == 0).unwrap() as u64;
+            let expected = PisanoInfo {
+                modulo,
+                period,
+                rank,
+                zeros_per_period: values[..period as usize].iter().filter(|&&f| f == 0).count() as u64,
+                multiplier: values[rank as usize + 1],
+            };
+            assert_eq!(pisano_info(modulo), expected, "modulo = {}", modulo);
+        }
+    }
+
+    #[test]
+    fn test_rank_of_apparition_large() {
+        let prime = 1_000_000_000_039;
+        let rank = rank_of_apparition(prime);
+        assert_eq!(fib_pair_u128(rank as u128, prime).0, 0);
+        assert_eq!(pisano_period(prime) % rank, 0);
+    }
+
+    #[test]
+    fn test_fib_index_large() {
+        let modulo = 1_000_000_007;
+        for &x in &[0, 1, 2, 12_345, 987_654_321, 1_999_999_999] {
+            let residue = crate::fib_with_mod(x, modulo);
+            let found = fib_index_with_mod(residue, modulo).unwrap();
+            assert!(found <= x);
+            assert_eq!(crate::fib_with_mod(found, modulo), residue);
+        }
+    }
+}
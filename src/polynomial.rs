@@ -0,0 +1,180 @@
+//! Fibonacci polynomials, F_0(x) = 0, F_1(x) = 1, F_n(x) = x * F_{n-1}(x) + F_{n-2}(x), and
+//! lucas polynomials, L_0(x) = 2, L_1(x) = x, L_n(x) = x * L_{n-1}(x) + L_{n-2}(x),
+//! evaluated at a given x with modulo.
+//!
+//! These generalize the fibonacci and lucas numbers, which are F_n(1) and L_n(1).
+
+use crate::matrix::power_with_mod_u64;
+use crate::{add_mod, mul_mod, FibError, Matrix};
+
+
+/// Evaluates the nth fibonacci polynomial F_n(x) with modulo. Runtime O(log(n))
+///
+/// Uses linear recurrence with the matrix [[0, 1], [1, x]].
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::polynomial::fib_poly_with_mod;
+///
+/// // F_4(x) = x^3 + 2x
+/// assert_eq!(12, fib_poly_with_mod(4, 2, 100));
+/// // x = 1 gives the fibonacci numbers, x = 2 the pell numbers
+/// assert_eq!(fast_fibonacci::fib_with_mod(90, 1_000_000_007), fib_poly_with_mod(90, 1, 1_000_000_007));
+/// assert_eq!(408, fib_poly_with_mod(8, 2, 1_000));
+/// ```
+pub fn fib_poly_with_mod(n: u64, x: u64, modulo: u64) -> u64 {
+    try_fib_poly_with_mod(n, x, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_poly_with_mod`, but returns an error instead of panicking.
+pub fn try_fib_poly_with_mod(n: u64, x: u64, modulo: u64) -> Result<u64, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    Ok(poly_matrix_power(n, x, modulo)[0][1])
+}
+
+
+/// Evaluates the nth lucas polynomial L_n(x) with modulo. Runtime O(log(n))
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::polynomial::lucas_poly_with_mod;
+///
+/// // L_4(x) = x^4 + 4x^2 + 2
+/// assert_eq!(34, lucas_poly_with_mod(4, 2, 100));
+/// // x = 1 gives the lucas numbers
+/// assert_eq!(123, lucas_poly_with_mod(10, 1, 1_000));
+/// ```
+pub fn lucas_poly_with_mod(n: u64, x: u64, modulo: u64) -> u64 {
+    try_lucas_poly_with_mod(n, x, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `lucas_poly_with_mod`, but returns an error instead of panicking.
+pub fn try_lucas_poly_with_mod(n: u64, x: u64, modulo: u64) -> Result<u64, FibError> {
+    try_fib_lucas_poly_with_mod(n, x, modulo).map(|(_, lucas)| lucas)
+}
+
+
+/// Evaluates both F_n(x) and L_n(x) with modulo, from a single matrix power. Runtime O(log(n))
+///
+/// Uses L_n(x) = x * F_n(x) + 2 * F_{n-1}(x).
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::polynomial::fib_lucas_poly_with_mod;
+///
+/// assert_eq!((55, 123), fib_lucas_poly_with_mod(10, 1, 1_000));
+/// assert_eq!((12, 34), fib_lucas_poly_with_mod(4, 2, 100));
+/// ```
+pub fn fib_lucas_poly_with_mod(n: u64, x: u64, modulo: u64) -> (u64, u64) {
+    try_fib_lucas_poly_with_mod(n, x, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_lucas_poly_with_mod`, but returns an error instead of panicking.
+pub fn try_fib_lucas_poly_with_mod(n: u64, x: u64, modulo: u64) -> Result<(u64, u64), FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    let power_t = poly_matrix_power(n, x, modulo);
+    let (f_n_minus_1, f_n) = (power_t[0][0], power_t[0][1]);
+    let lucas = add_mod(mul_mod(x % modulo, f_n, modulo), mul_mod(2, f_n_minus_1, modulo), modulo);
+    Ok((f_n, lucas))
+}
+
+
+// [[F_{n-1}(x), F_n(x)], [F_n(x), F_{n+1}(x)]] with modulo.
+fn poly_matrix_power(n: u64, x: u64, modulo: u64) -> Matrix {
+    if n == 0 {
+        return [
+            [1 % modulo, 0],
+            [0, 1 % modulo]
+        ];
+    }
+    let t = [
+        [0, 1 % modulo],
+        [1 % modulo, x % modulo]
+    ];
+    power_with_mod_u64(&t, n, modulo).0
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::polynomial::*;
+
+    fn naive_fib_poly(n: u64, x: u64, modulo: u64) -> u64 {
+        let (mut a, mut b) = (0u128, 1u128);
+        for _ in 0..n {
+            let next = (x as u128 * b + a) % modulo as u128;
+            a = b;
+            b = next;
+        }
+        (a % modulo as u128) as u64
+    }
+
+    #[test]
+    fn test_fib_poly_matches_naive() {
+        for &modulo in &[1, 7, 1_000_000_007, u64::MAX] {
+            for &x in &[0, 1, 2, 3, 12_345, u64::MAX - 1] {
+                for n in 0..50 {
+                    assert_eq!(
+                        fib_poly_with_mod(n, x, modulo),
+                        naive_fib_poly(n, x, modulo),
+                        "n = {}, x = {}, modulo = {}", n, x, modulo
+                    );
+                }
+            }
+        }
+    }
+
+    fn naive_lucas_poly(n: u64, x: u64, modulo: u64) -> u64 {
+        let (mut a, mut b) = (2u128, x as u128);
+        for _ in 0..n {
+            let next = (x as u128 * b + a) % modulo as u128;
+            a = b;
+            b = next;
+        }
+        (a % modulo as u128) as u64
+    }
+
+    #[test]
+    fn test_lucas_poly_matches_naive() {
+        for &modulo in &[1, 2, 7, 1_000_000_007, u64::MAX] {
+            for &x in &[0, 1, 2, 3, 12_345, u64::MAX - 1] {
+                for n in 0..50 {
+                    assert_eq!(
+                        fib_lucas_poly_with_mod(n, x, modulo),
+                        (naive_fib_poly(n, x, modulo), naive_lucas_poly(n, x, modulo)),
+                        "n = {}, x = {}, modulo = {}", n, x, modulo
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_errors() {
+        assert_eq!(try_fib_poly_with_mod(5, 1, 0), Err(FibError::ZeroModulus));
+        assert_eq!(try_lucas_poly_with_mod(5, 1, 0), Err(FibError::ZeroModulus));
+        assert_eq!(try_fib_lucas_poly_with_mod(5, 1, 0), Err(FibError::ZeroModulus));
+        assert_eq!(try_fib_poly_with_mod(4, 2, 100), Ok(12));
+        assert_eq!(try_lucas_poly_with_mod(4, 2, 100), Ok(34));
+    }
+
+    #[test]
+    #[should_panic(expected = "modulo must be positive")]
+    fn test_zero_modulus_panics() {
+        fib_poly_with_mod(5, 1, 0);
+    }
+
+    #[test]
+    fn test_fib_poly_is_fib_at_one() {
+        for &n in &[100, 1_000_000_000_000_000] {
+            assert_eq!(fib_poly_with_mod(n, 1, 1_000_000), crate::fib_with_mod(n, 1_000_000));
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Python bindings through pyo3, with the `python` feature. Build a wheel with maturin, see
+//! `pyproject.toml`.
+//!
+//! Python ints of any size are accepted. Inputs that fit in a u64 take the u64 path, and anything
+//! larger goes through BigUint.
+
+// pyo3's #[pyfunction] expansion converts PyErr into itself.
+#![allow(clippy::useless_conversion)]
+
+use num_bigint::BigUint;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyLong;
+
+use crate::FibError;
+
+
+/// fib_with_mod(n: int, modulo: int) -> int, see `fib_with_mod` and `bigfib_with_mod`.
+#[pyfunction]
+fn fib_with_mod(py: Python, n: &Bound<PyAny>, modulo: &Bound<PyAny>) -> PyResult<PyObject> {
+    if let (Ok(n), Ok(modulo)) = (n.extract::<u64>(), modulo.extract::<u64>()) {
+        return Ok(crate::try_fib_with_mod(n, modulo).map_err(to_py_error)?.into_py(py));
+    }
+    let fib = crate::try_bigfib_with_mod(&to_biguint(n)?, &to_biguint(modulo)?).map_err(to_py_error)?;
+    to_py_int(py, &fib)
+}
+
+
+/// fib_pair_with_mod(n: int, modulo: int) -> tuple[int, int], see `fib_pair_with_mod`.
+#[pyfunction]
+fn fib_pair_with_mod(n: u64, modulo: u64) -> PyResult<(u64, u64)> {
+    crate::try_fib_pair_with_mod(n, modulo).map_err(to_py_error)
+}
+
+
+/// last_k_digits(n: int, k: int) -> str, see `last_k_digits`.
+#[pyfunction]
+fn last_k_digits(n: u64, k: u32) -> String {
+    crate::last_k_digits(n, k)
+}
+
+
+/// pisano_period(modulo: int) -> int, see `pisano::pisano_period`.
+#[pyfunction]
+fn pisano_period(modulo: u64) -> PyResult<u64> {
+    crate::pisano::try_pisano_period(modulo).map_err(to_py_error)
+}
+
+
+/// fib_index_with_mod(residue: int, modulo: int) -> int | None, see `pisano::fib_index_with_mod`.
+#[pyfunction]
+fn fib_index_with_mod(residue: u64, modulo: u64) -> PyResult<Option<u64>> {
+    crate::pisano::try_fib_index_with_mod(residue, modulo).map_err(to_py_error)
+}
+
+
+/// The `fast_fibonacci` Python module.
+#[pymodule]
+fn fast_fibonacci(module: &Bound<PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(fib_with_mod, module)?)?;
+    module.add_function(wrap_pyfunction!(fib_pair_with_mod, module)?)?;
+    module.add_function(wrap_pyfunction!(last_k_digits, module)?)?;
+    module.add_function(wrap_pyfunction!(pisano_period, module)?)?;
+    module.add_function(wrap_pyfunction!(fib_index_with_mod, module)?)?;
+    Ok(())
+}
+
+
+// Goes through the decimal string, since pyo3's own BigUint conversion needs a newer num-bigint.
+fn to_biguint(obj: &Bound<PyAny>) -> PyResult<BigUint> {
+    let int = obj.downcast::<PyLong>().map_err(|_| PyTypeError::new_err("expected an int"))?;
+    let digits = int.str()?.to_string();
+    BigUint::parse_bytes(digits.as_bytes(), 10)
+        .ok_or_else(|| PyValueError::new_err("expected a non-negative int"))
+}
+
+
+fn to_py_int(py: Python, n: &BigUint) -> PyResult<PyObject> {
+    Ok(py.get_type_bound::<PyLong>().call1((n.to_str_radix(10),))?.unbind())
+}
+
+
+fn to_py_error(err: FibError) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::python::*;
+    use pyo3::types::IntoPyDict;
+
+    #[test]
+    fn test_module() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new_bound(py, "fast_fibonacci").unwrap();
+            fast_fibonacci(&module).unwrap();
+            let call = |code: &str| py.eval_bound(code, Some(&[("ff", &module)].into_py_dict_bound(py)), None);
+
+            assert_eq!(call("ff.fib_with_mod(10, 100)").unwrap().extract::<u64>().unwrap(), 55);
+            let big = call("ff.fib_with_mod(10**30, 2**100) == ff.fib_with_mod(10**30 % (3 * 2**99), 2**100)");
+            assert!(big.unwrap().extract::<bool>().unwrap());
+            assert!(call("ff.fib_with_mod(10**30, 2**100) < 2**100").unwrap().extract::<bool>().unwrap());
+            assert!(call("ff.fib_with_mod(5, 0)").unwrap_err().is_instance_of::<PyValueError>(py));
+            assert!(call("ff.fib_with_mod(-5, 10**30)").unwrap_err().is_instance_of::<PyValueError>(py));
+            assert!(call("ff.fib_with_mod(5.0, 10**30)").unwrap_err().is_instance_of::<PyTypeError>(py));
+            assert_eq!(call("ff.fib_pair_with_mod(10, 100)").unwrap().extract::<(u64, u64)>().unwrap(), (55, 89));
+            assert_eq!(call("ff.pisano_period(10)").unwrap().extract::<u64>().unwrap(), 60);
+            assert!(call("ff.fib_index_with_mod(4, 11) is None").unwrap().extract::<bool>().unwrap());
+        });
+    }
+}
@@ -0,0 +1,348 @@
+//! Random fibonacci sequences, x(1) = x(2) = 1 and x(n) = x(n - 1) ± x(n - 2) with each sign
+//! chosen by a fair coin.
+//!
+//! Viswanath showed that |x(n)|^(1 / n) tends to 1.13198824... with probability 1. The coins come
+//! from SplitMix64, a small generator that steps by `hash::GOLDEN_RATIO_64`, so a seed always
+//! gives the same sequence on every platform.
+//!
+//! `LaggedFibonacci` is the other way round: a generator built on x(n) = x(n - j) ∘ x(n - k), for
+//! simulations rather than cryptography. With the `rand_core` feature it's a `rand_core::RngCore`.
+
+use num_bigint::BigInt;
+
+use crate::hash::GOLDEN_RATIO_64;
+use crate::FibError;
+
+/// The growth rate of random fibonacci sequences, 1.1319882487943...
+pub const VISWANATH_CONSTANT: f64 = 1.131_988_248_794_3;
+
+
+/// Iterates over the random fibonacci sequence x(1), x(2), x(3), ... for a seed, exactly.
+/// Runtime O(n) for x(n)
+///
+/// # Examples
+/// ```
+/// let xs: Vec<i64> = fast_fibonacci::random::random_fib(7)
+///     .take(10)
+///     .map(|x| num::ToPrimitive::to_i64(&x).unwrap())
+///     .collect();
+/// assert_eq!(&xs[..2], &[1, 1]);
+/// for window in xs.windows(3) {
+///     assert!(window[2] == window[1] + window[0] || window[2] == window[1] - window[0]);
+/// }
+/// ```
+pub fn random_fib(seed: u64) -> RandomFib {
+    RandomFib { previous: BigInt::from(0), current: BigInt::from(1), coins: Coins::new(seed), n: 0 }
+}
+
+
+/// Estimates the Viswanath constant from the first n steps of the random fibonacci sequence for a
+/// seed, as max(|x(n - 1)|, |x(n)|)^(1 / n). The error shrinks like 1 / √n.
+/// Runtime O(n)
+///
+/// Runs in f64, rescaling as it goes, so n can be in the billions.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::random::{viswanath_estimate, VISWANATH_CONSTANT};
+///
+/// assert!((viswanath_estimate(1_000_000, 1) - VISWANATH_CONSTANT).abs() < 0.005);
+/// ```
+pub fn viswanath_estimate(n: u64, seed: u64) -> f64 {
+    try_viswanath_estimate(n, seed).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `viswanath_estimate`, but returns an error instead of panicking when n is 0.
+pub fn try_viswanath_estimate(n: u64, seed: u64) -> Result<f64, FibError> {
+    if n == 0 {
+        return Err(FibError::InvalidArgument("n must be at least 1"));
+    }
+    const SCALE: f64 = 1e150;
+    let mut coins = Coins::new(seed);
+    // x(n - 1) and x(n), times e^-log_scale.
+    let (mut previous, mut current, mut log_scale) = (if n == 1 { 0.0 } else { 1.0 }, 1.0f64, 0.0);
+    for _ in 2..n {
+        let next = if coins.flip() { current + previous } else { current - previous };
+        previous = current;
+        current = next;
+        let size = current.abs().max(previous.abs());
+        if size > SCALE {
+            previous /= SCALE;
+            current /= SCALE;
+            log_scale += SCALE.ln();
+        } else if size < 1.0 / SCALE {
+            previous *= SCALE;
+            current *= SCALE;
+            log_scale -= SCALE.ln();
+        }
+    }
+    Ok(((log_scale + current.abs().max(previous.abs()).ln()) / n as f64).exp())
+}
+
+
+/// The operation ∘ of `LaggedFibonacci`, on u64 words with wraparound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LaggedOperation {
+    Add,
+    Subtract,
+    Xor,
+}
+
+
+/// A lagged fibonacci generator, x(n) = x(n - j) ∘ x(n - k) for lags 0 < j < k, with ∘ one of
+/// `LaggedOperation`, seeded by SplitMix64. Runtime O(k) to seed and O(1) per word
+///
+/// With addition or subtraction and lags from a primitive trinomial x^k + x^j + 1, such as Knuth's
+/// (24, 55), the period is (2^k - 1) * 2^63, since seeding makes a word odd. With xor, every bit
+/// runs the trinomial's linear recurrence on its own, with period 2^k - 1 at most.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::random::{LaggedFibonacci, LaggedOperation};
+///
+/// let mut rng = LaggedFibonacci::new(24, 55, LaggedOperation::Add, 7);
+/// let words: Vec<u64> = (0..100).map(|_| rng.next_u64()).collect();
+/// // x(n) = x(n - 24) + x(n - 55)
+/// assert_eq!(words[99], words[75].wrapping_add(words[44]));
+/// assert_eq!(words, LaggedFibonacci::new(24, 55, LaggedOperation::Add, 7).take(100).collect::<Vec<u64>>());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LaggedFibonacci {
+    // The last k words as a ring buffer, x(n - k) at oldest.
+    words: Vec<u64>,
+    oldest: usize,
+    short_lag: usize,
+    operation: LaggedOperation,
+}
+
+impl LaggedFibonacci {
+    /// Knuth's lags, from the primitive trinomial x^55 + x^24 + 1.
+    pub const KNUTH_LAGS: (usize, usize) = (24, 55);
+
+    /// The generator with lags j and k for a seed.
+    pub fn new(j: usize, k: usize, operation: LaggedOperation, seed: u64) -> LaggedFibonacci {
+        LaggedFibonacci::try_new(j, k, operation, seed).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `new`, but returns an error instead of panicking unless 0 < j < k.
+    pub fn try_new(j: usize, k: usize, operation: LaggedOperation, seed: u64) -> Result<LaggedFibonacci, FibError> {
+        if j == 0 || j >= k {
+            return Err(FibError::InvalidArgument("lags must satisfy 0 < j < k"));
+        }
+        let mut state = seed;
+        let mut words: Vec<u64> = (0..k).map(|_| splitmix64(&mut state)).collect();
+        // An odd word for the full period with + and -, and a nonzero one for xor.
+        words[0] |= 1;
+        Ok(LaggedFibonacci { words, oldest: 0, short_lag: j, operation })
+    }
+
+    /// The lags (j, k).
+    pub fn lags(&self) -> (usize, usize) {
+        (self.short_lag, self.words.len())
+    }
+
+    /// How x(n - j) and x(n - k) combine into x(n).
+    pub fn operation(&self) -> LaggedOperation {
+        self.operation
+    }
+
+    /// The next word x(n), moving n forward. Runtime O(1)
+    pub fn next_u64(&mut self) -> u64 {
+        let k = self.words.len();
+        let (short, long) = (self.words[(self.oldest + k - self.short_lag) % k], self.words[self.oldest]);
+        let word = match self.operation {
+            LaggedOperation::Add => short.wrapping_add(long),
+            LaggedOperation::Subtract => short.wrapping_sub(long),
+            LaggedOperation::Xor => short ^ long,
+        };
+        self.words[self.oldest] = word;
+        self.oldest = (self.oldest + 1) % k;
+        word
+    }
+}
+
+impl Iterator for LaggedFibonacci {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        Some(self.next_u64())
+    }
+}
+
+#[cfg(feature = "rand_core")]
+impl rand_core::RngCore for LaggedFibonacci {
+    fn next_u32(&mut self) -> u32 {
+        // The high bits, which mix the most with + and -.
+        (LaggedFibonacci::next_u64(self) >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        LaggedFibonacci::next_u64(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand_core::impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Knuth's lags (24, 55) with addition, seeded by the little endian u64 of the seed.
+#[cfg(feature = "rand_core")]
+impl rand_core::SeedableRng for LaggedFibonacci {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: [u8; 8]) -> LaggedFibonacci {
+        let (j, k) = LaggedFibonacci::KNUTH_LAGS;
+        LaggedFibonacci::new(j, k, LaggedOperation::Add, u64::from_le_bytes(seed))
+    }
+}
+
+
+/// Iterator returned by `random_fib`.
+#[derive(Clone, Debug)]
+pub struct RandomFib {
+    previous: BigInt,
+    current: BigInt,
+    coins: Coins,
+    n: u64,
+}
+
+impl Iterator for RandomFib {
+    type Item = BigInt;
+
+    fn next(&mut self) -> Option<BigInt> {
+        self.n += 1;
+        if self.n > 2 {
+            let next = if self.coins.flip() {
+                &self.current + &self.previous
+            } else {
+                &self.current - &self.previous
+            };
+            self.previous = std::mem::replace(&mut self.current, next);
+        } else if self.n == 2 {
+            self.previous = self.current.clone();
+        }
+        Some(self.current.clone())
+    }
+}
+
+
+// Fair coins from SplitMix64, 64 to a draw, true for +.
+#[derive(Clone, Debug)]
+struct Coins {
+    state: u64,
+    bits: u64,
+    left: u32,
+}
+
+impl Coins {
+    fn new(seed: u64) -> Coins {
+        Coins { state: seed, bits: 0, left: 0 }
+    }
+
+    fn flip(&mut self) -> bool {
+        if self.left == 0 {
+            self.bits = splitmix64(&mut self.state);
+            self.left = 64;
+        }
+        let coin = self.bits & 1 == 1;
+        self.bits >>= 1;
+        self.left -= 1;
+        coin
+    }
+}
+
+
+// The next SplitMix64 output, stepping the state by the golden ratio.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(GOLDEN_RATIO_64);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::random::*;
+    use num::{Signed, ToPrimitive};
+
+    #[test]
+    fn test_sequence() {
+        for seed in 0..20 {
+            let xs: Vec<BigInt> = random_fib(seed).take(200).collect();
+            assert_eq!((xs[0].clone(), xs[1].clone()), (BigInt::from(1), BigInt::from(1)));
+            for window in xs.windows(3) {
+                assert!(window[2] == &window[1] + &window[0] || window[2] == &window[1] - &window[0]);
+            }
+            assert_eq!(xs, random_fib(seed).take(200).collect::<Vec<_>>());
+        }
+        assert_ne!(random_fib(1).nth(100), random_fib(2).nth(100));
+    }
+
+    #[test]
+    fn test_estimate_matches_exact() {
+        for seed in 0..5 {
+            for &n in &[1, 2, 3, 10, 3_000] {
+                let xs: Vec<BigInt> = random_fib(seed).take(n as usize).collect();
+                let size = xs.iter().rev().take(2).map(|x| x.abs().to_f64().unwrap()).fold(0.0, f64::max);
+                let expected = size.powf(1.0 / n as f64);
+                assert!((viswanath_estimate(n, seed) - expected).abs() < 1e-12, "seed {}, n {}", seed, n);
+            }
+        }
+        assert_eq!(try_viswanath_estimate(0, 1), Err(FibError::InvalidArgument("n must be at least 1")));
+    }
+
+    #[test]
+    fn test_converges() {
+        let mean = (0..10).map(|seed| viswanath_estimate(1_000_000, seed)).sum::<f64>() / 10.0;
+        assert!((mean - VISWANATH_CONSTANT).abs() < 0.001, "{}", mean);
+    }
+
+    #[test]
+    fn test_lagged_fibonacci() {
+        for &operation in &[LaggedOperation::Add, LaggedOperation::Subtract, LaggedOperation::Xor] {
+            for &(j, k) in &[(1, 2), (5, 17), LaggedFibonacci::KNUTH_LAGS] {
+                let words: Vec<u64> = LaggedFibonacci::new(j, k, operation, 3).take(1_000).collect();
+                for n in k..words.len() {
+                    let expected = match operation {
+                        LaggedOperation::Add => words[n - j].wrapping_add(words[n - k]),
+                        LaggedOperation::Subtract => words[n - j].wrapping_sub(words[n - k]),
+                        LaggedOperation::Xor => words[n - j] ^ words[n - k],
+                    };
+                    assert_eq!(words[n], expected, "{:?}, lags ({}, {}), n = {}", operation, j, k, n);
+                }
+            }
+        }
+        // Half the bits set on average.
+        let ones: u32 = LaggedFibonacci::new(24, 55, LaggedOperation::Add, 1).take(10_000).map(u64::count_ones).sum();
+        assert!((ones as f64 / 640_000.0 - 0.5).abs() < 0.01, "{}", ones);
+        assert_ne!(
+            LaggedFibonacci::new(24, 55, LaggedOperation::Add, 1).nth(100),
+            LaggedFibonacci::new(24, 55, LaggedOperation::Add, 2).nth(100)
+        );
+        let invalid = Err(FibError::InvalidArgument("lags must satisfy 0 < j < k"));
+        assert_eq!(LaggedFibonacci::try_new(0, 5, LaggedOperation::Xor, 1), invalid);
+        assert_eq!(LaggedFibonacci::try_new(5, 5, LaggedOperation::Xor, 1), invalid);
+    }
+
+    #[cfg(feature = "rand_core")]
+    #[test]
+    fn test_rng_core() {
+        use rand_core::{RngCore, SeedableRng};
+
+        let mut rng = LaggedFibonacci::from_seed(9u64.to_le_bytes());
+        let expected: Vec<u64> = LaggedFibonacci::new(24, 55, LaggedOperation::Add, 9).take(3).collect();
+        assert_eq!(RngCore::next_u64(&mut rng), expected[0]);
+        assert_eq!(rng.next_u32(), (expected[1] >> 32) as u32);
+        let mut bytes = [0; 8];
+        rng.fill_bytes(&mut bytes);
+        assert_eq!(u64::from_le_bytes(bytes), expected[2]);
+    }
+}
@@ -0,0 +1,152 @@
+//! Fibonacci retracement and extension levels of a price move, as drawn by charting tools.
+//!
+//! The ratios are powers of 1 / φ and their square roots, rounded the way charts show them:
+//! 0.236 ≈ 1 / φ^3, 0.382 ≈ 1 / φ^2, 0.618 ≈ 1 / φ, 0.786 ≈ √0.618, 1.272 ≈ √φ, 1.618 ≈ φ and
+//! 2.618 ≈ φ^2, plus the customary 0.5.
+
+use crate::FibError;
+
+/// The retracement ratios, from the fields `r_23_6` to `r_78_6` of `RetracementLevels`.
+pub const RETRACEMENT_RATIOS: [f64; 5] = [0.236, 0.382, 0.5, 0.618, 0.786];
+
+/// The extension ratios, from the fields `e_127_2` to `e_261_8` of `RetracementLevels`.
+pub const EXTENSION_RATIOS: [f64; 3] = [1.272, 1.618, 2.618];
+
+
+/// The direction of the move the levels are drawn on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Trend {
+    /// The price rose from low to high. Retracements fall back from high towards low, and
+    /// extensions continue above high.
+    Up,
+    /// The price fell from high to low. Retracements climb back from low towards high, and
+    /// extensions continue below low.
+    Down,
+}
+
+
+/// The prices at each retracement and extension ratio of a move, see `retracement_levels`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RetracementLevels {
+    /// Where the move started, the 0% extension and 100% retracement.
+    pub start: f64,
+    /// Where the move ended, the 0% retracement and 100% extension.
+    pub end: f64,
+    pub r_23_6: f64,
+    pub r_38_2: f64,
+    pub r_50: f64,
+    pub r_61_8: f64,
+    pub r_78_6: f64,
+    pub e_127_2: f64,
+    pub e_161_8: f64,
+    pub e_261_8: f64,
+}
+
+impl RetracementLevels {
+    /// (ratio, price) for every retracement, from the end of the move back towards its start.
+    pub fn retracements(&self) -> [(f64, f64); 5] {
+        let prices = [self.r_23_6, self.r_38_2, self.r_50, self.r_61_8, self.r_78_6];
+        let mut levels = [(0.0, 0.0); 5];
+        for (level, (&ratio, &price)) in levels.iter_mut().zip(RETRACEMENT_RATIOS.iter().zip(prices.iter())) {
+            *level = (ratio, price);
+        }
+        levels
+    }
+
+    /// (ratio, price) for every extension, moving away from the end of the move.
+    pub fn extensions(&self) -> [(f64, f64); 3] {
+        let prices = [self.e_127_2, self.e_161_8, self.e_261_8];
+        let mut levels = [(0.0, 0.0); 3];
+        for (level, (&ratio, &price)) in levels.iter_mut().zip(EXTENSION_RATIOS.iter().zip(prices.iter())) {
+            *level = (ratio, price);
+        }
+        levels
+    }
+}
+
+
+/// Finds the retracement and extension levels of a move between high and low. Runtime O(1)
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::retracement::{retracement_levels, Trend};
+///
+/// let levels = retracement_levels(200.0, 100.0, Trend::Up);
+/// assert!((levels.r_38_2 - 161.8).abs() < 1e-9);
+/// assert!((levels.r_61_8 - 138.2).abs() < 1e-9);
+/// assert!((levels.e_161_8 - 261.8).abs() < 1e-9);
+///
+/// let levels = retracement_levels(200.0, 100.0, Trend::Down);
+/// assert!((levels.r_61_8 - 161.8).abs() < 1e-9);
+/// assert!((levels.e_161_8 - 38.2).abs() < 1e-9);
+/// ```
+pub fn retracement_levels(high: f64, low: f64, trend: Trend) -> RetracementLevels {
+    try_retracement_levels(high, low, trend).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `retracement_levels`, but returns an error instead of panicking when high is below low
+/// or either isn't finite.
+pub fn try_retracement_levels(high: f64, low: f64, trend: Trend) -> Result<RetracementLevels, FibError> {
+    if !high.is_finite() || !low.is_finite() {
+        return Err(FibError::InvalidArgument("prices must be finite"));
+    }
+    if high < low {
+        return Err(FibError::InvalidArgument("high must be at least low"));
+    }
+    let (start, end) = match trend {
+        Trend::Up => (low, high),
+        Trend::Down => (high, low),
+    };
+    // Positive ratios go from the end back towards the start.
+    let retrace = |ratio: f64| end + ratio * (start - end);
+    let extend = |ratio: f64| start + ratio * (end - start);
+    Ok(RetracementLevels {
+        start,
+        end,
+        r_23_6: retrace(0.236),
+        r_38_2: retrace(0.382),
+        r_50: retrace(0.5),
+        r_61_8: retrace(0.618),
+        r_78_6: retrace(0.786),
+        e_127_2: extend(1.272),
+        e_161_8: extend(1.618),
+        e_261_8: extend(2.618),
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::retracement::*;
+
+    #[test]
+    fn test_levels() {
+        let up = retracement_levels(150.0, 50.0, Trend::Up);
+        let down = retracement_levels(150.0, 50.0, Trend::Down);
+        assert_eq!((up.start, up.end, down.start, down.end), (50.0, 150.0, 150.0, 50.0));
+        for (&(ratio, up_price), &(_, down_price)) in up.retracements().iter().zip(down.retracements().iter()) {
+            assert!((up_price - (150.0 - 100.0 * ratio)).abs() < 1e-9);
+            // Mirror images around the middle of the move.
+            assert!((up_price + down_price - 200.0).abs() < 1e-9);
+        }
+        for (&(ratio, up_price), &(_, down_price)) in up.extensions().iter().zip(down.extensions().iter()) {
+            assert!((up_price - (50.0 + 100.0 * ratio)).abs() < 1e-9);
+            assert!((down_price - (150.0 - 100.0 * ratio)).abs() < 1e-9);
+        }
+        let flat = retracement_levels(10.0, 10.0, Trend::Up);
+        assert!(flat.retracements().iter().chain(flat.extensions().iter()).all(|&(_, price)| price == 10.0));
+    }
+
+    #[test]
+    fn test_invalid_prices() {
+        assert_eq!(
+            try_retracement_levels(1.0, 2.0, Trend::Up),
+            Err(FibError::InvalidArgument("high must be at least low"))
+        );
+        assert_eq!(
+            try_retracement_levels(f64::NAN, 2.0, Trend::Down),
+            Err(FibError::InvalidArgument("prices must be finite"))
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! fib(n) modulo a big fixed modulo in a residue number system, for moduli used over and over.
+//!
+//! `RnsModulus` carries the doubling steps as their residues modulo two bases of word sized
+//! primes, the product of each past 32 times the modulo, and reduces products by RNS Montgomery
+//! multiplication. Every operation of the inner loop is then on u64s rather than BigUints, and
+//! only the result goes back to a BigUint, by mixed radix conversion. Picking the primes and their
+//! inverses is the part a reused modulo pays for once.
+//!
+//! # Examples
+//! ```
+//! use fast_fibonacci::rns::RnsModulus;
+//! use num_bigint::BigUint;
+//!
+//! let modulo = num::pow(BigUint::from(10u32), 300) + 7u32;
+//! let rns = RnsModulus::new(&modulo);
+//! for n in &[BigUint::from(100u32), num::pow(BigUint::from(10u32), 40)] {
+//!     assert_eq!(fast_fibonacci::bigfib_with_mod(n, &modulo), rns.fib(n));
+//! }
+//! ```
+
+use num_bigint::BigUint;
+use num::{ToPrimitive, Zero};
+
+use crate::nt::{is_prime, mod_inverse};
+use crate::{add_mod, mul_mod, sub_mod, FibError};
+
+
+/// A modulo prepared for `fib` in a residue number system. Runtime O(k^2) to set up, for the k
+/// primes of each base
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RnsModulus {
+    modulo: BigUint,
+    // B, whose product M the Montgomery reductions divide by, and B', which holds their results.
+    base: Basis,
+    extension: Basis,
+    // -modulo^-1 modulo the primes of B.
+    neg_inverse: Vec<u64>,
+    // modulo, modulo the primes of B'.
+    modulo_extension: Vec<u64>,
+    // M^-1 modulo the primes of B'.
+    base_inverse: Vec<u64>,
+    // 2 * modulo, added before subtracting a value below it.
+    twice_modulo: Residues,
+    // M modulo modulo, 1 in Montgomery form.
+    one: Residues,
+}
+
+impl RnsModulus {
+    /// Prepares a positive modulo. Panics if it's 0, see `try_new`.
+    pub fn new(modulo: &BigUint) -> RnsModulus {
+        RnsModulus::try_new(modulo).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `new`, but returns an error instead of panicking.
+    pub fn try_new(modulo: &BigUint) -> Result<RnsModulus, FibError> {
+        if modulo.is_zero() {
+            return Err(FibError::ZeroModulus);
+        }
+        // Primes below 2^62, from the top, that the Montgomery form can invert modulo.
+        let mut primes = ((1u64 << 61)..1 << 62)
+            .rev()
+            .step_by(2)
+            .filter(|&p| is_prime(p) && !(modulo % p).is_zero());
+        let bound = modulo * 32u32;
+        let mut pick = || {
+            let (mut picked, mut product) = (Vec::new(), BigUint::from(1u32));
+            while product <= bound {
+                let p = primes.next().unwrap();
+                picked.push(p);
+                product *= p;
+            }
+            (Basis::new(picked), product)
+        };
+        let (base, product) = pick();
+        let (extension, _) = pick();
+        let neg_inverse = base.residues(modulo).iter().zip(&base.primes)
+            .map(|(&m, &p)| p - mod_inverse(m, p).unwrap())
+            .collect();
+        let base_inverse = extension.residues(&product).iter().zip(&extension.primes)
+            .map(|(&m, &p)| mod_inverse(m, p).unwrap())
+            .collect();
+        let twice_modulo = modulo * 2u32;
+        let one = &product % modulo;
+        Ok(RnsModulus {
+            neg_inverse,
+            modulo_extension: extension.residues(modulo),
+            base_inverse,
+            twice_modulo: Residues { base: base.residues(&twice_modulo), extension: extension.residues(&twice_modulo) },
+            one: Residues { base: base.residues(&one), extension: extension.residues(&one) },
+            modulo: modulo.clone(),
+            base,
+            extension,
+        })
+    }
+
+    pub fn modulo(&self) -> &BigUint {
+        &self.modulo
+    }
+
+    /// The number of primes in each of the two bases.
+    pub fn primes(&self) -> usize {
+        self.base.primes.len()
+    }
+
+    /// Finds the nth fibonacci number modulo the prepared modulo. Runtime O(k^2 * log(n)) word
+    /// operations, for the k primes of each base
+    ///
+    /// Fast doubling, as `BigFibComputation`, with every value kept below twice the modulo by the
+    /// Montgomery reductions.
+    pub fn fib(&self, n: &BigUint) -> BigUint {
+        let zero = Residues {
+            base: vec![0; self.base.primes.len()],
+            extension: vec![0; self.extension.primes.len()],
+        };
+        let (mut a, mut b) = (zero, self.one.clone());
+        let n_bytes = n.to_bytes_le();
+        for bit in (0..8 * n_bytes.len()).rev() {
+            // fib(2k) = fib(k) * (2 * fib(k + 1) - fib(k)), fib(2k + 1) = fib(k)^2 + fib(k + 1)^2
+            // With a below 2m and b below 4m, both products are below 20m^2, which reduce below
+            // 2m for M past 32m.
+            let twice_b_minus_a = self.map(&[&b, &b, &self.twice_modulo, &a], |[b, b2, twice, a], p| {
+                sub_mod(add_mod(add_mod(b, b2, p), twice, p), a, p)
+            });
+            let even = self.reduce(&self.map(&[&a, &twice_b_minus_a], |[a, c], p| mul_mod(a, c, p)));
+            let odd = self.reduce(&self.map(&[&a, &b], |[a, b], p| add_mod(mul_mod(a, a, p), mul_mod(b, b, p), p)));
+            if n_bytes[bit / 8] >> (bit % 8) & 1 == 1 {
+                b = self.map(&[&even, &odd], |[even, odd], p| add_mod(even, odd, p));
+                a = odd;
+            } else {
+                a = even;
+                b = odd;
+            }
+        }
+        // a * 1 reduces to a * M^-1, out of the Montgomery form and at most the modulo.
+        let plain_one = Residues {
+            base: vec![1; self.base.primes.len()],
+            extension: vec![1; self.extension.primes.len()],
+        };
+        let fib = self.reduce(&self.map(&[&a, &plain_one], |[a, one], p| mul_mod(a, one, p)));
+        self.base.to_biguint(&fib.base) % &self.modulo
+    }
+
+    // Applies f to the residues of the values, prime by prime, in both bases.
+    fn map<const N: usize, F: Fn([u64; N], u64) -> u64>(&self, values: &[&Residues; N], f: F) -> Residues {
+        let channels = |basis: &Basis, residues: &dyn Fn(&Residues) -> &Vec<u64>| {
+            (0..basis.primes.len())
+                .map(|i| f(values.map(|value| residues(value)[i]), basis.primes[i]))
+                .collect()
+        };
+        Residues {
+            base: channels(&self.base, &|value| &value.base),
+            extension: channels(&self.extension, &|value| &value.extension),
+        }
+    }
+
+    // x * M^-1 modulo the modulo, below x / M + modulo: with q = -x * modulo^-1 modulo M,
+    // x + q * modulo is a multiple of M, and the quotient is found in B' and extended to B.
+    fn reduce(&self, x: &Residues) -> Residues {
+        let q: Vec<u64> = (0..self.base.primes.len())
+            .map(|i| mul_mod(x.base[i], self.neg_inverse[i], self.base.primes[i]))
+            .collect();
+        let q = self.base.extend(&q, &self.extension);
+        let extension: Vec<u64> = (0..self.extension.primes.len())
+            .map(|j| {
+                let p = self.extension.primes[j];
+                mul_mod(add_mod(x.extension[j], mul_mod(q[j], self.modulo_extension[j], p), p), self.base_inverse[j], p)
+            })
+            .collect();
+        Residues { base: self.extension.extend(&extension, &self.base), extension }
+    }
+}
+
+
+// A value by its residues modulo the primes of both bases.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Residues {
+    base: Vec<u64>,
+    extension: Vec<u64>,
+}
+
+
+// Distinct primes, with what converting out of their residues takes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Basis {
+    primes: Vec<u64>,
+    // inverses[i][j] = primes[j]^-1 modulo primes[i], for j < i.
+    inverses: Vec<Vec<u64>>,
+}
+
+impl Basis {
+    fn new(primes: Vec<u64>) -> Basis {
+        let inverses = (0..primes.len())
+            .map(|i| (0..i).map(|j| mod_inverse(primes[j], primes[i]).unwrap()).collect())
+            .collect();
+        Basis { primes, inverses }
+    }
+
+    fn residues(&self, x: &BigUint) -> Vec<u64> {
+        self.primes.iter().map(|&p| (x % p).to_u64().unwrap()).collect()
+    }
+
+    // The mixed radix digits v of the x below the product of the primes with the given residues,
+    // x = v[0] + v[1] * p[0] + v[2] * p[0] * p[1] + ... Runtime O(k^2)
+    fn mixed_radix(&self, residues: &[u64]) -> Vec<u64> {
+        let mut digits: Vec<u64> = Vec::with_capacity(residues.len());
+        for (i, &p) in self.primes.iter().enumerate() {
+            let mut digit = residues[i];
+            for (j, &v) in digits.iter().enumerate() {
+                digit = mul_mod(sub_mod(digit, v % p, p), self.inverses[i][j], p);
+            }
+            digits.push(digit);
+        }
+        digits
+    }
+
+    // The residues of the same x in another basis, exactly, by Horner's rule on its mixed radix
+    // digits. Runtime O(k^2)
+    fn extend(&self, residues: &[u64], to: &Basis) -> Vec<u64> {
+        let digits = self.mixed_radix(residues);
+        to.primes
+            .iter()
+            .map(|&q| {
+                self.primes.iter().zip(&digits).rev()
+                    .fold(0, |x, (&p, &v)| add_mod(mul_mod(x, p, q), v, q))
+            })
+            .collect()
+    }
+
+    fn to_biguint(&self, residues: &[u64]) -> BigUint {
+        let digits = self.mixed_radix(residues);
+        self.primes.iter().zip(&digits).rev()
+            .fold(BigUint::zero(), |x, (&p, &v)| x * p + v)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::rns::*;
+
+    #[test]
+    fn test_matches_bigfib_with_mod() {
+        let big = (BigUint::from(1u32) << 500) + 12_345u32;
+        let moduli = [
+            BigUint::from(1u32),
+            BigUint::from(10u32),
+            BigUint::from(u64::MAX),
+            (BigUint::from(1u32) << 62) - 57u32,
+            big.clone(),
+            &big * &big,
+        ];
+        let ns = [BigUint::from(0u32), BigUint::from(1u32), BigUint::from(2u32), BigUint::from(1_000u32), &big * 7u32];
+        for modulo in &moduli {
+            let rns = RnsModulus::new(modulo);
+            for n in &ns {
+                assert_eq!(rns.fib(n), crate::bigfib_with_mod(n, modulo), "n = {}, modulo = {}", n, modulo);
+            }
+        }
+        assert_eq!(RnsModulus::new(&big).primes(), 9);
+        assert_eq!(RnsModulus::try_new(&BigUint::zero()), Err(FibError::ZeroModulus));
+    }
+}
@@ -0,0 +1,141 @@
+//! Quasi-random point sets from the golden ratio: the fibonacci lattice on the unit square, and
+//! the fibonacci sphere, a golden angle spiral on the unit sphere.
+//!
+//! Multiples of 1 / φ modulo 1 spread out more evenly than those of any other number, so these
+//! cover their domains far more evenly than random points, for Monte Carlo integration, sampling
+//! directions or placing points on a globe. The fractions are taken in 64-bit fixed point, as in
+//! `hash::fib_hash`, so they stay exact for any index.
+
+use std::f64::consts::PI;
+
+use crate::hash::GOLDEN_RATIO_64;
+
+
+/// n points (x, y) of the unit square, with x = (i + 0.5) / n and y the fractional part of i / φ
+/// for i in 0..n. Runtime O(n)
+///
+/// # Examples
+/// ```
+/// let points: Vec<(f64, f64)> = fast_fibonacci::sampling::fibonacci_lattice(1_000).collect();
+/// assert_eq!(1_000, points.len());
+/// // About a tenth of the points in every tenth of the square.
+/// let corner = points.iter().filter(|&&(x, y)| x < 0.1 && y < 0.1).count();
+/// assert!((5..=15).contains(&corner));
+/// ```
+pub fn fibonacci_lattice(n: usize) -> FibonacciLattice {
+    FibonacciLattice { i: 0, n }
+}
+
+
+/// n points (x, y, z) on the unit sphere, evenly spaced in z from pole to pole and turning by the
+/// golden angle 2π / φ^2 from one to the next. Runtime O(n)
+///
+/// # Examples
+/// ```
+/// for (x, y, z) in fast_fibonacci::sampling::fibonacci_sphere(100) {
+///     assert!((x * x + y * y + z * z - 1.0).abs() < 1e-12);
+/// }
+/// ```
+pub fn fibonacci_sphere(n: usize) -> FibonacciSphere {
+    FibonacciSphere { i: 0, n }
+}
+
+
+// The fractional part of i / φ, from the top 53 bits so that it can't round up to 1.
+fn golden_fraction(i: usize) -> f64 {
+    ((i as u64).wrapping_mul(GOLDEN_RATIO_64) >> 11) as f64 / 2f64.powi(53)
+}
+
+
+/// Iterator returned by `fibonacci_lattice`.
+#[derive(Clone, Debug)]
+pub struct FibonacciLattice {
+    i: usize,
+    n: usize,
+}
+
+impl Iterator for FibonacciLattice {
+    type Item = (f64, f64);
+
+    fn next(&mut self) -> Option<(f64, f64)> {
+        if self.i == self.n {
+            return None;
+        }
+        let point = ((self.i as f64 + 0.5) / self.n as f64, golden_fraction(self.i));
+        self.i += 1;
+        Some(point)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n - self.i, Some(self.n - self.i))
+    }
+}
+
+impl ExactSizeIterator for FibonacciLattice {}
+
+
+/// Iterator returned by `fibonacci_sphere`.
+#[derive(Clone, Debug)]
+pub struct FibonacciSphere {
+    i: usize,
+    n: usize,
+}
+
+impl Iterator for FibonacciSphere {
+    type Item = (f64, f64, f64);
+
+    fn next(&mut self) -> Option<(f64, f64, f64)> {
+        if self.i == self.n {
+            return None;
+        }
+        let z = 1.0 - (2.0 * self.i as f64 + 1.0) / self.n as f64;
+        let radius = (1.0 - z * z).sqrt();
+        // A turn of 1 / φ is the golden angle the other way around.
+        let angle = 2.0 * PI * golden_fraction(self.i);
+        self.i += 1;
+        Some((radius * angle.cos(), radius * angle.sin(), z))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.n - self.i, Some(self.n - self.i))
+    }
+}
+
+impl ExactSizeIterator for FibonacciSphere {}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::sampling::*;
+
+    #[test]
+    fn test_lattice_is_even() {
+        let n = 10_000;
+        let mut cells = [[0; 10]; 10];
+        for (x, y) in fibonacci_lattice(n) {
+            assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+            cells[(10.0 * x) as usize][(10.0 * y) as usize] += 1;
+        }
+        // Random points would stray by about 10 from the 100 per cell.
+        assert!(cells.iter().flatten().all(|&count| (97..=103).contains(&count)), "{:?}", cells);
+        assert_eq!(fibonacci_lattice(0).count(), 0);
+        assert_eq!(fibonacci_lattice(5).len(), 5);
+    }
+
+    #[test]
+    fn test_sphere_is_even() {
+        let points: Vec<(f64, f64, f64)> = fibonacci_sphere(10_000).collect();
+        for &(x, y, z) in &points {
+            assert!((x * x + y * y + z * z - 1.0).abs() < 1e-12);
+        }
+        // Every octant gets an eighth of the points.
+        for octant in 0..8 {
+            let count = points
+                .iter()
+                .filter(|&&(x, y, z)| (x < 0.0, y < 0.0, z < 0.0) == (octant & 1 == 1, octant & 2 == 2, octant & 4 == 4))
+                .count();
+            assert!((1_240..=1_260).contains(&count), "octant {}: {}", octant, count);
+        }
+        assert_eq!(fibonacci_sphere(1).next(), Some((1.0, 0.0, 0.0)));
+    }
+}
@@ -0,0 +1,174 @@
+//! Fibonacci search for the minimum of a unimodal function, one that decreases and then
+//! increases.
+//!
+//! Each step compares the function at two points that split the bracket in the ratio of
+//! consecutive fibonacci numbers, so that one of them is reused by the next step. n evaluations
+//! shrink the bracket by a factor of about fib(n + 2) / 2, like golden section search but with
+//! exact integer points.
+
+use crate::FibError;
+
+
+/// Finds the x in lo..=hi where a unimodal f is smallest, in about log_φ(hi - lo) evaluations of
+/// f. If f is flat around its minimum, any x of the flat part may come back.
+/// Runtime O(log(hi - lo))
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::search::fibonacci_search;
+///
+/// assert_eq!(37, fibonacci_search(|x| (x - 37).abs(), -1_000, 1_000));
+/// let mut evaluations = 0;
+/// let x = fibonacci_search(|x| { evaluations += 1; (x - 123_456_789).abs() }, 0, 1 << 60);
+/// assert_eq!(123_456_789, x);
+/// assert!(evaluations < 100);
+/// ```
+pub fn fibonacci_search<T: PartialOrd, F: FnMut(i64) -> T>(f: F, lo: i64, hi: i64) -> i64 {
+    try_fibonacci_search(f, lo, hi).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fibonacci_search`, but returns an error instead of panicking when lo > hi.
+pub fn try_fibonacci_search<T: PartialOrd, F: FnMut(i64) -> T>(mut f: F, lo: i64, hi: i64) -> Result<i64, FibError> {
+    if lo > hi {
+        return Err(FibError::InvalidArgument("lo must be at most hi"));
+    }
+    // The candidates lie strictly between a and a + fib(k), with everything past hi counting as
+    // larger than any value of f.
+    let mut a = i128::from(lo) - 1;
+    let (mut fib_k2, mut fib_k1, mut fib_k) = (0u128, 1u128, 1u128);
+    while fib_k < (i128::from(hi) - a + 1) as u128 {
+        fib_k2 = fib_k1;
+        fib_k1 = fib_k;
+        fib_k = fib_k1 + fib_k2;
+    }
+    let mut eval = |x: i128| if x <= i128::from(hi) { Some(f(x as i64)) } else { None };
+    let mut x1 = a + fib_k2 as i128;
+    let mut x2 = a + fib_k1 as i128;
+    let (mut f1, mut f2) = (eval(x1), eval(x2));
+    // Down to a bracket of 3, with the two candidates x1 and x2.
+    while fib_k > 3 {
+        let left = match (&f1, &f2) {
+            (Some(f1), Some(f2)) => f1 <= f2,
+            (_, None) => true,
+            (None, Some(_)) => false,
+        };
+        let fib_k3 = fib_k1 - fib_k2;
+        if left {
+            x2 = x1;
+            f2 = f1;
+            x1 = a + fib_k3 as i128;
+            f1 = eval(x1);
+        } else {
+            a = x1;
+            x1 = x2;
+            f1 = f2;
+            x2 = a + fib_k2 as i128;
+            f2 = eval(x2);
+        }
+        fib_k = fib_k1;
+        fib_k1 = fib_k2;
+        fib_k2 = fib_k3;
+    }
+    // x1 is at most hi, since the bracket always holds a candidate that is.
+    let best = match (f1, f2) {
+        (Some(f1), Some(f2)) if f2 < f1 => x2,
+        _ => x1,
+    };
+    Ok(best as i64)
+}
+
+
+/// Narrows down the x in lo..=hi where a unimodal f is smallest, with `evaluations` evaluations
+/// of f, and returns the middle of the final bracket, which is within
+/// (hi - lo) / fib(evaluations + 2) of the minimum. Runtime O(evaluations)
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::search::fibonacci_search_f64;
+///
+/// let x = fibonacci_search_f64(|x| (x - 1.0) * (x - 1.0), 0.0, 10.0, 40);
+/// assert!((x - 1.0).abs() < 1e-6);
+/// ```
+pub fn fibonacci_search_f64<F: FnMut(f64) -> f64>(mut f: F, lo: f64, hi: f64, evaluations: u32) -> f64 {
+    let (mut a, mut b) = (lo, hi);
+    if evaluations < 2 {
+        return (a + b) / 2.0;
+    }
+    // Points at the fractions fib(m) / fib(m + 2) and fib(m + 1) / fib(m + 2) of the bracket.
+    let mut x1 = b - ratio(evaluations) * (b - a);
+    let mut x2 = a + ratio(evaluations) * (b - a);
+    let (mut f1, mut f2) = (f(x1), f(x2));
+    for m in (2..evaluations).rev() {
+        if f1 <= f2 {
+            b = x2;
+            x2 = x1;
+            f2 = f1;
+            x1 = b - ratio(m) * (b - a);
+            f1 = f(x1);
+        } else {
+            a = x1;
+            x1 = x2;
+            f1 = f2;
+            x2 = a + ratio(m) * (b - a);
+            f2 = f(x2);
+        }
+    }
+    if f1 <= f2 {
+        (a + x2) / 2.0
+    } else {
+        (x1 + b) / 2.0
+    }
+}
+
+
+// fib(m + 1) / fib(m + 2), which is 1 / φ to double precision from m = 40 on.
+fn ratio(m: u32) -> f64 {
+    let mut ratio = 0.5;
+    for _ in 1..m.min(40) {
+        ratio = 1.0 / (1.0 + ratio);
+    }
+    ratio
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::search::*;
+
+    #[test]
+    fn test_matches_brute_force() {
+        for lo in -5..5 {
+            for hi in lo..lo + 30 {
+                for minimum in lo - 2..=hi + 2 {
+                    let f = |x: i64| (x - minimum).abs();
+                    let expected = (lo..=hi).min_by_key(|&x| f(x)).unwrap();
+                    assert_eq!(fibonacci_search(f, lo, hi), expected, "{}..={}, minimum {}", lo, hi, minimum);
+                }
+                // A plateau at the bottom.
+                let f = |x: i64| (x - 3).max(0) + (-x - 3).max(0);
+                let min = (lo..=hi).map(f).min().unwrap();
+                assert_eq!(f(fibonacci_search(f, lo, hi)), min);
+            }
+        }
+    }
+
+    #[test]
+    fn test_extremes() {
+        assert_eq!(fibonacci_search(|x| x, i64::MIN, i64::MAX), i64::MIN);
+        assert_eq!(fibonacci_search(|x| -(x as i128), i64::MIN, i64::MAX), i64::MAX);
+        assert_eq!(fibonacci_search(|x| (x as i128 - 12_345).abs(), i64::MIN, i64::MAX), 12_345);
+        assert_eq!(fibonacci_search(|_| 0, 7, 7), 7);
+        assert_eq!(try_fibonacci_search(|x| x, 1, 0), Err(FibError::InvalidArgument("lo must be at most hi")));
+    }
+
+    #[test]
+    fn test_f64() {
+        for &minimum in &[-3.0, 0.0, 0.1, 2.5, 7.0] {
+            let x = fibonacci_search_f64(|x: f64| (x - minimum).powi(2), -3.0, 7.0, 30);
+            // fib(32) = 2_178_309
+            assert!((x - minimum).abs() <= 10.0 / 2_178_309.0, "{} vs {}", x, minimum);
+        }
+        assert_eq!(fibonacci_search_f64(|x| x, 0.0, 1.0, 0), 0.5);
+    }
+}
@@ -0,0 +1,572 @@
+//! Square matrix powers over any semiring, the engine behind the fibonacci matrix power.
+//!
+//! With `Modular`, entry (i, j) of M^k is the number of walks of length k from i to j modulo m,
+//! or any linear recurrence's terms for its companion matrix. With `MinPlus` it's the length of
+//! the shortest such walk, and with `Boolean` whether one exists. With `Ring`, the entries are
+//! any type with its own +, *, 0 and 1, such as modular newtypes, polynomials or matrices.
+
+use ndarray::Array2;
+use num::{One, Zero};
+use num_bigint::BigUint;
+use std::fmt;
+use std::marker::PhantomData;
+use std::num::NonZeroU64;
+use std::ops::{Add, Mul};
+
+use crate::bignum::BigNum;
+use crate::{add_mod, mul_mod, FibError};
+
+
+/// The element operations of a matrix power. `add` must be associative and commutative, `mul`
+/// associative and distributive over `add`, `zero` the identity of `add` and `one` the identity
+/// of `mul`.
+pub trait Semiring {
+    type Element: Clone;
+
+    fn zero(&self) -> Self::Element;
+
+    fn one(&self) -> Self::Element;
+
+    fn add(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+
+    fn mul(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+}
+
+
+/// u64 arithmetic modulo a positive modulo. Elements must already be reduced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Modular(pub NonZeroU64);
+
+impl Semiring for Modular {
+    type Element = u64;
+
+    fn zero(&self) -> u64 {
+        0
+    }
+
+    fn one(&self) -> u64 {
+        1 % self.0.get()
+    }
+
+    fn add(&self, a: &u64, b: &u64) -> u64 {
+        add_mod(*a, *b, self.0.get())
+    }
+
+    fn mul(&self, a: &u64, b: &u64) -> u64 {
+        mul_mod(*a, *b, self.0.get())
+    }
+}
+
+
+/// `BigNum` arithmetic modulo a positive modulo. Elements must already be reduced.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BigModular<T>(pub T);
+
+impl<T: BigNum> Semiring for BigModular<T> {
+    type Element = T;
+
+    fn zero(&self) -> T {
+        T::from_u64(0)
+    }
+
+    fn one(&self) -> T {
+        T::from_u64(1).rem(&self.0)
+    }
+
+    fn add(&self, a: &T, b: &T) -> T {
+        a.add(b).rem(&self.0)
+    }
+
+    fn mul(&self, a: &T, b: &T) -> T {
+        a.mul_rem(b, &self.0)
+    }
+}
+
+
+/// The tropical semiring: min for addition and + for multiplication, with u64::MAX standing for
+/// infinity, i.e. no path.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MinPlus;
+
+impl Semiring for MinPlus {
+    type Element = u64;
+
+    fn zero(&self) -> u64 {
+        u64::MAX
+    }
+
+    fn one(&self) -> u64 {
+        0
+    }
+
+    fn add(&self, a: &u64, b: &u64) -> u64 {
+        *a.min(b)
+    }
+
+    fn mul(&self, a: &u64, b: &u64) -> u64 {
+        a.saturating_add(*b)
+    }
+}
+
+
+/// Or for addition and and for multiplication.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Boolean;
+
+impl Semiring for Boolean {
+    type Element = bool;
+
+    fn zero(&self) -> bool {
+        false
+    }
+
+    fn one(&self) -> bool {
+        true
+    }
+
+    fn add(&self, a: &bool, b: &bool) -> bool {
+        *a || *b
+    }
+
+    fn mul(&self, a: &bool, b: &bool) -> bool {
+        *a && *b
+    }
+}
+
+
+/// A type with its own semiring operations: +, *, `Zero::zero` and `One::one`. Implemented for
+/// every such type, from u64 and BigUint to `Wrapping<u64>` and user defined modular newtypes,
+/// polynomials or matrices. * doesn't need to commute.
+pub trait RingElement: Clone + Add<Output = Self> + Mul<Output = Self> + Zero + One {}
+
+impl<T: Clone + Add<Output = T> + Mul<Output = T> + Zero + One> RingElement for T {}
+
+
+/// The semiring of a `RingElement` type, using its own operations.
+pub struct Ring<T>(PhantomData<fn() -> T>);
+
+impl<T> Ring<T> {
+    pub fn new() -> Ring<T> {
+        Ring(PhantomData)
+    }
+}
+
+impl<T> Default for Ring<T> {
+    fn default() -> Self {
+        Ring::new()
+    }
+}
+
+impl<T> Clone for Ring<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Ring<T> {}
+
+impl<T> fmt::Debug for Ring<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Ring")
+    }
+}
+
+impl<T: RingElement> Semiring for Ring<T> {
+    type Element = T;
+
+    fn zero(&self) -> T {
+        T::zero()
+    }
+
+    fn one(&self) -> T {
+        T::one()
+    }
+
+    fn add(&self, a: &T, b: &T) -> T {
+        a.clone() + b.clone()
+    }
+
+    fn mul(&self, a: &T, b: &T) -> T {
+        a.clone() * b.clone()
+    }
+}
+
+
+/// Raises a square matrix to the power pow over a semiring. Runtime O(size^3 * log(pow))
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::semiring::{self, MinPlus};
+/// use ndarray::arr2;
+///
+/// // Edge lengths of a directed graph, u64::MAX where there is no edge.
+/// let none = u64::MAX;
+/// let graph = arr2(&[
+///     [none, 1, 4],
+///     [none, none, 2],
+///     [1, none, none],
+/// ]);
+/// // The shortest walks of exactly 3 edges.
+/// let walks = semiring::matrix_power(&MinPlus, &graph, 3);
+/// assert_eq!(4, walks[[0, 0]]);
+/// assert_eq!(6, walks[[0, 1]]);
+/// ```
+pub fn matrix_power<S: Semiring>(semiring: &S, mat: &Array2<S::Element>, pow: u64) -> Array2<S::Element> {
+    try_matrix_power(semiring, mat, pow).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `matrix_power`, but returns an error instead of panicking when the matrix isn't square.
+pub fn try_matrix_power<S: Semiring>(
+    semiring: &S,
+    mat: &Array2<S::Element>,
+    pow: u64,
+) -> Result<Array2<S::Element>, FibError> {
+    let size = mat.nrows();
+    if mat.ncols() != size {
+        return Err(FibError::InvalidArgument("matrix must be square"));
+    }
+    let mut power = Array2::from_shape_fn((size, size), |(i, j)| {
+        if i == j {
+            semiring.one()
+        } else {
+            semiring.zero()
+        }
+    });
+    // Over the bits of pow from the top.
+    for bit in (0..64 - pow.leading_zeros()).rev() {
+        power = multiply(semiring, &power, &power);
+        if pow >> bit & 1 == 1 {
+            power = multiply(semiring, &power, mat);
+        }
+    }
+    Ok(power)
+}
+
+
+/// The element types of `mat_pow_mod`, u64 and BigUint.
+pub trait ModElement: Clone {
+    type Semiring: Semiring<Element = Self>;
+
+    /// The integers modulo `modulo`, or None when it's 0.
+    fn semiring(modulo: &Self) -> Option<Self::Semiring>;
+
+    fn reduce(&self, modulo: &Self) -> Self;
+}
+
+impl ModElement for u64 {
+    type Semiring = Modular;
+
+    fn semiring(modulo: &u64) -> Option<Modular> {
+        NonZeroU64::new(*modulo).map(Modular)
+    }
+
+    fn reduce(&self, modulo: &u64) -> u64 {
+        self % modulo
+    }
+}
+
+impl ModElement for BigUint {
+    type Semiring = BigModular<BigUint>;
+
+    fn semiring(modulo: &BigUint) -> Option<BigModular<BigUint>> {
+        if BigNum::is_zero(modulo) {
+            None
+        } else {
+            Some(BigModular(modulo.clone()))
+        }
+    }
+
+    fn reduce(&self, modulo: &BigUint) -> BigUint {
+        self % modulo
+    }
+}
+
+
+/// Raises a square matrix of u64 or BigUint entries to the power pow with modulo.
+/// Runtime O(size^3 * log(pow))
+///
+/// The entries don't need to be reduced. This is how fib_with_mod works for the 2x2 matrix
+/// [[0, 1], [1, 1]], and it works the same for the companion matrix of any linear recurrence.
+///
+/// # Examples
+/// ```
+/// use ndarray::arr2;
+///
+/// // tribonacci(n + 3) = tribonacci(n + 2) + tribonacci(n + 1) + tribonacci(n)
+/// let t = arr2(&[[0, 1, 0], [0, 0, 1], [1, 1, 1]]);
+/// let power = fast_fibonacci::mat_pow_mod(&t, 10, &1_000);
+/// // tribonacci(10) from tribonacci(0, 1, 2) = (0, 0, 1)
+/// assert_eq!(81, power[[0, 2]]);
+/// ```
+pub fn mat_pow_mod<T: ModElement>(mat: &Array2<T>, pow: u64, modulo: &T) -> Array2<T> {
+    try_mat_pow_mod(mat, pow, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `mat_pow_mod`, but returns an error instead of panicking.
+pub fn try_mat_pow_mod<T: ModElement>(mat: &Array2<T>, pow: u64, modulo: &T) -> Result<Array2<T>, FibError> {
+    let semiring = T::semiring(modulo).ok_or(FibError::ZeroModulus)?;
+    try_matrix_power(&semiring, &mat.map(|x| x.reduce(modulo)), pow)
+}
+
+
+/// Finds the nth fibonacci number in a `RingElement` type, as the sum of fib(n) ones.
+/// Runtime O(log(n)) operations
+///
+/// # Examples
+/// ```
+/// use std::num::Wrapping;
+///
+/// // fib(100) modulo 2^64
+/// let fib = fast_fibonacci::semiring::fib_in::<Wrapping<u64>>(100);
+/// assert_eq!(Wrapping(3_736_710_778_780_434_371), fib);
+/// ```
+pub fn fib_in<T: RingElement>(n: u64) -> T {
+    // (fib(k - 1), fib(k), fib(k + 1)) from the top bit of n down, with
+    // fib(2k - 1) = fib(k - 1)^2 + fib(k)^2, fib(2k) = fib(k) * (fib(k - 1) + fib(k + 1)) and
+    // fib(2k + 1) = fib(k)^2 + fib(k + 1)^2, which need no subtraction.
+    let (mut prev, mut fib, mut next) = (T::one(), T::zero(), T::one());
+    for bit in (0..64 - n.leading_zeros()).rev() {
+        let fib_squared = fib.clone() * fib.clone();
+        let doubled = fib * (prev.clone() + next.clone());
+        prev = prev.clone() * prev + fib_squared.clone();
+        next = fib_squared + next.clone() * next;
+        fib = doubled;
+        if n >> bit & 1 == 1 {
+            let sum = fib.clone() + next.clone();
+            prev = fib;
+            fib = next;
+            next = sum;
+        }
+    }
+    fib
+}
+
+
+/// Finds x(n) of the linear recurrence x(i) = coefficients[0] * x(i - 1) + ... +
+/// coefficients[k - 1] * x(i - k) over a `RingElement` type, from x(0), ..., x(k - 1) in initial.
+/// Runtime O(k^3 * log(n)) operations
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::semiring::linear_recurrence;
+/// use num_bigint::BigUint;
+///
+/// // Pell numbers, x(i) = 2x(i - 1) + x(i - 2)
+/// let two = BigUint::from(2u32);
+/// let pell = linear_recurrence(&[two, BigUint::from(1u32)], &[BigUint::from(0u32), BigUint::from(1u32)], 10);
+/// assert_eq!(BigUint::from(2_378u32), pell);
+/// ```
+pub fn linear_recurrence<T: RingElement>(coefficients: &[T], initial: &[T], n: u64) -> T {
+    try_linear_recurrence(coefficients, initial, n).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `linear_recurrence`, but returns an error instead of panicking when there are no
+/// coefficients, or not as many initial terms as coefficients.
+pub fn try_linear_recurrence<T: RingElement>(coefficients: &[T], initial: &[T], n: u64) -> Result<T, FibError> {
+    try_linear_recurrence_in(&Ring::new(), coefficients, initial, n)
+}
+
+
+/// `linear_recurrence` over a semiring, for elements whose operations need context, such as
+/// `galois::GaloisField`. Runtime O(k^3 * log(n)) operations
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::semiring::{linear_recurrence_in, Modular};
+/// use std::num::NonZeroU64;
+///
+/// let modulo = Modular(NonZeroU64::new(1_000).unwrap());
+/// assert_eq!(378, linear_recurrence_in(&modulo, &[2, 1], &[0, 1], 10));
+/// ```
+pub fn linear_recurrence_in<S: Semiring>(semiring: &S, coefficients: &[S::Element], initial: &[S::Element], n: u64) -> S::Element {
+    try_linear_recurrence_in(semiring, coefficients, initial, n).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `linear_recurrence_in`, but returns an error instead of panicking when there are no
+/// coefficients, or not as many initial terms as coefficients.
+pub fn try_linear_recurrence_in<S: Semiring>(
+    semiring: &S,
+    coefficients: &[S::Element],
+    initial: &[S::Element],
+    n: u64,
+) -> Result<S::Element, FibError> {
+    let k = coefficients.len();
+    if k == 0 {
+        return Err(FibError::InvalidArgument("coefficients must not be empty"));
+    }
+    if initial.len() != k {
+        return Err(FibError::InvalidArgument("initial must have a term per coefficient"));
+    }
+    // Shifts (x(i), ..., x(i + k - 1)) to (x(i + 1), ..., x(i + k)).
+    let companion = Array2::from_shape_fn((k, k), |(i, j)| {
+        if i + 1 < k {
+            if j == i + 1 {
+                semiring.one()
+            } else {
+                semiring.zero()
+            }
+        } else {
+            coefficients[k - 1 - j].clone()
+        }
+    });
+    let power = try_matrix_power(semiring, &companion, n)?;
+    Ok((0..k).fold(semiring.zero(), |sum, j| semiring.add(&sum, &semiring.mul(&power[[0, j]], &initial[j]))))
+}
+
+
+fn multiply<S: Semiring>(semiring: &S, a: &Array2<S::Element>, b: &Array2<S::Element>) -> Array2<S::Element> {
+    let size = a.nrows();
+    Array2::from_shape_fn((size, size), |(i, j)| {
+        (0..size).fold(semiring.zero(), |sum, k| {
+            semiring.add(&sum, &semiring.mul(&a[[i, k]], &b[[k, j]]))
+        })
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::semiring::*;
+    use ndarray::arr2;
+
+    #[test]
+    fn test_modular_matches_fib() {
+        let modulo = NonZeroU64::new(1_000_000_007).unwrap();
+        let t = arr2(&[[0, 1], [1, 1]]);
+        for &n in &[0, 1, 2, 10, 1_000_000_000_000_000, u64::MAX] {
+            assert_eq!(matrix_power(&Modular(modulo), &t, n)[[0, 1]], crate::fib_with_mod(n, modulo.get()));
+        }
+        let one = NonZeroU64::new(1).unwrap();
+        assert_eq!(matrix_power(&Modular(one), &arr2(&[[0]]), 0), arr2(&[[0]]));
+    }
+
+    #[test]
+    fn test_min_plus_and_boolean_match_brute_force() {
+        let none = u64::MAX;
+        let graph = arr2(&[
+            [none, 3, none, 1],
+            [none, none, 1, none],
+            [2, none, none, none],
+            [none, 1, 5, none],
+        ]);
+        let reachable = graph.mapv(|length| length != none);
+        let (mut shortest, mut walks) = (matrix_power(&MinPlus, &graph, 0), matrix_power(&Boolean, &reachable, 0));
+        for k in 0..20 {
+            assert_eq!(matrix_power(&MinPlus, &graph, k), shortest, "k = {}", k);
+            assert_eq!(matrix_power(&Boolean, &reachable, k), walks, "k = {}", k);
+            // One more edge, relaxing every walk the slow way.
+            shortest = Array2::from_shape_fn((4, 4), |(i, j)| {
+                (0..4).map(|m| shortest[[i, m]].saturating_add(graph[[m, j]])).min().unwrap()
+            });
+            walks = Array2::from_shape_fn((4, 4), |(i, j)| (0..4).any(|m| walks[[i, m]] && reachable[[m, j]]));
+        }
+    }
+
+    // Integers modulo 7, as a user would define them.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    struct Mod7(u8);
+
+    impl Add for Mod7 {
+        type Output = Mod7;
+
+        fn add(self, other: Mod7) -> Mod7 {
+            Mod7((self.0 + other.0) % 7)
+        }
+    }
+
+    impl Mul for Mod7 {
+        type Output = Mod7;
+
+        fn mul(self, other: Mod7) -> Mod7 {
+            Mod7(self.0 * other.0 % 7)
+        }
+    }
+
+    impl Zero for Mod7 {
+        fn zero() -> Mod7 {
+            Mod7(0)
+        }
+
+        fn is_zero(&self) -> bool {
+            self.0 == 0
+        }
+    }
+
+    impl One for Mod7 {
+        fn one() -> Mod7 {
+            Mod7(1)
+        }
+    }
+
+    #[test]
+    fn test_ring_elements() {
+        use std::num::Wrapping;
+
+        for &n in &[0, 1, 2, 3, 10, 93, 1_000_000_000_000_000, u64::MAX] {
+            assert_eq!(fib_in::<Mod7>(n), Mod7(crate::fib_with_mod(n, 7) as u8), "n = {}", n);
+            assert_eq!(fib_in::<Wrapping<u8>>(n), Wrapping(crate::fib_with_mod(n, 256) as u8), "n = {}", n);
+            let (zero, one) = (Mod7(0), Mod7(1));
+            assert_eq!(linear_recurrence(&[one, one], &[zero, one], n), fib_in(n));
+        }
+        for n in 0..100 {
+            let expected = crate::bigfib_with_mod(&BigUint::from(n), &(BigUint::from(1u32) << 100));
+            assert_eq!(fib_in::<BigUint>(n), expected);
+        }
+        let t = arr2(&[[Mod7(0), Mod7(1)], [Mod7(1), Mod7(1)]]);
+        assert_eq!(matrix_power(&Ring::new(), &t, 10)[[0, 1]], Mod7(55 % 7));
+    }
+
+    #[test]
+    fn test_linear_recurrence() {
+        // x(i) = x(i - 1) + 2x(i - 3) from 1, 0, 3, checked step by step.
+        let coefficients = [1u64, 0, 2];
+        let mut xs = vec![1u64, 0, 3];
+        while xs.len() < 40 {
+            let i = xs.len();
+            xs.push(xs[i - 1] + 2 * xs[i - 3]);
+        }
+        for (n, &x) in xs.iter().enumerate() {
+            assert_eq!(linear_recurrence(&coefficients, &xs[..3], n as u64), x);
+        }
+        assert_eq!(
+            try_linear_recurrence::<u64>(&[], &[], 5),
+            Err(FibError::InvalidArgument("coefficients must not be empty"))
+        );
+        assert_eq!(
+            try_linear_recurrence(&[1u64, 1], &[0], 5),
+            Err(FibError::InvalidArgument("initial must have a term per coefficient"))
+        );
+    }
+
+    #[test]
+    fn test_not_square() {
+        let mat = Array2::from_elem((2, 3), true);
+        assert_eq!(try_matrix_power(&Boolean, &mat, 2), Err(FibError::InvalidArgument("matrix must be square")));
+        assert_eq!(
+            try_mat_pow_mod(&Array2::from_elem((3, 2), 1u64), 2, &10),
+            Err(FibError::InvalidArgument("matrix must be square"))
+        );
+    }
+
+    #[test]
+    fn test_mat_pow_mod() {
+        let t = arr2(&[[0u64, 1], [1, 1]]);
+        let big_t = t.mapv(BigUint::from);
+        let big_modulo = (BigUint::from(1u32) << 200) - 1u32;
+        for &n in &[0, 1, 2, 10, 1_000_000_000_000_000, u64::MAX] {
+            assert_eq!(mat_pow_mod(&t, n, &u64::MAX)[[0, 1]], crate::fib_with_mod(n, u64::MAX));
+            assert_eq!(
+                mat_pow_mod(&big_t, n, &big_modulo)[[0, 1]],
+                crate::bigfib_with_mod(&BigUint::from(n), &big_modulo)
+            );
+        }
+        // Unreduced entries.
+        assert_eq!(mat_pow_mod(&arr2(&[[12u64]]), 2, &10), arr2(&[[4]]));
+        assert_eq!(try_mat_pow_mod(&t, 2, &0), Err(FibError::ZeroModulus));
+        assert_eq!(try_mat_pow_mod(&big_t, 2, &BigUint::from(0u32)), Err(FibError::ZeroModulus));
+    }
+}
@@ -0,0 +1,786 @@
+//! Linear recurrences looked up by name or built from parts, for callers that pick the sequence
+//! at run time, such as command lines and config files.
+//!
+//! `Sequence::by_name` knows the built-in sequences, and a `Registry` adds custom ones next to
+//! them. `Recurrence` builds one from its coefficients, initial terms and modulo, and serializes
+//! with the `serde` feature. Every sequence is a linear recurrence
+//! x(i) = c_1 x(i - 1) + ... + c_k x(i - k), so they all evaluate the same way, by powering the
+//! k x k companion matrix.
+//!
+//! Other crates plug their own sequences into the same engine by implementing `ModularSequence`.
+
+use std::collections::HashMap;
+use std::num::NonZeroU64;
+
+use ndarray::Array2;
+
+use num_bigint::BigInt;
+
+use crate::semiring::{linear_recurrence, try_matrix_power, Modular};
+use crate::{add_mod, mul_mod, FibError};
+
+/// The names `Sequence::by_name` knows.
+pub const BUILTIN_NAMES: [&str; 8] =
+    ["fibonacci", "jacobsthal", "lucas", "narayana", "padovan", "pell", "pell-lucas", "tribonacci"];
+
+
+// x(i) = x(i - 1) + x(i - 3) from 1, 1, 1
+const NARAYANA_COEFFICIENTS: [u64; 3] = [1, 0, 1];
+const NARAYANA_INITIAL: [u64; 3] = [1, 1, 1];
+
+
+/// A linear recurrence x(i) = coefficients[0] * x(i - 1) + ... + coefficients[k - 1] * x(i - k),
+/// from x(0), ..., x(k - 1).
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::sequence::Sequence;
+///
+/// assert_eq!(2_378, Sequence::by_name("pell").unwrap().nth_mod(10, 10_000));
+/// // x(i) = 3x(i - 1) + 1x(i - 2) from 0, 1
+/// let custom = Sequence::new(&[3, 1], &[0, 1]);
+/// assert_eq!(vec![0, 1, 3, 10, 33, 109], (0..6).map(|n| custom.nth_mod(n, 1_000)).collect::<Vec<u64>>());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Sequence {
+    coefficients: Vec<i128>,
+    initial: Vec<i128>,
+}
+
+impl Sequence {
+    /// The recurrence with these coefficients and initial terms, which may be negative.
+    pub fn new<T: Copy + Into<i128>>(coefficients: &[T], initial: &[T]) -> Sequence {
+        Sequence::try_new(coefficients, initial).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `new`, but returns an error instead of panicking when there are no coefficients, or
+    /// not as many initial terms as coefficients.
+    pub fn try_new<T: Copy + Into<i128>>(coefficients: &[T], initial: &[T]) -> Result<Sequence, FibError> {
+        if coefficients.is_empty() {
+            return Err(FibError::InvalidArgument("coefficients must not be empty"));
+        }
+        if initial.len() != coefficients.len() {
+            return Err(FibError::InvalidArgument("initial must have a term per coefficient"));
+        }
+        Ok(Sequence { coefficients: widen(coefficients), initial: widen(initial) })
+    }
+
+    /// The built-in sequence with this name, one of `BUILTIN_NAMES`.
+    pub fn by_name(name: &str) -> Option<Sequence> {
+        let (coefficients, initial): (&[u64], &[u64]) = match name {
+            "fibonacci" => (&[1, 1], &[0, 1]),
+            "jacobsthal" => (&[1, 2], &[0, 1]),
+            "lucas" => (&[1, 1], &[2, 1]),
+            "narayana" => (&NARAYANA_COEFFICIENTS, &NARAYANA_INITIAL),
+            "padovan" => (&[0, 1, 1], &[1, 1, 1]),
+            "pell" => (&[2, 1], &[0, 1]),
+            "pell-lucas" => (&[2, 1], &[2, 2]),
+            "tribonacci" => (&[1, 1, 1], &[0, 0, 1]),
+            _ => return None,
+        };
+        Some(Sequence::new(coefficients, initial))
+    }
+
+    pub fn coefficients(&self) -> &[i128] {
+        &self.coefficients
+    }
+
+    pub fn initial(&self) -> &[i128] {
+        &self.initial
+    }
+
+    /// Finds x(n) exactly. Runtime O(k^3 * log(n)) multiplications of numbers up to x(n)
+    ///
+    /// # Examples
+    /// ```
+    /// use fast_fibonacci::sequence::Sequence;
+    /// use num_bigint::BigInt;
+    ///
+    /// // x(i) = 2x(i - 1) - 3x(i - 2) from 1, 1: 1, 1, -1, -5, -7, 1, 23, ...
+    /// let sequence = Sequence::new(&[2, -3], &[1, 1]);
+    /// assert_eq!(BigInt::from(-7), sequence.nth(4));
+    /// assert_eq!(BigInt::from(23), sequence.nth(6));
+    /// ```
+    pub fn nth(&self, n: u64) -> BigInt {
+        let coefficients: Vec<BigInt> = self.coefficients.iter().map(|&c| BigInt::from(c)).collect();
+        let initial: Vec<BigInt> = self.initial.iter().map(|&x| BigInt::from(x)).collect();
+        linear_recurrence(&coefficients, &initial, n)
+    }
+
+    /// Finds x(n) with modulo. Runtime O(k^3 * log(n)) for k coefficients
+    pub fn nth_mod(&self, n: u64, modulo: u64) -> u64 {
+        self.try_nth_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `nth_mod`, but returns an error instead of panicking.
+    pub fn try_nth_mod(&self, n: u64, modulo: u64) -> Result<u64, FibError> {
+        let semiring = NonZeroU64::new(modulo).map(Modular).ok_or(FibError::ZeroModulus)?;
+        let companion = companion_matrix(&self.coefficients, modulo);
+        let initial = reduce(&self.initial, modulo);
+        Ok(nth_term(&semiring, &companion, &initial, n))
+    }
+}
+
+
+/// Finds the nth term of Narayana's cows sequence 1, 1, 1, 2, 3, 4, 6, 9, 13, ..., with
+/// x(i) = x(i - 1) + x(i - 3), with modulo. Runtime O(log(n))
+///
+/// Panics if modulo is zero, see `try_narayana_with_mod`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::sequence::narayana_with_mod;
+///
+/// let terms: Vec<u64> = (0..10).map(|n| narayana_with_mod(n, 1_000)).collect();
+/// assert_eq!(vec![1, 1, 1, 2, 3, 4, 6, 9, 13, 19], terms);
+/// assert_eq!(615_472_476, narayana_with_mod(1_000_000_000_000_000_000, 1_000_000_007));
+/// ```
+pub fn narayana_with_mod(n: u64, modulo: u64) -> u64 {
+    try_narayana_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `narayana_with_mod`, but returns an error instead of panicking.
+pub fn try_narayana_with_mod(n: u64, modulo: u64) -> Result<u64, FibError> {
+    let semiring = NonZeroU64::new(modulo).map(Modular).ok_or(FibError::ZeroModulus)?;
+    let coefficients = widen(&NARAYANA_COEFFICIENTS);
+    let initial = reduce(&widen(&NARAYANA_INITIAL), modulo);
+    Ok(nth_term(&semiring, &companion_matrix(&coefficients, modulo), &initial, n))
+}
+
+
+/// Finds the nth leonardo number 1, 1, 3, 5, 9, 15, 25, 41, ..., with x(i) = x(i - 1) + x(i - 2) + 1,
+/// with modulo. Runtime O(log(n))
+///
+/// leonardo(n) = 2 * fib(n + 1) - 1, so it takes a single fibonacci pair query.
+///
+/// Panics if modulo is zero, see `try_leonardo_with_mod`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::sequence::leonardo_with_mod;
+///
+/// let terms: Vec<u64> = (0..10).map(|n| leonardo_with_mod(n, 1_000)).collect();
+/// assert_eq!(vec![1, 1, 3, 5, 9, 15, 25, 41, 67, 109], terms);
+/// // 2 * fib(16) - 1 = 1973
+/// assert_eq!(973, leonardo_with_mod(15, 1_000));
+/// ```
+pub fn leonardo_with_mod(n: u64, modulo: u64) -> u64 {
+    try_leonardo_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `leonardo_with_mod`, but returns an error instead of panicking.
+pub fn try_leonardo_with_mod(n: u64, modulo: u64) -> Result<u64, FibError> {
+    // fib(n + 1) from the pair at n, so n = u64::MAX doesn't overflow.
+    let (_, next) = crate::try_fib_pair_with_mod(n, modulo)?;
+    // 2 * next + modulo - 1 < 3 * modulo, and modulo - 1 keeps it from going below 0.
+    Ok(((2 * next as u128 + modulo as u128 - 1) % modulo as u128) as u64)
+}
+
+
+/// Finds the nth hyperfibonacci number of order r with modulo, where order 0 is fib(n) and order
+/// r sums the ones of order r - 1, hyperfib(r, n) = hyperfib(r - 1, 0) + ... + hyperfib(r - 1, n).
+/// Runtime O(r^3 * log(n))
+///
+/// The state (hyperfib(r, n), ..., hyperfib(1, n), fib(n), fib(n + 1)) steps by an (r + 2) x (r + 2)
+/// matrix, with hyperfib(j, n + 1) = hyperfib(1, n) + ... + hyperfib(j, n) + fib(n + 1) as its
+/// summation rows, and its nth power gives the nth term.
+///
+/// Panics if modulo is zero, see `try_hyperfib_with_mod`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::sequence::hyperfib_with_mod;
+///
+/// let terms: Vec<u64> = (0..8).map(|n| hyperfib_with_mod(2, n, 1_000)).collect();
+/// assert_eq!(vec![0, 1, 3, 7, 14, 26, 46, 79], terms);
+/// // The first order is fib(n + 2) - 1.
+/// assert_eq!(fast_fibonacci::fib_with_mod(1_002, 1_000_000) - 1, hyperfib_with_mod(1, 1_000, 1_000_000));
+/// ```
+pub fn hyperfib_with_mod(r: usize, n: u64, modulo: u64) -> u64 {
+    try_hyperfib_with_mod(r, n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `hyperfib_with_mod`, but returns an error instead of panicking.
+pub fn try_hyperfib_with_mod(r: usize, n: u64, modulo: u64) -> Result<u64, FibError> {
+    let semiring = NonZeroU64::new(modulo).map(Modular).ok_or(FibError::ZeroModulus)?;
+    let one = 1 % modulo;
+    // hyperfib(j, n) at r - j, fib(n) at r and fib(n + 1) at r + 1.
+    let mut transition = Array2::zeros((r + 2, r + 2));
+    for j in 1..=r {
+        for l in 1..=j {
+            transition[[r - j, r - l]] = one;
+        }
+        transition[[r - j, r + 1]] = one;
+    }
+    transition[[r, r + 1]] = one;
+    transition[[r + 1, r]] = one;
+    transition[[r + 1, r + 1]] = one;
+    let mut initial = vec![0; r + 2];
+    initial[r + 1] = one;
+    Ok(nth_term(&semiring, &transition, &initial, n))
+}
+
+
+/// A linear recurrence built up step by step, as config files describe them, with
+/// `Recurrence::new().coeffs(..).init(..).modulo(..)`. `build` checks it and makes an evaluator.
+///
+/// With the `serde` feature it serializes as its coefficients, initial terms and modulo, and is
+/// only checked when built.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::sequence::Recurrence;
+///
+/// // x(i) = 2x(i - 1) + x(i - 2) from 0, 1, the pell numbers
+/// let pell = Recurrence::new().coeffs(&[2, 1]).init(&[0, 1]).modulo(10_000).build().unwrap();
+/// assert_eq!(2_378, pell.nth(10));
+/// assert_eq!(vec![0, 1, 2, 5, 12, 29], (0..6).map(|n| pell.nth(n)).collect::<Vec<u64>>());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Recurrence {
+    coefficients: Vec<i128>,
+    initial: Vec<i128>,
+    modulo: u64,
+}
+
+impl Recurrence {
+    /// A recurrence with no coefficients, no initial terms and modulo 0, to fill in.
+    pub fn new() -> Recurrence {
+        Recurrence::default()
+    }
+
+    /// Sets the coefficients, for
+    /// x(i) = coefficients[0] * x(i - 1) + ... + coefficients[k - 1] * x(i - k).
+    pub fn coeffs<T: Copy + Into<i128>>(mut self, coefficients: &[T]) -> Recurrence {
+        self.coefficients = widen(coefficients);
+        self
+    }
+
+    /// Sets the initial terms x(0), ..., x(k - 1).
+    pub fn init<T: Copy + Into<i128>>(mut self, initial: &[T]) -> Recurrence {
+        self.initial = widen(initial);
+        self
+    }
+
+    pub fn modulo(mut self, modulo: u64) -> Recurrence {
+        self.modulo = modulo;
+        self
+    }
+
+    /// The evaluator for this recurrence, or an error when there are no coefficients, not as many
+    /// initial terms as coefficients, or the modulo is 0. Runtime O(k^2)
+    pub fn build(&self) -> Result<RecurrenceEvaluator, FibError> {
+        let sequence = Sequence::try_new(&self.coefficients, &self.initial)?;
+        let semiring = NonZeroU64::new(self.modulo).map(Modular).ok_or(FibError::ZeroModulus)?;
+        let modulo = self.modulo;
+        Ok(RecurrenceEvaluator {
+            semiring,
+            companion: companion_matrix(&sequence.coefficients, modulo),
+            initial: reduce(&sequence.initial, modulo),
+        })
+    }
+}
+
+impl From<Sequence> for Recurrence {
+    fn from(sequence: Sequence) -> Recurrence {
+        Recurrence { coefficients: sequence.coefficients, initial: sequence.initial, modulo: 0 }
+    }
+}
+
+
+/// A checked `Recurrence`, with its companion matrix reduced once for all the terms asked for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecurrenceEvaluator {
+    semiring: Modular,
+    companion: Array2<u64>,
+    initial: Vec<u64>,
+}
+
+impl RecurrenceEvaluator {
+    /// Finds x(n) with the recurrence's modulo. Runtime O(k^3 * log(n)) for k coefficients
+    pub fn nth(&self, n: u64) -> u64 {
+        nth_term(&self.semiring, &self.companion, &self.initial, n)
+    }
+
+    pub fn modulo(&self) -> u64 {
+        self.semiring.0.get()
+    }
+}
+
+
+/// A sequence whose states follow s(i + 1) = T * s(i) for a fixed square matrix T, with x(i) the
+/// first entry of s(i). Downstream crates implement `transition_matrix` and `initial_state`, and
+/// the rest comes from the same matrix power as `fib_with_mod`.
+///
+/// Every linear recurrence is one, with T its companion matrix, and so is anything a fixed matrix
+/// steps forward, such as recurrences with a constant term.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::sequence::ModularSequence;
+/// use ndarray::{arr2, Array2};
+///
+/// // The leonardo numbers, x(i) = x(i - 1) + x(i - 2) + 1 from 1, 1, with s(i) = (x(i), x(i + 1), 1).
+/// struct Leonardo;
+///
+/// impl ModularSequence for Leonardo {
+///     fn transition_matrix(&self) -> Array2<i128> {
+///         arr2(&[[0, 1, 0], [1, 1, 1], [0, 0, 1]])
+///     }
+///
+///     fn initial_state(&self) -> Vec<i128> {
+///         vec![1, 1, 1]
+///     }
+/// }
+///
+/// assert_eq!(vec![1, 1, 3, 5, 9, 15, 25, 41], Leonardo.iter_mod(1_000).take(8).collect::<Vec<u64>>());
+/// assert_eq!(109, Leonardo.nth_mod(9, 1_000));
+/// assert_eq!(vec![67, 109], Leonardo.batch_mod(&[8, 9], 1_000));
+/// assert_eq!(20, Leonardo.period_mod(10));
+/// ```
+pub trait ModularSequence {
+    /// T, a square matrix whose entries may be negative.
+    fn transition_matrix(&self) -> Array2<i128>;
+
+    /// s(0), with an entry per row of T.
+    fn initial_state(&self) -> Vec<i128>;
+
+    /// Finds x(n) with modulo. Runtime O(k^3 * log(n)) for a k x k transition matrix
+    fn nth_mod(&self, n: u64, modulo: u64) -> u64 {
+        self.try_nth_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `nth_mod`, but returns an error instead of panicking when modulo is zero, or the
+    /// transition matrix and initial state don't fit together.
+    fn try_nth_mod(&self, n: u64, modulo: u64) -> Result<u64, FibError> {
+        let reduced = ReducedSequence::new(self, modulo)?;
+        Ok(nth_term(&reduced.semiring, &reduced.transition, &reduced.state, n))
+    }
+
+    /// x(0), x(1), ... with modulo. Runtime O(k^2) per term
+    fn iter_mod(&self, modulo: u64) -> ModularTerms {
+        self.try_iter_mod(modulo).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `iter_mod`, but returns an error instead of panicking.
+    fn try_iter_mod(&self, modulo: u64) -> Result<ModularTerms, FibError> {
+        Ok(ModularTerms { sequence: ReducedSequence::new(self, modulo)? })
+    }
+
+    /// x(n) with modulo for each n in indices, checking the sequence once.
+    /// Runtime O(q * k^3 * log(n)) for q indices
+    fn batch_mod(&self, indices: &[u64], modulo: u64) -> Vec<u64> {
+        self.try_batch_mod(indices, modulo).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `batch_mod`, but returns an error instead of panicking.
+    fn try_batch_mod(&self, indices: &[u64], modulo: u64) -> Result<Vec<u64>, FibError> {
+        let reduced = ReducedSequence::new(self, modulo)?;
+        Ok(indices.iter().map(|&n| nth_term(&reduced.semiring, &reduced.transition, &reduced.state, n)).collect())
+    }
+
+    /// The length of the cycle the states s(i) with modulo end up in, the pisano period for
+    /// fibonacci. The states can take up to modulo^k values, so this is for small moduli.
+    /// Runtime O((m + p) * k^2) for a period p reached after m states
+    fn period_mod(&self, modulo: u64) -> u64 {
+        self.try_period_mod(modulo).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `period_mod`, but returns an error instead of panicking.
+    fn try_period_mod(&self, modulo: u64) -> Result<u64, FibError> {
+        let reduced = ReducedSequence::new(self, modulo)?;
+        // Brent's cycle detection, comparing against the state at each power of two.
+        let (mut power, mut period) = (1, 1);
+        let mut saved = reduced.state.clone();
+        let mut state = reduced.step(&saved);
+        while state != saved {
+            if power == period {
+                saved = state.clone();
+                power *= 2;
+                period = 0;
+            }
+            state = reduced.step(&state);
+            period += 1;
+        }
+        Ok(period)
+    }
+}
+
+impl ModularSequence for Sequence {
+    fn transition_matrix(&self) -> Array2<i128> {
+        let k = self.coefficients.len();
+        Array2::from_shape_fn((k, k), |(i, j)| {
+            if i + 1 < k {
+                (j == i + 1) as i128
+            } else {
+                self.coefficients[k - 1 - j]
+            }
+        })
+    }
+
+    fn initial_state(&self) -> Vec<i128> {
+        self.initial.clone()
+    }
+}
+
+
+/// The terms of a `ModularSequence` with modulo, from `ModularSequence::iter_mod`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModularTerms {
+    sequence: ReducedSequence,
+}
+
+impl Iterator for ModularTerms {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let term = self.sequence.state[0];
+        self.sequence.state = self.sequence.step(&self.sequence.state);
+        Some(term)
+    }
+}
+
+
+// A checked ModularSequence, with its transition matrix and state reduced.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ReducedSequence {
+    semiring: Modular,
+    transition: Array2<u64>,
+    state: Vec<u64>,
+}
+
+impl ReducedSequence {
+    fn new<S: ModularSequence + ?Sized>(sequence: &S, modulo: u64) -> Result<ReducedSequence, FibError> {
+        let semiring = NonZeroU64::new(modulo).map(Modular).ok_or(FibError::ZeroModulus)?;
+        let transition = sequence.transition_matrix();
+        let state = sequence.initial_state();
+        if transition.is_empty() {
+            return Err(FibError::InvalidArgument("transition matrix must not be empty"));
+        }
+        if !transition.is_square() {
+            return Err(FibError::InvalidArgument("transition matrix must be square"));
+        }
+        if state.len() != transition.nrows() {
+            return Err(FibError::InvalidArgument("initial state must have an entry per row"));
+        }
+        Ok(ReducedSequence {
+            semiring,
+            transition: transition.map(|x| x.rem_euclid(modulo as i128) as u64),
+            state: reduce(&state, modulo),
+        })
+    }
+
+    // T * state
+    fn step(&self, state: &[u64]) -> Vec<u64> {
+        let modulo = self.semiring.0.get();
+        (0..state.len())
+            .map(|i| {
+                (0..state.len()).fold(0, |sum, j| add_mod(sum, mul_mod(self.transition[[i, j]], state[j], modulo), modulo))
+            })
+            .collect()
+    }
+}
+
+
+fn widen<T: Copy + Into<i128>>(values: &[T]) -> Vec<i128> {
+    values.iter().map(|&x| x.into()).collect()
+}
+
+
+// The residues in [0, modulo), so -1 becomes modulo - 1.
+fn reduce(values: &[i128], modulo: u64) -> Vec<u64> {
+    values.iter().map(|x| x.rem_euclid(modulo as i128) as u64).collect()
+}
+
+
+// Shifts (x(i), ..., x(i + k - 1)) to (x(i + 1), ..., x(i + k)), reduced.
+fn companion_matrix(coefficients: &[i128], modulo: u64) -> Array2<u64> {
+    let coefficients = reduce(coefficients, modulo);
+    let k = coefficients.len();
+    Array2::from_shape_fn((k, k), |(i, j)| {
+        if i + 1 < k {
+            (j == i + 1) as u64 % modulo
+        } else {
+            coefficients[k - 1 - j]
+        }
+    })
+}
+
+
+// x(n) from the first row of companion^n and the reduced initial terms.
+fn nth_term(semiring: &Modular, companion: &Array2<u64>, initial: &[u64], n: u64) -> u64 {
+    let modulo = semiring.0.get();
+    // Square, so the power can't fail.
+    let power = try_matrix_power(semiring, companion, n).unwrap();
+    (0..initial.len()).fold(0, |sum, j| add_mod(sum, mul_mod(power[[0, j]], initial[j], modulo), modulo))
+}
+
+
+/// The built-in sequences and custom ones, by name.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::sequence::{Registry, Sequence};
+///
+/// let mut registry = Registry::new();
+/// registry.register("perrin", Sequence::new(&[0, 1, 1], &[3, 0, 2]));
+/// assert_eq!(Some(277), registry.nth_mod("perrin", 20, 1_000));
+/// assert_eq!(Some(55), registry.nth_mod("fibonacci", 10, 1_000));
+/// assert_eq!(None, registry.nth_mod("primes", 10, 1_000));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Registry {
+    sequences: HashMap<String, Sequence>,
+}
+
+impl Registry {
+    /// A registry of the built-in sequences.
+    pub fn new() -> Registry {
+        let sequences = BUILTIN_NAMES
+            .iter()
+            .map(|&name| (name.to_string(), Sequence::by_name(name).unwrap()))
+            .collect();
+        Registry { sequences }
+    }
+
+    /// Adds a sequence under a name, returning the one it replaces, built-in or not.
+    pub fn register(&mut self, name: &str, sequence: Sequence) -> Option<Sequence> {
+        self.sequences.insert(name.to_string(), sequence)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Sequence> {
+        self.sequences.get(name)
+    }
+
+    /// The registered names in increasing order.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.sequences.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// x(n) with modulo of the sequence with this name, or None when there isn't one.
+    pub fn nth_mod(&self, name: &str, n: u64, modulo: u64) -> Option<u64> {
+        self.get(name).map(|sequence| sequence.nth_mod(n, modulo))
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Registry {
+        Registry::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::sequence::*;
+    use num::{Integer, ToPrimitive};
+    use num_bigint::BigUint;
+
+    #[test]
+    fn test_builtins_match_naive() {
+        for &name in &BUILTIN_NAMES {
+            let sequence = Sequence::by_name(name).unwrap();
+            let k = sequence.coefficients().len();
+            let mut terms: Vec<u128> = sequence.initial().iter().map(|&x| x as u128).collect();
+            for i in k..60 {
+                terms.push((0..k).map(|j| sequence.coefficients()[j] as u128 * terms[i - 1 - j]).sum());
+            }
+            for &modulo in &[1, 2, 10, 1_000_000_007, u64::MAX] {
+                for (n, &term) in terms.iter().enumerate() {
+                    let expected = (term % modulo as u128) as u64;
+                    assert_eq!(sequence.nth_mod(n as u64, modulo), expected, "{}({}) mod {}", name, n, modulo);
+                }
+            }
+        }
+        let fibonacci = Sequence::by_name("fibonacci").unwrap();
+        assert_eq!(fibonacci.nth_mod(u64::MAX, 1_000_000_007), crate::fib_with_mod(u64::MAX, 1_000_000_007));
+        assert_eq!(Sequence::by_name("Fibonacci"), None);
+    }
+
+    #[test]
+    fn test_narayana() {
+        let mut terms = vec![1u128, 1, 1];
+        for i in 3..150 {
+            terms.push(terms[i - 1] + terms[i - 3]);
+        }
+        for &modulo in &[1, 2, 1_000_000_007, u64::MAX] {
+            for (n, &term) in terms.iter().enumerate() {
+                assert_eq!(narayana_with_mod(n as u64, modulo), (term % modulo as u128) as u64, "n = {}, modulo = {}", n, modulo);
+            }
+        }
+        let narayana = Sequence::by_name("narayana").unwrap();
+        assert_eq!(narayana_with_mod(u64::MAX, 1_000_000_007), narayana.nth_mod(u64::MAX, 1_000_000_007));
+        assert_eq!(try_narayana_with_mod(5, 0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_hyperfib() {
+        let mut terms: Vec<u128> = (0..100).fold(vec![0, 1], |mut fibs, i| {
+            fibs.push(fibs[i] + fibs[i + 1]);
+            fibs
+        });
+        for r in 0..6 {
+            for &modulo in &[1, 2, 10, 1_000_000_007, u64::MAX] {
+                for (n, &term) in terms.iter().enumerate().take(60) {
+                    assert_eq!(hyperfib_with_mod(r, n as u64, modulo), (term % modulo as u128) as u64, "r = {}, n = {}, modulo = {}", r, n, modulo);
+                }
+            }
+            terms = terms.iter().scan(0, |sum, &term| { *sum += term; Some(*sum) }).collect();
+        }
+        let modulo = 1_000_000_007;
+        assert_eq!(hyperfib_with_mod(0, u64::MAX, modulo), crate::fib_with_mod(u64::MAX, modulo));
+        assert_eq!(hyperfib_with_mod(1, u64::MAX - 2, modulo), (crate::fib_with_mod(u64::MAX, modulo) + modulo - 1) % modulo);
+        assert_eq!(try_hyperfib_with_mod(2, 5, 0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_leonardo() {
+        let mut terms = vec![1u128, 1];
+        for i in 2..150 {
+            terms.push(terms[i - 1] + terms[i - 2] + 1);
+        }
+        for &modulo in &[1, 2, 3, 1_000_000_007, u64::MAX] {
+            for (n, &term) in terms.iter().enumerate() {
+                assert_eq!(leonardo_with_mod(n as u64, modulo), (term % modulo as u128) as u64, "n = {}, modulo = {}", n, modulo);
+            }
+        }
+        // 2 * fib(u64::MAX + 1) - 1
+        let next = crate::bigfib_with_mod(&(BigUint::from(u64::MAX) + 1u32), &BigUint::from(1_000_000_007u32));
+        let expected = (next * 2u32 + 1_000_000_006u32) % 1_000_000_007u32;
+        assert_eq!(BigUint::from(leonardo_with_mod(u64::MAX, 1_000_000_007)), expected);
+        assert_eq!(try_leonardo_with_mod(5, 0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_registry() {
+        let mut registry = Registry::default();
+        assert_eq!(registry.names(), BUILTIN_NAMES.to_vec());
+        let custom = Sequence::new(&[1], &[5]);
+        assert_eq!(registry.register("constant", custom.clone()), None);
+        assert_eq!(registry.get("constant"), Some(&custom));
+        assert_eq!(registry.nth_mod("constant", 1_000, 3), Some(2));
+        assert_eq!(registry.register("pell", custom), Sequence::by_name("pell"));
+        assert_eq!(registry.nth_mod("pell", 7, 100), Some(5));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(Sequence::try_new::<u64>(&[], &[]), Err(FibError::InvalidArgument("coefficients must not be empty")));
+        assert_eq!(
+            Sequence::try_new(&[1, 1], &[0]),
+            Err(FibError::InvalidArgument("initial must have a term per coefficient"))
+        );
+        assert_eq!(Sequence::by_name("lucas").unwrap().try_nth_mod(3, 0), Err(FibError::ZeroModulus));
+        assert_eq!(Recurrence::new().coeffs(&[1]).init(&[1]).build(), Err(FibError::ZeroModulus));
+        assert_eq!(
+            Recurrence::new().coeffs(&[1, 1]).modulo(10).build(),
+            Err(FibError::InvalidArgument("initial must have a term per coefficient"))
+        );
+    }
+
+    #[test]
+    fn test_recurrence_matches_sequence() {
+        for &name in &BUILTIN_NAMES {
+            let sequence = Sequence::by_name(name).unwrap();
+            for &modulo in &[1, 10, 1_000_000_007, u64::MAX] {
+                let evaluator = Recurrence::from(sequence.clone()).modulo(modulo).build().unwrap();
+                assert_eq!(evaluator.modulo(), modulo);
+                for &n in &[0, 1, 2, 3, 50, 1_000_000_000_000, u64::MAX] {
+                    assert_eq!(evaluator.nth(n), sequence.nth_mod(n, modulo), "{}({}) mod {}", name, n, modulo);
+                }
+            }
+        }
+        // Unreduced coefficients and initial terms
+        let evaluator = Recurrence::new().coeffs(&[11, 21]).init(&[10, 31]).modulo(10).build().unwrap();
+        assert_eq!((0..4).map(|n| evaluator.nth(n)).collect::<Vec<u64>>(), vec![0, 1, 1, 2]);
+    }
+
+    #[test]
+    fn test_signed_coefficients() {
+        // x(i) = 2x(i - 1) - 3x(i - 2) + x(i - 3) - 4 * 10^18 x(i - 4), exactly and then reduced.
+        let coefficients: [i64; 4] = [2, -3, 1, -4_000_000_000_000_000_000];
+        let initial = [1, -1, 0, 7];
+        let sequence = Sequence::new(&coefficients, &initial);
+        let mut terms: Vec<BigInt> = initial.iter().map(|&x| BigInt::from(x)).collect();
+        for i in 4..80 {
+            let term = (0..4).map(|j| BigInt::from(coefficients[j]) * &terms[i - 1 - j]).sum();
+            terms.push(term);
+        }
+        for &modulo in &[1, 2, 7, 1_000_000_007, u64::MAX] {
+            let evaluator = Recurrence::new().coeffs(&coefficients).init(&initial).modulo(modulo).build().unwrap();
+            for (n, term) in terms.iter().enumerate() {
+                assert_eq!(&sequence.nth(n as u64), term, "n = {}", n);
+                let expected = term.mod_floor(&BigInt::from(modulo)).to_u64().unwrap();
+                assert_eq!(sequence.nth_mod(n as u64, modulo), expected, "n = {}, modulo = {}", n, modulo);
+                assert_eq!(evaluator.nth(n as u64), expected, "n = {}, modulo = {}", n, modulo);
+            }
+        }
+        // i64::MIN and u64::MAX both fit.
+        let extremes = Sequence::new(&[i128::from(i64::MIN), i128::from(u64::MAX)], &[0, 1]);
+        assert_eq!(extremes.nth_mod(2, 10), 2);
+        assert_eq!(extremes.nth_mod(3, 10), 9);
+    }
+
+    #[test]
+    fn test_modular_sequence_matches_sequence() {
+        for &name in &BUILTIN_NAMES {
+            let sequence = Sequence::by_name(name).unwrap();
+            let dynamic: &dyn ModularSequence = &sequence;
+            for &modulo in &[1, 10, 1_000_000_007, u64::MAX] {
+                let indices: Vec<u64> = (0..40).chain([1_000_000_000_000, u64::MAX]).collect();
+                let expected: Vec<u64> = indices.iter().map(|&n| sequence.nth_mod(n, modulo)).collect();
+                assert_eq!(dynamic.batch_mod(&indices, modulo), expected, "{} mod {}", name, modulo);
+                assert_eq!(indices.iter().map(|&n| dynamic.nth_mod(n, modulo)).collect::<Vec<u64>>(), expected);
+                assert_eq!(dynamic.iter_mod(modulo).take(40).collect::<Vec<u64>>(), expected[..40].to_vec());
+            }
+        }
+        let fibonacci = Sequence::by_name("fibonacci").unwrap();
+        for modulo in 1..200 {
+            assert_eq!(fibonacci.period_mod(modulo), crate::pisano::pisano_period(modulo), "modulo = {}", modulo);
+        }
+        // x(i) = 2x(i - 2) reaches 0 modulo 8, and stays there.
+        assert_eq!(Sequence::new(&[0, 2], &[1, 1]).period_mod(8), 1);
+    }
+
+    #[test]
+    fn test_modular_sequence_errors() {
+        struct Shaped(Array2<i128>, Vec<i128>);
+
+        impl ModularSequence for Shaped {
+            fn transition_matrix(&self) -> Array2<i128> {
+                self.0.clone()
+            }
+
+            fn initial_state(&self) -> Vec<i128> {
+                self.1.clone()
+            }
+        }
+
+        let empty = Shaped(Array2::zeros((0, 0)), vec![]);
+        assert_eq!(empty.try_nth_mod(1, 10), Err(FibError::InvalidArgument("transition matrix must not be empty")));
+        let wide = Shaped(Array2::zeros((1, 2)), vec![0]);
+        assert_eq!(wide.try_batch_mod(&[1], 10), Err(FibError::InvalidArgument("transition matrix must be square")));
+        let short = Shaped(Array2::zeros((2, 2)), vec![0]);
+        assert_eq!(short.try_period_mod(10), Err(FibError::InvalidArgument("initial state must have an entry per row")));
+        let fine = Shaped(Array2::eye(2), vec![-1, 0]);
+        assert_eq!(fine.try_iter_mod(0), Err(FibError::ZeroModulus));
+        assert_eq!(fine.iter_mod(10).take(2).collect::<Vec<u64>>(), vec![9, 9]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let pell = Recurrence::new().coeffs(&[2, 1]).init(&[0, 1]).modulo(1_000);
+        let json = serde_json::to_string(&pell).unwrap();
+        assert_eq!(json, r#"{"coefficients":[2,1],"initial":[0,1],"modulo":1000}"#);
+        assert_eq!(serde_json::from_str::<Recurrence>(&json).unwrap(), pell);
+        let bad = serde_json::from_str::<Recurrence>(r#"{"coefficients":[],"initial":[],"modulo":7}"#).unwrap();
+        assert_eq!(bad.build(), Err(FibError::InvalidArgument("coefficients must not be empty")));
+    }
+}
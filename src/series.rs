@@ -0,0 +1,304 @@
+//! Truncated power series with modulo: the first coefficients of generating functions.
+//!
+//! The generating function of fib(n) is x / (1 - x - x^2), and that of any linear recurrence is a
+//! ratio P(x) / Q(x) of polynomials, with Q from the recurrence's coefficients and P from its
+//! initial terms. Expanding the ratio term by term costs O(deg(Q)) per coefficient, which is
+//! best for short recurrences. For long denominators, Q is inverted by Newton iteration on power
+//! series with Karatsuba multiplication instead.
+
+use std::num::NonZeroU64;
+
+use crate::nt::mod_inverse;
+use crate::semiring::{linear_recurrence_in, Modular};
+use crate::{add_mod, mul_mod, sub_mod, FibError};
+
+// Below this many coefficients, schoolbook multiplication beats Karatsuba.
+const KARATSUBA_THRESHOLD: usize = 32;
+
+
+/// Finds fib(0), fib(1), ..., fib(count - 1) with modulo, the first count coefficients of
+/// x / (1 - x - x^2). Runtime O(count)
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::series::fib_series_with_mod;
+///
+/// assert_eq!(vec![0, 1, 1, 2, 3, 5, 8, 13, 21, 34], fib_series_with_mod(10, 1_000));
+/// assert_eq!(vec![0, 1, 1, 2, 3, 5, 8, 3, 1, 4], fib_series_with_mod(10, 10));
+/// ```
+pub fn fib_series_with_mod(count: usize, modulo: u64) -> Vec<u64> {
+    try_fib_series_with_mod(count, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fib_series_with_mod`, but returns an error instead of panicking.
+pub fn try_fib_series_with_mod(count: usize, modulo: u64) -> Result<Vec<u64>, FibError> {
+    try_rational_series_with_mod(&[0, 1], &[1, modulo.wrapping_sub(1), modulo.wrapping_sub(1)], count, modulo)
+}
+
+
+/// Finds the first count coefficients of numerator(x) / denominator(x) with modulo, for
+/// polynomials given by their coefficients from the constant term up. The constant term of the
+/// denominator must be invertible modulo `modulo`.
+/// Runtime O(count * deg(denominator)), or O(count^1.59) by Newton iteration when that's less
+///
+/// A recurrence a(n) = c_1 a(n - 1) + ... + c_k a(n - k) has denominator 1 - c_1 x - ... - c_k x^k,
+/// and its numerator is the denominator times the initial terms, truncated to degree k - 1.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::series::rational_series_with_mod;
+///
+/// // 1 / (1 - 2x) = 1 + 2x + 4x^2 + ...
+/// assert_eq!(vec![1, 2, 4, 8, 16, 32], rational_series_with_mod(&[1], &[1, 98], 6, 100));
+/// // The lucas numbers (2 - x) / (1 - x - x^2)
+/// assert_eq!(vec![2, 1, 3, 4, 7, 11, 18], rational_series_with_mod(&[2, 999], &[1, 999, 999], 7, 1_000));
+/// ```
+pub fn rational_series_with_mod(numerator: &[u64], denominator: &[u64], count: usize, modulo: u64) -> Vec<u64> {
+    try_rational_series_with_mod(numerator, denominator, count, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `rational_series_with_mod`, but returns an error instead of panicking.
+pub fn try_rational_series_with_mod(
+    numerator: &[u64],
+    denominator: &[u64],
+    count: usize,
+    modulo: u64,
+) -> Result<Vec<u64>, FibError> {
+    if modulo == 0 {
+        return Err(FibError::ZeroModulus);
+    }
+    let constant_inverse = denominator
+        .first()
+        .and_then(|&constant| mod_inverse(constant, modulo))
+        .ok_or(FibError::InvalidArgument("denominator's constant term must be invertible"))?;
+    let reduce = |polynomial: &[u64]| polynomial.iter().take(count).map(|&c| c % modulo).collect::<Vec<u64>>();
+    let (numerator, denominator) = (reduce(numerator), reduce(denominator));
+
+    let linear_cost = count as f64 * denominator.len() as f64;
+    let newton_cost = 8.0 * (count as f64).powf(1.59);
+    if linear_cost <= newton_cost {
+        return Ok(expand(&numerator, &denominator, constant_inverse, count, modulo));
+    }
+    let mut series = multiply(&numerator, &inverse(&denominator, constant_inverse, count, modulo), modulo);
+    series.resize(count, 0);
+    Ok(series)
+}
+
+
+/// Finds the nth convolved fibonacci number of order r with modulo, the coefficient of x^n in
+/// (x / (1 - x - x^2))^r, which is the sum of fib(i_1)fib(i_2)...fib(i_r) over i_1 + ... + i_r = n.
+/// Runtime O(r^3 * log(n))
+///
+/// The coefficients of 1 / (1 - x - x^2)^r follow the linear recurrence of order 2r read off the
+/// expanded denominator, so after the first 2r terms by expansion, the nth is a power of its
+/// companion matrix.
+///
+/// Panics if modulo is zero, see `try_convolved_fib_with_mod`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::series::convolved_fib_with_mod;
+///
+/// let terms: Vec<u64> = (0..10).map(|n| convolved_fib_with_mod(2, n, 1_000)).collect();
+/// assert_eq!(vec![0, 0, 1, 2, 5, 10, 20, 38, 71, 130], terms);
+/// // The first order is fib(n).
+/// assert_eq!(fast_fibonacci::fib_with_mod(1_000, 1_000_000), convolved_fib_with_mod(1, 1_000, 1_000_000));
+/// ```
+pub fn convolved_fib_with_mod(r: usize, n: u64, modulo: u64) -> u64 {
+    try_convolved_fib_with_mod(r, n, modulo).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `convolved_fib_with_mod`, but returns an error instead of panicking.
+pub fn try_convolved_fib_with_mod(r: usize, n: u64, modulo: u64) -> Result<u64, FibError> {
+    let semiring = NonZeroU64::new(modulo).map(Modular).ok_or(FibError::ZeroModulus)?;
+    if n < r as u64 {
+        return Ok(0);
+    }
+    if r == 0 {
+        return Ok(1 % modulo);
+    }
+    // x^r / (1 - x - x^2)^r, so the coefficient of x^(n - r) in 1 / (1 - x - x^2)^r.
+    let n = n - r as u64;
+    let factor = [1 % modulo, modulo - 1 % modulo, modulo - 1 % modulo];
+    let denominator = (1..r).fold(factor.to_vec(), |power, _| multiply(&power, &factor, modulo));
+    let order = 2 * r;
+    let initial = expand(&[1 % modulo], &denominator, 1 % modulo, order, modulo);
+    if n < order as u64 {
+        return Ok(initial[n as usize]);
+    }
+    // c_n = -q_1 c_(n-1) - ... - q_2r c_(n-2r)
+    let coefficients: Vec<u64> = denominator[1..].iter().map(|&q| sub_mod(0, q, modulo)).collect();
+    Ok(linear_recurrence_in(&semiring, &coefficients, &initial, n))
+}
+
+
+// Term by term: c_n = (p_n - q_1 c_(n-1) - ... - q_k c_(n-k)) / q_0.
+fn expand(numerator: &[u64], denominator: &[u64], constant_inverse: u64, count: usize, modulo: u64) -> Vec<u64> {
+    let mut series = Vec::with_capacity(count);
+    for n in 0..count {
+        let mut c = numerator.get(n).copied().unwrap_or(0);
+        for (i, &q) in denominator.iter().enumerate().skip(1).take(n) {
+            c = sub_mod(c, mul_mod(q, series[n - i], modulo), modulo);
+        }
+        series.push(mul_mod(c, constant_inverse, modulo));
+    }
+    series
+}
+
+
+// 1 / series to count coefficients, doubling the precision with g = g * (2 - series * g).
+fn inverse(series: &[u64], constant_inverse: u64, count: usize, modulo: u64) -> Vec<u64> {
+    let mut inverse = vec![constant_inverse];
+    let mut length = 1;
+    while length < count {
+        length = (2 * length).min(count);
+        let mut correction = multiply(&series[..series.len().min(length)], &inverse, modulo);
+        correction.resize(length, 0);
+        for c in correction.iter_mut() {
+            *c = sub_mod(0, *c, modulo);
+        }
+        correction[0] = add_mod(correction[0], 2 % modulo, modulo);
+        inverse = multiply(&inverse, &correction, modulo);
+        inverse.truncate(length);
+    }
+    inverse.truncate(count);
+    inverse
+}
+
+
+// a * b with modulo, all len(a) + len(b) - 1 coefficients, by Karatsuba for long operands.
+fn multiply(a: &[u64], b: &[u64], modulo: u64) -> Vec<u64> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut product = vec![0; a.len() + b.len() - 1];
+    if a.len().min(b.len()) < KARATSUBA_THRESHOLD {
+        for (i, &x) in a.iter().enumerate() {
+            for (j, &y) in b.iter().enumerate() {
+                product[i + j] = add_mod(product[i + j], mul_mod(x, y, modulo), modulo);
+            }
+        }
+        return product;
+    }
+
+    // (a0 + a1 x^h)(b0 + b1 x^h) = z0 + z1 x^h + z2 x^2h, with z1 = (a0 + a1)(b0 + b1) - z0 - z2.
+    let half = a.len().max(b.len()) / 2;
+    let (a0, a1) = a.split_at(half.min(a.len()));
+    let (b0, b1) = b.split_at(half.min(b.len()));
+    let z0 = multiply(a0, b0, modulo);
+    let z2 = multiply(a1, b1, modulo);
+    let mut z1 = multiply(&add(a0, a1, modulo), &add(b0, b1, modulo), modulo);
+    for (i, &c) in z0.iter().enumerate() {
+        z1[i] = sub_mod(z1[i], c, modulo);
+    }
+    for (i, &c) in z2.iter().enumerate() {
+        z1[i] = sub_mod(z1[i], c, modulo);
+    }
+    for (offset, part) in [(0, &z0), (half, &z1), (2 * half, &z2)] {
+        for (i, &c) in part.iter().enumerate() {
+            product[offset + i] = add_mod(product[offset + i], c, modulo);
+        }
+    }
+    product
+}
+
+
+fn add(a: &[u64], b: &[u64], modulo: u64) -> Vec<u64> {
+    (0..a.len().max(b.len()))
+        .map(|i| add_mod(a.get(i).copied().unwrap_or(0), b.get(i).copied().unwrap_or(0), modulo))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::series::*;
+
+    #[test]
+    fn test_fib_series() {
+        for &modulo in &[1, 2, 10, 1_000_000_007, u64::MAX] {
+            let series = fib_series_with_mod(300, modulo);
+            for (n, &f) in series.iter().enumerate() {
+                assert_eq!(f, crate::fib_with_mod(n as u64, modulo), "n = {}, modulo = {}", n, modulo);
+            }
+        }
+        assert!(fib_series_with_mod(0, 10).is_empty());
+        assert_eq!(try_fib_series_with_mod(5, 0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_newton_matches_expansion() {
+        // A long denominator makes Newton iteration the cheaper choice.
+        for &modulo in &[998_244_353, 1 << 40, u64::MAX] {
+            let mut denominator: Vec<u64> = (0..2_000u64).map(|i| (i * i + 1) % 1_000 * 7 + 1).collect();
+            denominator[0] = 1;
+            let numerator: Vec<u64> = (0..100u64).map(|i| i * 31 + 5).collect();
+            let constant_inverse = mod_inverse(denominator[0], modulo).unwrap();
+            let count = 1_500;
+            let expected = expand(&numerator, &denominator, constant_inverse, count, modulo);
+            assert_eq!(rational_series_with_mod(&numerator, &denominator, count, modulo), expected);
+            let product = multiply(&denominator, &expected, modulo);
+            assert_eq!(&product[..count], &add(&numerator, &[0; 1_500], modulo)[..]);
+        }
+    }
+
+    #[test]
+    fn test_multiply_matches_schoolbook() {
+        let modulo = u64::MAX - 58;
+        for &(len_a, len_b) in &[(1, 100), (33, 33), (100, 40), (257, 31), (500, 499)] {
+            let a: Vec<u64> = (0..len_a).map(|i| u64::MAX - 3 * i as u64).map(|c| c % modulo).collect();
+            let b: Vec<u64> = (0..len_b).map(|i| (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) % modulo).collect();
+            let mut expected = vec![0; len_a + len_b - 1];
+            for i in 0..len_a {
+                for j in 0..len_b {
+                    expected[i + j] = add_mod(expected[i + j], mul_mod(a[i], b[j], modulo), modulo);
+                }
+            }
+            assert_eq!(multiply(&a, &b, modulo), expected, "{} x {}", len_a, len_b);
+        }
+    }
+
+    #[test]
+    fn test_convolved_fib() {
+        let fibs: Vec<u128> = (0..40).fold(vec![0, 1], |mut fibs, i| {
+            fibs.push(fibs[i] + fibs[i + 1]);
+            fibs
+        });
+        let mut power = vec![1u128];
+        for r in 0..6 {
+            for &modulo in &[1, 2, 10, 1_000_000_007, u64::MAX] {
+                for (n, &term) in power.iter().enumerate() {
+                    assert_eq!(convolved_fib_with_mod(r, n as u64, modulo), (term % modulo as u128) as u64, "r = {}, n = {}, modulo = {}", r, n, modulo);
+                }
+            }
+            // Times x / (1 - x - x^2), truncated to 40 terms.
+            power = (0..40).map(|n| (0..=n).map(|i| power.get(i).copied().unwrap_or(0) * fibs[n - i]).sum()).collect();
+        }
+        // Past the first 2r terms, against expanding the whole series.
+        let modulo = 998_244_353;
+        let factor = [1, modulo - 1, modulo - 1];
+        let denominator = (1..7).fold(factor.to_vec(), |power, _| multiply(&power, &factor, modulo));
+        let series = rational_series_with_mod(&[0, 0, 0, 0, 0, 0, 0, 1], &denominator, 3_000, modulo);
+        for &n in &[7, 20, 999, 2_999] {
+            assert_eq!(convolved_fib_with_mod(7, n, modulo), series[n as usize], "n = {}", n);
+        }
+        assert_eq!(convolved_fib_with_mod(1, u64::MAX, modulo), crate::fib_with_mod(u64::MAX, modulo));
+        assert_eq!(try_convolved_fib_with_mod(2, 5, 0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            try_rational_series_with_mod(&[1], &[2, 1], 5, 10),
+            Err(FibError::InvalidArgument("denominator's constant term must be invertible"))
+        );
+        assert_eq!(
+            try_rational_series_with_mod(&[1], &[], 5, 10),
+            Err(FibError::InvalidArgument("denominator's constant term must be invertible"))
+        );
+        assert_eq!(rational_series_with_mod(&[3, 4, 5], &[1], 5, 10), vec![3, 4, 5, 0, 0]);
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use num_bigint::BigUint;
+use num::ToPrimitive;
+
+use crate::hash::fib_hash;
+use crate::period_cache::MAX_CACHED_MODULI;
+use crate::pisano::{try_pisano_info, PisanoInfo};
+use crate::{fib_add_indices, FibError};
+
+// The moduli are spread over 2^SHARD_BITS maps by fib_hash, each behind its own lock.
+const SHARD_BITS: u32 = 4;
+const SHARDS: usize = 1 << SHARD_BITS;
+
+
+/// Per modulo state for fib(n) queries, shared by every thread of a server: the pairs
+/// (fib(2^i), fib(2^i + 1)) for each bit i, so that a query only adds up the pairs of its set
+/// bits, and the modulo's `PisanoInfo` once asked for, which reduces n from then on.
+///
+/// The moduli are split over 16 shards by `hash::fib_hash`, each behind its own lock, so queries
+/// for different moduli rarely wait on each other, and state is built without holding a lock.
+/// A shard that reaches its share of `max_moduli` starts over rather than growing without bound.
+///
+/// # Examples
+/// ```
+/// use std::thread;
+/// use fast_fibonacci::SharedFibCache;
+///
+/// let cache = SharedFibCache::new();
+/// thread::scope(|scope| {
+///     for _ in 0..4 {
+///         scope.spawn(|| assert_eq!(546_875, cache.fib_with_mod(1_000_000_000_000_000, 1_000_000)));
+///     }
+/// });
+/// assert_eq!(1_500_000, cache.pisano_info(1_000_000).period);
+/// assert_eq!(1, cache.len());
+/// ```
+#[derive(Debug)]
+pub struct SharedFibCache {
+    shards: Vec<RwLock<HashMap<u64, Arc<ModuloState>>>>,
+    max_moduli: usize,
+}
+
+// What the cache keeps for a modulo.
+#[derive(Debug)]
+struct ModuloState {
+    // (fib(2^i), fib(2^i + 1)) at index i.
+    powers: [(u64, u64); 64],
+    info: OnceLock<PisanoInfo>,
+}
+
+impl Default for SharedFibCache {
+    fn default() -> Self {
+        SharedFibCache::new()
+    }
+}
+
+impl SharedFibCache {
+    /// A cache of up to `period_cache::MAX_CACHED_MODULI` moduli.
+    pub fn new() -> SharedFibCache {
+        SharedFibCache::with_max_moduli(MAX_CACHED_MODULI)
+    }
+
+    /// A cache of up to about max_moduli moduli, give or take one per shard.
+    pub fn with_max_moduli(max_moduli: usize) -> SharedFibCache {
+        SharedFibCache { shards: (0..SHARDS).map(|_| RwLock::new(HashMap::new())).collect(), max_moduli }
+    }
+
+    /// About how many moduli the cache keeps state for, see `with_max_moduli`.
+    pub fn max_moduli(&self) -> usize {
+        self.max_moduli
+    }
+
+    /// Finds the nth fibonacci number with modulo. Runtime 63 doublings the first time a modulo
+    /// is seen, and then O(k) multiplications for the k set bits of n, or of n reduced by the
+    /// pisano period once `pisano_info` found it.
+    ///
+    /// Panics if modulo is 0, see `try_fib_with_mod`.
+    pub fn fib_with_mod(&self, n: u64, modulo: u64) -> u64 {
+        self.try_fib_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `fib_with_mod`, but returns an error instead of panicking.
+    pub fn try_fib_with_mod(&self, n: u64, modulo: u64) -> Result<u64, FibError> {
+        if modulo == 0 {
+            return Err(FibError::ZeroModulus);
+        }
+        let state = self.state(modulo);
+        let n = state.info.get().map_or(n, |info| n % info.period);
+        Ok(state.fib(n, modulo))
+    }
+
+    /// `fib_with_mod` for an n of any size. Past 2^64, the powers of two beyond the kept ones are
+    /// doubled on the way, unless the pisano period is known, which brings n below it.
+    ///
+    /// Panics if modulo is 0, see `try_bigfib_with_mod`.
+    ///
+    /// # Examples
+    /// ```
+    /// use fast_fibonacci::SharedFibCache;
+    /// use num_bigint::BigUint;
+    ///
+    /// let cache = SharedFibCache::new();
+    /// let n = num::pow(BigUint::from(10u32), 30);
+    /// assert_eq!(fast_fibonacci::bigfib_with_mod(&n, &BigUint::from(1_000u32)), BigUint::from(cache.bigfib_with_mod(&n, 1_000)));
+    /// ```
+    pub fn bigfib_with_mod(&self, n: &BigUint, modulo: u64) -> u64 {
+        self.try_bigfib_with_mod(n, modulo).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `bigfib_with_mod`, but returns an error instead of panicking.
+    pub fn try_bigfib_with_mod(&self, n: &BigUint, modulo: u64) -> Result<u64, FibError> {
+        if modulo == 0 {
+            return Err(FibError::ZeroModulus);
+        }
+        let state = self.state(modulo);
+        if let Some(info) = state.info.get() {
+            return Ok(state.fib((n % info.period).to_u64().unwrap(), modulo));
+        }
+        if let Some(n) = n.to_u64() {
+            return Ok(state.fib(n, modulo));
+        }
+        let n_bytes = n.to_bytes_le();
+        let (mut pair, mut power) = ((0, 1 % modulo), state.powers[0]);
+        for bit in 0..8 * n_bytes.len() {
+            power = if bit < 64 { state.powers[bit] } else { fib_add_indices(power, power, modulo) };
+            if n_bytes[bit / 8] >> (bit % 8) & 1 == 1 {
+                pair = fib_add_indices(pair, power, modulo);
+            }
+        }
+        Ok(pair.0)
+    }
+
+    /// `pisano::pisano_info`, found once per modulo. Runtime O(sqrt(modulo)) the first time,
+    /// and O(1) after
+    ///
+    /// Panics if modulo is 0 or the period doesn't fit in a u64, see `try_pisano_info`.
+    pub fn pisano_info(&self, modulo: u64) -> PisanoInfo {
+        self.try_pisano_info(modulo).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Like `pisano_info`, but returns an error instead of panicking. Errors aren't kept.
+    pub fn try_pisano_info(&self, modulo: u64) -> Result<PisanoInfo, FibError> {
+        if modulo == 0 {
+            return Err(FibError::ZeroModulus);
+        }
+        let state = self.state(modulo);
+        if let Some(&info) = state.info.get() {
+            return Ok(info);
+        }
+        let info = try_pisano_info(modulo)?;
+        Ok(*state.info.get_or_init(|| info))
+    }
+
+    /// How many moduli have state kept.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| read(shard).len()).sum()
+    }
+
+    /// Whether no modulo has state kept.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forgets the state of every modulo.
+    pub fn clear(&self) {
+        for shard in &self.shards {
+            write(shard).clear();
+        }
+    }
+
+    // The state of a positive modulo, built if it isn't kept.
+    fn state(&self, modulo: u64) -> Arc<ModuloState> {
+        let shard = &self.shards[fib_hash(modulo, SHARD_BITS) as usize];
+        if let Some(state) = read(shard).get(&modulo) {
+            return Arc::clone(state);
+        }
+        // Built without holding the lock, so queries of other moduli aren't held up meanwhile.
+        let state = Arc::new(ModuloState::new(modulo));
+        let mut states = write(shard);
+        if states.len() >= self.max_moduli.div_ceil(SHARDS) && !states.contains_key(&modulo) {
+            states.clear();
+        }
+        Arc::clone(states.entry(modulo).or_insert(state))
+    }
+}
+
+impl ModuloState {
+    fn new(modulo: u64) -> ModuloState {
+        let mut powers = [(0, 0); 64];
+        powers[0] = (1 % modulo, 1 % modulo);
+        for bit in 1..64 {
+            powers[bit] = fib_add_indices(powers[bit - 1], powers[bit - 1], modulo);
+        }
+        ModuloState { powers, info: OnceLock::new() }
+    }
+
+    // fib(n), adding up the pairs of the set bits of n.
+    fn fib(&self, n: u64, modulo: u64) -> u64 {
+        let mut pair = (0, 1 % modulo);
+        for (bit, &power) in self.powers.iter().enumerate() {
+            if n >> bit & 1 == 1 {
+                pair = fib_add_indices(pair, power, modulo);
+            }
+        }
+        pair.0
+    }
+}
+
+
+// A panic can't leave a shard inconsistent, only without some moduli, so poisoned locks are used
+// anyway.
+fn read<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+
+fn write<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::shared_cache::*;
+    use std::thread;
+
+    #[test]
+    fn test_matches_fib_with_mod() {
+        let cache = SharedFibCache::new();
+        let moduli = [1, 2, 10, 1_000, 1_000_000_007, u64::MAX];
+        let ns = [0, 1, 2, 93, 1 << 40, 1_000_000_000_000_000, u64::MAX];
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for &modulo in &moduli {
+                        for &n in &ns {
+                            assert_eq!(cache.fib_with_mod(n, modulo), crate::fib_with_mod(n, modulo), "n = {}", n);
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(cache.len(), moduli.len());
+
+        for &modulo in &moduli[..5] {
+            assert_eq!(cache.pisano_info(modulo), crate::pisano::pisano_info(modulo));
+            for &n in &ns {
+                assert_eq!(cache.fib_with_mod(n, modulo), crate::fib_with_mod(n, modulo), "n = {}", n);
+            }
+        }
+        assert_eq!(cache.try_fib_with_mod(1, 0), Err(FibError::ZeroModulus));
+        assert_eq!(cache.try_pisano_info(0), Err(FibError::ZeroModulus));
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_bigfib_with_mod() {
+        let cache = SharedFibCache::new();
+        let ns = [BigUint::from(0u32), BigUint::from(u64::MAX), BigUint::from(u64::MAX) + 1u32, num::pow(BigUint::from(7u32), 100)];
+        for &modulo in &[1, 10, 1_000_000_007, u64::MAX] {
+            for n in &ns {
+                let expected = crate::bigfib_with_mod(n, &BigUint::from(modulo)).to_u64().unwrap();
+                assert_eq!(cache.bigfib_with_mod(n, modulo), expected, "n = {}, modulo = {}", n, modulo);
+                if modulo != u64::MAX {
+                    cache.pisano_info(modulo);
+                    assert_eq!(cache.bigfib_with_mod(n, modulo), expected, "n = {}, modulo = {}", n, modulo);
+                }
+            }
+        }
+        assert_eq!(cache.try_bigfib_with_mod(&BigUint::from(1u32), 0), Err(FibError::ZeroModulus));
+    }
+
+    #[test]
+    fn test_forgets_moduli() {
+        let cache = SharedFibCache::with_max_moduli(64);
+        assert_eq!(cache.max_moduli(), 64);
+        for modulo in 1..=10_000 {
+            assert_eq!(cache.fib_with_mod(100, modulo), crate::fib_with_mod(100, modulo));
+            assert!(cache.len() <= 64);
+        }
+        assert!(!cache.is_empty());
+    }
+}
@@ -0,0 +1,70 @@
+//! JavaScript bindings through wasm-bindgen, with the `wasm` feature.
+//!
+//! u64 arguments arrive as JavaScript BigInts. Arbitrarily large arguments are passed as decimal
+//! strings, and errors are thrown as JavaScript strings.
+
+use num_bigint::BigUint;
+use wasm_bindgen::prelude::*;
+
+use crate::FibError;
+
+
+/// `fibWithMod(n: bigint, modulo: bigint): bigint` in JavaScript, see `fib_with_mod`.
+#[wasm_bindgen(js_name = fibWithMod)]
+pub fn fib_with_mod(n: u64, modulo: u64) -> Result<u64, JsValue> {
+    crate::try_fib_with_mod(n, modulo).map_err(to_js_error)
+}
+
+
+/// `bigfibWithMod(n: string, modulo: string): string` in JavaScript, see `bigfib_with_mod`.
+/// n and modulo are decimal strings, and so is the result.
+#[wasm_bindgen(js_name = bigfibWithMod)]
+pub fn bigfib_with_mod(n: &str, modulo: &str) -> Result<String, JsValue> {
+    decimal_bigfib_with_mod(n, modulo).map_err(|message| JsValue::from_str(&message))
+}
+
+
+fn decimal_bigfib_with_mod(n: &str, modulo: &str) -> Result<String, String> {
+    let n = parse_decimal(n)?;
+    let modulo = parse_decimal(modulo)?;
+    crate::try_bigfib_with_mod(&n, &modulo)
+        .map(|fib| fib.to_str_radix(10))
+        .map_err(|err| err.to_string())
+}
+
+
+fn parse_decimal(digits: &str) -> Result<BigUint, String> {
+    let digits = digits.trim();
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("expected a decimal number, found {:?}", digits));
+    }
+    BigUint::parse_bytes(digits.as_bytes(), 10)
+        .ok_or_else(|| format!("expected a decimal number, found {:?}", digits))
+}
+
+
+fn to_js_error(err: FibError) -> JsValue {
+    JsValue::from_str(&err.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::wasm::*;
+
+    #[test]
+    fn test_decimal_bigfib_with_mod() {
+        assert_eq!(decimal_bigfib_with_mod("10", "100"), Ok("55".to_string()));
+        assert_eq!(
+            decimal_bigfib_with_mod(" 1000000000000000000000 ", "1000000007"),
+            Ok(crate::fib_with_mod(
+                (1_000_000_000_000_000_000_000u128 % crate::pisano::pisano_period(1_000_000_007) as u128) as u64,
+                1_000_000_007
+            ).to_string())
+        );
+        assert_eq!(decimal_bigfib_with_mod("10", "0"), Err("modulo must be positive".to_string()));
+        assert!(decimal_bigfib_with_mod("-1", "10").is_err());
+        assert!(decimal_bigfib_with_mod("", "10").is_err());
+        assert!(decimal_bigfib_with_mod("1e5", "10").is_err());
+    }
+}
@@ -0,0 +1,50 @@
+//! Wiping of values derived from secret indices and moduli, with the `zeroize` feature. Without
+//! it, these are no-ops.
+//!
+//! num-bigint has no zeroize support, so BigUints are wiped by overwriting their digits with
+//! zeros in place. Temporaries inside num-bigint's own arithmetic aren't reachable, and aren't
+//! wiped, and neither are GMP's integers with the `rug` feature.
+
+use core::hint::black_box;
+use ndarray::Array2;
+use num_bigint::BigUint;
+
+
+pub(crate) fn wipe(x: &mut BigUint) {
+    if cfg!(feature = "zeroize") {
+        // assign_from_slice resizes the digits in place before copying, so the zeros land in the
+        // same allocation. num-bigint 0.2 digits are u32.
+        let digits = x.bits().div_ceil(32);
+        x.assign_from_slice(&vec![0; digits]);
+        black_box(&*x);
+    }
+}
+
+
+pub(crate) fn wipe_matrix(mat: &mut Array2<BigUint>) {
+    mat.iter_mut().for_each(wipe);
+}
+
+
+pub(crate) fn wipe_bytes(bytes: &mut [u8]) {
+    if cfg!(feature = "zeroize") {
+        bytes.iter_mut().for_each(|byte| *byte = 0);
+        black_box(&*bytes);
+    }
+}
+
+
+#[cfg(all(test, feature = "zeroize"))]
+mod tests {
+    use crate::wipe::*;
+
+    #[test]
+    fn test_wipe() {
+        let mut x = BigUint::parse_bytes(b"123456789012345678901234567890", 10).unwrap();
+        wipe(&mut x);
+        assert_eq!(x, BigUint::from(0u32));
+        let mut bytes = vec![1, 2, 3];
+        wipe_bytes(&mut bytes);
+        assert_eq!(bytes, vec![0, 0, 0]);
+    }
+}
@@ -0,0 +1,253 @@
+//! The infinite fibonacci word 0100101001001010010100100101..., the limit of S(0) = 0,
+//! S(1) = 01 and S(n) = S(n - 1) S(n - 2).
+//!
+//! Its kth letter is the last digit of the Zeckendorf representation of k, the sum of
+//! non-consecutive fibonacci numbers that adds up to k, so any letter can be found without
+//! building the prefix before it.
+//!
+//! `fractal_turns` and `fractal_path` draw the fibonacci word fractal from it: a unit step
+//! forward for every letter, then for a 0 at position n, counting from 1, a quarter turn left
+//! for even n and right for odd n.
+//!
+//! # Examples
+//! ```
+//! use fast_fibonacci::word::fractal_path;
+//!
+//! // The 8 letters 01001010 of generation 4.
+//! let path: Vec<(i64, i64)> = fractal_path(4).collect();
+//! assert_eq!(vec![(0, 0), (1, 0), (1, -1), (1, -2), (0, -2), (0, -3), (0, -4), (1, -4), (2, -4)], path);
+//! ```
+
+use crate::FibError;
+
+
+/// Finds the kth letter of the fibonacci word, counting from 0, with true for 1.
+/// Runtime O(log(k))
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::word::fib_word_char_at;
+///
+/// let prefix: String = (0..13).map(|k| if fib_word_char_at(k) { '1' } else { '0' }).collect();
+/// assert_eq!("0100101001001", prefix);
+/// assert!(!fib_word_char_at(1_000_000_000_000));
+/// ```
+pub fn fib_word_char_at(k: u64) -> bool {
+    // Up to the largest fibonacci number not above k, starting from fib(3) = 2.
+    let (mut smaller, mut fib) = (1u64, 2u64);
+    while let Some(next) = smaller.checked_add(fib).filter(|&next| next <= k) {
+        smaller = fib;
+        fib = next;
+    }
+    // Greedily down to fib(3), leaving 1 exactly when the representation uses fib(2) = 1.
+    let mut rest = k;
+    while fib >= 2 {
+        if fib <= rest {
+            rest -= fib;
+        }
+        let next_smaller = fib - smaller;
+        fib = smaller;
+        smaller = next_smaller;
+    }
+    rest == 1
+}
+
+
+/// The letters of the fibonacci word in order, with true for 1. Runtime O(log(k)) for the kth
+///
+/// # Examples
+/// ```
+/// let prefix: Vec<u8> = fast_fibonacci::word::fib_word().take(8).map(u8::from).collect();
+/// assert_eq!(vec![0, 1, 0, 0, 1, 0, 1, 0], prefix);
+/// ```
+pub fn fib_word() -> FibWord {
+    FibWord { k: 0 }
+}
+
+
+/// Iterator returned by `fib_word`. Ends after u64::MAX letters.
+#[derive(Clone, Debug)]
+pub struct FibWord {
+    k: u64,
+}
+
+impl Iterator for FibWord {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        let letter = fib_word_char_at(self.k);
+        self.k = self.k.checked_add(1)?;
+        Some(letter)
+    }
+}
+
+
+/// The largest generation of the fibonacci word fractal, whose fib(93) letters fit a u64.
+pub const MAX_FRACTAL_GENERATION: u32 = 91;
+
+
+/// A quarter turn of the fibonacci word fractal, or none.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Turn {
+    Left,
+    Right,
+    Straight,
+}
+
+
+/// The turtle graphics of generation n of the fibonacci word fractal: for each of the fib(n + 2)
+/// letters of S(n), a unit step forward and then the turn. Runtime O(log(k)) for the kth
+///
+/// Panics if generation is past `MAX_FRACTAL_GENERATION`, see `try_fractal_turns`.
+///
+/// # Examples
+/// ```
+/// use fast_fibonacci::word::{fractal_turns, Turn};
+///
+/// let turns: Vec<Turn> = fractal_turns(2).collect();
+/// // 010
+/// assert_eq!(vec![Turn::Right, Turn::Straight, Turn::Right], turns);
+/// ```
+pub fn fractal_turns(generation: u32) -> FractalTurns {
+    try_fractal_turns(generation).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fractal_turns`, but returns an error instead of panicking.
+pub fn try_fractal_turns(generation: u32) -> Result<FractalTurns, FibError> {
+    if generation > MAX_FRACTAL_GENERATION {
+        return Err(FibError::InvalidArgument("generation must be at most 91"));
+    }
+    // fib(generation + 2), the length of S(generation).
+    let (mut previous, mut len) = (1u64, 1u64);
+    for _ in 0..generation {
+        let next = previous + len;
+        previous = len;
+        len = next;
+    }
+    Ok(FractalTurns { word: fib_word(), remaining: len, n: 0 })
+}
+
+
+/// Iterator returned by `fractal_turns`.
+#[derive(Clone, Debug)]
+pub struct FractalTurns {
+    word: FibWord,
+    remaining: u64,
+    // The position of the last letter, counting from 1.
+    n: u64,
+}
+
+impl Iterator for FractalTurns {
+    type Item = Turn;
+
+    fn next(&mut self) -> Option<Turn> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let letter = self.word.next()?;
+        self.n += 1;
+        Some(if letter {
+            Turn::Straight
+        } else if self.n.is_multiple_of(2) {
+            Turn::Left
+        } else {
+            Turn::Right
+        })
+    }
+}
+
+
+/// The vertices of generation n of the fibonacci word fractal, from (0, 0) heading towards
+/// (1, 0), one after every step of `fractal_turns`. Runtime O(log(k)) for the kth
+///
+/// Panics if generation is past `MAX_FRACTAL_GENERATION`, see `try_fractal_path`.
+pub fn fractal_path(generation: u32) -> FractalPath {
+    try_fractal_path(generation).unwrap_or_else(|err| panic!("{}", err))
+}
+
+
+/// Like `fractal_path`, but returns an error instead of panicking.
+pub fn try_fractal_path(generation: u32) -> Result<FractalPath, FibError> {
+    Ok(FractalPath { turns: try_fractal_turns(generation)?, position: (0, 0), direction: (1, 0), started: false })
+}
+
+
+/// Iterator returned by `fractal_path`.
+#[derive(Clone, Debug)]
+pub struct FractalPath {
+    turns: FractalTurns,
+    position: (i64, i64),
+    direction: (i64, i64),
+    // Whether the starting point was returned.
+    started: bool,
+}
+
+impl Iterator for FractalPath {
+    type Item = (i64, i64);
+
+    fn next(&mut self) -> Option<(i64, i64)> {
+        if !self.started {
+            self.started = true;
+            return Some(self.position);
+        }
+        let turn = self.turns.next()?;
+        let (dx, dy) = self.direction;
+        self.position = (self.position.0 + dx, self.position.1 + dy);
+        self.direction = match turn {
+            Turn::Left => (-dy, dx),
+            Turn::Right => (dy, -dx),
+            Turn::Straight => (dx, dy),
+        };
+        Some(self.position)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::word::*;
+
+    #[test]
+    fn test_matches_recurrence() {
+        let (mut previous, mut word) = (String::from("0"), String::from("01"));
+        while word.len() < 50_000 {
+            let next = format!("{}{}", word, previous);
+            previous = word;
+            word = next;
+        }
+        let letters: String = fib_word().take(word.len()).map(|bit| if bit { '1' } else { '0' }).collect();
+        assert_eq!(letters, word);
+    }
+
+    #[test]
+    fn test_large_k() {
+        // fib(93), the largest fibonacci number in a u64, is its own Zeckendorf representation.
+        assert!(!fib_word_char_at(12_200_160_415_121_876_738));
+        assert!(fib_word_char_at(12_200_160_415_121_876_739));
+        // The word never has two 1s in a row.
+        for k in u64::MAX - 1_000..u64::MAX {
+            assert!(!(fib_word_char_at(k) && fib_word_char_at(k + 1)), "k = {}", k);
+        }
+    }
+
+    #[test]
+    fn test_fractal() {
+        assert_eq!(fractal_turns(0).count(), 1);
+        let mut fib = (1, 2);
+        for generation in 1..=22 {
+            assert_eq!(fractal_turns(generation).count(), fib.1, "generation = {}", generation);
+            // A self avoiding path of unit steps.
+            let path: Vec<(i64, i64)> = fractal_path(generation).collect();
+            assert_eq!(path.len(), fib.1 + 1);
+            assert!(path.windows(2).all(|step| (step[0].0 - step[1].0).abs() + (step[0].1 - step[1].1).abs() == 1));
+            let vertices: std::collections::HashSet<(i64, i64)> = path.iter().copied().collect();
+            assert_eq!(vertices.len(), path.len(), "generation = {}", generation);
+            fib = (fib.1, fib.0 + fib.1);
+        }
+        assert!(try_fractal_turns(MAX_FRACTAL_GENERATION).is_ok());
+        assert_eq!(try_fractal_path(92).err(), Some(FibError::InvalidArgument("generation must be at most 91")));
+    }
+}